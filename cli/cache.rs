@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use parser::ast::{Node, Program};
+use parser::{parse, ParseErrors};
+
+use crate::package::fnv1a;
+
+// parsed programs as JSON, named for a hash of the source and of `monkey`, so a build parses only
+// the files changed; an entry that can't be read is parsed again
+pub struct ParseCache {
+    dir: PathBuf,
+    // what's hashed ahead of each source, the `monkey` parsing it
+    build: String,
+}
+
+impl ParseCache {
+    pub fn new(dir: PathBuf) -> Self {
+        ParseCache { dir, build: build() }
+    }
+
+    // the program `source` parses to, from the cache when it's been parsed before
+    pub fn parse(&self, source: &str) -> Result<Program, ParseErrors> {
+        let entry = self.entry(source);
+        if let Some(program) = read(&entry) {
+            return Ok(program);
+        }
+        let program = match parse(source)? {
+            Node::Program(p) => p,
+            _ => unreachable!("parse always returns a program"),
+        };
+        write(&entry, &program);
+        Ok(program)
+    }
+
+    // where the program `source` parses to is kept, whether it is yet or not
+    pub fn entry(&self, source: &str) -> PathBuf {
+        let hash = fnv1a(&[self.build.as_bytes(), &[0], source.as_bytes()]);
+        self.dir
+            .join(format!("{:016x}-{}.json", hash, source.len()))
+    }
+}
+
+// the version of `monkey` and when its binary was built, falling back to only the version
+fn build() -> String {
+    let built = std::env::current_exe()
+        .and_then(fs::metadata)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok());
+    match built {
+        Some(t) => format!("{} {}", env!("CARGO_PKG_VERSION"), t.as_nanos()),
+        None => env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
+
+fn read(entry: &Path) -> Option<Program> {
+    serde_json::from_slice(&fs::read(entry).ok()?).ok()
+}
+
+// through a file of its own and a rename, so a build reading the entry at the same time sees
+// all of it or none
+fn write(entry: &Path, program: &Program) {
+    let json = match serde_json::to_vec(program) {
+        Ok(json) => json,
+        Err(_) => return,
+    };
+    let partial = entry.with_extension(format!("{}.partial", std::process::id()));
+    let written = entry
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&partial, json))
+        .and_then(|_| fs::rename(&partial, entry));
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+}
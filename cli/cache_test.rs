@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use object::Object;
+    use parser::ast::Node;
+    use parser::parse;
+
+    use crate::cache::ParseCache;
+    use crate::project::Project;
+    use crate::project_test::{run, scratch_dir, write};
+
+    #[test]
+    fn test_cached_program_is_the_parsed_one() {
+        let dir = scratch_dir("cache-round-trip");
+        let cache = ParseCache::new(dir.clone());
+        let sources = [
+            "let add = fn(a, b) { a + b }; add(1, 2)",
+            "for (x in [1, 2]) { puts(x); } let n = 0; n = 1;",
+            "match ([1, 2]) { [h, ...t] => h, {\"k\": v} if v > 1 => 'v', _ => 1.5d }",
+            "#!/usr/bin/env monkey\nlet s = \"a ${b} c\"; outer: while (true) { break outer; }",
+        ];
+        for source in sources {
+            let parsed = cache.parse(source).unwrap();
+            assert!(cache.entry(source).is_file(), "{}", source);
+            let cached = cache.parse(source).unwrap();
+            assert_eq!(cached, parsed, "{}", source);
+            assert_eq!(Node::Program(cached), parse(source).unwrap(), "{}", source);
+        }
+
+        // errors aren't kept, nor is what can't be read used
+        assert!(cache.parse("let = 1;").is_err());
+        assert!(!cache.entry("let = 1;").exists());
+        fs::write(cache.entry("1 + 2"), "not a program").unwrap();
+        assert_eq!(Node::Program(cache.parse("1 + 2").unwrap()), parse("1 + 2").unwrap());
+    }
+
+    #[test]
+    fn test_build_parses_only_changed_files() {
+        let dir = scratch_dir("cache-build");
+        write(&dir, "monkey.toml", "[project]\nname = \"demo\"");
+        write(&dir, "util.mk", "let two = 2;");
+        write(&dir, "main.monkey", "import \"util\";\ntwo * 10");
+        let project = Project::load(&dir).unwrap();
+        assert_eq!(run(&project), Object::Integer(20));
+
+        // the next build takes `util.mk` from the cache, shown by what it's been swapped for
+        let cache = ParseCache::new(project.cache_dir());
+        let entry = cache.entry("let two = 2;");
+        cache.parse("let two = 3;").unwrap();
+        fs::copy(cache.entry("let two = 3;"), &entry).unwrap();
+        assert_eq!(run(&project), Object::Integer(30));
+
+        // while an edited file is parsed again
+        write(&dir, "util.mk", "let two = 2; let two = two + 2;");
+        assert_eq!(run(&project), Object::Integer(40));
+        assert!(cache.entry("let two = 2; let two = two + 2;").is_file());
+    }
+}
@@ -0,0 +1,99 @@
+use std::io::{self, BufRead, Write};
+
+use compiler::event::Event;
+use compiler::replay::{Recording, MAX_STEPS};
+
+const HELP: &str = "commands:
+    :forward [n], :f [n]    run the next n instructions, 1 by default, also an empty line
+    :back [n], :b [n]       undo the last n instructions
+    :goto <n>               go to right before instruction n
+    :stack                  the stack, top first
+    :frames                 the calls in progress, innermost first
+    :globals                the globals bound so far
+    :quit, :q               stop debugging";
+
+// `monkey debug`: the program has already run to completion, recorded, so moving backwards is
+// as cheap as moving forwards.
+pub fn debug(recording: &Recording, input: impl BufRead, mut out: impl Write) -> io::Result<()> {
+    if recording.truncated {
+        writeln!(out, "only the first {} instructions were recorded", MAX_STEPS)?;
+    }
+    writeln!(out, "{} instructions, :help for the commands", recording.len())?;
+    let mut position = 0;
+    print_position(recording, position, &mut out)?;
+
+    let mut lines = input.lines();
+    loop {
+        write!(out, "(debug) ")?;
+        out.flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(()),
+        };
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or(":forward");
+        let count = match words.next().map(str::parse::<usize>) {
+            Some(Ok(n)) => Some(n),
+            Some(Err(_)) => {
+                writeln!(out, "expected a number of instructions")?;
+                continue;
+            }
+            None => None,
+        };
+
+        match command {
+            ":forward" | ":f" => position = (position + count.unwrap_or(1)).min(recording.len()),
+            ":back" | ":b" => position = position.saturating_sub(count.unwrap_or(1)),
+            ":goto" => match count {
+                Some(n) => position = n.min(recording.len()),
+                None => {
+                    writeln!(out, ":goto expects an instruction number")?;
+                    continue;
+                }
+            },
+            ":stack" => {
+                let state = recording.state_at(position);
+                for (slot, value) in state.stack.iter().enumerate().rev() {
+                    let value = if value.is_empty() { "-" } else { value };
+                    writeln!(out, "{:>4}  {}", slot, value)?;
+                }
+                continue;
+            }
+            ":frames" => {
+                let state = recording.state_at(position);
+                for f in state.frames.iter().rev() {
+                    writeln!(out, "{} (base {})", f.function, f.base_pointer)?;
+                }
+                continue;
+            }
+            ":globals" => {
+                for (index, value) in recording.state_at(position).globals.iter() {
+                    writeln!(out, "{:>4}  {}", index, value)?;
+                }
+                continue;
+            }
+            ":help" => {
+                writeln!(out, "{}", HELP)?;
+                continue;
+            }
+            ":quit" | ":q" => return Ok(()),
+            _ => {
+                writeln!(out, "unknown command `{}`, :help for the commands", command)?;
+                continue;
+            }
+        }
+        print_position(recording, position, &mut out)?;
+    }
+}
+
+fn print_position(recording: &Recording, position: usize, out: &mut impl Write) -> io::Result<()> {
+    match recording.step(position) {
+        Some(Event::Step { function, ip, op }) => {
+            writeln!(out, "[{}/{}] {} @ {}: {}", position, recording.len(), function, ip, op)
+        }
+        _ => match &recording.result {
+            Ok(()) => writeln!(out, "[{}/{}] end of run", position, recording.len()),
+            Err(e) => writeln!(out, "[{}/{}] failed: {}", position, recording.len(), e),
+        },
+    }
+}
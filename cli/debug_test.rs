@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use compiler::replay::Recording;
+
+    use crate::debug::debug;
+
+    fn session(source: &str, commands: &str) -> String {
+        let recording = Recording::record(compiler::compile(source).unwrap());
+        let mut out = vec![];
+        debug(&recording, commands.as_bytes(), &mut out).unwrap();
+        return String::from_utf8(out).unwrap();
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let out = session(
+            "let a = 1; let b = a + 1;",
+            ":forward 3\n:globals\n:back 2\n\n:stack\n:goto 100\n:b\n:globals\n:q\n",
+        );
+        let expected = "6 instructions, :help for the commands
+[0/6] <main> @ 0: OpConst
+(debug) [3/6] <main> @ 9: OpConst
+(debug)    0  1
+(debug) [1/6] <main> @ 3: OpSetGlobal
+(debug) [2/6] <main> @ 6: OpGetGlobal
+(debug) (debug) [6/6] end of run
+(debug) [5/6] <main> @ 13: OpSetGlobal
+(debug)    0  1
+(debug) ";
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_failed_run() {
+        let out = session("let f = fn(x) { x + true }; f(1);", ":goto 1000\n:frames\n:nope\n");
+        assert!(out.contains("failed: can't apply + to 1 and true\n    in f\n"), "{}", out);
+        assert!(out.contains("(debug) f (base 1)\n<main> (base 0)\n"), "{}", out);
+        assert!(out.contains("unknown command `:nope`"), "{}", out);
+    }
+}
@@ -0,0 +1,60 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use compiler::bundle;
+use compiler::compiler::{CompileOptions, Compiler};
+use parser::ast::Node;
+
+use crate::project::{ModuleLoader, Project, ProjectError};
+
+// A script compiled ahead of time, ready to be baked into a host binary.
+#[derive(Debug)]
+pub struct Embedded {
+    // the `.mkc` bundle, see `compiler::bundle`
+    pub bytes: Vec<u8>,
+    // the script and everything it imports, so the host can rebuild when one of them changes
+    pub sources: Vec<PathBuf>,
+    pub warnings: Vec<String>,
+}
+
+// Compiles `path` and its imports. A script living in a project resolves imports through the
+// project's module paths, exactly like `monkey run <file>`.
+pub fn compile_file(path: &Path) -> Result<Embedded, ProjectError> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let search_paths = Project::discover(dir)
+        .map(|p| p.module_paths())
+        .unwrap_or_default();
+    let mut loader = ModuleLoader::new(search_paths);
+    loader.load_file(path)?;
+    let sources = loader.sources();
+    let warnings = loader.warnings(&CompileOptions::default());
+
+    let mut compiler = Compiler::new();
+    let bytecode = compiler
+        .compile(&Node::Program(loader.program))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let bytes = bundle::encode(&bytecode)?;
+
+    Ok(Embedded { bytes, sources, warnings })
+}
+
+// For build scripts: compiles `src` into the bundle `out` and asks cargo to rerun the build
+// script when any of the sources change.
+//
+//     // build.rs
+//     let out = Path::new(&env::var("OUT_DIR").unwrap()).join("rules.mkc");
+//     cli::embed::build(Path::new("scripts/rules.mk"), &out).unwrap();
+//
+//     // main.rs
+//     static RULES: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/rules.mkc"));
+//     let bytecode = compiler::bundle::decode(RULES).unwrap();
+pub fn build(src: &Path, out: &Path) -> Result<(), ProjectError> {
+    let embedded = compile_file(src)?;
+    for source in embedded.sources.iter() {
+        println!("cargo:rerun-if-changed={}", source.display());
+    }
+    for warning in embedded.warnings.iter() {
+        println!("cargo:warning={}", warning);
+    }
+    fs::write(out, embedded.bytes).map_err(|e| format!("can't write {}: {}", out.display(), e))
+}
@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use compiler::bundle::decode;
+    use compiler::vm::VM;
+    use object::Object;
+
+    use crate::embed::{build, compile_file};
+    use crate::project_test::{scratch_dir, write};
+
+    #[test]
+    fn test_compile_file_with_imports() {
+        let dir = scratch_dir("embed");
+        write(&dir, "scripts/rules.mk", "import \"limits\"; max * 2");
+        write(&dir, "scripts/limits.mk", "let max = 21;");
+
+        let embedded = compile_file(&dir.join("scripts/rules.mk")).unwrap();
+        let names: Vec<String> = embedded
+            .sources
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["limits.mk", "rules.mk"]);
+
+        let out = dir.join("rules.mkc");
+        build(&dir.join("scripts/rules.mk"), &out).unwrap();
+        let bytes = std::fs::read(&out).unwrap();
+        assert_eq!(bytes, embedded.bytes);
+
+        let mut vm = VM::new(decode(&bytes).unwrap());
+        vm.run().unwrap();
+        assert_eq!(*vm.last_popped_stack_elm().unwrap(), Object::Integer(42));
+    }
+
+    #[test]
+    fn test_compile_file_errors() {
+        let dir = scratch_dir("embed-errors");
+        write(&dir, "broken.mk", "let = 1;");
+        assert!(compile_file(&dir.join("broken.mk")).is_err());
+        assert!(compile_file(&dir.join("missing.mk"))
+            .unwrap_err()
+            .starts_with("can't open"));
+    }
+}
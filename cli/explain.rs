@@ -0,0 +1,143 @@
+// `monkey explain E0001`: what an error code means, with an example and its fix, each code found
+// by a fragment of its messages
+pub struct Explanation {
+    pub code: &'static str,
+    pub title: &'static str,
+    // a part of the error messages this code covers
+    pub pattern: &'static str,
+    pub text: &'static str,
+    pub wrong: &'static str,
+    pub right: &'static str,
+}
+
+pub static EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E0001",
+        title: "a token is missing",
+        pattern: "expected `",
+        text: "The parser knew which token had to come next and found another one. A `let` is \
+            `let <name> = <expression>;`, a call closes its arguments with `)`, a block its \
+            statements with `}`.",
+        wrong: "let total 5;",
+        right: "let total = 5;",
+    },
+    Explanation {
+        code: "E0002",
+        title: "an expression can't start here",
+        pattern: "no prefix function for token",
+        text: "An expression starts with a literal, a name, `!` or `-`, `(`, `[`, `{`, `fn` or \
+            `if`. The token named in the error can only come after one, as `*` does in `2 * 3`, \
+            or the expression it should start is missing.",
+        wrong: "let total = ;",
+        right: "let total = 2 * 3;",
+    },
+    Explanation {
+        code: "E0003",
+        title: "not part of the language",
+        pattern: "illegal token",
+        text: "The lexer found characters that make no token: a symbol monkey doesn't use, or a \
+            number that isn't one, like `0x` with no digits after it, a digit past the base of \
+            `0o` or `0b`, or a literal too large for an integer.",
+        wrong: "let mask = 0x;",
+        right: "let mask = 0xFF;",
+    },
+    Explanation {
+        code: "E0004",
+        title: "floats aren't supported",
+        pattern: "float literals aren't supported",
+        text: "Monkey has integers and exact decimals, not floats. A number with a fraction needs \
+            the `d` suffix of a decimal literal, and is exact: `0.1d + 0.2d == 0.3d`.",
+        wrong: "let price = 19.99;",
+        right: "let price = 19.99d;",
+    },
+    Explanation {
+        code: "E0005",
+        title: "`let` needs a name",
+        pattern: " not an identifier",
+        text: "`let` binds a name, letters and `_`, to a value: `let <name> = <expression>;`. \
+            Keywords and literals can't be names.",
+        wrong: "let 5 = five;",
+        right: "let five = 5;",
+    },
+    Explanation {
+        code: "E0006",
+        title: "parameters are names",
+        pattern: "expected function params",
+        text: "The parameters of `fn(...)` are names separated by commas. Default values and \
+            patterns aren't supported.",
+        wrong: "let inc = fn(1) { 1 };",
+        right: "let inc = fn(x) { x + 1 };",
+    },
+    Explanation {
+        code: "E0007",
+        title: "only names and function literals can be called",
+        pattern: "expected function",
+        text: "A call is a name or a `fn` literal followed by `(...)`. To call a function taken \
+            from an array, a hash or a call, bind it to a name first.",
+        wrong: "let fns = [fn(x) { x }]; fns[0](1)",
+        right: "let fns = [fn(x) { x }]; let first_fn = fns[0]; first_fn(1)",
+    },
+    Explanation {
+        code: "E0008",
+        title: "imports are strings",
+        pattern: "expected import path",
+        text: "`import` takes the name of a module as a string, looked up in the project's \
+            module paths: `import \"limits\";` reads `limits.mk`.",
+        wrong: "import limits;",
+        right: "import \"limits\";",
+    },
+    Explanation {
+        code: "E0009",
+        title: "an escape doesn't decode",
+        pattern: " in string \"",
+        text: "A `\\` in a string starts an escape: `\\n` is a newline, `\\t` a tab, `\\\"` \
+            a quote, `\\\\` a backslash and `\\u{e9}` the character with that hexadecimal \
+            code point. Any other character after a `\\` is an error.",
+        wrong: "let path = \"C:\\monkey\";",
+        right: "let path = \"C:\\\\monkey\";",
+    },
+    Explanation {
+        code: "E0101",
+        title: "a name isn't defined",
+        pattern: "unknown identifier",
+        text: "Every name must be bound by a `let`, a parameter or a builtin before it's used. \
+            A function can call itself and read the globals bound before it, not those bound \
+            after it, even when it's only called once they are.",
+        wrong: "let total = price * 2;",
+        right: "let price = 3; let total = price * 2;",
+    },
+];
+
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    return EXPLANATIONS
+        .iter()
+        .find(|e| e.code.eq_ignore_ascii_case(code));
+}
+
+// the code of the first error in `message` that has one
+pub fn code_of(message: &str) -> Option<&'static Explanation> {
+    for line in message.lines() {
+        if let Some(e) = EXPLANATIONS.iter().find(|e| line.contains(e.pattern)) {
+            return Some(e);
+        }
+    }
+    return None;
+}
+
+// what `monkey explain` prints
+pub fn report(explanation: &Explanation) -> String {
+    let e = explanation;
+    return format!(
+        "{}: {}\n\n{}\n\nfor example\n    {}\nshould be\n    {}",
+        e.code, e.title, e.text, e.wrong, e.right
+    );
+}
+
+// the codes and their titles, for `monkey explain` without a code
+pub fn index() -> String {
+    let lines: Vec<String> = EXPLANATIONS
+        .iter()
+        .map(|e| format!("{}  {}", e.code, e.title))
+        .collect();
+    return lines.join("\n");
+}
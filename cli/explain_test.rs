@@ -0,0 +1,28 @@
+#[cfg(test)]
+mod tests {
+    use crate::explain::{code_of, explain, EXPLANATIONS};
+
+    #[test]
+    fn test_examples() {
+        for e in EXPLANATIONS.iter() {
+            let error = compiler::compile(e.wrong).err();
+            let code = error.as_deref().and_then(code_of).map(|found| found.code);
+            assert_eq!(code, Some(e.code), "{}: {:?}", e.wrong, error);
+            // an import needs a project to find the module in, it only has to parse
+            match e.right.starts_with("import ") {
+                true => assert!(parser::parse(e.right).is_ok(), "{}", e.right),
+                false => assert!(compiler::compile(e.right).is_ok(), "{}", e.right),
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(explain("e0004").map(|e| e.code), Some("E0004"));
+        assert!(explain("E9999").is_none());
+        // a message in another one, as the project loader reports them
+        let message = "main.mk: expected function params  to be an identifier, got 1";
+        assert_eq!(code_of(message).map(|e| e.code), Some("E0006"));
+        assert!(code_of("division by zero").is_none());
+    }
+}
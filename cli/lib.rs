@@ -0,0 +1,19 @@
+pub mod cache;
+#[cfg(test)]
+mod cache_test;
+pub mod debug;
+#[cfg(test)]
+mod debug_test;
+pub mod embed;
+#[cfg(test)]
+mod embed_test;
+pub mod explain;
+#[cfg(test)]
+mod explain_test;
+pub mod manifest;
+pub mod package;
+#[cfg(test)]
+mod package_test;
+pub mod project;
+#[cfg(test)]
+mod project_test;
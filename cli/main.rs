@@ -0,0 +1,280 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+
+use cli::cache::ParseCache;
+use cli::debug;
+use cli::explain;
+use cli::package;
+use cli::project::{read_bundle, write_bundle, ModuleLoader, Project, BUNDLE_EXTENSION};
+use compiler::bcdiff;
+use compiler::compiler::{Bytecode, CompileOptions, Compiler};
+use compiler::replay::Recording;
+use compiler::vm::VM;
+use object::features;
+use parser::ast::Node;
+
+const USAGE: &str = "usage: monkey <command> [args]
+
+commands:
+    build [-O<n>] [--out <file>]    compile the project in the current directory into a .mkc bundle
+    run [-O<n>] [file]              run a .mkc bundle or a source file, or the current project
+        --visualize <events>        also write what the VM does, one JSON event per line, for
+                                    the viewer in cli/visualizer.html
+        --threads <n>               how many threads `pmap` runs on, by default one per core
+    debug [-O<n>] [file]            run like `run`, then step through the run backwards and forwards
+    bcdiff [-O<n>] <a> <b>          show how the bytecode of b differs from a, each a .mkc bundle
+                                    or a source file
+    fetch [--update]                vendor dependencies into .monkey/vendor and write monkey.lock
+    features                        list the constructs and builtins each engine supports
+    explain [code]                  explain an error code, like E0001, or list them all
+    help                            print this message
+
+optimization levels:
+    -O0    compile as written (default)
+    -O1    evaluate pure top-level expressions at compile time, use integer opcodes
+    -O2    also reuse repeated pure computations, compute loop invariants once and inline small
+           pure functions
+    --no-inline    don't inline functions, whatever the level
+
+warnings:
+    --allow-shadowing    don't warn about a `let` hiding a builtin, like `let len = 3;`";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("build") => build(&args[1..]),
+        Some("run") => run(&args[1..]),
+        Some("fetch") => fetch(&args[1..]),
+        Some("debug") => debug(&args[1..]),
+        Some("bcdiff") => bcdiff(&args[1..]),
+        Some("explain") => explain(&args[1..]),
+        Some("features") => {
+            print!("{}", features::report());
+            Ok(())
+        }
+        Some("help") | Some("--help") | Some("-h") | None => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(command) => Err(format!("unknown command `{}`\n\n{}", command, USAGE)),
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        if let Some(explanation) = explain::code_of(&e) {
+            let code = explanation.code;
+            eprintln!("\nfor more about this error, run `monkey explain {}`", code);
+        }
+        process::exit(1);
+    }
+}
+
+fn current_project() -> Result<Project, String> {
+    let cwd = env::current_dir().map_err(|e| e.to_string())?;
+    Project::discover(&cwd)
+}
+
+// pulls `-O<n>`, `--no-inline` and `--allow-shadowing` out of the arguments of `build` and `run`
+fn compile_options(args: &[String]) -> Result<(CompileOptions, Vec<String>), String> {
+    let mut level = 0;
+    let mut inline = true;
+    let mut allow_shadowing = false;
+    let mut rest = vec![];
+    for arg in args.iter() {
+        if arg == "--no-inline" {
+            inline = false;
+            continue;
+        }
+        if arg == "--allow-shadowing" {
+            allow_shadowing = true;
+            continue;
+        }
+        match arg.strip_prefix("-O") {
+            Some(n) => match n.parse::<u8>() {
+                Ok(n) if n <= 2 => level = n,
+                _ => {
+                    return Err(format!(
+                        "unknown optimization level `{}`, expected -O0 to -O2",
+                        arg
+                    ))
+                }
+            },
+            None => rest.push(arg.clone()),
+        }
+    }
+    let mut options = CompileOptions::from_opt_level(level);
+    options.inline &= inline;
+    options.allow_shadowing = allow_shadowing;
+    Ok((options, rest))
+}
+
+fn warn(warnings: &[String]) {
+    for warning in warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+}
+
+fn build(args: &[String]) -> Result<(), String> {
+    let (options, args) = compile_options(args)?;
+    let project = current_project()?;
+    let out = match args.as_slice() {
+        [] => project.bundle_path(),
+        [flag, path] if flag == "--out" => PathBuf::from(path),
+        _ => return Err(format!("unexpected arguments for build: {}", args.join(" "))),
+    };
+
+    let (bytecode, warnings) = project.compile_with_warnings(&options)?;
+    warn(&warnings);
+    write_bundle(&out, &bytecode)?;
+    println!("built {}", out.display());
+    Ok(())
+}
+
+fn fetch(args: &[String]) -> Result<(), String> {
+    let update = match args {
+        [] => false,
+        [flag] if flag == "--update" => true,
+        _ => return Err(format!("unexpected arguments for fetch: {}", args.join(" "))),
+    };
+
+    let lock = package::fetch(&current_project()?, update)?;
+    for p in lock.packages.iter() {
+        println!("vendored {} ({})", p.name, p.rev.as_deref().unwrap_or(&p.source));
+    }
+    Ok(())
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let (options, mut args) = compile_options(args)?;
+    let mut events = None;
+    if let Some(i) = args.iter().position(|a| a == "--visualize") {
+        match args.get(i + 1) {
+            Some(path) => events = Some(PathBuf::from(path)),
+            None => return Err("--visualize expects a file to write the events to".to_string()),
+        }
+        args.drain(i..i + 2);
+    }
+    let mut threads = None;
+    if let Some(i) = args.iter().position(|a| a == "--threads") {
+        match args.get(i + 1).map(|n| n.parse::<usize>()) {
+            Some(Ok(n)) if n > 0 => threads = Some(n),
+            _ => return Err("--threads expects a number of threads, 1 or more".to_string()),
+        }
+        args.drain(i..i + 2);
+    }
+    let mut vm = VM::new(program("run", &args, &options)?);
+    if let Some(threads) = threads {
+        vm.set_threads(threads);
+    }
+    let events = match events {
+        Some(path) => Some(visualize(&mut vm, &path)?),
+        None => None,
+    };
+    let result = vm.run();
+    if let Some(events) = events {
+        events.borrow_mut().finish()?;
+    }
+    result
+}
+
+fn debug(args: &[String]) -> Result<(), String> {
+    let (options, args) = compile_options(args)?;
+    let recording = Recording::record(program("debug", &args, &options)?);
+    debug::debug(&recording, io::stdin().lock(), io::stdout()).map_err(|e| e.to_string())
+}
+
+fn explain(args: &[String]) -> Result<(), String> {
+    match args {
+        [] => {
+            println!("{}", explain::index());
+            Ok(())
+        }
+        [code] => match explain::explain(code) {
+            Some(explanation) => {
+                println!("{}", explain::report(explanation));
+                Ok(())
+            }
+            None => Err(format!("no explanation for `{}`, `monkey explain` lists them", code)),
+        },
+        _ => Err(format!("explain expects one error code, got: {}", args.join(" "))),
+    }
+}
+
+fn bcdiff(args: &[String]) -> Result<(), String> {
+    let (options, args) = compile_options(args)?;
+    match args.as_slice() {
+        [a, b] => {
+            let a = load(Path::new(a), &options)?;
+            let b = load(Path::new(b), &options)?;
+            print!("{}", bcdiff::diff(&a, &b));
+            Ok(())
+        }
+        _ => Err(format!("bcdiff expects two files, got: {}", args.join(" "))),
+    }
+}
+
+// what `run` and `debug` run: a bundle, a source file or the current project
+fn program(command: &str, args: &[String], options: &CompileOptions) -> Result<Bytecode, String> {
+    match args {
+        [] => {
+            let (bytecode, warnings) = current_project()?.compile_with_warnings(options)?;
+            warn(&warnings);
+            Ok(bytecode)
+        }
+        [file] => load(Path::new(file), options),
+        _ => Err(format!("unexpected arguments for {}: {}", command, args.join(" "))),
+    }
+}
+
+// the `--visualize` stream, the VM can't stop for write errors so they wait for the end of the run
+struct EventWriter {
+    out: BufWriter<File>,
+    error: Option<io::Error>,
+}
+
+impl EventWriter {
+    fn finish(&mut self) -> Result<(), String> {
+        let flushed = self.out.flush();
+        match self.error.take() {
+            Some(e) => Err(format!("writing events: {}", e)),
+            None => flushed.map_err(|e| format!("writing events: {}", e)),
+        }
+    }
+}
+
+fn visualize(vm: &mut VM, path: &Path) -> Result<Rc<RefCell<EventWriter>>, String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let writer = Rc::new(RefCell::new(EventWriter { out: BufWriter::new(file), error: None }));
+    let events = Rc::clone(&writer);
+    vm.set_observer(move |e| {
+        let mut w = events.borrow_mut();
+        if w.error.is_none() {
+            if let Err(err) = writeln!(w.out, "{}", e.to_json()) {
+                w.error = Some(err);
+            }
+        }
+    });
+    Ok(writer)
+}
+
+fn load(path: &Path, options: &CompileOptions) -> Result<Bytecode, String> {
+    if path.extension().and_then(|e| e.to_str()) == Some(BUNDLE_EXTENSION) {
+        return read_bundle(path);
+    }
+
+    // loose files still see the module paths of the project they live in, if any
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut loader = match Project::discover(dir) {
+        Ok(p) => ModuleLoader::with_cache(p.module_paths(), ParseCache::new(p.cache_dir())),
+        Err(_) => ModuleLoader::new(vec![]),
+    };
+    loader.load_file(path)?;
+    warn(&loader.warnings(options));
+    let mut compiler = Compiler::new_with_options(options.clone());
+    compiler.compile(&Node::Program(loader.program))
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+
+use serde::Deserialize;
+
+pub const MANIFEST_FILE: &str = "monkey.toml";
+
+pub type ManifestError = String;
+
+// a `monkey.toml` looks like
+//
+// ```toml
+// [project]
+// name = "hello"
+// entry = "main.monkey"
+// module-paths = ["lib"]
+// script-dirs = ["scripts"]
+//
+// [dependencies]
+// strings = { path = "../strings" }
+// json = { git = "https://github.com/someone/monkey-json", tag = "v1.0" }
+// ```
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub project: ProjectConfig,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub name: String,
+    #[serde(default = "default_entry")]
+    pub entry: String,
+    // directories searched by `import "name"`, after the importing file's own directory
+    #[serde(default)]
+    pub module_paths: Vec<String>,
+    // every script in these directories is loaded ahead of the entry, like a prelude
+    #[serde(default)]
+    pub script_dirs: Vec<String>,
+}
+
+// a library of monkey scripts, either next to the project on disk or in a git repository
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    pub path: Option<String>,
+    pub git: Option<String>,
+    pub rev: Option<String>,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl Dependency {
+    pub fn validate(&self, name: &str) -> Result<(), ManifestError> {
+        // it's the directory the dependency is vendored to, which is removed and written again
+        let mut components = Path::new(name).components();
+        let plain =
+            matches!((components.next(), components.next()), (Some(Component::Normal(_)), None));
+        if !plain || name.contains(['/', '\\']) {
+            return Err(format!("dependency name `{}` must be a plain name, not a path", name));
+        }
+        // git would read them as options
+        let values = [&self.git, &self.rev, &self.branch, &self.tag];
+        if let Some(value) = values
+            .iter()
+            .filter_map(|v| v.as_ref())
+            .find(|v| v.starts_with('-'))
+        {
+            return Err(format!("dependency `{}`: `{}` can't start with `-`", name, value));
+        }
+        let refs = [&self.rev, &self.branch, &self.tag]
+            .iter()
+            .filter(|r| r.is_some())
+            .count();
+        match (&self.path, &self.git) {
+            (Some(_), None) if refs > 0 => {
+                Err(format!("dependency `{}`: rev, branch and tag only apply to git", name))
+            }
+            (Some(_), None) => Ok(()),
+            (None, Some(_)) if refs > 1 => {
+                Err(format!("dependency `{}`: only one of rev, branch or tag can be set", name))
+            }
+            (None, Some(_)) => Ok(()),
+            _ => Err(format!("dependency `{}` needs exactly one of `path` or `git`", name)),
+        }
+    }
+}
+
+fn default_entry() -> String {
+    "main.monkey".to_string()
+}
+
+impl Manifest {
+    pub fn parse(input: &str) -> Result<Manifest, ManifestError> {
+        let manifest: Manifest =
+            toml::from_str(input).map_err(|e| format!("invalid {}: {}", MANIFEST_FILE, e))?;
+        for (name, dependency) in manifest.dependencies.iter() {
+            dependency.validate(name)?;
+        }
+        Ok(manifest)
+    }
+
+    pub fn read(path: &Path) -> Result<Manifest, ManifestError> {
+        match fs::read_to_string(path) {
+            Ok(content) => Manifest::parse(&content),
+            Err(e) => Err(format!("can't read {}: {}", path.display(), e)),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::{Dependency, Manifest, MANIFEST_FILE};
+use crate::project::{Project, ProjectError};
+
+pub const LOCK_FILE: &str = "monkey.lock";
+pub const VENDOR_DIR: &str = ".monkey/vendor";
+
+// `monkey.lock`, one `[[package]]` per resolved dependency, transitive ones included
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Lockfile {
+    #[serde(rename = "package", default)]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct LockedPackage {
+    pub name: String,
+    // `path+<dir>`, or `git+<url>` with the pin, as `git+<url>?tag=v1`
+    pub source: String,
+    // the commit a git dependency was resolved to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    pub checksum: String,
+}
+
+impl Lockfile {
+    pub fn read(path: &Path) -> Result<Option<Lockfile>, ProjectError> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+        match toml::from_str(&content) {
+            Ok(lock) => Ok(Some(lock)),
+            Err(e) => Err(format!("invalid {}: {}", LOCK_FILE, e)),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ProjectError> {
+        let content = toml::to_string(self).map_err(|e| e.to_string())?;
+        let content = format!("# generated by `monkey fetch`, do not edit\n{}", content);
+        fs::write(path, content).map_err(|e| format!("can't write {}: {}", path.display(), e))
+    }
+
+    pub fn find(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+}
+
+// vendors every dependency, theirs too, and returns the lockfile; git dependencies stay on the
+// locked commit unless `update` is set
+pub fn fetch(project: &Project, update: bool) -> Result<Lockfile, ProjectError> {
+    let lock_path = project.root.join(LOCK_FILE);
+    let previous = if update {
+        None
+    } else {
+        Lockfile::read(&lock_path)?
+    };
+    let vendor = project.root.join(VENDOR_DIR);
+
+    let mut resolved: BTreeMap<String, LockedPackage> = BTreeMap::new();
+    let mut queue: Vec<(String, Dependency, PathBuf)> = project
+        .manifest
+        .dependencies
+        .iter()
+        .map(|(name, dep)| (name.clone(), dep.clone(), project.root.clone()))
+        .collect();
+
+    while !queue.is_empty() {
+        let (name, dep, base) = queue.remove(0);
+        let source = source_of(&dep, &base, &project.root);
+        if let Some(existing) = resolved.get(&name) {
+            if existing.source != source {
+                return Err(format!(
+                    "dependency `{}` is required from both {} and {}",
+                    name, existing.source, source
+                ));
+            }
+            continue;
+        }
+
+        let locked = previous
+            .as_ref()
+            .and_then(|l| l.find(&name))
+            .filter(|p| p.source == source);
+        let dest = vendor.join(&name);
+        let (rev, origin) = match (&dep.path, &dep.git) {
+            (Some(path), _) => {
+                let src = base.join(path);
+                copy_dir(&src, &dest)?;
+                (None, src)
+            }
+            (_, Some(url)) => {
+                let rev = vendor_git(url, &dep, locked, &dest)?;
+                (Some(rev), dest.clone())
+            }
+            _ => unreachable!("validated by Manifest::parse"),
+        };
+
+        let checksum = checksum_dir(&dest)?;
+        if let Some(locked) = locked {
+            if locked.rev.is_some() && locked.rev == rev && locked.checksum != checksum {
+                return Err(format!(
+                    "checksum mismatch for `{}` at {}, run `monkey fetch --update` if this is expected",
+                    name,
+                    rev.unwrap_or_default()
+                ));
+            }
+        }
+
+        let nested = dest.join(MANIFEST_FILE);
+        if nested.is_file() {
+            let manifest = Manifest::read(&nested)?;
+            for (n, d) in manifest.dependencies.into_iter() {
+                queue.push((n, d, origin.clone()));
+            }
+        }
+
+        resolved.insert(name.clone(), LockedPackage { name, source, rev, checksum });
+    }
+
+    let lock = Lockfile { packages: resolved.into_values().collect() };
+    if !lock.packages.is_empty() || lock_path.is_file() {
+        lock.write(&lock_path)?;
+    }
+    Ok(lock)
+}
+
+fn source_of(dep: &Dependency, base: &Path, root: &Path) -> String {
+    match (&dep.path, &dep.git) {
+        (Some(path), _) => {
+            let full = base.join(path);
+            let shown = full
+                .strip_prefix(root)
+                .map(Path::to_path_buf)
+                .unwrap_or(full);
+            format!("path+{}", shown.display())
+        }
+        // a new pin resolves the dependency again, as a new url does
+        (_, Some(url)) => {
+            let pins = [
+                ("rev", &dep.rev),
+                ("tag", &dep.tag),
+                ("branch", &dep.branch),
+            ];
+            match pins
+                .iter()
+                .find_map(|(kind, pin)| pin.as_ref().map(|p| (kind, p)))
+            {
+                Some((kind, pin)) => format!("git+{}?{}={}", url, kind, pin),
+                None => format!("git+{}", url),
+            }
+        }
+        _ => unreachable!("validated by Manifest::parse"),
+    }
+}
+
+// whether `monkey fetch` vendored what the manifest asks for as it asks now, which building the
+// project needs
+pub fn check_fetched(project: &Project) -> Result<(), ProjectError> {
+    let lock = Lockfile::read(&project.root.join(LOCK_FILE))?.unwrap_or_default();
+    let vendor = project.root.join(VENDOR_DIR);
+    for (name, dep) in project.manifest.dependencies.iter() {
+        let source = source_of(dep, &project.root, &project.root);
+        if lock.find(name).filter(|p| p.source == source).is_none() {
+            return Err(format!("dependency `{}` isn't fetched, run `monkey fetch`", name));
+        }
+    }
+    for package in lock.packages.iter() {
+        if !vendor.join(&package.name).is_dir() {
+            let name = &package.name;
+            return Err(format!("dependency `{}` isn't fetched, run `monkey fetch`", name));
+        }
+    }
+    Ok(())
+}
+
+fn vendor_git(
+    url: &str,
+    dep: &Dependency,
+    locked: Option<&LockedPackage>,
+    dest: &Path,
+) -> Result<String, ProjectError> {
+    let wanted = match locked.and_then(|l| l.rev.clone()) {
+        Some(rev) => Some(rev),
+        None => dep
+            .rev
+            .clone()
+            .or_else(|| dep.tag.clone())
+            .or_else(|| dep.branch.clone()),
+    };
+
+    // the manifest is checked, the lockfile might not be
+    if let Some(rev) = wanted.as_ref().filter(|rev| rev.starts_with('-')) {
+        return Err(format!("invalid revision `{}` for `{}`", rev, url));
+    }
+
+    remove_dir(dest)?;
+    let dest_str = dest.to_string_lossy().to_string();
+    git(&["clone", "--quiet", "--", url, &dest_str])?;
+    if let Some(rev) = &wanted {
+        git(&["-C", &dest_str, "checkout", "--quiet", rev, "--"])?;
+    }
+    let rev = git(&["-C", &dest_str, "rev-parse", "HEAD"])?;
+    // vendored sources are plain files, history only makes the checksum unstable
+    remove_dir(&dest.join(".git"))?;
+
+    Ok(rev)
+}
+
+fn git(args: &[&str]) -> Result<String, ProjectError> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| format!("can't run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn remove_dir(dir: &Path) -> Result<(), ProjectError> {
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(|e| format!("can't remove {}: {}", dir.display(), e))?;
+    }
+    Ok(())
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> Result<(), ProjectError> {
+    if !src.is_dir() {
+        return Err(format!("dependency path {} is not a directory", src.display()));
+    }
+    remove_dir(dest)?;
+    for file in list_files(src)? {
+        let target = dest.join(file.strip_prefix(src).unwrap());
+        fs::create_dir_all(target.parent().unwrap())
+            .and_then(|_| fs::copy(&file, &target))
+            .map_err(|e| format!("can't vendor {}: {}", file.display(), e))?;
+    }
+    Ok(())
+}
+
+// every file below `dir` in a stable order, skipping hidden entries such as `.git` and `.monkey`
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>, ProjectError> {
+    let mut files = vec![];
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(d) = pending.pop() {
+        let entries = fs::read_dir(&d).map_err(|e| format!("can't read {}: {}", d.display(), e))?;
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// FNV-1a: cheap, stable across platforms and releases, and enough to notice a change
+pub fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in parts.iter().flat_map(|p| p.iter()) {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// over relative paths and contents, to notice a vendored library changing under a pinned revision
+pub fn checksum_dir(dir: &Path) -> Result<String, ProjectError> {
+    let mut data = vec![];
+    for file in list_files(dir)? {
+        let relative = file
+            .strip_prefix(dir)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        data.extend_from_slice(relative.as_bytes());
+        data.push(0);
+        data.extend(fs::read(&file).map_err(|e| format!("can't read {}: {}", file.display(), e))?);
+        data.push(0);
+    }
+    Ok(format!("fnv1a:{:016x}", fnv1a(&[&data])))
+}
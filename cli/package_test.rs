@@ -0,0 +1,164 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    use object::Object;
+
+    use crate::manifest::Manifest;
+    use crate::package::{fetch, Lockfile, LOCK_FILE, VENDOR_DIR};
+    use crate::project::Project;
+    use crate::project_test::{run, scratch_dir, write};
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args([
+                "-c",
+                "user.name=monkey",
+                "-c",
+                "user.email=monkey@example.com",
+            ])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[test]
+    fn test_dependency_validation() {
+        let both = "[project]\nname = \"a\"\n[dependencies]\nx = { path = \"x\", git = \"y\" }";
+        assert_eq!(
+            Manifest::parse(both).unwrap_err(),
+            "dependency `x` needs exactly one of `path` or `git`"
+        );
+
+        let rev_on_path =
+            "[project]\nname = \"a\"\n[dependencies]\nx = { path = \"x\", rev = \"1\" }";
+        assert_eq!(
+            Manifest::parse(rev_on_path).unwrap_err(),
+            "dependency `x`: rev, branch and tag only apply to git"
+        );
+
+        // the name is where it's vendored, and what's passed to git isn't an option
+        let manifest =
+            |dependency: &str| format!("[project]\nname = \"a\"\n[dependencies]\n{}", dependency);
+        for name in ["\"../..\"", "\"/tmp\"", "\"a/b\"", "\".\"", "\"\""] {
+            let error = Manifest::parse(&manifest(&format!("{} = {{ path = \"x\" }}", name)));
+            assert!(
+                error
+                    .unwrap_err()
+                    .ends_with("must be a plain name, not a path"),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(
+            Manifest::parse(&manifest("x = { git = \"--upload-pack=touch\" }")).unwrap_err(),
+            "dependency `x`: `--upload-pack=touch` can't start with `-`"
+        );
+        assert_eq!(
+            Manifest::parse(&manifest("x = { git = \"u\", rev = \"-p\" }")).unwrap_err(),
+            "dependency `x`: `-p` can't start with `-`"
+        );
+    }
+
+    #[test]
+    fn test_path_dependencies() {
+        let dir = scratch_dir("path-deps");
+        let app = dir.join("app");
+        write(
+            &app,
+            "monkey.toml",
+            "[project]\nname = \"app\"\n[dependencies]\nstrings = { path = \"../strings\" }",
+        );
+        write(
+            &app,
+            "main.monkey",
+            "import \"strings\";\nimport \"strings/extra\";\nshout(\"hi\") + bang",
+        );
+        write(
+            &dir,
+            "strings/monkey.toml",
+            "[project]\nname = \"strings\"\nentry = \"lib.monkey\"\n[dependencies]\nchars = { path = \"../chars\" }",
+        );
+        write(&dir, "strings/lib.monkey", "import \"chars\"; let shout = fn(s) { s + bang };");
+        write(&dir, "strings/extra.monkey", "let unused = 1;");
+        write(&dir, "chars/lib.monkey", "let bang = \"!\";");
+
+        let project = Project::load(&app).unwrap();
+        let error = project.compile(&Default::default()).unwrap_err();
+        assert_eq!(error, "dependency `strings` isn't fetched, run `monkey fetch`");
+        fetch(&project, false).unwrap();
+        assert_eq!(run(&project), Object::String("hi!!".to_string()));
+        assert!(app
+            .join(VENDOR_DIR)
+            .join("chars")
+            .join("lib.monkey")
+            .is_file());
+
+        let lock = Lockfile::read(&app.join(LOCK_FILE)).unwrap().unwrap();
+        let sources: Vec<(&str, &str)> = lock
+            .packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.source.as_str()))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("chars", "path+../strings/../chars"),
+                ("strings", "path+../strings")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_git_dependency_is_locked() {
+        let dir = scratch_dir("git-deps");
+        let lib = dir.join("version");
+        write(&lib, "lib.monkey", "let version = 1;");
+        git(&lib, &["init", "--quiet", "--initial-branch=main"]);
+        git(&lib, &["add", "."]);
+        git(&lib, &["commit", "--quiet", "-m", "one"]);
+        git(&lib, &["tag", "v1"]);
+        let first = git(&lib, &["rev-parse", "HEAD"]);
+        write(&lib, "lib.monkey", "let version = 2;");
+        git(&lib, &["commit", "--quiet", "-am", "two"]);
+
+        let app = dir.join("app");
+        let manifest = |pin: &str| {
+            format!(
+                "[project]\nname = \"app\"\n[dependencies]\nversion = {{ git = \"{}\"{} }}",
+                lib.display(),
+                pin
+            )
+        };
+        write(&app, "main.monkey", "import \"version\"; version");
+        write(&app, "monkey.toml", &manifest(", tag = \"v1\""));
+        let project = Project::load(&app).unwrap();
+        fetch(&project, false).unwrap();
+        assert_eq!(run(&project), Object::Integer(1));
+        let lock = Lockfile::read(&app.join(LOCK_FILE)).unwrap().unwrap();
+        assert_eq!(lock.packages[0].rev.as_deref(), Some(first.as_str()));
+
+        // a new pin is resolved again, and has to be fetched before it's built
+        write(&app, "monkey.toml", &manifest(", branch = \"main\""));
+        let project = Project::load(&app).unwrap();
+        assert!(project.compile(&Default::default()).is_err());
+        fetch(&project, false).unwrap();
+        assert_eq!(run(&project), Object::Integer(2));
+
+        // an unchanged one keeps the locked commit until an explicit update
+        write(&lib, "lib.monkey", "let version = 3;");
+        git(&lib, &["commit", "--quiet", "-am", "three"]);
+        fetch(&project, false).unwrap();
+        assert_eq!(run(&project), Object::Integer(2));
+        fetch(&project, true).unwrap();
+        assert_eq!(run(&project), Object::Integer(3));
+
+        assert!(!app.join(VENDOR_DIR).join("version").join(".git").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
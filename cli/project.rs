@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use compiler::bundle;
+use compiler::compiler::{Bytecode, CompileOptions, Compiler};
+use compiler::lint;
+use parser::ast::{Node, Program, Statement};
+use parser::error::render;
+use parser::parse;
+
+use crate::cache::ParseCache;
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::package::{self, VENDOR_DIR};
+
+pub const SOURCE_EXTENSIONS: [&str; 2] = ["monkey", "mk"];
+pub const BUNDLE_EXTENSION: &str = "mkc";
+
+pub type ProjectError = String;
+
+pub struct Project {
+    pub root: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Project {
+    pub fn load(root: &Path) -> Result<Project, ProjectError> {
+        let manifest = Manifest::read(&root.join(MANIFEST_FILE))?;
+        Ok(Project { root: root.to_path_buf(), manifest })
+    }
+
+    // walk up from `start` until a directory holding a `monkey.toml` is found
+    pub fn discover(start: &Path) -> Result<Project, ProjectError> {
+        let mut dir = Some(start);
+        while let Some(d) = dir {
+            if d.join(MANIFEST_FILE).is_file() {
+                return Project::load(d);
+            }
+            dir = d.parent();
+        }
+
+        Err(format!(
+            "could not find {} in {} or any parent directory",
+            MANIFEST_FILE,
+            start.display()
+        ))
+    }
+
+    pub fn entry(&self) -> PathBuf {
+        self.root.join(&self.manifest.project.entry)
+    }
+
+    // the manifest's module paths, then the vendored dependencies
+    pub fn module_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .manifest
+            .project
+            .module_paths
+            .iter()
+            .map(|p| self.root.join(p))
+            .collect();
+        if !self.manifest.dependencies.is_empty() {
+            paths.push(self.root.join(VENDOR_DIR));
+        }
+        paths
+    }
+
+    pub fn bundle_path(&self) -> PathBuf {
+        self.root
+            .join("target")
+            .join(format!("{}.{}", self.manifest.project.name, BUNDLE_EXTENSION))
+    }
+
+    // where the programs of the project's files are kept between builds, see `ParseCache`
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join("target").join("parse-cache")
+    }
+
+    // scripts of `script-dirs` first (sorted, so builds are reproducible), then the entry
+    pub fn link(&self) -> Result<Program, ProjectError> {
+        Ok(self.load_scripts()?.program)
+    }
+
+    fn load_scripts(&self) -> Result<ModuleLoader, ProjectError> {
+        let cache = ParseCache::new(self.cache_dir());
+        let mut loader = ModuleLoader::with_cache(self.module_paths(), cache);
+        for dir in self.manifest.project.script_dirs.iter() {
+            for script in list_scripts(&self.root.join(dir))? {
+                loader.load_file(&script)?;
+            }
+        }
+        loader.load_file(&self.entry())?;
+
+        Ok(loader)
+    }
+
+    pub fn compile(&self, options: &CompileOptions) -> Result<Bytecode, ProjectError> {
+        Ok(self.compile_with_warnings(options)?.0)
+    }
+
+    // `compile`, and the warnings about every script it loaded, see `ModuleLoader::warnings`
+    pub fn compile_with_warnings(
+        &self,
+        options: &CompileOptions,
+    ) -> Result<(Bytecode, Vec<String>), ProjectError> {
+        package::check_fetched(self)?;
+        let loader = self.load_scripts()?;
+        let warnings = loader.warnings(options);
+        let mut compiler = Compiler::new_with_options(options.clone());
+        Ok((compiler.compile(&Node::Program(loader.program))?, warnings))
+    }
+
+    pub fn build(&self, options: &CompileOptions) -> Result<PathBuf, ProjectError> {
+        let bytecode = self.compile(options)?;
+        let out = self.bundle_path();
+        write_bundle(&out, &bytecode)?;
+        Ok(out)
+    }
+}
+
+// Resolves `import "name"` statements by splicing the imported file into the program the first
+// time it is seen, so every module is evaluated exactly once and ahead of its importer.
+pub struct ModuleLoader {
+    search_paths: Vec<PathBuf>,
+    loaded: HashSet<PathBuf>,
+    pub program: Program,
+    // the `let`s hiding a builtin, by file, see `compiler::lint`
+    shadowed_builtins: Vec<String>,
+    cache: Option<ParseCache>,
+}
+
+impl ModuleLoader {
+    pub fn new(search_paths: Vec<PathBuf>) -> Self {
+        ModuleLoader {
+            search_paths,
+            loaded: HashSet::new(),
+            program: Program::new(),
+            shadowed_builtins: vec![],
+            cache: None,
+        }
+    }
+
+    // a loader parsing each file only when `cache` hasn't got its program yet
+    pub fn with_cache(search_paths: Vec<PathBuf>, cache: ParseCache) -> Self {
+        ModuleLoader { cache: Some(cache), ..ModuleLoader::new(search_paths) }
+    }
+
+    // what's worth a look in the files loaded so far, those `options` allow left out
+    pub fn warnings(&self, options: &CompileOptions) -> Vec<String> {
+        if options.allow_shadowing {
+            return vec![];
+        }
+        self.shadowed_builtins.clone()
+    }
+
+    pub fn load_file(&mut self, path: &Path) -> Result<(), ProjectError> {
+        let path = match path.canonicalize() {
+            Ok(p) => p,
+            Err(e) => return Err(format!("can't open {}: {}", path.display(), e)),
+        };
+        if !self.loaded.insert(path.clone()) {
+            return Ok(());
+        }
+
+        let source = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("can't read {}: {}", path.display(), e)),
+        };
+        let parsed = match &self.cache {
+            Some(cache) => cache.parse(&source),
+            None => parse(&source).map(|node| match node {
+                Node::Program(p) => p,
+                _ => unreachable!("parse always returns a program"),
+            }),
+        };
+        let program = match parsed {
+            Ok(p) => p,
+            Err(errors) => return Err(format!("{}: {}", path.display(), render(&errors))),
+        };
+        for warning in lint::shadowed_builtins(&program) {
+            let warning = format!("{}: {}", path.display(), warning);
+            self.shadowed_builtins.push(warning);
+        }
+
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        for stmt in program.body {
+            match stmt {
+                Statement::Import(import) => {
+                    let module = self.resolve(&dir, &import.path)?;
+                    self.load_file(&module)?;
+                }
+                s => self.program.body.push(s),
+            }
+        }
+
+        Ok(())
+    }
+
+    // every file loaded so far, sorted
+    pub fn sources(&self) -> Vec<PathBuf> {
+        let mut sources: Vec<PathBuf> = self.loaded.iter().cloned().collect();
+        sources.sort();
+        sources
+    }
+
+    // `import "math"` looks for `math`, `math.monkey` or `math.mk` next to the file, then in each
+    // module path; a directory is its entry point
+    pub fn resolve(&self, from: &Path, name: &str) -> Result<PathBuf, ProjectError> {
+        let dirs = std::iter::once(from).chain(self.search_paths.iter().map(PathBuf::as_path));
+        for dir in dirs {
+            let candidate = dir.join(name);
+            if candidate.is_file() {
+                return Ok(candidate);
+            }
+            if candidate.is_dir() {
+                if let Some(entry) = package_entry(&candidate)? {
+                    return Ok(entry);
+                }
+            }
+            for ext in SOURCE_EXTENSIONS.iter() {
+                let candidate = dir.join(format!("{}.{}", name, ext));
+                if candidate.is_file() {
+                    return Ok(candidate);
+                }
+            }
+        }
+
+        Err(format!("module \"{}\" not found (imported from {})", name, from.display()))
+    }
+}
+
+// a package's `monkey.toml` entry, or `lib.monkey` / `main.monkey` for a bare directory of scripts
+pub fn package_entry(dir: &Path) -> Result<Option<PathBuf>, ProjectError> {
+    let manifest = dir.join(MANIFEST_FILE);
+    if manifest.is_file() {
+        return Ok(Some(dir.join(Manifest::read(&manifest)?.project.entry)));
+    }
+    Ok(["lib.monkey", "main.monkey"]
+        .iter()
+        .map(|f| dir.join(f))
+        .find(|p| p.is_file()))
+}
+
+pub fn list_scripts(dir: &Path) -> Result<Vec<PathBuf>, ProjectError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => return Err(format!("can't read script directory {}: {}", dir.display(), e)),
+    };
+    let mut scripts: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| is_source_file(p))
+        .collect();
+    scripts.sort();
+
+    Ok(scripts)
+}
+
+pub fn is_source_file(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => path.is_file() && SOURCE_EXTENSIONS.contains(&ext),
+        None => false,
+    }
+}
+
+pub fn write_bundle(out: &Path, bytecode: &Bytecode) -> Result<(), ProjectError> {
+    let bytes = bundle::encode(bytecode)?;
+    if let Some(dir) = out.parent() {
+        if let Err(e) = fs::create_dir_all(dir) {
+            return Err(format!("can't create {}: {}", dir.display(), e));
+        }
+    }
+    fs::write(out, bytes).map_err(|e| format!("can't write {}: {}", out.display(), e))
+}
+
+pub fn read_bundle(path: &Path) -> Result<Bytecode, ProjectError> {
+    match fs::read(path) {
+        Ok(bytes) => bundle::decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) => Err(format!("can't read {}: {}", path.display(), e)),
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use compiler::compiler::CompileOptions;
+use compiler::vm::VM;
+use object::Object;
+
+use crate::project::Project;
+
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("monkey-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn write(dir: &Path, file: &str, content: &str) {
+    let path = dir.join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+pub fn run(project: &Project) -> Object {
+    let mut vm = VM::new(project.compile(&CompileOptions::default()).unwrap());
+    vm.run().unwrap();
+    (*vm.last_popped_stack_elm().unwrap()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use compiler::compiler::CompileOptions;
+    use compiler::vm::VM;
+    use object::Object;
+
+    use super::{run, scratch_dir, write};
+    use crate::manifest::Manifest;
+    use crate::project::{read_bundle, Project};
+
+    #[test]
+    fn test_manifest_defaults() {
+        let manifest = Manifest::parse("[project]\nname = \"demo\"").unwrap();
+        assert_eq!(manifest.project.entry, "main.monkey");
+        assert!(manifest.project.module_paths.is_empty());
+        assert!(manifest.project.script_dirs.is_empty());
+
+        let err = Manifest::parse("[project]\nname = \"demo\"\nentri = \"a\"").unwrap_err();
+        assert!(err.starts_with("invalid monkey.toml"), "{}", err);
+    }
+
+    #[test]
+    fn test_build_with_imports() {
+        let dir = scratch_dir("imports");
+        write(
+            &dir,
+            "monkey.toml",
+            "[project]\nname = \"demo\"\nentry = \"src/main.monkey\"\nmodule-paths = [\"lib\"]",
+        );
+        write(&dir, "lib/math.monkey", "import \"base\"; let double = fn(x) { add(x, x) };");
+        write(&dir, "lib/base.mk", "let add = fn(a, b) { a + b };");
+        write(&dir, "src/main.monkey", "import \"math\";\nimport \"base\";\ndouble(add(1, 20))");
+
+        let project = Project::load(&dir).unwrap();
+        assert_eq!(run(&project), Object::Integer(42));
+
+        let bundle = project.build(&CompileOptions::default()).unwrap();
+        assert_eq!(bundle, dir.join("target").join("demo.mkc"));
+        let mut vm = VM::new(read_bundle(&bundle).unwrap());
+        vm.run().unwrap();
+        assert_eq!(*vm.last_popped_stack_elm().unwrap(), Object::Integer(42));
+
+        let mut vm = VM::new(project.compile(&CompileOptions::from_opt_level(2)).unwrap());
+        vm.run().unwrap();
+        assert_eq!(*vm.last_popped_stack_elm().unwrap(), Object::Integer(42));
+    }
+
+    #[test]
+    fn test_script_dirs_run_before_entry() {
+        let dir = scratch_dir("scripts");
+        write(&dir, "monkey.toml", "[project]\nname = \"demo\"\nscript-dirs = [\"deps\"]");
+        write(&dir, "deps/b.monkey", "let greeting = prefix + \"monkey\";");
+        write(&dir, "deps/a.monkey", "let prefix = \"hello \";");
+        write(&dir, "deps/notes.txt", "not a script");
+        write(&dir, "main.monkey", "greeting");
+
+        let project = Project::discover(&dir.join("deps")).unwrap();
+        assert_eq!(run(&project), Object::String("hello monkey".to_string()));
+    }
+
+    #[test]
+    fn test_missing_module() {
+        let dir = scratch_dir("missing");
+        write(&dir, "monkey.toml", "[project]\nname = \"demo\"");
+        write(&dir, "main.monkey", "import \"nope\";");
+
+        let project = Project::load(&dir).unwrap();
+        let err = project.compile(&CompileOptions::default()).err().unwrap();
+        assert!(err.starts_with("module \"nope\" not found"), "{}", err);
+    }
+
+    #[test]
+    fn test_shadowed_builtin_warnings() {
+        let dir = scratch_dir("shadowing");
+        write(&dir, "monkey.toml", "[project]\nname = \"demo\"");
+        write(&dir, "util.mk", "let first = fn(xs) { xs[0] };");
+        write(&dir, "main.monkey", "import \"util\";\nlet len = 2;\nfirst([len])");
+
+        let project = Project::load(&dir).unwrap();
+        let options = CompileOptions::default();
+        let (_, warnings) = project.compile_with_warnings(&options).unwrap();
+        let file = |name: &str| dir.join(name).canonicalize().unwrap().display().to_string();
+        let expected = vec![
+            format!(
+                "{}: `let len` at line 2, column 5 shadows the builtin len",
+                file("main.monkey")
+            ),
+            format!(
+                "{}: `let first` at line 1, column 5 shadows the builtin first",
+                file("util.mk")
+            ),
+        ];
+        assert_eq!(warnings, expected);
+        assert_eq!(run(&project), Object::Integer(2));
+
+        let options = CompileOptions { allow_shadowing: true, ..Default::default() };
+        let (_, warnings) = project.compile_with_warnings(&options).unwrap();
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::rc::Rc;
+
+use object::builtins::BuiltIns;
+use object::{CompiledFunction, Object};
+
+use crate::compiler::Bytecode;
+use crate::op_code::Opcode::*;
+use crate::op_code::{cast_u8_to_opcode, read_operands, DEFINITIONS};
+
+// unchanged lines shown around each change
+const CONTEXT: usize = 2;
+
+// a disassembled instruction or a constant, compared by `text` only, constants by value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub offset: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Same(Line),
+    Removed(Line),
+    Added(Line),
+}
+
+// the changes to the non-function constants, to `<main>` or to one function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub changes: Vec<Change>,
+}
+
+impl Section {
+    pub fn is_unchanged(&self) -> bool {
+        return self.changes.iter().all(|c| matches!(c, Change::Same(_)));
+    }
+}
+
+// How `b` differs from `a`. Functions are matched up by name, the n-th `fn@3` of `a` with the
+// n-th `fn@3` of `b`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BytecodeDiff {
+    pub sections: Vec<Section>,
+}
+
+impl BytecodeDiff {
+    pub fn is_empty(&self) -> bool {
+        return self.sections.iter().all(Section::is_unchanged);
+    }
+}
+
+pub fn diff(a: &Bytecode, b: &Bytecode) -> BytecodeDiff {
+    let mut sections = vec![Section {
+        name: "constants".to_string(),
+        changes: diff_lines(&constant_lines(a), &constant_lines(b)),
+    }];
+    sections.push(Section {
+        name: "<main>".to_string(),
+        changes: diff_lines(
+            &disassemble(&a.instructions.data, a),
+            &disassemble(&b.instructions.data, b),
+        ),
+    });
+
+    let (a_functions, b_functions) = (functions(a), functions(b));
+    let mut matched = vec![];
+    for (key, g) in b_functions.iter() {
+        let before = match a_functions.iter().find(|(k, _)| k == key) {
+            Some((_, f)) => {
+                matched.push(key.clone());
+                disassemble(&f.instructions, a)
+            }
+            None => vec![],
+        };
+        sections.push(Section {
+            name: format!("fn {}", key.0),
+            changes: diff_lines(&before, &disassemble(&g.instructions, b)),
+        });
+    }
+    for (key, f) in a_functions.iter().filter(|(k, _)| !matched.contains(k)) {
+        sections.push(Section {
+            name: format!("fn {}", key.0),
+            changes: diff_lines(&disassemble(&f.instructions, a), &[]),
+        });
+    }
+
+    return BytecodeDiff { sections };
+}
+
+// the compiled functions of the pool, keyed by name and by how many went by that name before
+fn functions(bytecode: &Bytecode) -> Vec<((String, usize), Rc<CompiledFunction>)> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let mut functions = vec![];
+    for c in bytecode.constants.iter() {
+        if let Object::CompiledFunction(f) = &**c {
+            let n = seen.entry(f.name.as_str()).or_insert(0);
+            functions.push(((f.name.clone(), *n), Rc::clone(f)));
+            *n += 1;
+        }
+    }
+    return functions;
+}
+
+fn constant_lines(bytecode: &Bytecode) -> Vec<Line> {
+    return bytecode
+        .constants
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !matches!(&***c, Object::CompiledFunction(_)))
+        .map(|(i, c)| Line { offset: i, text: describe(c) })
+        .collect();
+}
+
+fn describe(constant: &Object) -> String {
+    match constant {
+        Object::String(s) => return format!("{:?}", s),
+        Object::CompiledFunction(f) => return format!("fn {}", f.name),
+        c => return c.to_string(),
+    }
+}
+
+fn disassemble(ins: &[u8], bytecode: &Bytecode) -> Vec<Line> {
+    let mut lines = vec![];
+    let mut i = 0;
+    while i < ins.len() {
+        let opcode = cast_u8_to_opcode(ins[i]);
+        let definition = &DEFINITIONS[&opcode];
+        let (operands, read_size) = read_operands(definition, &ins[i + 1..]);
+        let operands = &operands[..definition.operand_width.len()];
+
+        let mut text = definition.name.to_string();
+        for operand in operands.iter() {
+            text.push_str(&format!(" {}", operand));
+        }
+        let referenced = match opcode {
+            OpConst | OpClosure | OpClosureRef => {
+                bytecode.constants.get(operands[0]).map(|c| describe(c))
+            }
+            OpGetBuiltin => BuiltIns.get(operands[0]).map(|b| b.0.to_string()),
+            _ => None,
+        };
+        if let Some(r) = referenced {
+            // the pool index is left out of the comparison, the constant itself is what counts
+            text = format!("{} ({})", definition.name, r);
+            for operand in operands[1..].iter() {
+                text.push_str(&format!(" {}", operand));
+            }
+        }
+
+        lines.push(Line { offset: i, text });
+        i += 1 + read_size;
+    }
+    return lines;
+}
+
+// the longest common subsequence of the two, as a list of changes
+fn diff_lines(a: &[Line], b: &[Line]) -> Vec<Change> {
+    // most of two versions of a program is the same, only what's between that is compared
+    let prefix = a
+        .iter()
+        .zip(b.iter())
+        .take_while(|(x, y)| x.text == y.text)
+        .count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x.text == y.text)
+        .count();
+    let (middle_a, middle_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut changes: Vec<Change> = b[..prefix].iter().cloned().map(Change::Same).collect();
+    changes.extend(diff_middle(middle_a, middle_b));
+    changes.extend(b[b.len() - suffix..].iter().cloned().map(Change::Same));
+    return changes;
+}
+
+fn diff_middle(a: &[Line], b: &[Line]) -> Vec<Change> {
+    // common[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i].text == b[j].text {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i].text == b[j].text {
+            changes.push(Change::Same(b[j].clone()));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(Change::Removed(a[i].clone()));
+            i += 1;
+        } else {
+            changes.push(Change::Added(b[j].clone()));
+            j += 1;
+        }
+    }
+    return changes;
+}
+
+// only the changed sections, each change with `CONTEXT` lines around it
+impl fmt::Display for BytecodeDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        for section in self.sections.iter().filter(|s| !s.is_unchanged()) {
+            writeln!(f, "{}", section.name)?;
+            let changed: Vec<usize> = section
+                .changes
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| !matches!(c, Change::Same(_)))
+                .map(|(i, _)| i)
+                .collect();
+            let mut last_shown = None;
+            for (i, change) in section.changes.iter().enumerate() {
+                let near = changed.iter().any(|&c| c.abs_diff(i) <= CONTEXT);
+                if !near {
+                    continue;
+                }
+                if last_shown.is_some_and(|l| l + 1 < i) {
+                    writeln!(f, "    ...")?;
+                }
+                last_shown = Some(i);
+                match change {
+                    Change::Same(l) => writeln!(f, "    {:04} {}", l.offset, l.text)?,
+                    Change::Removed(l) => writeln!(f, "  - {:04} {}", l.offset, l.text)?,
+                    Change::Added(l) => writeln!(f, "  + {:04} {}", l.offset, l.text)?,
+                }
+            }
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use crate::bcdiff::{diff, Change};
+    use crate::compile;
+
+    #[test]
+    fn test_identical() {
+        let a = compile("let add = fn(a, b) { a + b }; add(1, 2)").unwrap();
+        let d = diff(&a, &a);
+        assert!(d.is_empty());
+        assert_eq!(d.to_string(), "no differences\n");
+    }
+
+    #[test]
+    fn test_changed_constant() {
+        let a = compile("let a = 1;\nlet b = a + 2;\nputs(b);").unwrap();
+        let b = compile("let a = 1;\nlet b = a + 3;\nputs(b);").unwrap();
+        let expected = "constants
+    0000 1
+  - 0001 2
+  + 0001 3
+<main>
+    0003 OpSetGlobal 0
+    0006 OpGetGlobal 0
+  - 0009 OpConst (2)
+  + 0009 OpConst (3)
+    0012 OpAdd
+    0013 OpSetGlobal 1
+";
+        assert_eq!(diff(&a, &b).to_string(), expected);
+    }
+
+    #[test]
+    fn test_functions_matched_by_name() {
+        let a = compile("let f = fn(x) { x * 2 };\nlet g = fn() { 1 };\nf(g())").unwrap();
+        let b =
+            compile("let g = fn() { 1 };\nlet h = fn() { 2 };\nlet f = fn(x) { x + x };\nf(g())")
+                .unwrap();
+        let d = diff(&a, &b);
+        let section = |name: &str| d.sections.iter().find(|s| s.name == name).unwrap();
+
+        // the pool indexes of the functions changed, their code didn't
+        assert!(section("fn g").is_unchanged());
+        let f = &section("fn f").changes;
+        assert!(f
+            .iter()
+            .any(|c| matches!(c, Change::Removed(l) if l.text == "OpMul")));
+        assert!(f
+            .iter()
+            .any(|c| matches!(c, Change::Added(l) if l.text == "OpGetLocal 0")));
+        assert!(section("fn h")
+            .changes
+            .iter()
+            .all(|c| matches!(c, Change::Added(_))));
+        assert!(section("<main>")
+            .changes
+            .iter()
+            .any(|c| matches!(c, Change::Added(l) if l.text == "OpClosure (fn h) 0")));
+    }
+}
@@ -0,0 +1,26 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use compiler::compiler::Compiler;
+use compiler::vm::VM;
+use parser::parse;
+
+const FIB: &str = "
+let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+fib(20);
+";
+
+fn bench_fib(c: &mut Criterion) {
+    let program = parse(FIB).unwrap();
+    c.bench_function("compile fib", |b| b.iter(|| Compiler::new().compile(&program).unwrap()));
+
+    let bytecode = Compiler::new().compile(&program).unwrap();
+    c.bench_function("vm fib(20)", |b| {
+        b.iter(|| {
+            let mut vm = VM::new(bytecode.clone());
+            vm.run().unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, bench_fib);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use compiler::compiler::{Bytecode, CompileOptions, Compiler};
+use compiler::vm::VM;
+use parser::parse;
+
+// arithmetic helpers called from recursive drivers, the calls the inliner removes
+const HELPERS: &str = "
+let square = fn(x) { x * x };
+let add = fn(a, b) { a + b };
+let clamp = fn(x, lo, hi) { if (x < lo) { lo } else { if (x > hi) { hi } else { x } } };
+let step = fn(acc, i) { add(acc, clamp(square(i) - i, 0, 500)) };
+let sum = fn(i, acc) { if (i == 0) { acc } else { sum(i - 1, step(acc, i)) } };
+let repeat = fn(n) { if (n == 0) { 0 } else { sum(100, 0) + repeat(n - 1) } };
+repeat(20);
+";
+
+// compile-time evaluation is left off, it would fold the whole program into a constant
+fn compile(inline: bool) -> Bytecode {
+    let options = CompileOptions { inline, ..Default::default() };
+    let mut compiler = Compiler::new_with_options(options);
+    return compiler.compile(&parse(HELPERS).unwrap()).unwrap();
+}
+
+fn bench_inline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("helpers");
+    for (name, inline) in [("calls", false), ("inlined", true)] {
+        let bytecode = compile(inline);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut vm = VM::new(bytecode.clone());
+                vm.run().unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_inline);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use compiler::compiler::Compiler;
+use compiler::vm::VM;
+use parser::parse;
+
+// a 1MB string built from 1024 pieces of 1KB, copied each time by `+` and in place by `append`
+const BUILD: &str = "
+let piece = \"\";
+for (let i = 0; i < 1024; i = i + 1) { let piece = append(piece, 'x'); }
+let s = \"\";
+for (let i = 0; i < 1024; i = i + 1) { let s = JOIN; }
+len(s);
+";
+
+// a page of rows, each a template string joining seven pieces
+const TEMPLATE: &str = "
+let name = \"monkey\"; let kind = \"ape\"; let rows = 0;
+for (let i = 0; i < 2000; i = i + 1) {
+    let row = \"<tr><td>${name}</td><td>${kind}</td><td>${name}</td></tr>\";
+    let rows = rows + len(row);
+}
+rows;
+";
+
+fn bench_strings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build 1MB");
+    group.sample_size(10);
+    for (name, join) in [("plus", "s + piece"), ("append", "append(s, piece)")] {
+        let program = parse(&BUILD.replace("JOIN", join)).unwrap();
+        let bytecode = Compiler::new().compile(&program).unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut vm = VM::new(bytecode.clone());
+                vm.run().unwrap();
+            })
+        });
+    }
+    group.finish();
+
+    let bytecode = Compiler::new().compile(&parse(TEMPLATE).unwrap()).unwrap();
+    c.bench_function("template rows", |b| {
+        b.iter(|| {
+            let mut vm = VM::new(bytecode.clone());
+            vm.run().unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, bench_strings);
+criterion_main!(benches);
@@ -0,0 +1,513 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ptr;
+use std::rc::Rc;
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use object::builtins::BuiltIns;
+use object::memo::Memo;
+use object::partial::Partial;
+#[cfg(feature = "bigint")]
+use object::{integer, BigInt};
+use object::{Closure, CompiledFunction, Decimal, Object};
+
+use crate::compiler::Bytecode;
+use crate::op_code::{read_operands, Instructions, Opcode, DEFINITIONS};
+use crate::vm::GLOBAL_SIZE;
+
+// `.mkc` layout, all integers are big endian:
+//
+//   magic "MKC\0" | version u8 | globals u32 | constant count u32 | constants... |
+//   instructions len u32 | bytes
+//
+// every constant starts with a one byte tag, see `Tag`.
+pub const MAGIC: &[u8; 4] = b"MKC\0";
+pub const VERSION: u8 = 5;
+
+pub type BundleError = String;
+
+#[repr(u8)]
+enum Tag {
+    Integer = 0,
+    Boolean = 1,
+    String = 2,
+    Null = 3,
+    CompiledFunction = 4,
+    Array = 5,
+    Hash = 6,
+    // an integer out of `i64` range, as its two's complement bytes
+    BigInteger = 7,
+    // rust_decimal's 16 byte representation
+    Decimal = 8,
+    // values of a running program, never constants, only for handing them to another thread,
+    // see `encode_values`
+    Closure = 9,
+    // by its index in `BuiltIns`
+    Builtin = 10,
+    Channel = 11,
+    Error = 12,
+    Cell = 13,
+    // its function and limit, a copy remembers nothing yet
+    Memo = 14,
+    // its function and the arguments it binds
+    Partial = 15,
+    // its code point
+    Char = 16,
+}
+
+pub fn encode(bytecode: &Bytecode) -> Result<Vec<u8>, BundleError> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.write_u32::<BigEndian>(bytecode.num_globals as u32)
+        .unwrap();
+    out.write_u32::<BigEndian>(bytecode.constants.len() as u32)
+        .unwrap();
+    for constant in bytecode.constants.iter() {
+        encode_object(&mut out, constant)?;
+    }
+    write_bytes(&mut out, &bytecode.instructions.data);
+
+    return Ok(out);
+}
+
+// values alone, for another thread of the same program, see `parallel` and `concurrent`
+pub fn encode_values(values: Vec<Rc<Object>>) -> Result<Vec<u8>, BundleError> {
+    let instructions = Instructions { data: vec![] };
+    return encode(&Bytecode { instructions, constants: values, num_globals: 0 });
+}
+
+// the functions among the values index the constants and globals of the program they came from,
+// which only it knows, so only their instructions themselves are checked
+pub fn decode_values(data: &[u8]) -> Result<Vec<Rc<Object>>, BundleError> {
+    let bytecode = read(data)?;
+    let limits = Limits { constants: None, globals: None, locals: None, free: None };
+    for constant in bytecode.constants.iter() {
+        check_functions(constant, &limits)?;
+    }
+    return Ok(bytecode.constants);
+}
+
+// checked before anything runs it, operands, jump targets and indices, so a corrupt bundle is an
+// error rather than a crash; stack depth isn't checked
+pub fn decode(data: &[u8]) -> Result<Bytecode, BundleError> {
+    let bytecode = read(data)?;
+    let limits = Limits {
+        constants: Some(&bytecode.constants),
+        globals: Some(bytecode.num_globals),
+        locals: None,
+        free: None,
+    };
+    check_instructions(&bytecode.instructions.data, &limits)
+        .map_err(|e| format!("{} in the program", e))?;
+    for constant in bytecode.constants.iter() {
+        check_functions(constant, &limits)?;
+    }
+    return Ok(bytecode);
+}
+
+fn read(data: &[u8]) -> Result<Bytecode, BundleError> {
+    let mut reader = Reader { data, offset: 0 };
+    if reader.read(4)? != MAGIC {
+        return Err("not a monkey bundle: bad magic".to_string());
+    }
+    let version = reader.read_u8()?;
+    if version != VERSION {
+        return Err(format!("unsupported bundle version {}, expected {}", version, VERSION));
+    }
+
+    let num_globals = reader.read_u32()? as usize;
+    if num_globals > GLOBAL_SIZE {
+        return Err(format!("bundle has {} globals, at most {}", num_globals, GLOBAL_SIZE));
+    }
+    let count = reader.read_u32()? as usize;
+    let mut constants = Vec::with_capacity(count);
+    for _ in 0..count {
+        constants.push(Rc::new(reader.read_object()?));
+    }
+    let instructions = Instructions { data: reader.read_bytes()? };
+
+    if reader.offset != data.len() {
+        return Err(format!("{} trailing bytes after bundle", data.len() - reader.offset));
+    }
+
+    return Ok(Bytecode { instructions, constants, num_globals });
+}
+
+// what the instructions can index, when that's known
+#[derive(Clone, Copy)]
+struct Limits<'a> {
+    constants: Option<&'a [Rc<Object>]>,
+    globals: Option<usize>,
+    locals: Option<usize>,
+    free: Option<usize>,
+}
+
+// the instructions of each function in `obj`, however deep
+fn check_functions(obj: &Object, limits: &Limits) -> Result<(), BundleError> {
+    match obj {
+        // its free variables are checked where a closure is made of it
+        Object::CompiledFunction(f) => return check_function(f, limits, None),
+        Object::ClosureObj(closure) => {
+            check_function(&closure.func, limits, Some(closure.free.len()))?;
+            for free in closure.free.iter() {
+                check_functions(free, limits)?;
+            }
+        }
+        Object::Array(elements) => {
+            for e in elements.iter() {
+                check_functions(e, limits)?;
+            }
+        }
+        Object::Hash(map) => {
+            for v in map.values() {
+                check_functions(v, limits)?;
+            }
+        }
+        Object::Memo(memo) => check_functions(&memo.function, limits)?,
+        Object::Partial(partial) => {
+            check_functions(&partial.function, limits)?;
+            for argument in partial.arguments.iter() {
+                check_functions(argument, limits)?;
+            }
+        }
+        _ => {}
+    }
+    return Ok(());
+}
+
+fn check_function(
+    f: &CompiledFunction,
+    limits: &Limits,
+    free: Option<usize>,
+) -> Result<(), BundleError> {
+    let limits = Limits { locals: Some(f.num_locals), free, ..*limits };
+    return check_instructions(&f.instructions, &limits)
+        .map_err(|e| format!("{} in function `{}`", e, f.name));
+}
+
+fn check_instructions(ins: &[u8], limits: &Limits) -> Result<(), BundleError> {
+    // where each instruction starts, and the end, which a jump can land on too
+    let mut starts = vec![false; ins.len() + 1];
+    let mut jumps = vec![];
+    let mut i = 0;
+    while i < ins.len() {
+        let opcode = Opcode::try_from(ins[i]).map_err(|e| format!("{} at byte {}", e, i))?;
+        let definition = &DEFINITIONS[&opcode];
+        let width: usize = definition.operand_width.iter().sum();
+        if i + 1 + width > ins.len() {
+            return Err(format!("{} at byte {} is cut short", definition.name, i));
+        }
+        let (operands, _) = read_operands(definition, &ins[i + 1..]);
+        let (kind, limit) = match opcode {
+            Opcode::OpJump | Opcode::OpJumpNotTruthy => {
+                jumps.push((i, operands[0]));
+                ("", None)
+            }
+            Opcode::OpConst | Opcode::OpClosure | Opcode::OpClosureRef => {
+                ("constant", limits.constants.map(|constants| constants.len()))
+            }
+            Opcode::OpGetGlobal | Opcode::OpSetGlobal | Opcode::OpTakeGlobal => {
+                ("global", limits.globals)
+            }
+            Opcode::OpGetLocal | Opcode::OpSetLocal | Opcode::OpTakeLocal => {
+                ("local", limits.locals)
+            }
+            Opcode::OpGetFree => ("free variable", limits.free),
+            Opcode::OpGetBuiltin => ("builtin", Some(BuiltIns.len())),
+            _ => ("", None),
+        };
+        let name = definition.name;
+        if let Some(limit) = limit.filter(|limit| operands[0] >= *limit) {
+            return Err(format!(
+                "{} at byte {} reads {} {} of {}",
+                name, i, kind, operands[0], limit
+            ));
+        }
+        // a closure made by reference reads its outer frame, it's given no free variables
+        let given = match opcode {
+            Opcode::OpClosure => Some(operands[1]),
+            Opcode::OpClosureRef => Some(0),
+            _ => None,
+        };
+        if let (Some(given), Some(constants)) = (given, limits.constants) {
+            if let Object::CompiledFunction(f) = &*constants[operands[0]] {
+                let needed = free_needed(&f.instructions);
+                if needed > given {
+                    return Err(format!(
+                        "{} at byte {} gives `{}` {} free variables, it reads {}",
+                        name, i, f.name, given, needed
+                    ));
+                }
+            }
+        }
+        starts[i] = true;
+        i += 1 + width;
+    }
+    starts[ins.len()] = true;
+    for (at, target) in jumps {
+        if !starts.get(target).copied().unwrap_or(false) {
+            return Err(format!("jump at byte {} to {}, not an instruction", at, target));
+        }
+    }
+    return Ok(());
+}
+
+// one more than the highest free variable the instructions read, they're checked themselves
+// elsewhere, so this only stops where they stop making sense
+fn free_needed(ins: &[u8]) -> usize {
+    let mut needed = 0;
+    let mut i = 0;
+    while let Some(opcode) = ins.get(i).and_then(|b| Opcode::try_from(*b).ok()) {
+        if let (Opcode::OpGetFree, Some(index)) = (opcode, ins.get(i + 1)) {
+            needed = needed.max(*index as usize + 1);
+        }
+        i += 1 + DEFINITIONS[&opcode].operand_width.iter().sum::<usize>();
+    }
+    return needed;
+}
+
+fn encode_object(out: &mut Vec<u8>, obj: &Object) -> Result<(), BundleError> {
+    match obj {
+        Object::Integer(i) => {
+            out.push(Tag::Integer as u8);
+            out.write_i64::<BigEndian>(*i).unwrap();
+        }
+        #[cfg(feature = "bigint")]
+        Object::BigInteger(i) => {
+            out.push(Tag::BigInteger as u8);
+            write_bytes(out, &i.to_signed_bytes_be());
+        }
+        Object::Decimal(d) => {
+            out.push(Tag::Decimal as u8);
+            out.extend_from_slice(&d.serialize());
+        }
+        Object::Boolean(b) => {
+            out.push(Tag::Boolean as u8);
+            out.push(*b as u8);
+        }
+        Object::String(s) => {
+            out.push(Tag::String as u8);
+            write_bytes(out, s.as_bytes());
+        }
+        Object::Null => out.push(Tag::Null as u8),
+        Object::CompiledFunction(f) => {
+            out.push(Tag::CompiledFunction as u8);
+            out.write_u32::<BigEndian>(f.num_locals as u32).unwrap();
+            out.write_u32::<BigEndian>(f.num_parameters as u32).unwrap();
+            out.push(f.rest as u8);
+            write_bytes(out, &f.instructions);
+            write_bytes(out, f.name.as_bytes());
+        }
+        // only produced by compile-time evaluation
+        Object::Array(elements) => {
+            out.push(Tag::Array as u8);
+            out.write_u32::<BigEndian>(elements.len() as u32).unwrap();
+            for e in elements.iter() {
+                encode_object(out, e)?;
+            }
+        }
+        Object::Hash(map) => {
+            out.push(Tag::Hash as u8);
+            out.write_u32::<BigEndian>(map.len() as u32).unwrap();
+            for (k, v) in map.iter() {
+                encode_object(out, k)?;
+                encode_object(out, v)?;
+            }
+        }
+        // one reading the frame that made it only runs while that frame does
+        Object::ClosureObj(closure) if closure.frame.is_none() => {
+            out.push(Tag::Closure as u8);
+            encode_object(out, &Object::CompiledFunction(Rc::clone(&closure.func)))?;
+            out.write_u32::<BigEndian>(closure.free.len() as u32)
+                .unwrap();
+            for free in closure.free.iter() {
+                encode_object(out, free)?;
+            }
+        }
+        Object::Builtin(builtin) => {
+            let index = BuiltIns
+                .iter()
+                .position(|(_, b)| ptr::fn_addr_eq(*b, *builtin));
+            out.push(Tag::Builtin as u8);
+            out.push(index.expect("builtins are in `BuiltIns`") as u8);
+        }
+        Object::Char(c) => {
+            out.push(Tag::Char as u8);
+            out.write_u32::<BigEndian>(*c as u32).unwrap();
+        }
+        Object::Channel(id) => {
+            out.push(Tag::Channel as u8);
+            out.write_u64::<BigEndian>(*id).unwrap();
+        }
+        Object::Error(e) => {
+            out.push(Tag::Error as u8);
+            write_bytes(out, e.as_bytes());
+        }
+        Object::Cell(name) => {
+            out.push(Tag::Cell as u8);
+            write_bytes(out, name.as_bytes());
+        }
+        Object::Memo(memo) => {
+            out.push(Tag::Memo as u8);
+            encode_object(out, &memo.function)?;
+            out.write_u32::<BigEndian>(memo.limit.min(u32::MAX as usize) as u32)
+                .unwrap();
+        }
+        Object::Partial(partial) => {
+            out.push(Tag::Partial as u8);
+            encode_object(out, &partial.function)?;
+            out.write_u32::<BigEndian>(partial.arguments.len() as u32)
+                .unwrap();
+            for argument in partial.arguments.iter() {
+                encode_object(out, argument)?;
+            }
+        }
+        o => return Err(format!("can't encode constant {} into a bundle", o)),
+    }
+
+    return Ok(());
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.write_u32::<BigEndian>(bytes.len() as u32).unwrap();
+    out.extend_from_slice(bytes);
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, n: usize) -> Result<&'a [u8], BundleError> {
+        if self.offset + n > self.data.len() {
+            return Err(format!("unexpected end of bundle at byte {}", self.offset));
+        }
+        let bytes = &self.data[self.offset..self.offset + n];
+        self.offset += n;
+        return Ok(bytes);
+    }
+
+    fn read_u8(&mut self) -> Result<u8, BundleError> {
+        return Ok(self.read(1)?[0]);
+    }
+
+    fn read_u32(&mut self) -> Result<u32, BundleError> {
+        return Ok(BigEndian::read_u32(self.read(4)?));
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>, BundleError> {
+        let len = self.read_u32()? as usize;
+        return Ok(self.read(len)?.to_vec());
+    }
+
+    fn read_object(&mut self) -> Result<Object, BundleError> {
+        let tag = self.read_u8()?;
+        let obj = match tag {
+            t if t == Tag::Integer as u8 => Object::Integer(BigEndian::read_i64(self.read(8)?)),
+            t if t == Tag::Boolean as u8 => Object::Boolean(self.read_u8()? != 0),
+            t if t == Tag::Decimal as u8 => {
+                let mut bytes = [0; 16];
+                bytes.copy_from_slice(self.read(16)?);
+                Object::Decimal(Decimal::deserialize(bytes))
+            }
+            #[cfg(feature = "bigint")]
+            t if t == Tag::BigInteger as u8 => {
+                integer::normalize(BigInt::from_signed_bytes_be(&self.read_bytes()?))
+            }
+            #[cfg(not(feature = "bigint"))]
+            t if t == Tag::BigInteger as u8 => {
+                return Err("big integer constant, rebuild with the bigint feature".to_string());
+            }
+            t if t == Tag::String as u8 => match String::from_utf8(self.read_bytes()?) {
+                Ok(s) => Object::String(s),
+                Err(e) => return Err(format!("invalid utf-8 string constant: {}", e)),
+            },
+            t if t == Tag::Null as u8 => Object::Null,
+            t if t == Tag::CompiledFunction as u8 => {
+                let num_locals = self.read_u32()? as usize;
+                let num_parameters = self.read_u32()? as usize;
+                let rest = self.read_u8()? != 0;
+                let instructions = self.read_bytes()?;
+                let name = match String::from_utf8(self.read_bytes()?) {
+                    Ok(name) => name,
+                    Err(e) => return Err(format!("invalid utf-8 function name: {}", e)),
+                };
+                Object::CompiledFunction(Rc::new(CompiledFunction {
+                    instructions,
+                    num_locals,
+                    num_parameters,
+                    rest,
+                    name,
+                }))
+            }
+            t if t == Tag::Array as u8 => {
+                let len = self.read_u32()? as usize;
+                let mut elements = Vec::with_capacity(len);
+                for _ in 0..len {
+                    elements.push(Rc::new(self.read_object()?));
+                }
+                Object::Array(elements.into())
+            }
+            t if t == Tag::Hash as u8 => {
+                let len = self.read_u32()? as usize;
+                let mut map = HashMap::with_capacity(len);
+                for _ in 0..len {
+                    let key = self.read_object()?;
+                    if !key.is_hashable() {
+                        return Err(format!("unhashable key {} in hash constant", key));
+                    }
+                    map.insert(Rc::new(key), Rc::new(self.read_object()?));
+                }
+                Object::Hash(map)
+            }
+            t if t == Tag::Closure as u8 => {
+                let func = match self.read_object()? {
+                    Object::CompiledFunction(func) => func,
+                    o => return Err(format!("closure of {}, not a function", o)),
+                };
+                let len = self.read_u32()? as usize;
+                let mut free = Vec::with_capacity(len);
+                for _ in 0..len {
+                    free.push(Rc::new(self.read_object()?));
+                }
+                Object::ClosureObj(Closure { func, free, frame: None })
+            }
+            t if t == Tag::Builtin as u8 => match BuiltIns.get(self.read_u8()? as usize) {
+                Some((_, builtin)) => Object::Builtin(*builtin),
+                None => return Err(format!("unknown builtin at byte {}", self.offset - 1)),
+            },
+            t if t == Tag::Channel as u8 => Object::Channel(BigEndian::read_u64(self.read(8)?)),
+            t if t == Tag::Char as u8 => match char::from_u32(self.read_u32()?) {
+                Some(c) => Object::Char(c),
+                None => return Err(format!("invalid char at byte {}", self.offset - 4)),
+            },
+            t if t == Tag::Error as u8 => match String::from_utf8(self.read_bytes()?) {
+                Ok(e) => Object::Error(e),
+                Err(e) => return Err(format!("invalid utf-8 error: {}", e)),
+            },
+            t if t == Tag::Cell as u8 => match String::from_utf8(self.read_bytes()?) {
+                Ok(name) => Object::Cell(name),
+                Err(e) => return Err(format!("invalid utf-8 cell name: {}", e)),
+            },
+            t if t == Tag::Memo as u8 => {
+                let function = Rc::new(self.read_object()?);
+                let limit = self.read_u32()? as usize;
+                Object::Memo(Rc::new(Memo::new(function, limit)))
+            }
+            t if t == Tag::Partial as u8 => {
+                let function = Rc::new(self.read_object()?);
+                let len = self.read_u32()? as usize;
+                let mut arguments = Vec::with_capacity(len);
+                for _ in 0..len {
+                    arguments.push(Rc::new(self.read_object()?));
+                }
+                Object::Partial(Rc::new(Partial { function, arguments }))
+            }
+            t => return Err(format!("unknown constant tag {} at byte {}", t, self.offset - 1)),
+        };
+
+        return Ok(obj);
+    }
+}
@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::bundle::{decode, decode_values, encode, encode_values};
+    use crate::compiler::{Bytecode, Compiler};
+    use crate::op_code::Opcode::*;
+    use crate::op_code::{make_instructions, Instructions};
+    use crate::vm::VM;
+    use object::{Closure, CompiledFunction, Object};
+    use parser::parse;
+
+    fn round_trip(input: &str) -> Object {
+        let program = parse(input).unwrap();
+        let mut compiler = Compiler::new();
+        let bytecode = compiler.compile(&program).unwrap();
+        let bytes = encode(&bytecode).unwrap();
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.instructions, bytecode.instructions);
+        assert_eq!(decoded.constants, bytecode.constants);
+        assert_eq!(decoded.num_globals, bytecode.num_globals);
+
+        let mut vm = VM::new(decoded);
+        vm.run().unwrap();
+        return (*vm.last_popped_stack_elm().unwrap()).clone();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(round_trip("1 + 2"), Object::Integer(3));
+        assert_eq!(round_trip(r#""mon" + "key""#), Object::String("monkey".to_string()));
+        assert_eq!(round_trip("1.10d * 3").to_string(), "3.30");
+        assert_eq!(
+            round_trip("let add = fn(a, b) { a + b }; let twice = fn(x) { add(x, x) }; twice(21)"),
+            Object::Integer(42)
+        );
+        assert_eq!(
+            round_trip("let count = fn(a, ...xs) { len(xs) }; count(1, 2, 3)"),
+            Object::Integer(2)
+        );
+    }
+
+    #[test]
+    fn test_values() {
+        let input = "let k = 2; let f = fn(x) { fn(y) { x * y } }; [f(k), len, partial(div, _, 2)]";
+        let bytecode = crate::compile(input).unwrap();
+        let mut vm = VM::new(bytecode);
+        vm.run().unwrap();
+        let values = vec![
+            vm.last_popped_stack_elm().unwrap(),
+            Rc::new(Object::Channel(7)),
+            Rc::new(Object::Error("division by zero".to_string())),
+        ];
+        let copies = decode_values(&encode_values(values.clone()).unwrap()).unwrap();
+        assert_eq!(copies[1..], values[1..]);
+        assert_eq!(copies[0].to_string(), values[0].to_string());
+        let mut vm = VM::new(crate::compile("1").unwrap());
+        let (f, len, half) = match &*copies[0] {
+            Object::Array(all) => (Rc::clone(&all[0]), Rc::clone(&all[1]), Rc::clone(&all[2])),
+            o => panic!("expected an array, got {}", o),
+        };
+        assert_eq!(*vm.call(f, &[Rc::new(Object::Integer(21))]).unwrap(), Object::Integer(42));
+        let three = Rc::new(Object::String("abc".to_string()));
+        assert_eq!(*vm.call(len, &[three]).unwrap(), Object::Integer(3));
+        assert_eq!(*vm.call(half, &[Rc::new(Object::Integer(9))]).unwrap(), Object::Integer(4));
+        let iterator = crate::compile("range(3)").unwrap();
+        let mut vm = VM::new(iterator);
+        vm.run().unwrap();
+        assert!(encode_values(vec![vm.last_popped_stack_elm().unwrap()]).is_err());
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_big_integer_constant() {
+        let options = crate::compiler::CompileOptions::from_opt_level(1);
+        let bytecode = crate::compile_with_options("2 * 9223372036854775807", options).unwrap();
+        let decoded = decode(&encode(&bytecode).unwrap()).unwrap();
+        assert_eq!(decoded.constants, bytecode.constants);
+        assert_eq!(decoded.constants[0].to_string(), "18446744073709551614");
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let bytecode = Compiler::new()
+            .compile(&parse("let a = 1; a").unwrap())
+            .unwrap();
+        let bytes = encode(&bytecode).unwrap();
+
+        assert_eq!(decode(b"ELF\0").unwrap_err(), "not a monkey bundle: bad magic");
+        assert!(decode(&bytes[..bytes.len() - 1])
+            .unwrap_err()
+            .starts_with("unexpected end of bundle"));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode(&trailing).unwrap_err(), "1 trailing bytes after bundle");
+    }
+
+    fn with_instructions(data: Vec<u8>, constants: Vec<Rc<Object>>) -> Vec<u8> {
+        let bytecode = Bytecode { instructions: Instructions { data }, constants, num_globals: 1 };
+        return encode(&bytecode).unwrap();
+    }
+
+    #[test]
+    fn test_decode_checks_instructions() {
+        let cases = [
+            (vec![0xfa], "unknown opcode 0xfa at byte 0 in the program"),
+            (vec![OpPop as u8, OpConst as u8, 0], "OpConst at byte 1 is cut short in the program"),
+            (
+                make_instructions(OpConst, &[1]).data,
+                "OpConst at byte 0 reads constant 1 of 1 in the program",
+            ),
+            (
+                make_instructions(OpSetGlobal, &[3]).data,
+                "OpSetGlobal at byte 0 reads global 3 of 1 in the program",
+            ),
+            (
+                make_instructions(OpJump, &[2]).data,
+                "jump at byte 0 to 2, not an instruction in the program",
+            ),
+        ];
+        for (data, error) in cases {
+            let bytes = with_instructions(data, vec![Rc::new(Object::Integer(1))]);
+            assert_eq!(decode(&bytes).unwrap_err(), error);
+        }
+        // a jump to the end is the end of the program
+        let bytes = with_instructions(make_instructions(OpJump, &[3]).data, vec![]);
+        assert!(decode(&bytes).is_ok());
+
+        // and those of functions, where they're sent between threads too
+        let f = CompiledFunction {
+            instructions: vec![OpNull as u8, 0xff],
+            num_locals: 0,
+            num_parameters: 0,
+            rest: false,
+            name: "f".to_string(),
+        };
+        let bytes = with_instructions(vec![], vec![Rc::new(Object::CompiledFunction(Rc::new(f)))]);
+        assert_eq!(decode(&bytes).unwrap_err(), "unknown opcode 0xff at byte 1 in function `f`");
+        assert_eq!(
+            decode_values(&bytes).unwrap_err(),
+            "unknown opcode 0xff at byte 1 in function `f`"
+        );
+    }
+
+    #[test]
+    fn test_decode_checks_locals_and_free() {
+        let function = |instructions: Vec<u8>| {
+            let f = CompiledFunction {
+                instructions,
+                num_locals: 1,
+                num_parameters: 0,
+                rest: false,
+                name: "f".to_string(),
+            };
+            return Rc::new(Object::CompiledFunction(Rc::new(f)));
+        };
+        let get_local = make_instructions(OpGetLocal, &[1]).data;
+        let bytes = with_instructions(vec![], vec![function(get_local)]);
+        assert_eq!(
+            decode(&bytes).unwrap_err(),
+            "OpGetLocal at byte 0 reads local 1 of 1 in function `f`"
+        );
+
+        let get_free = make_instructions(OpGetFree, &[1]).data;
+        let cases = [
+            (make_instructions(OpClosure, &[0, 2]).data, None),
+            (
+                make_instructions(OpClosure, &[0, 1]).data,
+                Some("OpClosure at byte 0 gives `f` 1 free variables, it reads 2 in the program"),
+            ),
+            (
+                make_instructions(OpClosureRef, &[0]).data,
+                Some(
+                    "OpClosureRef at byte 0 gives `f` 0 free variables, it reads 2 in the program",
+                ),
+            ),
+        ];
+        for (data, error) in cases {
+            let bytes = with_instructions(data, vec![function(get_free.clone())]);
+            assert_eq!(decode(&bytes).err().as_deref(), error);
+        }
+
+        // a closure sent between threads has its free variables with it
+        let Object::CompiledFunction(f) = &*function(get_free) else {
+            unreachable!()
+        };
+        let closure = Closure { func: Rc::clone(f), free: vec![], frame: None };
+        let bytes = encode_values(vec![Rc::new(Object::ClosureObj(closure))]).unwrap();
+        assert_eq!(
+            decode_values(&bytes).unwrap_err(),
+            "OpGetFree at byte 0 reads free variable 1 of 0 in function `f`"
+        );
+    }
+}
@@ -1,18 +1,46 @@
 use object::builtins::BuiltIns;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
+use object::suggest;
 use object::Object;
-use parser::ast::{BlockStatement, Expression, Literal, Node, Statement};
-use parser::lexer::token::TokenKind;
+use parser::ast::{
+    Assign, BlockStatement, Expression, FunctionCall, FunctionDeclaration, Literal, Match, Node,
+    Pattern, Statement, While, IDENTIFIER,
+};
+use parser::lexer::token::{Span, Token, TokenKind};
 
+use crate::const_eval::{self, ConstEvaluator};
+use crate::cse::{self, Candidate, CseState};
+use crate::escape::EscapeInfo;
+use crate::inline::InlineFunction;
+use crate::licm::Invariants;
 use crate::op_code::Opcode::*;
 use crate::op_code::{cast_u8_to_opcode, make_instructions, Instructions, Opcode};
 use crate::symbol_table::{Symbol, SymbolScope, SymbolTable};
+use crate::vm::GLOBAL_SIZE;
 
 struct CompilationScope {
     instructions: Instructions,
     last_instruction: EmittedInstruction,
     previous_instruction: EmittedInstruction,
+    // compiling a non-escaping closure, free variables are read from the creating frame
+    by_ref: bool,
+    // it captures something that isn't a local of the creating frame, compile it again normally
+    by_ref_failed: bool,
+    cse: Option<CseState>,
+    // locals bound to an integer expression, by index
+    integer_locals: HashSet<usize>,
+    // the loops around the code being compiled, innermost last
+    loops: Vec<LoopContext>,
+    // invariants of those loops computed before them, by key, see `licm`
+    hoisted: HashMap<String, Rc<Symbol>>,
+}
+
+struct LoopContext {
+    label: Option<String>,
+    // the `OpJump`s of the `break`s out of the loop, pointed past it once it's compiled
+    breaks: Vec<usize>,
 }
 
 pub struct Compiler {
@@ -20,11 +48,71 @@ pub struct Compiler {
     pub symbol_table: SymbolTable,
     scopes: Vec<CompilationScope>,
     scope_index: usize,
+    const_eval: Option<ConstEvaluator>,
+    cse: bool,
+    licm: bool,
+    inline: bool,
+    // top-level functions inlined at their call sites, by global index
+    inline_functions: HashMap<usize, InlineFunction>,
+    // while compiling an inlined body, where its parameters were stored
+    inline_params: Vec<HashMap<String, Rc<Symbol>>>,
+    analyze_escapes: bool,
+    escape_info: Option<EscapeInfo>,
+    // byte offsets where each source line starts, to name anonymous functions `fn@<line>`
+    line_starts: Vec<usize>,
+    int_ops: bool,
+    // globals bound to an integer expression or constant, by index
+    integer_globals: HashSet<usize>,
+    // the `match`es around the code being compiled, each keeps its subject in a slot of its own
+    matches: usize,
+    // names some `name = value` of the program sets, a function may change them whenever it's
+    // called, so nothing is assumed about their values
+    assigned: HashSet<String>,
+    // the jumps of top-level `return`s, to the end of the program, which they end
+    returns: Vec<usize>,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    // evaluate pure top-level expressions, calls to pure functions on literals included, and
+    // calls to pure builtins on literals anywhere while compiling and store their results in the
+    // constant pool
+    pub const_eval: bool,
+    // compute repeated pure expressions once per function body and reuse the stored value
+    pub cse: bool,
+    // compute pure expressions a `while` can't change once before it, see `licm`
+    pub licm: bool,
+    // compile calls to small pure top-level functions as their bodies, see `inline::MAX_INLINE_SIZE`
+    pub inline: bool,
+    // emit `OpAddInt` and friends when both operands are known to be integers: literals, results
+    // of integer arithmetic and names bound to them
+    pub int_ops: bool,
+    // don't warn about a `let` hiding a builtin, see `lint::shadowed_builtins`
+    pub allow_shadowing: bool,
+}
+
+impl CompileOptions {
+    // `-O0` compiles every expression as written, `-O1` adds compile-time evaluation and integer
+    // opcodes and `-O2` common subexpression elimination, loop-invariant code motion and inlining
+    // on top
+    pub fn from_opt_level(level: u8) -> CompileOptions {
+        return CompileOptions {
+            const_eval: level >= 1,
+            cse: level >= 2,
+            licm: level >= 2,
+            inline: level >= 2,
+            int_ops: level >= 1,
+            allow_shadowing: false,
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Bytecode {
     pub instructions: Instructions,
     pub constants: Vec<Rc<Object>>,
+    // how many globals the vm makes room for, every global index is below it
+    pub num_globals: usize,
 }
 
 #[derive(Clone)]
@@ -33,7 +121,7 @@ pub struct EmittedInstruction {
     pub position: usize,
 }
 
-type CompileError = String;
+pub type CompileError = String;
 
 impl Compiler {
     pub fn new() -> Compiler {
@@ -41,6 +129,12 @@ impl Compiler {
             instructions: Instructions { data: vec![] },
             last_instruction: EmittedInstruction { opcode: OpNull, position: 0 },
             previous_instruction: EmittedInstruction { opcode: OpNull, position: 0 },
+            by_ref: false,
+            by_ref_failed: false,
+            cse: None,
+            integer_locals: HashSet::new(),
+            loops: vec![],
+            hoisted: HashMap::new(),
         };
 
         let mut symbol_table = SymbolTable::new();
@@ -53,21 +147,94 @@ impl Compiler {
             symbol_table,
             scopes: vec![main_scope],
             scope_index: 0,
+            const_eval: None,
+            cse: false,
+            licm: false,
+            inline: false,
+            inline_functions: HashMap::new(),
+            inline_params: vec![],
+            analyze_escapes: true,
+            line_starts: vec![],
+            escape_info: None,
+            int_ops: false,
+            integer_globals: HashSet::new(),
+            matches: 0,
+            assigned: HashSet::new(),
+            returns: vec![],
         };
     }
 
+    pub fn new_with_options(options: CompileOptions) -> Compiler {
+        let mut compiler = Compiler::new();
+        if options.const_eval {
+            compiler.const_eval = Some(ConstEvaluator::new());
+        }
+        compiler.cse = options.cse;
+        compiler.licm = options.licm;
+        compiler.inline = options.inline;
+        compiler.int_ops = options.int_ops;
+        return compiler;
+    }
+
     pub fn new_with_state(symbol_table: SymbolTable, constants: Vec<Rc<Object>>) -> Compiler {
         let mut compiler = Compiler::new();
         compiler.constants = constants;
         compiler.symbol_table = symbol_table;
+        // globals of earlier inputs are unknown to the escape analysis
+        compiler.analyze_escapes = false;
         return compiler;
     }
 
+    // the source the compiled nodes were parsed from, without it anonymous functions are just `fn`
+    pub fn set_source(&mut self, source: &str) {
+        self.line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+    }
+
+    fn function_name(&self, f: &FunctionDeclaration) -> String {
+        if !f.name.is_empty() {
+            return f.name.clone();
+        }
+        match self.line_of(f.span.start) {
+            Some(line) => return format!("fn@{}", line),
+            None => return "fn".to_string(),
+        }
+    }
+
+    // 1-based line of a byte offset into the source, when it's known
+    fn line_of(&self, offset: usize) -> Option<usize> {
+        if self.line_starts.is_empty() {
+            return None;
+        }
+        return Some(self.line_starts.partition_point(|&start| start <= offset));
+    }
+
+    // a construct the parser accepts but the compiler has no bytecode for yet
+    fn not_supported(&self, construct: String, span: &Span) -> CompileError {
+        match self.line_of(span.start) {
+            Some(line) => return format!("not yet supported: {} at line {}", construct, line),
+            None => {
+                return format!("not yet supported: {} at {}..{}", construct, span.start, span.end)
+            }
+        }
+    }
+
     pub fn compile(&mut self, node: &Node) -> Result<Bytecode, CompileError> {
         match node {
             Node::Program(p) => {
+                self.assigned = p.assigned_names().into_iter().map(String::from).collect();
+                if self.analyze_escapes {
+                    self.escape_info = Some(EscapeInfo::analyze(p));
+                }
+                if self.cse {
+                    self.scopes[self.scope_index].cse = Some(CseState::new(&p.body));
+                }
                 for stmt in &p.body {
-                    self.compile_stmt(stmt)?;
+                    if !self.compile_const_stmt(stmt)? {
+                        self.compile_stmt(stmt)?;
+                    }
+                    self.record_inline_function(stmt);
                 }
             }
             Node::Statement(s) => {
@@ -77,47 +244,224 @@ impl Compiler {
                 self.compile_expr(e)?;
             }
         }
+        let end = self.current_instruction().data.len();
+        for jump in std::mem::take(&mut self.returns) {
+            self.change_operand(jump, end);
+        }
 
+        if self.symbol_table.num_definitions > GLOBAL_SIZE {
+            return Err(format!(
+                "too many globals: {}, at most {}",
+                self.symbol_table.num_definitions, GLOBAL_SIZE
+            ));
+        }
         return Ok(self.bytecode());
     }
 
     fn compile_stmt(&mut self, s: &Statement) -> Result<(), CompileError> {
         match s {
-            Statement::Let(let_statement) => {
-                let symbol = self
-                    .symbol_table
-                    .define(let_statement.identifier.kind.to_string());
+            // the value popped isn't the statement's, which is null as for any `let`
+            Statement::Let(let_statement) if let_statement.is_throwaway() => {
                 self.compile_expr(&let_statement.expr)?;
+                self.emit(OpPop, &[]);
+                self.emit(OpNull, &[]);
+                self.emit(OpPop, &[]);
+                return Ok(());
+            }
+            Statement::Let(let_statement) => {
+                let integer = self.int_ops && self.is_integer_expr(&let_statement.expr);
+                // outside a loop the value reads the name's old binding, so `let a = push(a, 1)`
+                // works, unless it's a function, or a call given one, which may call itself by it
+                let in_loop = !self.scopes[self.scope_index].loops.is_empty();
+                let name = let_statement.identifier.kind.to_string();
+                let wraps_function = match &let_statement.expr {
+                    Expression::FunctionCall(call) if self.scope_index == 0 => {
+                        let function = |a: &Expression| matches!(a, Expression::FUNCTION(_));
+                        self.symbol_table.lookup(&name).is_none()
+                            && call.arguments.iter().any(function)
+                    }
+                    _ => false,
+                };
+                let symbol = match (in_loop, &let_statement.expr) {
+                    (false, e) if !matches!(e, Expression::FUNCTION(_)) && !wraps_function => None,
+                    _ => Some(self.bind_let(&let_statement.identifier, integer)),
+                };
+                let handed_over = match (&symbol, let_statement.handover()) {
+                    (Some(symbol), Some(call)) if in_loop => self.compile_handover(call, symbol)?,
+                    _ => false,
+                };
+                if !handed_over {
+                    self.compile_expr(&let_statement.expr)?;
+                }
+                let symbol = match symbol {
+                    Some(symbol) => symbol,
+                    None => self.bind_let(&let_statement.identifier, integer),
+                };
+                self.cse_kill(&symbol.name);
                 if symbol.scope == SymbolScope::Global {
-                    self.emit(Opcode::OpSetGlobal, &vec![symbol.index]);
+                    self.emit(Opcode::OpSetGlobal, &[symbol.index]);
                 } else {
-                    self.emit(Opcode::OpSetLocal, &vec![symbol.index]);
+                    self.emit(Opcode::OpSetLocal, &[symbol.index]);
                 }
                 return Ok(());
             }
             Statement::Return(r) => {
                 self.compile_expr(&r.argument)?;
-                self.emit(Opcode::OpReturnValue, &vec![]);
+                // at the top level its value is the program's, the last one popped
+                if self.scope_index == 0 {
+                    self.emit(OpPop, &[]);
+                    let jump = self.emit(OpJump, &[9527]);
+                    self.returns.push(jump);
+                    return Ok(());
+                }
+                self.emit(Opcode::OpReturnValue, &[]);
                 return Ok(());
             }
             Statement::Expr(e) => {
                 self.compile_expr(e)?;
-                self.emit(OpPop, &vec![]);
+                self.emit(OpPop, &[]);
+                return Ok(());
+            }
+            Statement::Import(i) => {
+                return Err(format!(
+                    "import \"{}\" must be resolved by the project loader",
+                    i.path
+                ));
+            }
+            Statement::While(w) => {
+                let invariants = self.loop_invariants(w);
+                let hoisted = self.scopes[self.scope_index].hoisted.clone();
+                self.hoist(&invariants.condition)?;
+                // a value computed before the loop may be stale on its next turn
+                self.cse_restore(&HashMap::new());
+                // the first turn checks the condition on its own, then computes the invariants of
+                // the body and jumps into it
+                let mut first_turn = None;
+                if !invariants.body.is_empty() {
+                    self.compile_expr(&w.condition)?;
+                    let jump_not_truthy = self.emit(OpJumpNotTruthy, &[9527]);
+                    self.hoist(&invariants.body)?;
+                    first_turn = Some((jump_not_truthy, self.emit(OpJump, &[9527])));
+                }
+                let start = self.current_instruction().data.len();
+                self.compile_expr(&w.condition)?;
+                let jump_not_truthy = self.emit(OpJumpNotTruthy, &[9527]);
+                if let Some((_, jump)) = first_turn {
+                    // what the condition computed isn't there on the first turn
+                    self.cse_restore(&HashMap::new());
+                    let body = self.current_instruction().data.len();
+                    self.change_operand(jump, body);
+                }
+
+                let label = w.label.as_ref().map(|l| l.name.clone());
+                let context = LoopContext { label, breaks: vec![] };
+                self.scopes[self.scope_index].loops.push(context);
+                self.compile_block_statement(&w.body)?;
+                let context = self.scopes[self.scope_index].loops.pop().unwrap();
+                self.emit(OpJump, &[start]);
+
+                let after_body_location = self.current_instruction().data.len();
+                self.change_operand(jump_not_truthy, after_body_location);
+                if let Some((jump, _)) = first_turn {
+                    self.change_operand(jump, after_body_location);
+                }
+                for jump in context.breaks {
+                    self.change_operand(jump, after_body_location);
+                }
+                self.cse_restore(&HashMap::new());
+                self.scopes[self.scope_index].hoisted = hoisted;
+                return Ok(());
+            }
+            Statement::Break(b) => {
+                let loops = &self.scopes[self.scope_index].loops;
+                let target = match &b.label {
+                    Some(label) => loops
+                        .iter()
+                        .rposition(|l| l.label.as_ref() == Some(&label.name)),
+                    None => loops.len().checked_sub(1),
+                };
+                let target = target.ok_or_else(|| b.misplaced())?;
+                let jump = self.emit(OpJump, &[9527]);
+                let loops = &mut self.scopes[self.scope_index].loops;
+                loops[target].breaks.push(jump);
                 return Ok(());
             }
         }
     }
 
+    // a top-level statement folded by compile-time evaluation, false when it has to be compiled
+    fn compile_const_stmt(&mut self, s: &Statement) -> Result<bool, CompileError> {
+        if let Statement::Let(l) = s {
+            if l.is_throwaway() || self.assigned.contains(&l.identifier.kind.to_string()) {
+                return Ok(false);
+            }
+        }
+        let const_eval = match self.const_eval.as_mut() {
+            Some(c) => c,
+            None => return Ok(false),
+        };
+        match s {
+            Statement::Let(let_statement) => {
+                let name = let_statement.identifier.kind.to_string();
+                if let Expression::FUNCTION(f) = &let_statement.expr {
+                    self.compile_stmt(s)?;
+                    self.const_eval.as_mut().unwrap().define_function(&name, f);
+                    return Ok(true);
+                }
+                let value = match const_eval.fold(&let_statement.expr) {
+                    Some(v) => v,
+                    None => return Ok(false),
+                };
+                const_eval.define_value(&name, value.clone());
+                self.cse_kill(&name);
+                let span = let_statement.identifier.span.clone();
+                let symbol = self.symbol_table.define_at(name, span);
+                if self.int_ops && matches!(value, Object::Integer(_)) {
+                    self.bind_integer(&symbol);
+                }
+                let operands = vec![self.add_constant(value)];
+                self.emit(OpConst, &operands);
+                self.emit(OpSetGlobal, &[symbol.index]);
+                return Ok(true);
+            }
+            Statement::Expr(e) => {
+                let value = match const_eval.fold(e) {
+                    Some(v) => v,
+                    None => return Ok(false),
+                };
+                let operands = vec![self.add_constant(value)];
+                self.emit(OpConst, &operands);
+                self.emit(OpPop, &[]);
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        }
+    }
+
     fn compile_expr(&mut self, e: &Expression) -> Result<(), CompileError> {
+        if let Some(symbol) = self.hoisted(e) {
+            self.load_symbol(&symbol);
+            return Ok(());
+        }
+        match self.cse_candidate(e) {
+            Some(key) => return self.compile_cse(e, key),
+            None => return self.compile_uncached_expr(e),
+        }
+    }
+
+    fn compile_uncached_expr(&mut self, e: &Expression) -> Result<(), CompileError> {
         match e {
             Expression::IDENTIFIER(identifier) => {
-                let symbol = self.symbol_table.resolve(identifier.name.clone());
+                let symbol = self.resolve_name(&identifier.name);
                 match symbol {
                     Some(symbol) => {
                         self.load_symbol(&symbol);
                     }
                     None => {
-                        return Err(format!("Undefined variable '{}'", identifier.name));
+                        let names = self.symbol_table.names();
+                        let candidates = names.iter().map(String::as_str);
+                        let suggestion = suggest::did_you_mean(&identifier.name, candidates);
+                        return Err(identifier.unbound(&suggestion));
                     }
                 }
             }
@@ -127,13 +471,22 @@ impl Compiler {
                     let operands = vec![self.add_constant(int)];
                     self.emit(OpConst, &operands);
                 }
+                Literal::Decimal(d) => {
+                    let decimal = object::decimal::parse(&d.raw)?;
+                    let operands = vec![self.add_constant(decimal)];
+                    self.emit(OpConst, &operands);
+                }
                 Literal::Boolean(i) => {
                     if i.raw {
-                        self.emit(OpTrue, &vec![]);
+                        self.emit(OpTrue, &[]);
                     } else {
-                        self.emit(OpFalse, &vec![]);
+                        self.emit(OpFalse, &[]);
                     }
                 }
+                Literal::Char(c) => {
+                    let operands = vec![self.add_constant(Object::Char(c.raw))];
+                    self.emit(OpConst, &operands);
+                }
                 Literal::String(s) => {
                     let string_object = Object::String(s.raw.clone());
                     let operands = vec![self.add_constant(string_object)];
@@ -143,86 +496,103 @@ impl Compiler {
                     for element in array.elements.iter() {
                         self.compile_expr(element)?;
                     }
-                    self.emit(OpArray, &vec![array.elements.len()]);
+                    self.emit(OpArray, &[array.elements.len()]);
                 }
                 Literal::Hash(hash) => {
                     for (key, value) in hash.elements.iter() {
-                        self.compile_expr(&key)?;
-                        self.compile_expr(&value)?;
+                        self.compile_expr(key)?;
+                        self.compile_expr(value)?;
                     }
-                    self.emit(OpHash, &vec![hash.elements.len() * 2]);
+                    self.emit(OpHash, &[hash.elements.len() * 2]);
                 }
             },
             Expression::PREFIX(prefix) => {
-                self.compile_expr(&prefix.operand).unwrap();
+                self.compile_expr(&prefix.operand)?;
                 match prefix.op.kind {
                     TokenKind::MINUS => {
-                        self.emit(OpMinus, &vec![]);
+                        self.emit(OpMinus, &[]);
                     }
                     TokenKind::BANG => {
-                        self.emit(OpBang, &vec![]);
+                        self.emit(OpBang, &[]);
                     }
                     _ => {
-                        return Err(format!("unexpected prefix op: {}", prefix.op));
+                        return Err(self.not_supported(
+                            format!("prefix operator `{}`", prefix.op.kind),
+                            &prefix.span,
+                        ));
                     }
                 }
             }
             Expression::INFIX(infix) => {
-                if infix.op.kind == TokenKind::LT {
-                    self.compile_expr(&infix.right).unwrap();
-                    self.compile_expr(&infix.left).unwrap();
-                    self.emit(Opcode::OpGreaterThan, &vec![]);
-                    return Ok(());
-                }
-                self.compile_expr(&infix.left).unwrap();
-                self.compile_expr(&infix.right).unwrap();
+                let int_ops = self.int_ops
+                    && self.is_integer_expr(&infix.left)
+                    && self.is_integer_expr(&infix.right);
+                self.compile_expr(&infix.left)?;
+                self.compile_expr(&infix.right)?;
                 match infix.op.kind {
+                    TokenKind::PLUS if int_ops => {
+                        self.emit(OpAddInt, &[]);
+                    }
+                    TokenKind::MINUS if int_ops => {
+                        self.emit(OpSubInt, &[]);
+                    }
+                    TokenKind::ASTERISK if int_ops => {
+                        self.emit(OpMulInt, &[]);
+                    }
+                    TokenKind::SLASH if int_ops => {
+                        self.emit(OpDivInt, &[]);
+                    }
                     TokenKind::PLUS => {
-                        self.emit(OpAdd, &vec![]);
+                        self.emit(OpAdd, &[]);
                     }
                     TokenKind::MINUS => {
-                        self.emit(OpSub, &vec![]);
+                        self.emit(OpSub, &[]);
                     }
                     TokenKind::ASTERISK => {
-                        self.emit(OpMul, &vec![]);
+                        self.emit(OpMul, &[]);
                     }
                     TokenKind::SLASH => {
-                        self.emit(OpDiv, &vec![]);
+                        self.emit(OpDiv, &[]);
                     }
                     TokenKind::GT => {
-                        self.emit(Opcode::OpGreaterThan, &vec![]);
+                        self.emit(Opcode::OpGreaterThan, &[]);
+                    }
+                    TokenKind::LT => {
+                        self.emit(Opcode::OpLessThan, &[]);
                     }
                     TokenKind::EQ => {
-                        self.emit(Opcode::OpEqual, &vec![]);
+                        self.emit(Opcode::OpEqual, &[]);
                     }
                     TokenKind::NotEq => {
-                        self.emit(Opcode::OpNotEqual, &vec![]);
+                        self.emit(Opcode::OpNotEqual, &[]);
                     }
                     _ => {
-                        return Err(format!("unexpected infix op: {}", infix.op));
+                        return Err(self.not_supported(
+                            format!("infix operator `{}`", infix.op.kind),
+                            &infix.span,
+                        ));
                     }
                 }
             }
             Expression::IF(if_node) => {
                 self.compile_expr(&if_node.condition)?;
-                let jump_not_truthy = self.emit(OpJumpNotTruthy, &vec![9527]);
+                let jump_not_truthy = self.emit(OpJumpNotTruthy, &[9527]);
+                let available = self.cse_available();
                 self.compile_block_statement(&if_node.consequent)?;
-                if self.last_instruction_is(OpPop) {
-                    self.remove_last_pop();
-                }
+                self.cse_restore(&available);
+                self.keep_block_value();
 
-                let jump_pos = self.emit(OpJump, &vec![9527]);
+                let jump_pos = self.emit(OpJump, &[9527]);
 
                 let after_consequence_location = self.current_instruction().data.len();
                 self.change_operand(jump_not_truthy, after_consequence_location);
 
                 if if_node.alternate.is_none() {
-                    self.emit(OpNull, &vec![]);
+                    self.emit(OpNull, &[]);
                 } else {
                     self.compile_block_statement(&if_node.clone().alternate.unwrap())?;
-                    if self.last_instruction_is(OpPop) {
-                        self.remove_last_pop();
-                    }
+                    self.cse_restore(&available);
+                    self.keep_block_value();
                 }
                 let after_alternative_location = self.current_instruction().data.len();
                 self.change_operand(jump_pos, after_alternative_location);
@@ -230,66 +600,648 @@ impl Compiler {
             Expression::Index(index) => {
                 self.compile_expr(&index.object)?;
                 self.compile_expr(&index.index)?;
-                self.emit(OpIndex, &vec![]);
+                self.emit(OpIndex, &[]);
             }
             Expression::FUNCTION(f) => {
-                self.enter_scope();
-                // f.name
-                for param in f.params.iter() {
-                    self.symbol_table.define(param.name.clone());
+                let non_escaping = self
+                    .escape_info
+                    .as_ref()
+                    .is_some_and(|e| e.is_non_escaping(f));
+                if non_escaping {
+                    let symbol_table = self.symbol_table.clone();
+                    let num_constants = self.constants.len();
+                    if self.compile_function(f, true)? {
+                        return Ok(());
+                    }
+                    self.symbol_table = symbol_table;
+                    self.constants.truncate(num_constants);
                 }
-                self.compile_block_statement(&f.body)?;
-                if self.last_instruction_is(OpPop) {
-                    self.replace_last_pop_with_return();
+                self.compile_function(f, false)?;
+            }
+            Expression::FunctionCall(fc) => {
+                if let Some(value) = self.fold_builtin_call(fc) {
+                    let operands = vec![self.add_constant(value)];
+                    self.emit(OpConst, &operands);
+                    return Ok(());
                 }
-                if !(self.last_instruction_is(OpReturnValue)) {
-                    self.emit(OpReturn, &vec![]);
+                if let Some(index) = self.inline_target(&fc.callee, fc.arguments.len()) {
+                    return self.compile_inline(index, &fc.arguments);
                 }
-                let num_locals = self.symbol_table.num_definitions;
-                let free_symbols = self.symbol_table.free_symbols.clone();
-                let instructions = self.leave_scope();
-                for x in free_symbols.clone() {
-                    self.load_symbol(&x);
+                self.compile_expr(&fc.callee)?;
+                for arg in fc.arguments.iter() {
+                    self.compile_expr(arg)?;
                 }
+                self.emit(OpCall, &[fc.arguments.len()]);
+            }
+            Expression::Match(m) => self.compile_match(m)?,
+            Expression::Assign(a) => self.compile_assign(a)?,
+        }
+
+        return Ok(());
+    }
 
-                let compiled_function = Rc::from(object::CompiledFunction {
-                    instructions: instructions.data,
-                    num_locals,
-                    num_parameters: f.params.len(),
-                });
+    // `name = value` sets the slot `name` already has, a local of this function or a global,
+    // and leaves the value on the stack
+    fn compile_assign(&mut self, a: &Assign) -> Result<(), CompileError> {
+        let symbol = match self.symbol_table.resolve(a.name.name.clone()) {
+            Some(symbol) => symbol,
+            None => return Err(a.unbound()),
+        };
+        match symbol.scope {
+            SymbolScope::Global | SymbolScope::LOCAL => {}
+            SymbolScope::Builtin => return Err(a.builtin()),
+            SymbolScope::Free | SymbolScope::Function => return Err(a.enclosing()),
+        }
+        let integer = self.int_ops && self.is_integer_expr(&a.value);
+        self.compile_expr(&a.value)?;
 
-                let operands = vec![self.add_constant(Object::CompiledFunction(compiled_function)), free_symbols.len()];
-                self.emit(OpClosure, &operands);
+        // whatever was known of the old value is gone
+        self.cse_kill(&symbol.name);
+        if integer {
+            self.bind_integer(&symbol);
+        } else {
+            self.unbind_integer(&symbol);
+        }
+        if symbol.scope == SymbolScope::Global {
+            self.inline_functions.remove(&symbol.index);
+            if let Some(c) = self.const_eval.as_mut() {
+                c.forget(&symbol.name);
             }
-            Expression::FunctionCall(fc) => {
-                self.compile_expr(&fc.callee)?;
-                for arg in fc.arguments.iter() {
-                    self.compile_expr(arg)?;
+            self.emit(OpSetGlobal, &[symbol.index]);
+        } else {
+            self.emit(OpSetLocal, &[symbol.index]);
+        }
+        self.load_symbol(&symbol);
+        return Ok(());
+    }
+
+    // The subject goes into a hidden slot, then each arm tests the parts of it its pattern looks
+    // at, jumping to the next arm at the first that doesn't fit, binds its names and checks its
+    // guard. Past the last arm the value is null, see `object::matching`.
+    fn compile_match(&mut self, m: &Match) -> Result<(), CompileError> {
+        self.compile_expr(&m.subject)?;
+        let subject = format!("$match{}", self.matches);
+        let subject = self.symbol_table.rebind_at(subject, m.span.clone());
+        self.set_symbol(&subject);
+
+        // a value computed before may read a name an arm binds, and one computed in an arm
+        // isn't there for the next
+        for arm in m.arms.iter() {
+            for name in arm.pattern.names() {
+                self.cse_kill(&name.name);
+            }
+        }
+        let available = self.cse_available();
+        self.matches += 1;
+        let mut ends = vec![];
+        for arm in m.arms.iter() {
+            let mut next_arm = vec![];
+            self.compile_pattern_tests(&arm.pattern, &subject, &mut vec![], &mut next_arm)?;
+            self.compile_pattern_bindings(&arm.pattern, &subject, &mut vec![])?;
+            if let Some(guard) = &arm.guard {
+                self.compile_expr(guard)?;
+                next_arm.push(self.emit(OpJumpNotTruthy, &[9527]));
+            }
+            self.compile_expr(&arm.body)?;
+            ends.push(self.emit(OpJump, &[9527]));
+
+            let next_arm_location = self.current_instruction().data.len();
+            for jump in next_arm {
+                self.change_operand(jump, next_arm_location);
+            }
+            self.cse_restore(&available);
+        }
+        self.matches -= 1;
+        self.emit(OpNull, &[]);
+
+        let end = self.current_instruction().data.len();
+        for jump in ends {
+            self.change_operand(jump, end);
+        }
+        return Ok(());
+    }
+
+    // jumps to fill in with the next arm when the value at `path` in `subject` doesn't fit
+    fn compile_pattern_tests(
+        &mut self,
+        pattern: &Pattern,
+        subject: &Rc<Symbol>,
+        path: &mut Vec<usize>,
+        next_arm: &mut Vec<usize>,
+    ) -> Result<(), CompileError> {
+        match pattern {
+            Pattern::Binding(_) => {}
+            Pattern::Literal(literal) => {
+                self.load_path(subject, path);
+                let operands = vec![self.add_constant(pattern_constant(literal)?)];
+                self.emit(OpConst, &operands);
+                self.emit(OpEqual, &[]);
+                next_arm.push(self.emit(OpJumpNotTruthy, &[9527]));
+            }
+            Pattern::Array { elements, rest, .. } => {
+                self.load_path(subject, path);
+                self.emit(OpMatchArray, &[elements.len(), rest.is_some() as usize]);
+                next_arm.push(self.emit(OpJumpNotTruthy, &[9527]));
+                for (i, element) in elements.iter().enumerate() {
+                    if let Pattern::Binding(_) = element {
+                        continue;
+                    }
+                    path.push(self.add_constant(Object::Integer(i as i64)));
+                    self.compile_pattern_tests(element, subject, path, next_arm)?;
+                    path.pop();
+                }
+            }
+            Pattern::Hash { entries, .. } => {
+                for (key, entry) in entries.iter() {
+                    let key = self.add_constant(pattern_constant(key)?);
+                    self.load_path(subject, path);
+                    self.emit(OpConst, &[key]);
+                    self.emit(OpMatchHash, &[]);
+                    next_arm.push(self.emit(OpJumpNotTruthy, &[9527]));
+                    path.push(key);
+                    self.compile_pattern_tests(entry, subject, path, next_arm)?;
+                    path.pop();
                 }
-                self.emit(OpCall, &vec![fc.arguments.len()]);
             }
         }
+        return Ok(());
+    }
 
+    fn compile_pattern_bindings(
+        &mut self,
+        pattern: &Pattern,
+        subject: &Rc<Symbol>,
+        path: &mut Vec<usize>,
+    ) -> Result<(), CompileError> {
+        match pattern {
+            Pattern::Binding(id) if id.is_throwaway() => {}
+            Pattern::Binding(id) => {
+                self.load_path(subject, path);
+                let symbol = self.bind_pattern(id);
+                self.set_symbol(&symbol);
+            }
+            Pattern::Literal(_) => {}
+            Pattern::Array { elements, rest, .. } => {
+                for (i, element) in elements.iter().enumerate() {
+                    if element.names().is_empty() {
+                        continue;
+                    }
+                    path.push(self.add_constant(Object::Integer(i as i64)));
+                    self.compile_pattern_bindings(element, subject, path)?;
+                    path.pop();
+                }
+                if let Some(rest) = rest.as_ref().filter(|r| !r.is_throwaway()) {
+                    self.load_path(subject, path);
+                    self.emit(OpArrayRest, &[elements.len()]);
+                    let symbol = self.bind_pattern(rest);
+                    self.set_symbol(&symbol);
+                }
+            }
+            Pattern::Hash { entries, .. } => {
+                for (key, entry) in entries.iter().filter(|(_, e)| !e.names().is_empty()) {
+                    path.push(self.add_constant(pattern_constant(key)?));
+                    self.compile_pattern_bindings(entry, subject, path)?;
+                    path.pop();
+                }
+            }
+        }
         return Ok(());
     }
 
+    // the value at `path`, constants indexing into `subject` one after the other
+    fn load_path(&mut self, subject: &Rc<Symbol>, path: &[usize]) {
+        self.load_symbol(subject);
+        for key in path.iter() {
+            self.emit(OpConst, &[*key]);
+            self.emit(OpIndex, &[]);
+        }
+    }
+
+    // The symbol a name in a pattern sets: the slot the name has in this scope already, if it
+    // has one, since the arm may not be taken and the code after the `match` reads it.
+    fn bind_pattern(&mut self, id: &IDENTIFIER) -> Rc<Symbol> {
+        let symbol = self.rebind(id.name.clone(), id.span.clone());
+        if let (SymbolScope::Global, Some(c)) = (&symbol.scope, self.const_eval.as_mut()) {
+            c.forget(&symbol.name);
+        }
+        self.cse_kill(&symbol.name);
+        return symbol;
+    }
+
+    fn set_symbol(&mut self, symbol: &Symbol) {
+        if symbol.scope == SymbolScope::Global {
+            self.emit(OpSetGlobal, &[symbol.index]);
+        } else {
+            self.emit(OpSetLocal, &[symbol.index]);
+        }
+    }
+
+    // false when a closure compiled `by_ref` turned out to need copies of its free variables,
+    // nothing is emitted then
+    fn compile_function(
+        &mut self,
+        f: &FunctionDeclaration,
+        by_ref: bool,
+    ) -> Result<bool, CompileError> {
+        self.enter_scope();
+        self.scopes[self.scope_index].by_ref = by_ref;
+        if self.cse {
+            self.scopes[self.scope_index].cse = Some(CseState::new(&f.body.body));
+        }
+        if !f.name.is_empty() {
+            self.symbol_table.define_function_name(f.name.clone());
+        }
+        for param in f.params.iter() {
+            if param.is_throwaway() {
+                self.symbol_table.define_slot();
+                continue;
+            }
+            self.symbol_table
+                .define_at(param.name.clone(), param.span.clone());
+        }
+        self.compile_block_statement(&f.body)?;
+        if self.last_instruction_is(OpPop) {
+            self.replace_last_pop_with_return();
+        }
+        if !(self.last_instruction_is(OpReturnValue)) {
+            self.emit(OpReturn, &[]);
+        }
+        let num_locals = self.symbol_table.num_definitions;
+        let free_symbols = self.symbol_table.free_symbols.clone();
+        let by_ref_failed = self.scopes[self.scope_index].by_ref_failed;
+        let instructions = self.leave_scope();
+        if by_ref && by_ref_failed {
+            return Ok(false);
+        }
+
+        let compiled_function = Rc::from(object::CompiledFunction {
+            instructions: instructions.data,
+            num_locals,
+            num_parameters: f.params.len(),
+            rest: f.rest,
+            name: self.function_name(f),
+        });
+        let const_index = self.add_constant(Object::CompiledFunction(compiled_function));
+        if by_ref {
+            self.emit(OpClosureRef, &[const_index]);
+        } else {
+            for x in free_symbols.iter() {
+                self.load_symbol(x);
+            }
+            self.emit(OpClosure, &[const_index, free_symbols.len()]);
+        }
+        return Ok(true);
+    }
+
     fn load_symbol(&mut self, symbol: &Rc<Symbol>) {
         match symbol.scope {
             SymbolScope::Global => {
-                self.emit(OpGetGlobal, &vec![symbol.index]);
+                self.emit(OpGetGlobal, &[symbol.index]);
             }
             SymbolScope::LOCAL => {
-                self.emit(OpGetLocal, &vec![symbol.index]);
+                self.emit(OpGetLocal, &[symbol.index]);
             }
             SymbolScope::Builtin => {
-                self.emit(OpGetBuiltin, &vec![symbol.index]);
+                self.emit(OpGetBuiltin, &[symbol.index]);
             }
             SymbolScope::Free => {
-                self.emit(OpGetFree, &vec![symbol.index]);
+                let original = Rc::clone(&self.symbol_table.free_symbols[symbol.index]);
+                let scope = &mut self.scopes[self.scope_index];
+                if scope.by_ref && original.scope == SymbolScope::LOCAL {
+                    self.emit(OpGetOuter, &[original.index]);
+                    return;
+                }
+                scope.by_ref_failed |= scope.by_ref;
+                self.emit(OpGetFree, &[symbol.index]);
             }
             SymbolScope::Function => {
-                self.emit(OpCurrentClosure, &vec![]);
+                self.emit(OpCurrentClosure, &[]);
+            }
+        }
+    }
+
+    fn record_inline_function(&mut self, s: &Statement) {
+        let (name, f) = match s {
+            Statement::Let(l) if self.inline && !l.is_throwaway() => match &l.expr {
+                Expression::FUNCTION(f) => (l.identifier.kind.to_string(), f),
+                _ => return,
+            },
+            _ => return,
+        };
+        if self.assigned.contains(&name) {
+            return;
+        }
+        let symbol = self.symbol_table.resolve(name).unwrap();
+        let inline = InlineFunction::new(f, &mut self.symbol_table, &self.inline_functions);
+        if let Some(inline) = inline {
+            self.inline_functions.insert(symbol.index, inline);
+        }
+    }
+
+    // the global index of an inlined function `callee` refers to
+    fn inline_target(&mut self, callee: &Expression, num_args: usize) -> Option<usize> {
+        let name = match callee {
+            Expression::IDENTIFIER(id) if !self.inline_functions.is_empty() => &id.name,
+            _ => return None,
+        };
+        if self
+            .inline_params
+            .last()
+            .is_some_and(|p| p.contains_key(name))
+        {
+            return None;
+        }
+        let symbol = self.symbol_table.resolve(name.clone())?;
+        if symbol.scope != SymbolScope::Global {
+            return None;
+        }
+        let f = self.inline_functions.get(&symbol.index)?;
+        if f.decl.params.len() != num_args || !f.visible_from(&mut self.symbol_table) {
+            return None;
+        }
+        return Some(symbol.index);
+    }
+
+    // arguments are evaluated into hidden slots, in order, then the body reads them from there
+    fn compile_inline(&mut self, index: usize, args: &[Expression]) -> Result<(), CompileError> {
+        let f = &self.inline_functions[&index];
+        let (params, body) = (f.decl.params.clone(), f.body().clone());
+        let mut slots = HashMap::new();
+        for (param, arg) in params.iter().zip(args.iter()) {
+            // a variable can be read where the parameter is, no need to copy it
+            if let Expression::IDENTIFIER(id) = arg {
+                if let Some(symbol) = self.resolve_name(&id.name) {
+                    slots.insert(param.name.clone(), symbol);
+                    continue;
+                }
+            }
+            self.compile_expr(arg)?;
+            let symbol = self.symbol_table.define(format!("$inline.{}", param.name));
+            if symbol.scope == SymbolScope::Global {
+                self.emit(OpSetGlobal, &[symbol.index]);
+            } else {
+                self.emit(OpSetLocal, &[symbol.index]);
+            }
+            slots.insert(param.name.clone(), symbol);
+        }
+
+        // names in the body mean something else than at the call site
+        let cse = self.scopes[self.scope_index].cse.take();
+        self.inline_params.push(slots);
+        let result = self.compile_expr(&body);
+        self.inline_params.pop();
+        self.scopes[self.scope_index].cse = cse;
+        return result;
+    }
+
+    fn resolve_name(&mut self, name: &str) -> Option<Rc<Symbol>> {
+        let param = self.inline_params.last().and_then(|p| p.get(name));
+        if let Some(symbol) = param {
+            return Some(Rc::clone(symbol));
+        }
+        return self.symbol_table.resolve(name.to_string());
+    }
+
+    // Whether `e` always evaluates to an integer, or fails. Integers past `i64` are still
+    // integers, the vm checks the operands of `OpAddInt` before taking its fast path.
+    fn is_integer_expr(&self, e: &Expression) -> bool {
+        match e {
+            Expression::LITERAL(Literal::Integer(_)) => return true,
+            Expression::PREFIX(prefix) => {
+                return prefix.op.kind == TokenKind::MINUS && self.is_integer_expr(&prefix.operand)
+            }
+            Expression::INFIX(infix) => {
+                return matches!(
+                    infix.op.kind,
+                    TokenKind::PLUS | TokenKind::MINUS | TokenKind::ASTERISK | TokenKind::SLASH
+                ) && self.is_integer_expr(&infix.left)
+                    && self.is_integer_expr(&infix.right)
+            }
+            Expression::IDENTIFIER(id) => {
+                let inline_params = self.inline_params.last();
+                if inline_params.is_some_and(|p| p.contains_key(&id.name)) {
+                    return false;
+                }
+                let locals = &self.scopes[self.scope_index].integer_locals;
+                match self.symbol_table.lookup(&id.name) {
+                    Some(s) if s.scope == SymbolScope::Global => {
+                        return self.integer_globals.contains(&s.index)
+                    }
+                    Some(s) if s.scope == SymbolScope::LOCAL => return locals.contains(&s.index),
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        }
+    }
+
+    // the value of `call` when it's a pure builtin's on literals, see `const_eval::fold_builtin_call`
+    fn fold_builtin_call(&mut self, call: &FunctionCall) -> Option<Object> {
+        self.const_eval.as_ref()?;
+        let symbol_table = &mut self.symbol_table;
+        let mut builtin = |name: &str| match symbol_table.resolve(name.to_string()) {
+            Some(symbol) => symbol.scope == SymbolScope::Builtin,
+            None => false,
+        };
+        return const_eval::fold_builtin_call(call, &mut builtin);
+    }
+
+    // `let q = f(q, ...)` in a loop, where `q` keeps its slot: a builtin `f` gets the value of
+    // `q` taken out of it, so it can change it in place, see `object::deque`. False when `f`
+    // isn't a builtin and nothing was emitted.
+    fn compile_handover(
+        &mut self,
+        call: &FunctionCall,
+        symbol: &Symbol,
+    ) -> Result<bool, CompileError> {
+        let (callee, first) = match (&*call.callee, &call.arguments[0]) {
+            (Expression::IDENTIFIER(callee), Expression::IDENTIFIER(first)) => (callee, first),
+            _ => return Ok(false),
+        };
+        let callee = match self.symbol_table.resolve(callee.name.clone()) {
+            Some(callee) if callee.scope == SymbolScope::Builtin => callee,
+            _ => return Ok(false),
+        };
+        match self.symbol_table.resolve(first.name.clone()) {
+            Some(first) if first.scope == symbol.scope && first.index == symbol.index => {}
+            _ => return Ok(false),
+        }
+        self.load_symbol(&callee);
+        if symbol.scope == SymbolScope::Global {
+            self.emit(OpTakeGlobal, &[symbol.index]);
+        } else {
+            self.emit(OpTakeLocal, &[symbol.index]);
+        }
+        for arg in call.arguments[1..].iter() {
+            self.compile_expr(arg)?;
+        }
+        self.emit(OpCall, &[call.arguments.len()]);
+        return Ok(true);
+    }
+
+    // the symbol a `let` of `identifier` sets, `integer` if its value is known to be one
+    fn bind_let(&mut self, identifier: &Token, integer: bool) -> Rc<Symbol> {
+        let name = identifier.kind.to_string();
+        let span = identifier.span.clone();
+        let symbol = if !self.scopes[self.scope_index].loops.is_empty() {
+            self.rebind(name, span)
+        } else {
+            self.symbol_table.define_at(name, span)
+        };
+        if let (SymbolScope::Global, Some(c)) = (&symbol.scope, self.const_eval.as_mut()) {
+            c.forget(&symbol.name);
+        }
+        if integer {
+            self.bind_integer(&symbol);
+        }
+        return symbol;
+    }
+
+    // the slot `name` has in this scope, or a new one
+    fn rebind(&mut self, name: String, span: Span) -> Rc<Symbol> {
+        let symbol = self.symbol_table.rebind_at(name, span);
+        // whatever was known of the slot's old value is gone
+        self.unbind_integer(&symbol);
+        if symbol.scope == SymbolScope::Global {
+            self.inline_functions.remove(&symbol.index);
+        }
+        return symbol;
+    }
+
+    fn unbind_integer(&mut self, symbol: &Symbol) {
+        if symbol.scope == SymbolScope::Global {
+            self.integer_globals.remove(&symbol.index);
+        } else {
+            let locals = &mut self.scopes[self.scope_index].integer_locals;
+            locals.remove(&symbol.index);
+        }
+    }
+
+    fn bind_integer(&mut self, symbol: &Symbol) {
+        if symbol.scope == SymbolScope::Global {
+            self.integer_globals.insert(symbol.index);
+        } else {
+            let locals = &mut self.scopes[self.scope_index].integer_locals;
+            locals.insert(symbol.index);
+        }
+    }
+
+    // the key of `e` if it was seen more than once, every builtin it calls is still a builtin and
+    // nothing it reads is ever assigned
+    fn cse_candidate(&mut self, e: &Expression) -> Option<String> {
+        let cse = self.scopes[self.scope_index].cse.as_ref()?;
+        let (key, candidate) = cse.candidate(e)?;
+        // a call or a branch may have assigned it since
+        let assigned = &self.assigned;
+        if candidate.names.iter().any(|name| assigned.contains(name)) {
+            return None;
+        }
+        for name in candidate.builtins.clone() {
+            match self.symbol_table.resolve(name) {
+                Some(symbol) if symbol.scope == SymbolScope::Builtin => {}
+                _ => return None,
+            }
+        }
+        return Some(key);
+    }
+
+    // the first time `e` is reached its value is stored into a hidden local, later it is loaded
+    fn compile_cse(&mut self, e: &Expression, key: String) -> Result<(), CompileError> {
+        let cse = self.scopes[self.scope_index].cse.as_ref().unwrap();
+        if let Some(symbol) = cse.available.get(&key).cloned() {
+            self.load_symbol(&symbol);
+            return Ok(());
+        }
+
+        self.compile_uncached_expr(e)?;
+        let cse = self.scopes[self.scope_index].cse.as_ref().unwrap();
+        let symbol = match cse.temps.get(&key) {
+            Some(symbol) => Rc::clone(symbol),
+            None => self.symbol_table.define(format!("$cse{}", cse.temps.len())),
+        };
+        if symbol.scope == SymbolScope::Global {
+            self.emit(OpSetGlobal, &[symbol.index]);
+        } else {
+            self.emit(OpSetLocal, &[symbol.index]);
+        }
+        self.load_symbol(&symbol);
+
+        let cse = self.scopes[self.scope_index].cse.as_mut().unwrap();
+        cse.temps.insert(key.clone(), Rc::clone(&symbol));
+        cse.available.insert(key, symbol);
+        return Ok(());
+    }
+
+    // the invariants of `w` to compute before it: pure computations reading only globals and
+    // locals of this function no `name = value` sets, and builtins no binding hides
+    fn loop_invariants(&mut self, w: &While) -> Invariants {
+        if !self.licm || !self.inline_params.is_empty() {
+            return Invariants { condition: vec![], body: vec![] };
+        }
+        let assigned = &self.assigned;
+        let symbol_table = &mut self.symbol_table;
+        let mut invariant = |candidate: &Candidate| {
+            let builtins = &candidate.builtins;
+            for name in candidate.names.iter() {
+                if builtins.contains(name) {
+                    continue;
+                }
+                if assigned.contains(name) {
+                    return false;
+                }
+                match symbol_table.lookup(name) {
+                    Some(s) if s.scope == SymbolScope::Global || s.scope == SymbolScope::LOCAL => {}
+                    _ => return false,
+                }
+            }
+            for name in builtins.iter() {
+                match symbol_table.resolve(name.clone()) {
+                    Some(symbol) if symbol.scope == SymbolScope::Builtin => {}
+                    _ => return false,
+                }
             }
+            return true;
+        };
+        return Invariants::find(w, &mut invariant);
+    }
+
+    // computes each invariant into a hidden slot, loaded instead wherever the loop computes it
+    fn hoist(&mut self, invariants: &[Expression]) -> Result<(), CompileError> {
+        for e in invariants.iter() {
+            self.compile_expr(e)?;
+            let name = format!("$licm{}", self.symbol_table.num_definitions);
+            let symbol = self.symbol_table.define(name);
+            self.set_symbol(&symbol);
+            let hoisted = &mut self.scopes[self.scope_index].hoisted;
+            hoisted.insert(e.to_string(), symbol);
+        }
+        return Ok(());
+    }
+
+    fn hoisted(&self, e: &Expression) -> Option<Rc<Symbol>> {
+        let hoisted = &self.scopes[self.scope_index].hoisted;
+        if hoisted.is_empty() || !self.inline_params.is_empty() || !cse::is_computation(e) {
+            return None;
+        }
+        return hoisted.get(&e.to_string()).cloned();
+    }
+
+    fn cse_kill(&mut self, name: &str) {
+        if let Some(cse) = self.scopes[self.scope_index].cse.as_mut() {
+            cse.kill(name);
+        }
+    }
+
+    fn cse_available(&self) -> HashMap<String, Rc<Symbol>> {
+        match &self.scopes[self.scope_index].cse {
+            Some(cse) => return cse.available.clone(),
+            None => return HashMap::new(),
+        }
+    }
+
+    fn cse_restore(&mut self, available: &HashMap<String, Rc<Symbol>>) {
+        if let Some(cse) = self.scopes[self.scope_index].cse.as_mut() {
+            cse.available = available.clone();
         }
     }
 
@@ -297,6 +1249,7 @@ impl Compiler {
         return Bytecode {
             instructions: self.current_instruction().clone(),
             constants: self.constants.clone(),
+            num_globals: self.symbol_table.num_definitions,
         };
     }
 
@@ -305,14 +1258,25 @@ impl Compiler {
         return self.constants.len() - 1;
     }
 
-    pub fn emit(&mut self, op: Opcode, operands: &Vec<usize>) -> usize {
-        let ins = make_instructions(op, operands);
-        let pos = self.add_instructions(&ins);
+    pub fn emit(&mut self, op: Opcode, operands: &[usize]) -> usize {
+        let pos = self.scopes[self.scope_index]
+            .instructions
+            .push(op, operands);
         self.set_last_instruction(op, pos);
 
         return pos;
     }
 
+    // a branch leaves its value on the stack: the last expression's, or null after a `let` or a
+    // loop
+    fn keep_block_value(&mut self) {
+        if self.last_instruction_is(OpPop) {
+            self.remove_last_pop();
+        } else {
+            self.emit(OpNull, &[]);
+        }
+    }
+
     fn compile_block_statement(
         &mut self,
         block_statement: &BlockStatement,
@@ -325,10 +1289,10 @@ impl Compiler {
 
     pub fn add_instructions(&mut self, ins: &Instructions) -> usize {
         let pos = self.current_instruction().data.len();
-        let updated_ins = self.scopes[self.scope_index]
+        self.scopes[self.scope_index]
             .instructions
-            .merge_instructions(ins);
-        self.scopes[self.scope_index].instructions = updated_ins;
+            .data
+            .extend_from_slice(&ins.data);
         return pos;
     }
 
@@ -340,7 +1304,7 @@ impl Compiler {
     }
 
     fn last_instruction_is(&self, op: Opcode) -> bool {
-        if self.current_instruction().data.len() == 0 {
+        if self.current_instruction().data.is_empty() {
             return false;
         }
         return self.scopes[self.scope_index].last_instruction.opcode == op;
@@ -366,13 +1330,13 @@ impl Compiler {
 
     fn replace_last_pop_with_return(&mut self) {
         let last_pos = self.scopes[self.scope_index].last_instruction.position;
-        self.replace_instruction(last_pos, &make_instructions(OpReturnValue, &vec![]));
+        self.replace_instruction(last_pos, &make_instructions(OpReturnValue, &[]));
         self.scopes[self.scope_index].last_instruction.opcode = OpReturnValue;
     }
 
     fn change_operand(&mut self, pos: usize, operand: usize) {
         let op = cast_u8_to_opcode(self.current_instruction().data[pos]);
-        let ins = make_instructions(op, &vec![operand]);
+        let ins = make_instructions(op, &[operand]);
         self.replace_instruction(pos, &ins);
     }
 
@@ -385,6 +1349,12 @@ impl Compiler {
             instructions: Instructions { data: vec![] },
             last_instruction: EmittedInstruction { opcode: OpNull, position: 0 },
             previous_instruction: EmittedInstruction { opcode: OpNull, position: 0 },
+            by_ref: false,
+            by_ref_failed: false,
+            cse: None,
+            integer_locals: HashSet::new(),
+            loops: vec![],
+            hoisted: HashMap::new(),
         };
         self.scopes.push(scope);
         self.scope_index += 1;
@@ -400,3 +1370,17 @@ impl Compiler {
         return instructions;
     }
 }
+
+// the value of a literal in a pattern, which is never an array or a hash
+fn pattern_constant(literal: &Literal) -> Result<Object, CompileError> {
+    match literal {
+        Literal::Integer(i) => return Ok(Object::Integer(i.raw)),
+        Literal::Decimal(d) => return object::decimal::parse(&d.raw),
+        Literal::Boolean(b) => return Ok(Object::Boolean(b.raw)),
+        Literal::String(s) => return Ok(Object::String(s.raw.clone())),
+        Literal::Char(c) => return Ok(Object::Char(c.raw)),
+        Literal::Array(_) | Literal::Hash(_) => {
+            return Err(format!("{} isn't a pattern", literal));
+        }
+    }
+}
@@ -16,19 +16,21 @@ mod tests {
                     Object::Integer(10),
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpConst, &vec![0]),
-                            make_instructions(OpConst, &vec![1]),
-                            make_instructions(OpAdd, &vec![0]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpConst, &[0]),
+                            make_instructions(OpConst, &[1]),
+                            make_instructions(OpAdd, &[0]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 0,
                         num_parameters: 0,
+                        rest: false,
+                        name: "fn@1".to_string(),
                     })),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![2, 0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[2, 0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -38,19 +40,21 @@ mod tests {
                     Object::Integer(10),
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpConst, &vec![0]),
-                            make_instructions(OpConst, &vec![1]),
-                            make_instructions(OpAdd, &vec![0]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpConst, &[0]),
+                            make_instructions(OpConst, &[1]),
+                            make_instructions(OpAdd, &[0]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 0,
                         num_parameters: 0,
+                        rest: false,
+                        name: "fn@1".to_string(),
                     })),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![2, 0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[2, 0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -60,19 +64,21 @@ mod tests {
                     Object::Integer(2),
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpConst, &vec![0]),
-                            make_instructions(OpPop, &vec![0]),
-                            make_instructions(OpConst, &vec![1]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpConst, &[0]),
+                            make_instructions(OpPop, &[0]),
+                            make_instructions(OpConst, &[1]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 0,
                         num_parameters: 0,
+                        rest: false,
+                        name: "fn@1".to_string(),
                     })),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![2, 0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[2, 0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
@@ -84,14 +90,16 @@ mod tests {
         let tests = vec![CompilerTestCase {
             input: "fn() { }",
             expected_constants: vec![Object::CompiledFunction(Rc::from(object::CompiledFunction {
-                instructions: concat_instructions(&vec![make_instructions(OpReturn, &vec![0])])
+                instructions: concat_instructions(&vec![make_instructions(OpReturn, &[0])])
                     .data,
                 num_locals: 0,
                 num_parameters: 0,
+                rest: false,
+                name: "fn@1".to_string(),
             }))],
             expected_instructions: vec![
-                make_instructions(OpClosure, &vec![0, 0]),
-                make_instructions(OpPop, &vec![0]),
+                make_instructions(OpClosure, &[0, 0]),
+                make_instructions(OpPop, &[0]),
             ],
         }];
         run_compiler_test(tests);
@@ -106,18 +114,20 @@ mod tests {
                     Object::Integer(24),
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpConst, &vec![0]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpConst, &[0]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 0,
                         num_parameters: 0,
+                        rest: false,
+                        name: "fn@1".to_string(),
                     })),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![1, 0]),
-                    make_instructions(OpCall, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[1, 0]),
+                    make_instructions(OpCall, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -126,20 +136,22 @@ mod tests {
                     Object::Integer(24),
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpConst, &vec![0]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpConst, &[0]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 0,
                         num_parameters: 0,
+                        rest: false,
+                        name: "noArg".to_string(),
                     })),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![1, 0]),
-                    make_instructions(OpSetGlobal, &vec![0]),
-                    make_instructions(OpGetGlobal, &vec![0]),
-                    make_instructions(OpCall, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[1, 0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpCall, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -147,22 +159,24 @@ mod tests {
                 expected_constants: vec![
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpGetLocal, &vec![0]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpGetLocal, &[0]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 1,
                         num_parameters: 1,
+                        rest: false,
+                        name: "oneArg".to_string(),
                     })),
                     Object::Integer(24),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![0, 0]),
-                    make_instructions(OpSetGlobal, &vec![0]),
-                    make_instructions(OpGetGlobal, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpCall, &vec![1]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[0, 0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpCall, &[1]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -170,30 +184,32 @@ mod tests {
                 expected_constants: vec![
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpGetLocal, &vec![0]),
-                            make_instructions(OpPop, &vec![0]),
-                            make_instructions(OpGetLocal, &vec![1]),
-                            make_instructions(OpPop, &vec![0]),
-                            make_instructions(OpGetLocal, &vec![2]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpGetLocal, &[0]),
+                            make_instructions(OpPop, &[0]),
+                            make_instructions(OpGetLocal, &[1]),
+                            make_instructions(OpPop, &[0]),
+                            make_instructions(OpGetLocal, &[2]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 3,
                         num_parameters: 3,
+                        rest: false,
+                        name: "manyArg".to_string(),
                     })),
                     Object::Integer(24),
                     Object::Integer(25),
                     Object::Integer(26),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![0, 0]),
-                    make_instructions(OpSetGlobal, &vec![0]),
-                    make_instructions(OpGetGlobal, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpConst, &vec![2]),
-                    make_instructions(OpConst, &vec![3]),
-                    make_instructions(OpCall, &vec![3]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[0, 0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpConst, &[3]),
+                    make_instructions(OpCall, &[3]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
@@ -210,19 +226,21 @@ mod tests {
                     Object::Integer(55),
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpGetGlobal, &vec![0]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpGetGlobal, &[0]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 0,
                         num_parameters: 0,
+                        rest: false,
+                        name: "fn@1".to_string(),
                     })),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpSetGlobal, &vec![0]),
-                    make_instructions(OpClosure, &vec![1, 0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpClosure, &[1, 0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -231,19 +249,21 @@ mod tests {
                     Object::Integer(55),
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpConst, &vec![0]),
-                            make_instructions(OpSetLocal, &vec![0]),
-                            make_instructions(OpGetLocal, &vec![0]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpConst, &[0]),
+                            make_instructions(OpSetLocal, &[0]),
+                            make_instructions(OpGetLocal, &[0]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 1,
                         num_parameters: 0,
+                        rest: false,
+                        name: "fn@1".to_string(),
                     })),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![1, 0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[1, 0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -253,23 +273,25 @@ mod tests {
                     Object::Integer(77),
                     Object::CompiledFunction(Rc::from(object::CompiledFunction {
                         instructions: concat_instructions(&vec![
-                            make_instructions(OpConst, &vec![0]),
-                            make_instructions(OpSetLocal, &vec![0]),
-                            make_instructions(OpConst, &vec![1]),
-                            make_instructions(OpSetLocal, &vec![1]),
-                            make_instructions(OpGetLocal, &vec![0]),
-                            make_instructions(OpGetLocal, &vec![1]),
-                            make_instructions(OpAdd, &vec![0]),
-                            make_instructions(OpReturnValue, &vec![0]),
+                            make_instructions(OpConst, &[0]),
+                            make_instructions(OpSetLocal, &[0]),
+                            make_instructions(OpConst, &[1]),
+                            make_instructions(OpSetLocal, &[1]),
+                            make_instructions(OpGetLocal, &[0]),
+                            make_instructions(OpGetLocal, &[1]),
+                            make_instructions(OpAdd, &[0]),
+                            make_instructions(OpReturnValue, &[0]),
                         ])
                             .data,
                         num_locals: 2,
                         num_parameters: 0,
+                        rest: false,
+                        name: "fn@1".to_string(),
                     })),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![2, 0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[2, 0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
@@ -283,33 +305,35 @@ mod tests {
                 input: "len([]); push([], 1);",
                 expected_constants: vec![Object::Integer(1)],
                 expected_instructions: vec![
-                    make_instructions(OpGetBuiltin, &vec![0]),
-                    make_instructions(OpArray, &vec![0]),
-                    make_instructions(OpCall, &vec![1]),
-                    make_instructions(OpPop, &vec![0]),
-                    make_instructions(OpGetBuiltin, &vec![5]),
-                    make_instructions(OpArray, &vec![0]),
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpCall, &vec![2]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpGetBuiltin, &[0]),
+                    make_instructions(OpArray, &[0]),
+                    make_instructions(OpCall, &[1]),
+                    make_instructions(OpPop, &[0]),
+                    make_instructions(OpGetBuiltin, &[5]),
+                    make_instructions(OpArray, &[0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpCall, &[2]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "fn() { len([]) }",
                 expected_constants: vec![Object::CompiledFunction(Rc::from(object::CompiledFunction {
                     instructions: concat_instructions(&vec![
-                        make_instructions(OpGetBuiltin, &vec![0]),
-                        make_instructions(OpArray, &vec![0]),
-                        make_instructions(OpCall, &vec![1]),
-                        make_instructions(OpReturnValue, &vec![0]),
+                        make_instructions(OpGetBuiltin, &[0]),
+                        make_instructions(OpArray, &[0]),
+                        make_instructions(OpCall, &[1]),
+                        make_instructions(OpReturnValue, &[0]),
                     ])
                         .data,
                     num_locals: 0,
                     num_parameters: 0,
+                    rest: false,
+                    name: "fn@1".to_string(),
                 }))],
                 expected_instructions: vec![
-                    make_instructions(OpClosure, &vec![0, 0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpClosure, &[0, 0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
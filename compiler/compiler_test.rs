@@ -1,12 +1,11 @@
-use crate::compiler::Compiler;
+use crate::compile;
 use crate::op_code::{concat_instructions, Instructions};
-use parser::parse;
 use std::borrow::Borrow;
 use std::rc::Rc;
 
 use object::Object;
 
-pub fn test_constants(expected: &Vec<Object>, actual: &Vec<Rc<Object>>) {
+pub fn test_constants(expected: &[Object], actual: &[Rc<Object>]) {
     assert_eq!(expected.len(), actual.len());
     for (exp, b_got) in expected.iter().zip(actual) {
         let got = b_got.borrow();
@@ -23,9 +22,7 @@ pub struct CompilerTestCase<'a> {
 
 pub fn run_compiler_test(tests: Vec<CompilerTestCase>) {
     for t in tests {
-        let program = parse(t.input).unwrap();
-        let mut compiler = Compiler::new();
-        let bytecodes = compiler.compile(&program).unwrap();
+        let bytecodes = compile(t.input).unwrap();
         test_instructions(&t.expected_instructions, &bytecodes.instructions);
         test_constants(&t.expected_constants, &bytecodes.constants);
     }
@@ -50,8 +47,11 @@ fn test_instructions(expected: &Vec<Instructions>, actual: &Instructions) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::compiler::{CompileOptions, Compiler};
     use crate::op_code::make_instructions;
     use crate::op_code::Opcode::*;
+    use parser::ast::{Expression, Node, Statement};
+    use parser::lexer::token::TokenKind;
 
     #[test]
     fn integer_arithmetic() {
@@ -60,68 +60,68 @@ mod tests {
                 input: "1 + 2",
                 expected_constants: vec![Object::Integer(1), Object::Integer(2)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpAdd, &vec![1]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpAdd, &[1]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "1; 2",
                 expected_constants: vec![Object::Integer(1), Object::Integer(2)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpPop, &vec![1]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpPop, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpPop, &[1]),
                 ],
             },
             CompilerTestCase {
                 input: "1 - 2",
                 expected_constants: vec![Object::Integer(1), Object::Integer(2)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpSub, &vec![1]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpSub, &[1]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "1 * 2",
                 expected_constants: vec![Object::Integer(1), Object::Integer(2)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpMul, &vec![1]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpMul, &[1]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "2 / 1",
                 expected_constants: vec![Object::Integer(2), Object::Integer(1)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpDiv, &vec![1]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpDiv, &[1]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "-1",
                 expected_constants: vec![Object::Integer(1)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpMinus, &vec![1]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpMinus, &[1]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "!true",
                 expected_constants: vec![],
                 expected_instructions: vec![
-                    make_instructions(OpTrue, &vec![0]),
-                    make_instructions(OpBang, &vec![1]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpTrue, &[0]),
+                    make_instructions(OpBang, &[1]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
@@ -135,76 +135,76 @@ mod tests {
                 input: "true",
                 expected_constants: vec![],
                 expected_instructions: vec![
-                    make_instructions(OpTrue, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpTrue, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "false",
                 expected_constants: vec![],
                 expected_instructions: vec![
-                    make_instructions(OpFalse, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpFalse, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "1 > 2",
                 expected_constants: vec![Object::Integer(1), Object::Integer(2)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpGreaterThan, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpGreaterThan, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "1 < 2",
-                expected_constants: vec![Object::Integer(2), Object::Integer(1)],
+                expected_constants: vec![Object::Integer(1), Object::Integer(2)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpGreaterThan, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpLessThan, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "1 == 2",
                 expected_constants: vec![Object::Integer(1), Object::Integer(2)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpEqual, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpEqual, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "1 != 2",
                 expected_constants: vec![Object::Integer(1), Object::Integer(2)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpNotEqual, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpNotEqual, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "true == false",
                 expected_constants: vec![],
                 expected_instructions: vec![
-                    make_instructions(OpTrue, &vec![0]),
-                    make_instructions(OpFalse, &vec![0]),
-                    make_instructions(OpEqual, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpTrue, &[0]),
+                    make_instructions(OpFalse, &[0]),
+                    make_instructions(OpEqual, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "true != false",
                 expected_constants: vec![],
                 expected_instructions: vec![
-                    make_instructions(OpTrue, &vec![0]),
-                    make_instructions(OpFalse, &vec![0]),
-                    make_instructions(OpNotEqual, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpTrue, &[0]),
+                    make_instructions(OpFalse, &[0]),
+                    make_instructions(OpNotEqual, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
@@ -218,14 +218,14 @@ mod tests {
             input: "if (true) { 10 }; 3333;",
             expected_constants: vec![Object::Integer(10), Object::Integer(3333)],
             expected_instructions: vec![
-                make_instructions(OpTrue, &vec![0]),
-                make_instructions(OpJumpNotTruthy, &vec![10]),
-                make_instructions(OpConst, &vec![0]),
-                make_instructions(OpJump, &vec![11]),
-                make_instructions(OpNull, &vec![0]),
-                make_instructions(OpPop, &vec![0]),
-                make_instructions(OpConst, &vec![1]),
-                make_instructions(OpPop, &vec![0]),
+                make_instructions(OpTrue, &[0]),
+                make_instructions(OpJumpNotTruthy, &[10]),
+                make_instructions(OpConst, &[0]),
+                make_instructions(OpJump, &[11]),
+                make_instructions(OpNull, &[0]),
+                make_instructions(OpPop, &[0]),
+                make_instructions(OpConst, &[1]),
+                make_instructions(OpPop, &[0]),
             ],
         }];
 
@@ -242,14 +242,141 @@ mod tests {
                 Object::Integer(3333),
             ],
             expected_instructions: vec![
-                make_instructions(OpTrue, &vec![0]),
-                make_instructions(OpJumpNotTruthy, &vec![10]),
-                make_instructions(OpConst, &vec![0]),
-                make_instructions(OpJump, &vec![13]),
-                make_instructions(OpConst, &vec![1]),
-                make_instructions(OpPop, &vec![0]),
-                make_instructions(OpConst, &vec![2]),
-                make_instructions(OpPop, &vec![0]),
+                make_instructions(OpTrue, &[0]),
+                make_instructions(OpJumpNotTruthy, &[10]),
+                make_instructions(OpConst, &[0]),
+                make_instructions(OpJump, &[13]),
+                make_instructions(OpConst, &[1]),
+                make_instructions(OpPop, &[0]),
+                make_instructions(OpConst, &[2]),
+                make_instructions(OpPop, &[0]),
+            ],
+        }];
+
+        run_compiler_test(tests);
+    }
+
+    #[test]
+    fn conditions_ending_with_let() {
+        // the branch is null, like one that isn't taken
+        let tests = vec![CompilerTestCase {
+            input: "if (true) { let a = 1; }",
+            expected_constants: vec![Object::Integer(1)],
+            expected_instructions: vec![
+                make_instructions(OpTrue, &[0]),
+                make_instructions(OpJumpNotTruthy, &[14]),
+                make_instructions(OpConst, &[0]),
+                make_instructions(OpSetGlobal, &[0]),
+                make_instructions(OpNull, &[0]),
+                make_instructions(OpJump, &[15]),
+                make_instructions(OpNull, &[0]),
+                make_instructions(OpPop, &[0]),
+            ],
+        }];
+
+        run_compiler_test(tests);
+    }
+
+    #[test]
+    fn throwaway_let() {
+        // its value is dropped and `a` takes the first global
+        let tests = vec![CompilerTestCase {
+            input: "let _ = 1; let a = 2;",
+            expected_constants: vec![Object::Integer(1), Object::Integer(2)],
+            expected_instructions: vec![
+                make_instructions(OpConst, &[0]),
+                make_instructions(OpPop, &[0]),
+                make_instructions(OpNull, &[0]),
+                make_instructions(OpPop, &[0]),
+                make_instructions(OpConst, &[1]),
+                make_instructions(OpSetGlobal, &[0]),
+            ],
+        }];
+
+        run_compiler_test(tests);
+    }
+
+    #[test]
+    fn while_loop() {
+        // the `let` in the body writes the global the condition reads
+        let tests = vec![CompilerTestCase {
+            input: "let i = 0; while (i < 3) { let i = i + 1; }",
+            expected_constants: vec![Object::Integer(0), Object::Integer(3), Object::Integer(1)],
+            expected_instructions: vec![
+                make_instructions(OpConst, &[0]),
+                make_instructions(OpSetGlobal, &[0]),
+                make_instructions(OpGetGlobal, &[0]),
+                make_instructions(OpConst, &[1]),
+                make_instructions(OpLessThan, &[0]),
+                make_instructions(OpJumpNotTruthy, &[29]),
+                make_instructions(OpGetGlobal, &[0]),
+                make_instructions(OpConst, &[2]),
+                make_instructions(OpAdd, &[0]),
+                make_instructions(OpSetGlobal, &[0]),
+                make_instructions(OpJump, &[6]),
+            ],
+        }];
+
+        run_compiler_test(tests);
+    }
+
+    #[test]
+    fn handover_in_loop() {
+        // the builtin gets the array out of the global it's rebinding, not a second reference
+        let tests = vec![
+            CompilerTestCase {
+                input: "let q = []; while (true) { let q = push(q, 1); }",
+                expected_constants: vec![Object::Integer(1)],
+                expected_instructions: vec![
+                    make_instructions(OpArray, &[0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpTrue, &[0]),
+                    make_instructions(OpJumpNotTruthy, &[26]),
+                    make_instructions(OpGetBuiltin, &[5]),
+                    make_instructions(OpTakeGlobal, &[0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpCall, &[2]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpJump, &[6]),
+                ],
+            },
+            // `len(q)` needs `q` too
+            CompilerTestCase {
+                input: "let q = []; while (true) { let q = push(q, len(q)); }",
+                expected_constants: vec![],
+                expected_instructions: vec![
+                    make_instructions(OpArray, &[0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpTrue, &[0]),
+                    make_instructions(OpJumpNotTruthy, &[30]),
+                    make_instructions(OpGetBuiltin, &[5]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpGetBuiltin, &[0]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpCall, &[1]),
+                    make_instructions(OpCall, &[2]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpJump, &[6]),
+                ],
+            },
+        ];
+
+        run_compiler_test(tests);
+    }
+
+    #[test]
+    fn assignment() {
+        // the value is set in the global's slot and read back as the value of the expression
+        let tests = vec![CompilerTestCase {
+            input: "let x = 1; x = 2;",
+            expected_constants: vec![Object::Integer(1), Object::Integer(2)],
+            expected_instructions: vec![
+                make_instructions(OpConst, &[0]),
+                make_instructions(OpSetGlobal, &[0]),
+                make_instructions(OpConst, &[1]),
+                make_instructions(OpSetGlobal, &[0]),
+                make_instructions(OpGetGlobal, &[0]),
+                make_instructions(OpPop, &[]),
             ],
         }];
 
@@ -263,32 +390,32 @@ mod tests {
                 input: "let one = 1; let two = 2;",
                 expected_constants: vec![Object::Integer(1), Object::Integer(2)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpSetGlobal, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpSetGlobal, &vec![1]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpSetGlobal, &[1]),
                 ],
             },
             CompilerTestCase {
                 input: "let one = 1; one",
                 expected_constants: vec![Object::Integer(1)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpSetGlobal, &vec![0]),
-                    make_instructions(OpGetGlobal, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
                 input: "let one = 1; let two = one; two",
                 expected_constants: vec![Object::Integer(1)],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpSetGlobal, &vec![0]),
-                    make_instructions(OpGetGlobal, &vec![0]),
-                    make_instructions(OpSetGlobal, &vec![1]),
-                    make_instructions(OpGetGlobal, &vec![1]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpSetGlobal, &[1]),
+                    make_instructions(OpGetGlobal, &[1]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
@@ -296,6 +423,65 @@ mod tests {
         run_compiler_test(tests);
     }
 
+    #[test]
+    fn test_integer_opcodes() {
+        let tests = vec![
+            CompilerTestCase {
+                input: "1 + 2 * 3",
+                expected_constants: vec![
+                    Object::Integer(1),
+                    Object::Integer(2),
+                    Object::Integer(3),
+                ],
+                expected_instructions: vec![
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpMulInt, &[]),
+                    make_instructions(OpAddInt, &[]),
+                    make_instructions(OpPop, &[]),
+                ],
+            },
+            CompilerTestCase {
+                input: "let x = 2; -x / x",
+                expected_constants: vec![Object::Integer(2)],
+                expected_instructions: vec![
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpMinus, &[]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpDivInt, &[]),
+                    make_instructions(OpPop, &[]),
+                ],
+            },
+            // what a call returns isn't known
+            CompilerTestCase {
+                input: "let n = len([]); n - 1",
+                expected_constants: vec![Object::Integer(1)],
+                expected_instructions: vec![
+                    make_instructions(OpGetBuiltin, &[0]),
+                    make_instructions(OpArray, &[0]),
+                    make_instructions(OpCall, &[1]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpSub, &[]),
+                    make_instructions(OpPop, &[]),
+                ],
+            },
+        ];
+
+        let options = CompileOptions { int_ops: true, ..Default::default() };
+        for t in tests {
+            let program = parser::parse(t.input).unwrap();
+            let mut compiler = Compiler::new_with_options(options.clone());
+            let bytecode = compiler.compile(&program).unwrap();
+            test_instructions(&t.expected_instructions, &bytecode.instructions);
+            test_constants(&t.expected_constants, &bytecode.constants);
+        }
+    }
+
     #[test]
     fn test_string() {
         let tests = vec![
@@ -303,8 +489,8 @@ mod tests {
                 input: "\"monkey\"",
                 expected_constants: vec![Object::String("monkey".to_string())],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -314,10 +500,10 @@ mod tests {
                     Object::String("key".to_string()),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpAdd, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpAdd, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
@@ -332,8 +518,8 @@ mod tests {
                 input: "[]",
                 expected_constants: vec![],
                 expected_instructions: vec![
-                    make_instructions(OpArray, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpArray, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -344,11 +530,11 @@ mod tests {
                     Object::Integer(3),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpConst, &vec![2]),
-                    make_instructions(OpArray, &vec![3]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpArray, &[3]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -362,17 +548,17 @@ mod tests {
                     Object::Integer(6),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpAdd, &vec![0]),
-                    make_instructions(OpConst, &vec![2]),
-                    make_instructions(OpConst, &vec![3]),
-                    make_instructions(OpSub, &vec![0]),
-                    make_instructions(OpConst, &vec![4]),
-                    make_instructions(OpConst, &vec![5]),
-                    make_instructions(OpMul, &vec![0]),
-                    make_instructions(OpArray, &vec![3]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpAdd, &[0]),
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpConst, &[3]),
+                    make_instructions(OpSub, &[0]),
+                    make_instructions(OpConst, &[4]),
+                    make_instructions(OpConst, &[5]),
+                    make_instructions(OpMul, &[0]),
+                    make_instructions(OpArray, &[3]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
@@ -387,8 +573,8 @@ mod tests {
                 input: "{}",
                 expected_constants: vec![],
                 expected_instructions: vec![
-                    make_instructions(OpHash, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpHash, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -402,14 +588,14 @@ mod tests {
                     Object::Integer(6),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpConst, &vec![2]),
-                    make_instructions(OpConst, &vec![3]),
-                    make_instructions(OpConst, &vec![4]),
-                    make_instructions(OpConst, &vec![5]),
-                    make_instructions(OpHash, &vec![6]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpConst, &[3]),
+                    make_instructions(OpConst, &[4]),
+                    make_instructions(OpConst, &[5]),
+                    make_instructions(OpHash, &[6]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -423,16 +609,16 @@ mod tests {
                     Object::Integer(6),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpConst, &vec![2]),
-                    make_instructions(OpAdd, &vec![0]),
-                    make_instructions(OpConst, &vec![3]),
-                    make_instructions(OpConst, &vec![4]),
-                    make_instructions(OpConst, &vec![5]),
-                    make_instructions(OpMul, &vec![0]),
-                    make_instructions(OpHash, &vec![4]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpAdd, &[0]),
+                    make_instructions(OpConst, &[3]),
+                    make_instructions(OpConst, &[4]),
+                    make_instructions(OpConst, &[5]),
+                    make_instructions(OpMul, &[0]),
+                    make_instructions(OpHash, &[4]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
@@ -453,15 +639,15 @@ mod tests {
                     Object::Integer(1),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpConst, &vec![2]),
-                    make_instructions(OpArray, &vec![3]),
-                    make_instructions(OpConst, &vec![3]),
-                    make_instructions(OpConst, &vec![4]),
-                    make_instructions(OpAdd, &vec![0]),
-                    make_instructions(OpIndex, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpArray, &[3]),
+                    make_instructions(OpConst, &[3]),
+                    make_instructions(OpConst, &[4]),
+                    make_instructions(OpAdd, &[0]),
+                    make_instructions(OpIndex, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
             CompilerTestCase {
@@ -473,18 +659,193 @@ mod tests {
                     Object::Integer(1),
                 ],
                 expected_instructions: vec![
-                    make_instructions(OpConst, &vec![0]),
-                    make_instructions(OpConst, &vec![1]),
-                    make_instructions(OpHash, &vec![2]),
-                    make_instructions(OpConst, &vec![2]),
-                    make_instructions(OpConst, &vec![3]),
-                    make_instructions(OpSub, &vec![0]),
-                    make_instructions(OpIndex, &vec![0]),
-                    make_instructions(OpPop, &vec![0]),
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpHash, &[2]),
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpConst, &[3]),
+                    make_instructions(OpSub, &[0]),
+                    make_instructions(OpIndex, &[0]),
+                    make_instructions(OpPop, &[0]),
                 ],
             },
         ];
 
         run_compiler_test(tests);
     }
+    #[test]
+    fn test_match() {
+        let tests = vec![
+            CompilerTestCase {
+                input: "match ([1]) { [x] => x, _ => 0 }",
+                expected_constants: vec![
+                    Object::Integer(1),
+                    Object::Integer(0),
+                    Object::Integer(0),
+                ],
+                expected_instructions: vec![
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpArray, &[1]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    // [x]
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpMatchArray, &[1, 0]),
+                    make_instructions(OpJumpNotTruthy, &[35]),
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpIndex, &[]),
+                    make_instructions(OpSetGlobal, &[1]),
+                    make_instructions(OpGetGlobal, &[1]),
+                    make_instructions(OpJump, &[42]),
+                    // _
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpJump, &[42]),
+                    make_instructions(OpNull, &[]),
+                    make_instructions(OpPop, &[]),
+                ],
+            },
+            CompilerTestCase {
+                // literal arms are a chain of equality checks, each jumping to the next arm
+                input: "match (2) { 1 => 10, \"a\" => 20, _ => 30 }",
+                expected_constants: vec![
+                    Object::Integer(2),
+                    Object::Integer(1),
+                    Object::Integer(10),
+                    Object::String("a".to_string()),
+                    Object::Integer(20),
+                    Object::Integer(30),
+                ],
+                expected_instructions: vec![
+                    make_instructions(OpConst, &[0]),
+                    make_instructions(OpSetGlobal, &[0]),
+                    // 1
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpConst, &[1]),
+                    make_instructions(OpEqual, &[]),
+                    make_instructions(OpJumpNotTruthy, &[22]),
+                    make_instructions(OpConst, &[2]),
+                    make_instructions(OpJump, &[45]),
+                    // "a"
+                    make_instructions(OpGetGlobal, &[0]),
+                    make_instructions(OpConst, &[3]),
+                    make_instructions(OpEqual, &[]),
+                    make_instructions(OpJumpNotTruthy, &[38]),
+                    make_instructions(OpConst, &[4]),
+                    make_instructions(OpJump, &[45]),
+                    // _
+                    make_instructions(OpConst, &[5]),
+                    make_instructions(OpJump, &[45]),
+                    make_instructions(OpNull, &[]),
+                    make_instructions(OpPop, &[]),
+                ],
+            },
+        ];
+
+        run_compiler_test(tests);
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let err = compile("let = 1; let x 2;").unwrap_err();
+        let expected = [
+            "`=` at line 1, column 5 not an identifier",
+            "  1 | let = 1; let x 2;",
+            "    |     ^",
+            "expected `=` at line 1, column 16, got `2`",
+            "  1 | let = 1; let x 2;",
+            "    |                ^",
+        ];
+        assert_eq!(err.lines().collect::<Vec<_>>(), expected, "{}", err);
+
+        let err = compile("len(y)").unwrap_err();
+        assert_eq!(err, "unknown identifier y at line 1, column 5");
+        let err = compile("1 +\n  -y").unwrap_err();
+        assert_eq!(err, "unknown identifier y at line 2, column 4");
+
+        // the closest name in scope, a builtin too, if it's close enough to be a typo
+        let test_case = [
+            (
+                "let length = 3; lenght",
+                "unknown identifier lenght at line 1, column 17, did you mean 'length'?",
+            ),
+            ("lenn([1])", "unknown identifier lenn at line 1, column 1, did you mean 'len'?"),
+            (
+                "fn(count) { cont }",
+                "unknown identifier cont at line 1, column 13, did you mean 'count'?",
+            ),
+            (
+                "let total = 1; fn() { fn() { totl } }",
+                "unknown identifier totl at line 1, column 30, did you mean 'total'?",
+            ),
+            ("let x = 1; banana", "unknown identifier banana at line 1, column 12"),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(compile(input).unwrap_err(), error);
+        }
+
+        let test_case = [
+            ("break;", "`break` outside a loop at line 1, column 1"),
+            ("while (true) { fn() { break; }; }", "`break` outside a loop at line 1, column 23"),
+            (
+                "a: while (true) { break b; }",
+                "no loop labeled `b` around the `break` at line 1, column 19",
+            ),
+        ];
+        // a parse error, shown over the line it's on
+        for (input, error) in test_case {
+            assert_eq!(compile(input).unwrap_err().lines().next(), Some(error));
+        }
+
+        // an assignment sets a name bound in its own function or a global
+        let test_case = [
+            ("y = 1", "assignment to undefined variable y at line 1, column 1"),
+            (
+                "fn() { let a = 1; }; a = 2",
+                "assignment to undefined variable a at line 1, column 22",
+            ),
+            ("len = 1", "can't assign to the builtin len at line 1, column 1"),
+            (
+                "fn() { let n = 0; fn() { n = n + 1 } }",
+                "can't assign to n at line 1, column 26, a function around this one binds it",
+            ),
+            (
+                "fn(n) { fn() { n = 1 } }",
+                "can't assign to n at line 1, column 16, a function around this one binds it",
+            ),
+            // and so does a compound or an index assignment
+            ("q += 1", "assignment to undefined variable q at line 1, column 1"),
+            ("qs[0] = 1", "assignment to undefined variable qs at line 1, column 1"),
+            ("len += 1", "can't assign to the builtin len at line 1, column 1"),
+            (
+                "fn(n) { fn() { n += 1; n } }",
+                "can't assign to n at line 1, column 16, a function around this one binds it",
+            ),
+            (
+                "fn(xs) { fn() { xs[0] = 1 } }",
+                "can't assign to xs at line 1, column 17, a function around this one binds it",
+            ),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(compile(input).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_operators() {
+        // the parser never builds these, but a hand-made AST can
+        let mut program = parser::parse("1;\n-2").unwrap();
+        if let Node::Program(p) = &mut program {
+            if let Statement::Expr(Expression::PREFIX(prefix)) = &mut p.body[1] {
+                prefix.op.kind = TokenKind::ASTERISK;
+            }
+        }
+
+        let err = Compiler::new().compile(&program).unwrap_err();
+        assert_eq!(err, "not yet supported: prefix operator `*` at 3..5");
+
+        let mut compiler = Compiler::new();
+        compiler.set_source("1;\n-2");
+        let err = compiler.compile(&program).unwrap_err();
+        assert_eq!(err, "not yet supported: prefix operator `*` at line 2");
+    }
 }
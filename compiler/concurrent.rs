@@ -0,0 +1,178 @@
+use std::ptr;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use object::channel::{chan, recv, send, spawn};
+use object::{arity_error, interrupt, BuiltinFunc, Object};
+
+use crate::bundle::{decode_values, encode_values};
+use crate::compiler::Bytecode;
+use crate::op_code::Instructions;
+use crate::vm::{VMError, VM};
+
+// `spawn`, `chan`, `send` and `recv` on threads, see `object::channel`, values crossing between
+// them as bundle bytes, see `bundle`
+type Sent = Vec<u8>;
+
+lazy_static! {
+    // every channel of the process, a channel value is its index
+    static ref CHANNELS: Mutex<Vec<(Sender<Sent>, Receiver<Sent>)>> = Mutex::new(vec![]);
+}
+
+// how long `recv` waits before it checks for Ctrl-C, see `interrupt`
+const RECV_WAIT: Duration = Duration::from_millis(10);
+
+// the result of the builtin `bt`, `None` when it isn't one of the four
+pub fn call(
+    bt: BuiltinFunc,
+    args: &[Rc<Object>],
+    constants: &[Rc<Object>],
+    globals: &[Rc<Object>],
+) -> Option<Result<Rc<Object>, VMError>> {
+    if ptr::fn_addr_eq(bt, spawn as BuiltinFunc) {
+        return Some(spawn_thread(args, constants, globals));
+    }
+    if ptr::fn_addr_eq(bt, chan as BuiltinFunc) {
+        if !args.is_empty() {
+            return Some(Ok(error(format!(
+                "builtin chan expected 0 arguments, got {}",
+                args.len()
+            ))));
+        }
+        return Some(Ok(Rc::new(Object::Channel(open()))));
+    }
+    if ptr::fn_addr_eq(bt, send as BuiltinFunc) {
+        return Some(send_value(args));
+    }
+    if ptr::fn_addr_eq(bt, recv as BuiltinFunc) {
+        return Some(recv_value(args));
+    }
+    return None;
+}
+
+fn open() -> u64 {
+    let mut channels = CHANNELS.lock().unwrap();
+    channels.push(unbounded());
+    return (channels.len() - 1) as u64;
+}
+
+fn sender(id: u64) -> Sender<Sent> {
+    return CHANNELS.lock().unwrap()[id as usize].0.clone();
+}
+
+fn spawn_thread(
+    args: &[Rc<Object>],
+    constants: &[Rc<Object>],
+    globals: &[Rc<Object>],
+) -> Result<Rc<Object>, VMError> {
+    let f = match args {
+        [f] => f,
+        _ => return Ok(error(format!("builtin spawn expected 1 argument, got {}", args.len()))),
+    };
+    match &**f {
+        Object::ClosureObj(closure) => {
+            if let Some(e) = arity_error(closure.func.num_parameters, closure.func.rest, 0) {
+                return Ok(error(e));
+            }
+        }
+        o if o.is_callable() => {}
+        o => return Ok(error(format!("builtin spawn expected a function, got {}", o.type_name()))),
+    }
+    let program = match encode_values(constants.to_vec()) {
+        Ok(program) => program,
+        Err(_) => return Ok(error("builtin spawn can't send an iterator".to_string())),
+    };
+    // a global that can't be copied is null on the new thread, for it can't have any use there
+    let copies = globals
+        .iter()
+        .map(|g| encode_values(vec![Rc::clone(g)]).unwrap_or_default());
+    let globals: Vec<Vec<u8>> = copies.collect();
+    let f = match encode_values(vec![Rc::clone(f)]) {
+        Ok(f) => f,
+        Err(_) => return Ok(error("builtin spawn can't send an iterator".to_string())),
+    };
+    let result = open();
+    let results = sender(result);
+    let work = move || {
+        let value = run(&program, &globals, &f).unwrap_or_else(error);
+        let sent = encode_values(vec![value]).unwrap_or_else(|_| {
+            let unsendable = error("builtin spawn can't send an iterator".to_string());
+            return encode_values(vec![unsendable]).unwrap();
+        });
+        // no one is left to receive it once the program is done
+        let _ = results.send(sent);
+    };
+    if let Err(e) = thread::Builder::new().spawn(work) {
+        return Err(format!("can't spawn a thread: {}", e));
+    }
+    return Ok(Rc::new(Object::Channel(result)));
+}
+
+// the function in `f` called in a vm of its own, with `program`'s constants and `globals`
+fn run(program: &[u8], globals: &[Vec<u8>], f: &[u8]) -> Result<Rc<Object>, VMError> {
+    let constants = decode_values(program)?;
+    let globals = globals
+        .iter()
+        .map(|g| match g.is_empty() {
+            true => Ok(Rc::new(Object::Null)),
+            false => Ok(decode_values(g)?.remove(0)),
+        })
+        .collect::<Result<Vec<Rc<Object>>, VMError>>()?;
+    let f = decode_values(f)?.remove(0);
+    let instructions = Instructions { data: vec![] };
+    let mut vm =
+        VM::new_with_global_store(Bytecode { instructions, constants, num_globals: 0 }, globals);
+    // the spawning thread's frames mean nothing here
+    return vm
+        .call(f, &[])
+        .map_err(|e| e.lines().next().unwrap_or("").to_string());
+}
+
+fn send_value(args: &[Rc<Object>]) -> Result<Rc<Object>, VMError> {
+    let (id, value) = match args {
+        [ch, value] => match **ch {
+            Object::Channel(id) => (id, value),
+            ref o => {
+                let message = format!("builtin send not supported for type {}", o.type_name());
+                return Ok(error(message));
+            }
+        },
+        _ => return Ok(error(format!("builtin send expected 2 arguments, got {}", args.len()))),
+    };
+    let bytes = match encode_values(vec![Rc::clone(value)]) {
+        Ok(bytes) => bytes,
+        Err(_) => return Ok(error("builtin send can't send an iterator".to_string())),
+    };
+    // the channel holds its own receiver, so sending can't fail
+    sender(id).send(bytes).unwrap();
+    return Ok(Rc::new(Object::Null));
+}
+
+fn recv_value(args: &[Rc<Object>]) -> Result<Rc<Object>, VMError> {
+    let id = match args {
+        [ch] => match **ch {
+            Object::Channel(id) => id,
+            ref o => {
+                let message = format!("builtin recv not supported for type {}", o.type_name());
+                return Ok(error(message));
+            }
+        },
+        _ => return Ok(error(format!("builtin recv expected 1 argument, got {}", args.len()))),
+    };
+    // cloned out of the lock, for other threads to use the channels while this one waits
+    let receiver = CHANNELS.lock().unwrap()[id as usize].1.clone();
+    loop {
+        match receiver.recv_timeout(RECV_WAIT) {
+            Ok(bytes) => return Ok(decode_values(&bytes)?.remove(0)),
+            Err(RecvTimeoutError::Timeout) => interrupt::check()?,
+            Err(RecvTimeoutError::Disconnected) => unreachable!("channels hold their own senders"),
+        }
+    }
+}
+
+fn error(message: String) -> Rc<Object> {
+    return Rc::new(Object::Error(message));
+}
@@ -0,0 +1,274 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use object::builtins::PURE;
+use object::environment::{Budget, Env, Environment};
+use object::number;
+use object::Object;
+use parser::ast::{
+    BlockStatement, Expression, FunctionCall, FunctionDeclaration, Literal, Node, Statement,
+};
+
+// how many calls and loop turns folding an expression can take, and how deep its calls can nest,
+// well within the stack of a thread compiling it
+const FOLD_STEPS: usize = 100_000;
+const FOLD_DEPTH: usize = 64;
+
+// compile-time evaluation of pure top-level expressions, see `CompileOptions::const_eval`, with the
+// bindings folded so far in `env`
+pub struct ConstEvaluator {
+    env: Env,
+    values: HashSet<String>,
+    functions: HashMap<String, FunctionDeclaration>,
+    // globals that are neither, they hide a builtin of the same name
+    shadowed: HashSet<String>,
+}
+
+impl ConstEvaluator {
+    pub fn new() -> ConstEvaluator {
+        return ConstEvaluator {
+            env: Rc::new(RefCell::new(Environment::default())),
+            values: HashSet::new(),
+            functions: HashMap::new(),
+            shadowed: HashSet::new(),
+        };
+    }
+
+    // the value of `expr` if it is pure and worth baking into the constant pool
+    pub fn fold(&self, expr: &Expression) -> Option<Object> {
+        match expr {
+            Expression::LITERAL(Literal::Integer(_))
+            | Expression::LITERAL(Literal::Decimal(_))
+            | Expression::LITERAL(Literal::Boolean(_))
+            | Expression::LITERAL(Literal::String(_))
+            | Expression::LITERAL(Literal::Char(_))
+            | Expression::FUNCTION(_) => return None,
+            _ => {}
+        }
+        if binds_globals(expr) || !self.is_pure_expr(expr, &mut HashSet::new(), &mut HashSet::new())
+        {
+            return None;
+        }
+
+        // evaluation errors are left for the VM to report at runtime, and so is a call that
+        // recurses without end or takes too long to be worth waiting for while compiling
+        self.env
+            .borrow_mut()
+            .set_budget(Budget::new(FOLD_STEPS, FOLD_DEPTH));
+        let value = interpreter::eval(Node::Expression(expr.clone()), &self.env).ok()?;
+        if !is_constant(&value) {
+            return None;
+        }
+
+        return Some((*value).clone());
+    }
+
+    pub fn define_value(&mut self, name: &str, value: Object) {
+        self.forget(name);
+        self.values.insert(name.to_string());
+        self.env.borrow_mut().set(name.to_string(), Rc::new(value));
+    }
+
+    pub fn define_function(&mut self, name: &str, f: &FunctionDeclaration) {
+        self.forget(name);
+        self.functions.insert(name.to_string(), f.clone());
+        let env = Rc::clone(&self.env);
+        let function = Object::Function(f.params.clone(), f.body.clone(), env, f.rest);
+        self.env
+            .borrow_mut()
+            .set(name.to_string(), Rc::new(function));
+    }
+
+    // `name` was bound to something only known at runtime
+    pub fn forget(&mut self, name: &str) {
+        self.values.remove(name);
+        self.functions.remove(name);
+        self.shadowed.insert(name.to_string());
+    }
+
+    // `visiting` holds the functions being checked, so recursion counts as pure
+    fn is_pure_expr(
+        &self,
+        e: &Expression,
+        locals: &mut HashSet<String>,
+        visiting: &mut HashSet<String>,
+    ) -> bool {
+        match e {
+            Expression::IDENTIFIER(id) => self.is_pure_name(&id.name, locals, visiting),
+            Expression::LITERAL(Literal::Array(array)) => array
+                .elements
+                .iter()
+                .all(|e| self.is_pure_expr(e, locals, visiting)),
+            Expression::LITERAL(Literal::Hash(hash)) => hash.elements.iter().all(|(k, v)| {
+                self.is_pure_expr(k, locals, visiting) && self.is_pure_expr(v, locals, visiting)
+            }),
+            Expression::LITERAL(_) => true,
+            Expression::PREFIX(prefix) => self.is_pure_expr(&prefix.operand, locals, visiting),
+            Expression::INFIX(infix) => {
+                self.is_pure_expr(&infix.left, locals, visiting)
+                    && self.is_pure_expr(&infix.right, locals, visiting)
+            }
+            Expression::IF(if_node) => {
+                self.is_pure_expr(&if_node.condition, locals, visiting)
+                    && self.is_pure_block(&if_node.consequent, locals, visiting)
+                    && match &if_node.alternate {
+                        Some(alternate) => self.is_pure_block(alternate, locals, visiting),
+                        None => true,
+                    }
+            }
+            Expression::Index(index) => {
+                self.is_pure_expr(&index.object, locals, visiting)
+                    && self.is_pure_expr(&index.index, locals, visiting)
+            }
+            Expression::FUNCTION(f) => {
+                let mut scope = locals.clone();
+                scope.extend(f.params.iter().map(|p| p.name.clone()));
+                self.is_pure_block(&f.body, &mut scope, visiting)
+            }
+            // its arms bind names, as `let` in a block does
+            Expression::Match(_) | Expression::Assign(_) => false,
+            // values only ever come from pure code, so calling a parameter is fine too
+            Expression::FunctionCall(call) => {
+                self.is_pure_expr(&call.callee, locals, visiting)
+                    && call
+                        .arguments
+                        .iter()
+                        .all(|a| self.is_pure_expr(a, locals, visiting))
+            }
+        }
+    }
+
+    fn is_pure_block(
+        &self,
+        block: &BlockStatement,
+        locals: &mut HashSet<String>,
+        visiting: &mut HashSet<String>,
+    ) -> bool {
+        for stmt in block.body.iter() {
+            let pure = match stmt {
+                Statement::Let(l) => {
+                    let pure = self.is_pure_expr(&l.expr, locals, visiting);
+                    locals.insert(l.identifier.kind.to_string());
+                    pure
+                }
+                Statement::Return(r) => self.is_pure_expr(&r.argument, locals, visiting),
+                Statement::Expr(e) => self.is_pure_expr(e, locals, visiting),
+                // it may never end, and the compiler with it
+                Statement::Import(_) | Statement::While(_) | Statement::Break(_) => false,
+            };
+            if !pure {
+                return false;
+            }
+        }
+
+        return true;
+    }
+
+    fn is_pure_name(
+        &self,
+        name: &str,
+        locals: &mut HashSet<String>,
+        visiting: &mut HashSet<String>,
+    ) -> bool {
+        if locals.contains(name) || self.values.contains(name) {
+            return true;
+        }
+        if let Some(f) = self.functions.get(name) {
+            if !visiting.insert(name.to_string()) {
+                return true;
+            }
+            let mut scope: HashSet<String> = f.params.iter().map(|p| p.name.clone()).collect();
+            return self.is_pure_block(&f.body, &mut scope, visiting);
+        }
+
+        return !self.shadowed.contains(name) && PURE.contains(&name);
+    }
+}
+
+// a call of a pure builtin on literals, anywhere, folded; one that fails is left for the VM
+pub fn fold_builtin_call(
+    call: &FunctionCall,
+    builtin: &mut dyn FnMut(&str) -> bool,
+) -> Option<Object> {
+    if !is_literal_call(call, builtin) {
+        return None;
+    }
+    let env = Rc::new(RefCell::new(Environment::default()));
+    let value = interpreter::eval(Node::Expression(Expression::FunctionCall(call.clone())), &env);
+    let value = value.ok()?;
+    if !is_constant(&value) {
+        return None;
+    }
+
+    return Some((*value).clone());
+}
+
+fn is_literal_call(call: &FunctionCall, builtin: &mut dyn FnMut(&str) -> bool) -> bool {
+    match &*call.callee {
+        Expression::IDENTIFIER(id) if PURE.contains(&id.name.as_str()) && builtin(&id.name) => {}
+        _ => return false,
+    }
+    return call.arguments.iter().all(|a| is_literal(a, builtin));
+}
+
+fn is_literal(e: &Expression, builtin: &mut dyn FnMut(&str) -> bool) -> bool {
+    match e {
+        Expression::LITERAL(Literal::Array(array)) => {
+            array.elements.iter().all(|e| is_literal(e, builtin))
+        }
+        Expression::LITERAL(Literal::Hash(hash)) => hash
+            .elements
+            .iter()
+            .all(|(k, v)| is_literal(k, builtin) && is_literal(v, builtin)),
+        Expression::LITERAL(_) => true,
+        Expression::PREFIX(prefix) => is_literal(&prefix.operand, builtin),
+        Expression::FunctionCall(call) => is_literal_call(call, builtin),
+        _ => false,
+    }
+}
+
+// a `let` in a top-level `if` block defines a global, and so does a name in a `match` pattern,
+// folding it away would lose the binding
+fn binds_globals(e: &Expression) -> bool {
+    let block_binds = |block: &BlockStatement| {
+        block.body.iter().any(|s| match s {
+            Statement::Let(_) => true,
+            Statement::Return(r) => binds_globals(&r.argument),
+            Statement::Expr(e) => binds_globals(e),
+            Statement::Import(_) | Statement::Break(_) => false,
+            Statement::While(_) => true,
+        })
+    };
+    match e {
+        Expression::IF(if_node) => {
+            binds_globals(&if_node.condition)
+                || block_binds(&if_node.consequent)
+                || if_node.alternate.as_ref().is_some_and(block_binds)
+        }
+        Expression::LITERAL(Literal::Array(array)) => array.elements.iter().any(binds_globals),
+        Expression::LITERAL(Literal::Hash(hash)) => hash
+            .elements
+            .iter()
+            .any(|(k, v)| binds_globals(k) || binds_globals(v)),
+        Expression::PREFIX(prefix) => binds_globals(&prefix.operand),
+        Expression::INFIX(infix) => binds_globals(&infix.left) || binds_globals(&infix.right),
+        Expression::Index(index) => binds_globals(&index.object) || binds_globals(&index.index),
+        Expression::FunctionCall(call) => {
+            binds_globals(&call.callee) || call.arguments.iter().any(binds_globals)
+        }
+        Expression::Match(_) | Expression::Assign(_) => true,
+        Expression::LITERAL(_) | Expression::IDENTIFIER(_) | Expression::FUNCTION(_) => false,
+    }
+}
+
+// objects the constant pool (and a bundle) can hold
+fn is_constant(obj: &Object) -> bool {
+    match obj {
+        Object::Integer(_) | Object::Boolean(_) | Object::String(_) | Object::Null => true,
+        o if number::is_number(o) => true,
+        Object::Array(elements) => elements.iter().all(|e| is_constant(e)),
+        Object::Hash(map) => map.iter().all(|(k, v)| is_constant(k) && is_constant(v)),
+        _ => false,
+    }
+}
@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use object::Object;
+    use parser::parse;
+
+    use crate::bundle::{decode, encode};
+    use crate::compiler::{Bytecode, CompileOptions, Compiler};
+    use crate::op_code::Opcode::{OpConst, OpPop};
+    use crate::op_code::{concat_instructions, make_instructions};
+    use crate::vm::VM;
+
+    fn compile(input: &str) -> Bytecode {
+        let mut compiler = Compiler::new_with_options(CompileOptions::from_opt_level(1));
+        return compiler.compile(&parse(input).unwrap()).unwrap();
+    }
+
+    fn run(bytecode: Bytecode) -> Object {
+        let mut vm = VM::new(bytecode);
+        vm.run().unwrap();
+        return (*vm.last_popped_stack_elm().unwrap()).clone();
+    }
+
+    fn folded(bytecode: &Bytecode, expected: &Object) -> bool {
+        return bytecode.constants.iter().any(|c| **c == *expected);
+    }
+
+    #[test]
+    fn test_pure_calls_are_folded() {
+        let input = "let double = fn(x) { x * 2 }; double(21)";
+        let bytecode = compile(input);
+        assert!(folded(&bytecode, &Object::Integer(42)));
+        // `double(21)` became a constant load
+        let tail = concat_instructions(&vec![
+            make_instructions(OpConst, &[bytecode.constants.len() - 1]),
+            make_instructions(OpPop, &[]),
+        ]);
+        assert!(bytecode.instructions.data.ends_with(&tail.data));
+        assert_eq!(run(bytecode), Object::Integer(42));
+
+        let fib = "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(15)";
+        let bytecode = compile(fib);
+        assert!(folded(&bytecode, &Object::Integer(610)));
+        assert_eq!(run(bytecode), Object::Integer(610));
+    }
+
+    #[test]
+    fn test_composites_and_builtins() {
+        let bytecode =
+            compile("let xs = push([1, 2], 3); let config = {\"size\": len(xs)}; config");
+        let xs = Object::Array(
+            vec![
+                Rc::new(Object::Integer(1)),
+                Rc::new(Object::Integer(2)),
+                Rc::new(Object::Integer(3)),
+            ]
+            .into(),
+        );
+        assert!(folded(&bytecode, &xs));
+
+        let decoded = decode(&encode(&bytecode).unwrap()).unwrap();
+        assert_eq!(decoded.constants, bytecode.constants);
+        assert_eq!(run(decoded).to_string(), "[size: 3]");
+    }
+
+    #[test]
+    fn test_impure_code_is_compiled() {
+        let bytecode = compile("let shout = fn(x) { puts(x); x }; shout(7) + 1");
+        assert!(!folded(&bytecode, &Object::Integer(8)));
+        assert_eq!(run(bytecode), Object::Integer(8));
+
+        // `g` reads a global that is rebound to a runtime value
+        let rebound = "let x = 1 + 1; let g = fn() { x }; let x = puts(2); g()";
+        let plain = Compiler::new().compile(&parse(rebound).unwrap()).unwrap();
+        assert_eq!(run(compile(rebound)), run(plain));
+
+        // a global hiding a pure builtin is not pure
+        let bytecode = compile("let len = fn(x) { puts(x) }; len([5, 6]); let n = len([5, 6]); n");
+        assert!(!folded(&bytecode, &Object::Integer(2)));
+
+        // the `let` inside the block still has to define `y`
+        let bytecode = compile("if (true) { let y = 2; y }; y * 3");
+        assert_eq!(run(bytecode), Object::Integer(6));
+    }
+
+    #[test]
+    fn test_builtin_calls_on_literals() {
+        // in a function that isn't folded itself, and through the call it's passed
+        let input = "let greet = fn(name) { puts(name); len(\"hello\") * len(push([10, 20], 30)) };
+            greet(\"you\")";
+        let bytecode = compile(input);
+        assert!(folded(&bytecode, &Object::Integer(5)));
+        assert!(folded(&bytecode, &Object::Integer(3)));
+        assert!(!folded(&bytecode, &Object::Integer(30)));
+        assert_eq!(run(bytecode), Object::Integer(15));
+
+        // not a parameter hiding the builtin
+        let bytecode = compile("let f = fn(len) { len(\"hello\") }; f(fn(s) { 1 })");
+        assert!(!folded(&bytecode, &Object::Integer(5)));
+        assert_eq!(run(bytecode), Object::Integer(1));
+
+        // a call that fails fails when it's run
+        let failing = "let f = fn() { len(1) }; f()";
+        let mut vm = VM::new(compile(failing));
+        let plain = Compiler::new().compile(&parse(failing).unwrap()).unwrap();
+        assert_eq!(vm.run(), VM::new(plain).run());
+    }
+
+    #[test]
+    fn test_unbounded_calls_run_at_runtime() {
+        // folding gives up on them, and the VM does what it does at -O0
+        for input in [
+            "let f = fn(x) { f(x) }; f(1)",
+            "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(100000)",
+        ] {
+            let plain = Compiler::new().compile(&parse(input).unwrap()).unwrap();
+            assert_eq!(VM::new(compile(input)).run(), VM::new(plain).run(), "{}", input);
+        }
+        let f = "let f = fn(x) { f(x) }; f(1)";
+        let error = VM::new(compile(f)).run().unwrap_err();
+        assert!(error.starts_with("call stack overflow"), "{}", error);
+
+        let count =
+            "let count = fn(n) { let i = 0; while (i < n) { let i = i + 1; } i }; count(200000) + 1";
+        let bytecode = compile(count);
+        assert!(!folded(&bytecode, &Object::Integer(200001)));
+        assert_eq!(run(bytecode), Object::Integer(200001));
+
+        // what fits is still folded
+        let deep = "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(50)";
+        assert!(folded(&compile(deep), &Object::Integer(50)));
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let program = parse("let double = fn(x) { x * 2 }; double(21)").unwrap();
+        let bytecode = Compiler::new().compile(&program).unwrap();
+        assert!(!folded(&bytecode, &Object::Integer(42)));
+    }
+}
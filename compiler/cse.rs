@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use object::builtins::PURE;
+use parser::ast::{BlockStatement, Expression, Literal, Statement};
+
+use crate::symbol_table::Symbol;
+
+// common subexpression elimination for one function body, see `CompileOptions::cse`: a pure
+// computation written twice is loaded from a hidden local the second time
+pub struct CseState {
+    // expressions seen more than once, with the names they read
+    candidates: HashMap<String, Candidate>,
+    // computed and stored, on every path reaching the current instruction
+    pub available: HashMap<String, Rc<Symbol>>,
+    // the hidden local of each expression, reused when it has to be computed again
+    pub temps: HashMap<String, Rc<Symbol>>,
+}
+
+pub struct Candidate {
+    pub names: HashSet<String>,
+    // builtins called, only pure as long as no binding hides them
+    pub builtins: Vec<String>,
+}
+
+impl Candidate {
+    pub fn of(e: &Expression) -> Candidate {
+        let mut candidate = Candidate { names: HashSet::new(), builtins: vec![] };
+        references(e, &mut candidate);
+        return candidate;
+    }
+}
+
+impl CseState {
+    pub fn new(body: &[Statement]) -> CseState {
+        let mut counts: HashMap<String, (usize, Candidate)> = HashMap::new();
+        for stmt in body.iter() {
+            count_stmt(stmt, &mut counts);
+        }
+
+        let candidates = counts
+            .into_iter()
+            .filter(|(_, (count, _))| *count > 1)
+            .map(|(key, (_, candidate))| (key, candidate))
+            .collect();
+        return CseState { candidates, available: HashMap::new(), temps: HashMap::new() };
+    }
+
+    pub fn candidate(&self, e: &Expression) -> Option<(String, &Candidate)> {
+        if self.candidates.is_empty() || !is_computation(e) {
+            return None;
+        }
+        let key = e.to_string();
+        return self.candidates.get(&key).map(|c| (key, c));
+    }
+
+    // `name` was rebound, values read from the old binding are stale
+    pub fn kill(&mut self, name: &str) {
+        let candidates = &self.candidates;
+        self.available
+            .retain(|key, _| !candidates[key].names.contains(name));
+    }
+}
+
+fn count_stmt(stmt: &Statement, counts: &mut HashMap<String, (usize, Candidate)>) {
+    match stmt {
+        Statement::Let(l) => count(&l.expr, counts),
+        Statement::Return(r) => count(&r.argument, counts),
+        Statement::Expr(e) => count(e, counts),
+        Statement::Import(_) | Statement::Break(_) => {}
+        Statement::While(w) => {
+            count(&w.condition, counts);
+            count_block(&w.body, counts);
+        }
+    }
+}
+
+fn count_block(block: &BlockStatement, counts: &mut HashMap<String, (usize, Candidate)>) {
+    block.body.iter().for_each(|s| count_stmt(s, counts));
+}
+
+fn count(e: &Expression, counts: &mut HashMap<String, (usize, Candidate)>) {
+    if is_computation(e) && is_pure(e) {
+        let entry = counts
+            .entry(e.to_string())
+            .or_insert_with(|| (0, Candidate::of(e)));
+        entry.0 += 1;
+    }
+
+    match e {
+        Expression::PREFIX(prefix) => count(&prefix.operand, counts),
+        Expression::INFIX(infix) => {
+            count(&infix.left, counts);
+            count(&infix.right, counts);
+        }
+        Expression::Index(index) => {
+            count(&index.object, counts);
+            count(&index.index, counts);
+        }
+        Expression::FunctionCall(call) => {
+            count(&call.callee, counts);
+            call.arguments.iter().for_each(|a| count(a, counts));
+        }
+        Expression::IF(if_node) => {
+            count(&if_node.condition, counts);
+            count_block(&if_node.consequent, counts);
+            if let Some(alternate) = &if_node.alternate {
+                count_block(alternate, counts);
+            }
+        }
+        Expression::LITERAL(Literal::Array(array)) => {
+            array.elements.iter().for_each(|e| count(e, counts));
+        }
+        Expression::LITERAL(Literal::Hash(hash)) => hash.elements.iter().for_each(|(k, v)| {
+            count(k, counts);
+            count(v, counts);
+        }),
+        Expression::Match(m) => {
+            count(&m.subject, counts);
+            for arm in m.arms.iter() {
+                arm.guard.iter().for_each(|g| count(g, counts));
+                count(&arm.body, counts);
+            }
+        }
+        Expression::Assign(a) => count(&a.value, counts),
+        // a nested function has its own state
+        Expression::FUNCTION(_) | Expression::LITERAL(_) | Expression::IDENTIFIER(_) => {}
+    }
+}
+
+// worth caching: loading a variable or a literal is as cheap as loading the cached value
+pub fn is_computation(e: &Expression) -> bool {
+    match e {
+        Expression::PREFIX(_) | Expression::INFIX(_) | Expression::Index(_) => true,
+        Expression::FunctionCall(call) => is_pure_builtin_call(&call.callee),
+        _ => false,
+    }
+}
+
+fn is_pure_builtin_call(callee: &Expression) -> bool {
+    match callee {
+        Expression::IDENTIFIER(id) => PURE.contains(&id.name.as_str()),
+        _ => false,
+    }
+}
+
+pub fn is_pure(e: &Expression) -> bool {
+    match e {
+        Expression::IDENTIFIER(_) => true,
+        Expression::LITERAL(Literal::Integer(_))
+        | Expression::LITERAL(Literal::Decimal(_))
+        | Expression::LITERAL(Literal::Boolean(_))
+        | Expression::LITERAL(Literal::String(_))
+        | Expression::LITERAL(Literal::Char(_)) => true,
+        Expression::PREFIX(prefix) => is_pure(&prefix.operand),
+        Expression::INFIX(infix) => is_pure(&infix.left) && is_pure(&infix.right),
+        Expression::Index(index) => is_pure(&index.object) && is_pure(&index.index),
+        Expression::FunctionCall(call) => {
+            is_pure_builtin_call(&call.callee) && call.arguments.iter().all(is_pure)
+        }
+        _ => false,
+    }
+}
+
+fn references(e: &Expression, candidate: &mut Candidate) {
+    match e {
+        Expression::IDENTIFIER(id) => {
+            candidate.names.insert(id.name.clone());
+        }
+        Expression::PREFIX(prefix) => references(&prefix.operand, candidate),
+        Expression::INFIX(infix) => {
+            references(&infix.left, candidate);
+            references(&infix.right, candidate);
+        }
+        Expression::Index(index) => {
+            references(&index.object, candidate);
+            references(&index.index, candidate);
+        }
+        Expression::FunctionCall(call) => {
+            if let Expression::IDENTIFIER(id) = &*call.callee {
+                candidate.builtins.push(id.name.clone());
+            }
+            references(&call.callee, candidate);
+            call.arguments.iter().for_each(|a| references(a, candidate));
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use object::Object;
+    use parser::parse;
+
+    use crate::compiler::{Bytecode, CompileOptions, Compiler};
+    use crate::op_code::Opcode::{OpIndex, OpMul, OpMulInt};
+    use crate::op_code::{cast_u8_to_opcode, read_operands, Instructions, Opcode, DEFINITIONS};
+    use crate::vm::VM;
+
+    fn compile(input: &str, level: u8) -> Bytecode {
+        let mut compiler = Compiler::new_with_options(CompileOptions::from_opt_level(level));
+        return compiler.compile(&parse(input).unwrap()).unwrap();
+    }
+
+    fn run(bytecode: Bytecode) -> Object {
+        let mut vm = VM::new(bytecode);
+        vm.run().unwrap();
+        return (*vm.last_popped_stack_elm().unwrap()).clone();
+    }
+
+    // how many times `op` is executed by straight-line code, nested functions included
+    fn count(bytecode: &Bytecode, op: Opcode) -> usize {
+        let mut total = count_in(&bytecode.instructions, op);
+        for c in bytecode.constants.iter() {
+            if let Object::CompiledFunction(f) = &**c {
+                total += count_in(&Instructions { data: f.instructions.clone() }, op);
+            }
+        }
+        return total;
+    }
+
+    fn count_in(ins: &Instructions, op: Opcode) -> usize {
+        let mut total = 0;
+        let mut i = 0;
+        while i < ins.data.len() {
+            let opcode = cast_u8_to_opcode(ins.data[i]);
+            if opcode == op {
+                total += 1;
+            }
+            let (_, read) = read_operands(DEFINITIONS.get(&opcode).unwrap(), &ins.data[i + 1..]);
+            i += 1 + read;
+        }
+        return total;
+    }
+
+    // the optimized program computes what the plain one does
+    fn assert_same(input: &str) -> Bytecode {
+        let optimized = compile(input, 2);
+        assert_eq!(run(optimized.clone()), run(compile(input, 0)), "{}", input);
+        return optimized;
+    }
+
+    #[test]
+    fn test_repeated_expressions_are_computed_once() {
+        let input = "let square = fn(a, i) { a[i] * a[i] }; square([1, 2, 3], 2)";
+        let bytecode = assert_same(input);
+        assert_eq!(count(&bytecode, OpIndex), 1);
+        assert_eq!(count(&compile(input, 0), OpIndex), 2);
+
+        let input =
+            "let f = fn(x, y) { let a = (x + y) * (x + y); let b = (x + y) * (x + y); a + b }; \
+            f(2, 3)";
+        let bytecode = assert_same(input);
+        assert_eq!(count(&bytecode, OpMul), 1);
+        assert_eq!(run(bytecode), Object::Integer(50));
+
+        let input = "let xs = [1, 2, 3]; len(xs) * len(xs)";
+        assert_eq!(run(assert_same(input)), Object::Integer(9));
+    }
+
+    #[test]
+    fn test_rebinding_and_branches() {
+        // `x` changes between the two `x * 2`
+        let input = "let f = fn(x) { let a = x * 2; let x = 10; a + x * 2 }; f(1)";
+        let bytecode = assert_same(input);
+        // the second is on integers, `x` is `10`
+        assert_eq!(count(&bytecode, OpMul), 1);
+        assert_eq!(count(&bytecode, OpMulInt), 1);
+
+        // computed in the branch only, recomputed after it
+        let inputs = vec![
+            "let f = fn(x) { if (x > 0) { x * x } else { 0 }; x * x }; f(3)",
+            "let f = fn(x) { if (x > 0) { 0 } else { x * x }; x * x }; f(3)",
+            "let f = fn(x) { let y = if (x * x > 4) { x * x } else { 1 }; y + x * x }; f(3)",
+            "let a = [1, 2]; let i = 0; let b = a[i] + a[i]; let i = 1; b + a[i] + a[i]",
+        ];
+        for input in inputs {
+            assert_same(input);
+        }
+    }
+
+    #[test]
+    fn test_shadowed_builtins_are_not_cached() {
+        let input = "let calls = fn(len) { len([1]) + len([1]) }; \
+            calls(fn(x) { 20 })";
+        assert_eq!(run(assert_same(input)), Object::Integer(40));
+
+        let input = "let len = fn(x) { 5 }; len([1]) + len([1])";
+        assert_eq!(run(assert_same(input)), Object::Integer(10));
+    }
+}
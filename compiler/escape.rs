@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet};
+
+use parser::ast::{
+    BlockStatement, Expression, FunctionDeclaration, Literal, Pattern, Program, Statement,
+};
+
+// escape analysis: a function literal only called while its creator runs, right away, bound
+// and called, or passed to a parameter that doesn't escape, reads its captures from that frame
+pub struct EscapeInfo {
+    non_escaping: HashSet<*const FunctionDeclaration>,
+}
+
+impl EscapeInfo {
+    pub fn analyze(program: &Program) -> EscapeInfo {
+        let analysis = Analysis { globals: global_functions(program), params: HashMap::new() };
+        let analysis = analysis.solve_params();
+
+        let mut non_escaping = HashSet::new();
+        for stmt in program.body.iter() {
+            analysis.visit_stmt(stmt, None, &mut non_escaping);
+        }
+
+        return EscapeInfo { non_escaping };
+    }
+
+    pub fn is_non_escaping(&self, f: &FunctionDeclaration) -> bool {
+        return self
+            .non_escaping
+            .contains(&(f as *const FunctionDeclaration));
+    }
+}
+
+struct Analysis<'a> {
+    globals: HashMap<String, &'a FunctionDeclaration>,
+    // per global function, whether each parameter escapes
+    params: HashMap<String, Vec<bool>>,
+}
+
+// the function a node is nested in
+struct Scope<'a> {
+    body: &'a BlockStatement,
+    locals: HashSet<String>,
+}
+
+impl<'a> Analysis<'a> {
+    // starts from "nothing escapes" and marks parameters until nothing changes, so recursive
+    // functions passing a parameter on to themselves keep it non-escaping
+    fn solve_params(mut self) -> Self {
+        for (name, f) in self.globals.iter() {
+            self.params
+                .insert(name.clone(), vec![false; f.params.len()]);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (name, f) in self.globals.iter() {
+                let locals = function_locals(f);
+                for (i, param) in f.params.iter().enumerate() {
+                    if !self.params[name][i] && self.escapes(&param.name, &f.body, &locals) {
+                        self.params.get_mut(name).unwrap()[i] = true;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        return self;
+    }
+
+    // the escape flags of the parameters of `callee` when it is a known global function
+    fn param_flags(
+        &self,
+        callee: &Expression,
+        arity: usize,
+        locals: &HashSet<String>,
+    ) -> Option<&Vec<bool>> {
+        match callee {
+            Expression::IDENTIFIER(id) if !locals.contains(&id.name) => self
+                .params
+                .get(&id.name)
+                .filter(|flags| flags.len() == arity),
+            _ => None,
+        }
+    }
+
+    fn escapes(&self, name: &str, block: &BlockStatement, locals: &HashSet<String>) -> bool {
+        return block
+            .body
+            .iter()
+            .any(|s| self.escapes_in_stmt(name, s, locals));
+    }
+
+    fn escapes_in_stmt(&self, name: &str, stmt: &Statement, locals: &HashSet<String>) -> bool {
+        match stmt {
+            // rebinding the name makes later uses ambiguous
+            Statement::Let(l) => {
+                l.identifier.kind.to_string() == name || self.escapes_in(name, &l.expr, locals)
+            }
+            Statement::Return(r) => self.escapes_in(name, &r.argument, locals),
+            Statement::Expr(e) => self.escapes_in(name, e, locals),
+            Statement::Import(_) | Statement::Break(_) => false,
+            Statement::While(w) => {
+                self.escapes_in(name, &w.condition, locals) || self.escapes(name, &w.body, locals)
+            }
+        }
+    }
+
+    fn escapes_in(&self, name: &str, e: &Expression, locals: &HashSet<String>) -> bool {
+        match e {
+            Expression::IDENTIFIER(id) => id.name == name,
+            // assigning the name makes later uses ambiguous, as rebinding it does
+            Expression::Assign(a) => a.name.name == name || self.escapes_in(name, &a.value, locals),
+            Expression::FunctionCall(call) => {
+                let called = matches!(&*call.callee, Expression::IDENTIFIER(id) if id.name == name);
+                if !called && self.escapes_in(name, &call.callee, locals) {
+                    return true;
+                }
+                let flags = self.param_flags(&call.callee, call.arguments.len(), locals);
+                call.arguments.iter().enumerate().any(|(i, arg)| match arg {
+                    Expression::IDENTIFIER(id) if id.name == name => {
+                        flags.is_none_or(|flags| flags[i])
+                    }
+                    arg => self.escapes_in(name, arg, locals),
+                })
+            }
+            // captured by another closure, which may outlive the frame
+            Expression::FUNCTION(f) => mentions_block(name, &f.body),
+            Expression::LITERAL(Literal::Array(array)) => array
+                .elements
+                .iter()
+                .any(|e| self.escapes_in(name, e, locals)),
+            Expression::LITERAL(Literal::Hash(hash)) => hash
+                .elements
+                .iter()
+                .any(|(k, v)| self.escapes_in(name, k, locals) || self.escapes_in(name, v, locals)),
+            Expression::LITERAL(_) => false,
+            Expression::PREFIX(prefix) => self.escapes_in(name, &prefix.operand, locals),
+            Expression::INFIX(infix) => {
+                self.escapes_in(name, &infix.left, locals)
+                    || self.escapes_in(name, &infix.right, locals)
+            }
+            Expression::IF(if_node) => {
+                self.escapes_in(name, &if_node.condition, locals)
+                    || self.escapes(name, &if_node.consequent, locals)
+                    || if_node
+                        .alternate
+                        .as_ref()
+                        .is_some_and(|alternate| self.escapes(name, alternate, locals))
+            }
+            Expression::Index(index) => {
+                self.escapes_in(name, &index.object, locals)
+                    || self.escapes_in(name, &index.index, locals)
+            }
+            // a pattern binding the name rebinds it
+            Expression::Match(m) => {
+                self.escapes_in(name, &m.subject, locals)
+                    || m.arms.iter().any(|arm| {
+                        binds(name, &arm.pattern)
+                            || arm
+                                .guard
+                                .as_ref()
+                                .is_some_and(|g| self.escapes_in(name, g, locals))
+                            || self.escapes_in(name, &arm.body, locals)
+                    })
+            }
+        }
+    }
+
+    fn visit_stmt(
+        &self,
+        stmt: &'a Statement,
+        scope: Option<&Scope>,
+        found: &mut HashSet<*const FunctionDeclaration>,
+    ) {
+        match stmt {
+            Statement::Let(l) => {
+                if let (Expression::FUNCTION(f), Some(scope)) = (&l.expr, scope) {
+                    let name = l.identifier.kind.to_string();
+                    let escapes = mentions_block(&name, &f.body)
+                        || scope
+                            .body
+                            .body
+                            .iter()
+                            .filter(|s| !std::ptr::eq(*s, stmt))
+                            .any(|s| self.escapes_in_stmt(&name, s, &scope.locals));
+                    if !escapes {
+                        found.insert(f as *const FunctionDeclaration);
+                    }
+                }
+                self.visit_expr(&l.expr, scope, found);
+            }
+            Statement::Return(r) => self.visit_expr(&r.argument, scope, found),
+            Statement::Expr(e) => self.visit_expr(e, scope, found),
+            Statement::Import(_) | Statement::Break(_) => {}
+            Statement::While(w) => {
+                self.visit_expr(&w.condition, scope, found);
+                self.visit_block(&w.body, scope, found);
+            }
+        }
+    }
+
+    fn visit_block(
+        &self,
+        block: &'a BlockStatement,
+        scope: Option<&Scope>,
+        found: &mut HashSet<*const FunctionDeclaration>,
+    ) {
+        for stmt in block.body.iter() {
+            self.visit_stmt(stmt, scope, found);
+        }
+    }
+
+    fn visit_expr(
+        &self,
+        e: &'a Expression,
+        scope: Option<&Scope>,
+        found: &mut HashSet<*const FunctionDeclaration>,
+    ) {
+        match e {
+            Expression::FunctionCall(call) => {
+                if let Some(scope) = scope {
+                    if let Expression::FUNCTION(f) = &*call.callee {
+                        found.insert(f as *const FunctionDeclaration);
+                    }
+                    let flags = self.param_flags(&call.callee, call.arguments.len(), &scope.locals);
+                    for (i, arg) in call.arguments.iter().enumerate() {
+                        if let (Expression::FUNCTION(f), Some(false)) = (arg, flags.map(|f| f[i])) {
+                            found.insert(f as *const FunctionDeclaration);
+                        }
+                    }
+                }
+                self.visit_expr(&call.callee, scope, found);
+                for arg in call.arguments.iter() {
+                    self.visit_expr(arg, scope, found);
+                }
+            }
+            Expression::FUNCTION(f) => {
+                let inner = Scope { body: &f.body, locals: function_locals(f) };
+                self.visit_block(&f.body, Some(&inner), found);
+            }
+            Expression::LITERAL(Literal::Array(array)) => {
+                for e in array.elements.iter() {
+                    self.visit_expr(e, scope, found);
+                }
+            }
+            Expression::LITERAL(Literal::Hash(hash)) => {
+                for (k, v) in hash.elements.iter() {
+                    self.visit_expr(k, scope, found);
+                    self.visit_expr(v, scope, found);
+                }
+            }
+            Expression::LITERAL(_) | Expression::IDENTIFIER(_) => {}
+            Expression::Assign(a) => self.visit_expr(&a.value, scope, found),
+            Expression::PREFIX(prefix) => self.visit_expr(&prefix.operand, scope, found),
+            Expression::INFIX(infix) => {
+                self.visit_expr(&infix.left, scope, found);
+                self.visit_expr(&infix.right, scope, found);
+            }
+            Expression::IF(if_node) => {
+                self.visit_expr(&if_node.condition, scope, found);
+                self.visit_block(&if_node.consequent, scope, found);
+                if let Some(alternate) = &if_node.alternate {
+                    self.visit_block(alternate, scope, found);
+                }
+            }
+            Expression::Index(index) => {
+                self.visit_expr(&index.object, scope, found);
+                self.visit_expr(&index.index, scope, found);
+            }
+            Expression::Match(m) => {
+                self.visit_expr(&m.subject, scope, found);
+                for arm in m.arms.iter() {
+                    if let Some(guard) = &arm.guard {
+                        self.visit_expr(guard, scope, found);
+                    }
+                    self.visit_expr(&arm.body, scope, found);
+                }
+            }
+        }
+    }
+}
+
+// top-level `let name = fn(...) {...}`, for names bound exactly once and never assigned
+fn global_functions(program: &Program) -> HashMap<String, &FunctionDeclaration> {
+    let mut bindings: HashMap<String, Option<&FunctionDeclaration>> = HashMap::new();
+    for name in program.assigned_names() {
+        bindings.insert(name.to_string(), None);
+    }
+    for stmt in program
+        .body
+        .iter()
+        .filter(|s| !matches!(s, Statement::Let(l) if l.is_throwaway()))
+    {
+        if let Statement::Let(l) = stmt {
+            // the arguments past the others of a `...rest` are in an array, which isn't followed
+            let f = match &l.expr {
+                Expression::FUNCTION(f) if !f.rest => Some(f),
+                _ => None,
+            };
+            let name = l.identifier.kind.to_string();
+            let once = !bindings.contains_key(&name);
+            bindings.insert(name, f.filter(|_| once));
+        }
+    }
+
+    return bindings
+        .into_iter()
+        .filter_map(|(name, f)| f.map(|f| (name, f)))
+        .collect();
+}
+
+// parameters and every `let` of the body outside nested functions
+fn function_locals(f: &FunctionDeclaration) -> HashSet<String> {
+    fn collect_block(block: &BlockStatement, locals: &mut HashSet<String>) {
+        for stmt in block.body.iter() {
+            match stmt {
+                Statement::Let(l) => {
+                    locals.insert(l.identifier.kind.to_string());
+                    collect(&l.expr, locals);
+                }
+                Statement::Return(r) => collect(&r.argument, locals),
+                Statement::Expr(e) => collect(e, locals),
+                Statement::Import(_) | Statement::Break(_) => {}
+                Statement::While(w) => {
+                    collect(&w.condition, locals);
+                    collect_block(&w.body, locals);
+                }
+            }
+        }
+    }
+    fn collect(e: &Expression, locals: &mut HashSet<String>) {
+        match e {
+            Expression::IF(if_node) => {
+                collect(&if_node.condition, locals);
+                collect_block(&if_node.consequent, locals);
+                if let Some(alternate) = &if_node.alternate {
+                    collect_block(alternate, locals);
+                }
+            }
+            Expression::FunctionCall(call) => {
+                collect(&call.callee, locals);
+                call.arguments.iter().for_each(|a| collect(a, locals));
+            }
+            Expression::LITERAL(Literal::Array(array)) => {
+                array.elements.iter().for_each(|e| collect(e, locals));
+            }
+            Expression::LITERAL(Literal::Hash(hash)) => hash.elements.iter().for_each(|(k, v)| {
+                collect(k, locals);
+                collect(v, locals);
+            }),
+            Expression::PREFIX(prefix) => collect(&prefix.operand, locals),
+            Expression::Assign(a) => collect(&a.value, locals),
+            Expression::INFIX(infix) => {
+                collect(&infix.left, locals);
+                collect(&infix.right, locals);
+            }
+            Expression::Index(index) => {
+                collect(&index.object, locals);
+                collect(&index.index, locals);
+            }
+            Expression::Match(m) => {
+                collect(&m.subject, locals);
+                for arm in m.arms.iter() {
+                    locals.extend(arm.pattern.names().iter().map(|n| n.name.clone()));
+                    arm.guard.iter().for_each(|g| collect(g, locals));
+                    collect(&arm.body, locals);
+                }
+            }
+            Expression::LITERAL(_) | Expression::IDENTIFIER(_) | Expression::FUNCTION(_) => {}
+        }
+    }
+
+    let mut locals: HashSet<String> = f.params.iter().map(|p| p.name.clone()).collect();
+    collect_block(&f.body, &mut locals);
+    return locals;
+}
+
+fn mentions_block(name: &str, block: &BlockStatement) -> bool {
+    return block.body.iter().any(|stmt| match stmt {
+        Statement::Let(l) => mentions(name, &l.expr),
+        Statement::Return(r) => mentions(name, &r.argument),
+        Statement::Expr(e) => mentions(name, e),
+        Statement::Import(_) | Statement::Break(_) => false,
+        Statement::While(w) => mentions(name, &w.condition) || mentions_block(name, &w.body),
+    });
+}
+
+fn mentions(name: &str, e: &Expression) -> bool {
+    match e {
+        Expression::IDENTIFIER(id) => id.name == name,
+        Expression::Assign(a) => a.name.name == name || mentions(name, &a.value),
+        Expression::FunctionCall(call) => {
+            mentions(name, &call.callee) || call.arguments.iter().any(|a| mentions(name, a))
+        }
+        Expression::FUNCTION(f) => mentions_block(name, &f.body),
+        Expression::LITERAL(Literal::Array(array)) => {
+            array.elements.iter().any(|e| mentions(name, e))
+        }
+        Expression::LITERAL(Literal::Hash(hash)) => hash
+            .elements
+            .iter()
+            .any(|(k, v)| mentions(name, k) || mentions(name, v)),
+        Expression::LITERAL(_) => false,
+        Expression::PREFIX(prefix) => mentions(name, &prefix.operand),
+        Expression::INFIX(infix) => mentions(name, &infix.left) || mentions(name, &infix.right),
+        Expression::IF(if_node) => {
+            mentions(name, &if_node.condition)
+                || mentions_block(name, &if_node.consequent)
+                || if_node
+                    .alternate
+                    .as_ref()
+                    .is_some_and(|alternate| mentions_block(name, alternate))
+        }
+        Expression::Index(index) => mentions(name, &index.object) || mentions(name, &index.index),
+        Expression::Match(m) => {
+            mentions(name, &m.subject)
+                || m.arms.iter().any(|arm| {
+                    binds(name, &arm.pattern)
+                        || arm.guard.as_ref().is_some_and(|g| mentions(name, g))
+                        || mentions(name, &arm.body)
+                })
+        }
+    }
+}
+
+fn binds(name: &str, pattern: &Pattern) -> bool {
+    return pattern.names().iter().any(|n| n.name == name);
+}
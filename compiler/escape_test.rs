@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests {
+    use parser::ast::{BlockStatement, Expression, FunctionDeclaration, Literal, Node, Statement};
+    use parser::parse;
+
+    use crate::compiler::Compiler;
+    use crate::escape::EscapeInfo;
+
+    // whether each function literal of `input`, in source order, is non-escaping
+    fn non_escaping(input: &str) -> Vec<bool> {
+        let program = match parse(input).unwrap() {
+            Node::Program(p) => p,
+            _ => unreachable!(),
+        };
+        let info = EscapeInfo::analyze(&program);
+        let mut functions = vec![];
+        for stmt in program.body.iter() {
+            collect_stmt(stmt, &mut functions);
+        }
+        return functions.iter().map(|f| info.is_non_escaping(f)).collect();
+    }
+
+    fn collect_stmt<'a>(stmt: &'a Statement, out: &mut Vec<&'a FunctionDeclaration>) {
+        match stmt {
+            Statement::Let(l) => collect(&l.expr, out),
+            Statement::Return(r) => collect(&r.argument, out),
+            Statement::Expr(e) => collect(e, out),
+            Statement::Import(_) | Statement::Break(_) => {}
+            Statement::While(w) => {
+                collect(&w.condition, out);
+                collect_block(&w.body, out);
+            }
+        }
+    }
+
+    fn collect_block<'a>(block: &'a BlockStatement, out: &mut Vec<&'a FunctionDeclaration>) {
+        block.body.iter().for_each(|s| collect_stmt(s, out));
+    }
+
+    fn collect<'a>(e: &'a Expression, out: &mut Vec<&'a FunctionDeclaration>) {
+        match e {
+            Expression::FUNCTION(f) => {
+                out.push(f);
+                collect_block(&f.body, out);
+            }
+            Expression::FunctionCall(call) => {
+                collect(&call.callee, out);
+                call.arguments.iter().for_each(|a| collect(a, out));
+            }
+            Expression::IF(if_node) => {
+                collect(&if_node.condition, out);
+                collect_block(&if_node.consequent, out);
+                if let Some(alternate) = &if_node.alternate {
+                    collect_block(alternate, out);
+                }
+            }
+            Expression::INFIX(infix) => {
+                collect(&infix.left, out);
+                collect(&infix.right, out);
+            }
+            Expression::LITERAL(Literal::Array(array)) => {
+                array.elements.iter().for_each(|e| collect(e, out));
+            }
+            _ => {}
+        }
+    }
+
+    const MAP: &str = "let map = fn(arr, f) { \
+        if (len(arr) == 0) { [] } else { let head = f(first(arr)); push(map(rest(arr), f), head) } \
+    };";
+
+    #[test]
+    fn test_callbacks_of_non_escaping_parameters() {
+        let input = format!("{} let scale = fn(arr, k) {{ map(arr, fn(x) {{ x * k }}) }};", MAP);
+        assert_eq!(non_escaping(&input), vec![false, false, true]);
+
+        let input = "let keep = fn(f) { [f] }; let g = fn(k) { keep(fn() { k }) };";
+        assert_eq!(non_escaping(input), vec![false, false, false]);
+
+        let input =
+            "let call = fn(f) { f() }; let g = fn(k) { let call = fn(f) { f }; call(fn() { k }) };";
+        // the local `call` hides the global one
+        assert_eq!(non_escaping(input), vec![false, false, true, false]);
+    }
+
+    #[test]
+    fn test_local_closures() {
+        assert_eq!(non_escaping("let g = fn(k) { fn(x) { x + k }(1) };"), vec![false, true]);
+        assert_eq!(
+            non_escaping("let g = fn(k) { let add = fn(x) { x + k }; add(1) + add(2) };"),
+            vec![false, true]
+        );
+        assert_eq!(
+            non_escaping("let g = fn(k) { let add = fn(x) { x + k }; add };"),
+            vec![false, false]
+        );
+        assert_eq!(non_escaping("let adder = fn(k) { fn(x) { x + k } };"), vec![false, false]);
+        // captured by a closure that is returned
+        assert_eq!(
+            non_escaping("let g = fn(k) { let add = fn(x) { x + k }; fn() { add(1) } };"),
+            vec![false, false, false]
+        );
+    }
+
+    #[test]
+    fn test_closures_capture_by_reference() {
+        let input = format!("{} let scale = fn(arr, k) {{ map(arr, fn(x) {{ x * k }}) }};", MAP);
+        let bytecode = Compiler::new().compile(&parse(&input).unwrap()).unwrap();
+        let listings: Vec<String> = bytecode
+            .constants
+            .iter()
+            .filter_map(|c| match &**c {
+                object::Object::CompiledFunction(f) => {
+                    Some(crate::op_code::Instructions { data: f.instructions.clone() }.string())
+                }
+                _ => None,
+            })
+            .collect();
+        // `fn(x) { x * k }` reads `k` from `scale`'s frame
+        assert!(listings.iter().any(|l| l.contains("OpGetOuter 1")));
+        assert!(listings.iter().any(|l| l.contains("OpClosureRef")));
+        assert!(!listings.iter().any(|l| l.contains("OpGetFree")));
+    }
+}
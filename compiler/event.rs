@@ -0,0 +1,54 @@
+use serde::Serialize;
+
+// what the VM reports to its observer, see `VM::set_observer`, enough to rebuild every state for
+// `monkey run --visualize`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    // about to execute the instruction at `ip` of `function`
+    Step {
+        function: String,
+        ip: usize,
+        op: &'static str,
+    },
+    // `value` went into stack slot `slot`, the new top
+    Push {
+        slot: usize,
+        value: String,
+    },
+    // the stack shrank to `sp` values
+    Pop {
+        sp: usize,
+    },
+    // a call, its arguments start at `base_pointer` and its locals end below `sp`
+    Enter {
+        function: String,
+        base_pointer: usize,
+        sp: usize,
+    },
+    // a return, leaving `sp` values on the stack
+    Exit {
+        function: String,
+        sp: usize,
+    },
+    // a `let` stored `value` into a global, or into the local at the frame's base pointer + `index`
+    Bind {
+        scope: Scope,
+        index: usize,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Global,
+    Local,
+}
+
+impl Event {
+    // one line of the `--visualize` stream
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).unwrap();
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::event::{Event, Scope};
+    use crate::vm::VM;
+
+    fn events(input: &str) -> Vec<Event> {
+        let events = Rc::new(RefCell::new(vec![]));
+        let mut vm = VM::new(crate::compile(input).unwrap());
+        let recorded = Rc::clone(&events);
+        vm.set_observer(move |e| recorded.borrow_mut().push(e));
+        vm.run().unwrap();
+        return events.take();
+    }
+
+    #[test]
+    fn test_frames_and_binds() {
+        let got: Vec<Event> = events("let id = fn(x) { let y = x; y }; id(5);")
+            .into_iter()
+            .filter(|e| !matches!(e, Event::Step { .. } | Event::Push { .. } | Event::Pop { .. }))
+            .map(|e| match e {
+                Event::Bind { scope: Scope::Global, index, .. } => {
+                    Event::Bind { scope: Scope::Global, index, value: String::new() }
+                }
+                e => e,
+            })
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                Event::Bind { scope: Scope::Global, index: 0, value: String::new() },
+                Event::Enter { function: "id".to_string(), base_pointer: 1, sp: 3 },
+                Event::Bind { scope: Scope::Local, index: 1, value: "5".to_string() },
+                Event::Exit { function: "id".to_string(), sp: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stack_replay() {
+        // replaying the stack events leaves what the VM popped last right above the stack
+        let mut stack: Vec<String> = vec![];
+        let mut steps = vec![];
+        for e in events("[1, 2 + 3, len(\"ab\")][2] * 10") {
+            match e {
+                Event::Step { op, .. } => steps.push(op),
+                Event::Push { slot, value } => {
+                    stack.truncate(slot);
+                    stack.push(value);
+                }
+                Event::Pop { sp } | Event::Exit { sp, .. } => stack.truncate(sp),
+                _ => {}
+            }
+        }
+        assert!(stack.is_empty());
+        assert_eq!(steps.last(), Some(&"OpPop"));
+        assert_eq!(steps.iter().filter(|&&op| op == "OpConst").count(), 6);
+
+        let e = Event::Bind { scope: Scope::Global, index: 3, value: "monkey".to_string() };
+        assert_eq!(e.to_json(), r#"{"event":"bind","scope":"global","index":3,"value":"monkey"}"#);
+    }
+}
@@ -1,4 +1,3 @@
-use crate::op_code::Instructions;
 use object::{Closure};
 
 #[derive(Debug, Clone)]
@@ -12,8 +11,4 @@ impl Frame {
     pub fn new(func: Closure, base_pointer: usize) -> Self {
         Frame { cl: func, ip: -1, base_pointer }
     }
-
-    pub fn instructions(&self) -> Instructions {
-        return Instructions { data: self.cl.func.instructions.clone() };
-    }
 }
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use object::builtins::PURE;
+use parser::ast::{BlockStatement, Expression, FunctionDeclaration, Literal, Statement};
+
+use crate::symbol_table::{Symbol, SymbolScope, SymbolTable};
+
+// bodies larger than this many expression nodes are always called
+pub const MAX_INLINE_SIZE: usize = 16;
+
+// a top-level function whose calls are compiled as its one-expression body, see
+// `CompileOptions::inline`
+pub struct InlineFunction {
+    pub decl: FunctionDeclaration,
+    // the globals and builtins the body reads, as resolved where the function was defined
+    pub globals: HashMap<String, Rc<Symbol>>,
+}
+
+impl InlineFunction {
+    pub fn new(
+        f: &FunctionDeclaration,
+        symbol_table: &mut SymbolTable,
+        known: &HashMap<usize, InlineFunction>,
+    ) -> Option<InlineFunction> {
+        // its arguments past the others would have to be made an array, and one for `_` dropped
+        if f.rest || f.params.iter().any(|p| p.is_throwaway()) {
+            return None;
+        }
+        let body = body(f)?;
+        let params: Vec<&str> = f.params.iter().map(|p| p.name.as_str()).collect();
+        let mut names = vec![];
+        if size(body) > MAX_INLINE_SIZE || !is_inlinable(body, &params, &mut names) {
+            return None;
+        }
+
+        let mut globals = HashMap::new();
+        for name in names {
+            let symbol = symbol_table.resolve(name.clone())?;
+            match symbol.scope {
+                SymbolScope::Builtin if !PURE.contains(&name.as_str()) => return None,
+                SymbolScope::Global
+                    if called(body, &name) && !known.contains_key(&symbol.index) =>
+                {
+                    return None;
+                }
+                SymbolScope::Builtin | SymbolScope::Global => {}
+                _ => return None,
+            }
+            globals.insert(name, symbol);
+        }
+
+        return Some(InlineFunction { decl: f.clone(), globals });
+    }
+
+    pub fn body(&self) -> &Expression {
+        return body(&self.decl).unwrap();
+    }
+
+    // every name the body reads still means the same at the call site
+    pub fn visible_from(&self, symbol_table: &mut SymbolTable) -> bool {
+        return self
+            .globals
+            .iter()
+            .all(|(name, symbol)| symbol_table.resolve(name.clone()).as_ref() == Some(symbol));
+    }
+}
+
+fn body(f: &FunctionDeclaration) -> Option<&Expression> {
+    match f.body.body.as_slice() {
+        [Statement::Expr(e)] => return Some(e),
+        [Statement::Return(r)] => return Some(&r.argument),
+        _ => return None,
+    }
+}
+
+fn block_expr(block: &BlockStatement) -> Option<&Expression> {
+    match block.body.as_slice() {
+        [Statement::Expr(e)] => return Some(e),
+        _ => return None,
+    }
+}
+
+// collects the names that aren't parameters into `names`
+fn is_inlinable(e: &Expression, params: &[&str], names: &mut Vec<String>) -> bool {
+    match e {
+        Expression::IDENTIFIER(id) => {
+            if !params.contains(&id.name.as_str()) && !names.contains(&id.name) {
+                names.push(id.name.clone());
+            }
+            return true;
+        }
+        Expression::LITERAL(Literal::Array(array)) => {
+            return array
+                .elements
+                .iter()
+                .all(|e| is_inlinable(e, params, names));
+        }
+        Expression::LITERAL(Literal::Hash(hash)) => {
+            return hash
+                .elements
+                .iter()
+                .all(|(k, v)| is_inlinable(k, params, names) && is_inlinable(v, params, names));
+        }
+        Expression::LITERAL(_) => return true,
+        Expression::PREFIX(prefix) => return is_inlinable(&prefix.operand, params, names),
+        Expression::INFIX(infix) => {
+            return is_inlinable(&infix.left, params, names)
+                && is_inlinable(&infix.right, params, names);
+        }
+        Expression::Index(index) => {
+            return is_inlinable(&index.object, params, names)
+                && is_inlinable(&index.index, params, names);
+        }
+        // blocks would need locals of their own
+        Expression::IF(if_node) => {
+            let mut block_inlinable = |block: &BlockStatement| match block_expr(block) {
+                Some(e) => is_inlinable(e, params, names),
+                None => false,
+            };
+            return block_inlinable(&if_node.consequent)
+                && if_node.alternate.as_ref().is_none_or(&mut block_inlinable)
+                && is_inlinable(&if_node.condition, params, names);
+        }
+        Expression::FunctionCall(call) => match &*call.callee {
+            Expression::IDENTIFIER(id) if !params.contains(&id.name.as_str()) => {
+                return is_inlinable(&call.callee, params, names)
+                    && call
+                        .arguments
+                        .iter()
+                        .all(|a| is_inlinable(a, params, names));
+            }
+            _ => return false,
+        },
+        Expression::FUNCTION(_) | Expression::Match(_) | Expression::Assign(_) => return false,
+    }
+}
+
+// whether `name` is called somewhere in `e`
+fn called(e: &Expression, name: &str) -> bool {
+    match e {
+        Expression::FunctionCall(call) => {
+            return matches!(&*call.callee, Expression::IDENTIFIER(id) if id.name == name)
+                || call.arguments.iter().any(|a| called(a, name));
+        }
+        Expression::LITERAL(Literal::Array(array)) => {
+            return array.elements.iter().any(|e| called(e, name));
+        }
+        Expression::LITERAL(Literal::Hash(hash)) => {
+            return hash
+                .elements
+                .iter()
+                .any(|(k, v)| called(k, name) || called(v, name));
+        }
+        Expression::PREFIX(prefix) => return called(&prefix.operand, name),
+        Expression::INFIX(infix) => return called(&infix.left, name) || called(&infix.right, name),
+        Expression::Index(index) => {
+            return called(&index.object, name) || called(&index.index, name)
+        }
+        Expression::IF(if_node) => {
+            let in_block =
+                |block: &BlockStatement| block_expr(block).is_some_and(|e| called(e, name));
+            return called(&if_node.condition, name)
+                || in_block(&if_node.consequent)
+                || if_node.alternate.as_ref().is_some_and(in_block);
+        }
+        _ => return false,
+    }
+}
+
+// the number of expression nodes, an estimate of the code inlining duplicates
+pub fn size(e: &Expression) -> usize {
+    let nested: usize = match e {
+        Expression::LITERAL(Literal::Array(array)) => array.elements.iter().map(size).sum(),
+        Expression::LITERAL(Literal::Hash(hash)) => {
+            hash.elements.iter().map(|(k, v)| size(k) + size(v)).sum()
+        }
+        Expression::PREFIX(prefix) => size(&prefix.operand),
+        Expression::INFIX(infix) => size(&infix.left) + size(&infix.right),
+        Expression::Index(index) => size(&index.object) + size(&index.index),
+        Expression::IF(if_node) => {
+            let block_size = |block: &BlockStatement| block_expr(block).map_or(0, size);
+            size(&if_node.condition)
+                + block_size(&if_node.consequent)
+                + if_node.alternate.as_ref().map_or(0, block_size)
+        }
+        Expression::FunctionCall(call) => {
+            size(&call.callee) + call.arguments.iter().map(size).sum::<usize>()
+        }
+        _ => 0,
+    };
+    return nested + 1;
+}
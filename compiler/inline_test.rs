@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use object::Object;
+    use parser::parse;
+
+    use crate::compiler::{Bytecode, CompileOptions, Compiler};
+    use crate::op_code::Opcode::OpCall;
+    use crate::op_code::{cast_u8_to_opcode, read_operands, Opcode, DEFINITIONS};
+    use crate::vm::VM;
+
+    fn compile(input: &str, inline: bool) -> Bytecode {
+        let options = CompileOptions { inline, ..Default::default() };
+        let mut compiler = Compiler::new_with_options(options);
+        return compiler.compile(&parse(input).unwrap()).unwrap();
+    }
+
+    fn run(bytecode: Bytecode) -> Object {
+        let mut vm = VM::new(bytecode);
+        vm.run().unwrap();
+        return (*vm.last_popped_stack_elm().unwrap()).clone();
+    }
+
+    // how many `op`s the top-level code has
+    fn count(bytecode: &Bytecode, op: Opcode) -> usize {
+        let ins = &bytecode.instructions.data;
+        let mut total = 0;
+        let mut i = 0;
+        while i < ins.len() {
+            let opcode = cast_u8_to_opcode(ins[i]);
+            if opcode == op {
+                total += 1;
+            }
+            let (_, read) = read_operands(DEFINITIONS.get(&opcode).unwrap(), &ins[i + 1..]);
+            i += 1 + read;
+        }
+        return total;
+    }
+
+    // the number of calls left after inlining, checking the result is unchanged
+    fn calls_left(input: &str, expected: Object) -> usize {
+        let inlined = compile(input, true);
+        assert_eq!(run(compile(input, false)), expected, "{}", input);
+        assert_eq!(run(inlined.clone()), expected, "{}", input);
+        return count(&inlined, OpCall);
+    }
+
+    #[test]
+    fn test_small_functions_are_inlined() {
+        let input = "let add = fn(a, b) { a + b }; let x = 1; add(x, 2) + add(3, x)";
+        assert_eq!(calls_left(input, Object::Integer(7)), 0);
+
+        let input = "let k = 10; let scale = fn(x) { return x * k; }; \
+            let clamp = fn(x) { if (x > 50) { 50 } else { scale(x) } }; \
+            let n = 3; clamp(n) + clamp(n * 20)";
+        assert_eq!(calls_left(input, Object::Integer(80)), 0);
+
+        let input = "let second = fn(xs) { xs[1] }; let xs = [1, 2, 3]; second(push(xs, 4))";
+        // only the call to `push` is left
+        assert_eq!(calls_left(input, Object::Integer(2)), 1);
+    }
+
+    #[test]
+    fn test_arguments_are_evaluated_once_in_order() {
+        let input = "let twice = fn(x) { [x, x] }; let log = fn(x) { puts(x); x }; \
+            let y = 5; twice(log(y))";
+        let expected = Object::Array(
+            vec![
+                std::rc::Rc::new(Object::Integer(5)),
+                std::rc::Rc::new(Object::Integer(5)),
+            ]
+            .into(),
+        );
+        // `log` has two statements and stays a call
+        assert_eq!(calls_left(input, expected), 1);
+
+        let input = "let first = fn(a, b) { a }; let x = 1; first(x, x + 1)";
+        assert_eq!(calls_left(input, Object::Integer(1)), 0);
+    }
+
+    #[test]
+    fn test_functions_that_are_not_inlined() {
+        let inputs = vec![
+            // recursive
+            ("let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; let x = 5; fact(x)", 120),
+            // calls something that isn't known to be pure
+            ("let f = fn(x) { puts(x) }; let g = fn(x) { len(f(x)) }; let x = \"ab\"; 3", 3),
+            // returns a closure
+            ("let adder = fn(x) { fn(y) { x + y } }; let add = adder(1); add(2)", 3),
+        ];
+        for (input, expected) in inputs {
+            let inlined = compile(input, true);
+            assert_eq!(run(inlined), Object::Integer(expected), "{}", input);
+        }
+
+        let big = "let big = fn(x) { x + x + x + x + x + x + x + x + x + x }; let x = 1; big(x)";
+        assert_eq!(calls_left(big, Object::Integer(10)), 1);
+    }
+
+    #[test]
+    fn test_shadowed_names_are_not_inlined() {
+        // `k` inside `scale` is the global, not the caller's parameter
+        let input = "let k = 2; let scale = fn(x) { x * k }; let f = fn(k) { scale(k) }; f(5)";
+        assert_eq!(run(compile(input, true)), Object::Integer(10));
+
+        // the global `k` was rebound after `scale` was defined
+        let input = "let k = 2; let scale = fn(x) { x * k }; let k = 3; let y = 5; scale(y)";
+        let bytecode = compile(input, true);
+        assert_eq!(count(&bytecode, OpCall), 1);
+        assert_eq!(run(bytecode), Object::Integer(10));
+
+        let input = "let sq = fn(x) { x * x }; let f = fn(sq) { sq(3) }; f(fn(x) { x + 1 })";
+        assert_eq!(run(compile(input, true)), Object::Integer(4));
+    }
+}
@@ -1,14 +1,62 @@
 #[macro_use]
 extern crate lazy_static;
 
+pub mod bcdiff;
+mod bcdiff_test;
+pub mod bundle;
+mod bundle_test;
 pub mod compiler;
 mod compiler_function_test;
+#[cfg(test)]
 mod compiler_test;
+#[cfg(feature = "parallel")]
+pub mod concurrent;
+pub mod const_eval;
+mod const_eval_test;
+pub mod cse;
+mod cse_test;
+pub mod escape;
+mod escape_test;
+pub mod event;
+mod event_test;
 mod frame;
+pub mod inline;
+mod inline_test;
+pub mod licm;
+mod licm_test;
+pub mod lint;
+mod lint_test;
 pub mod op_code;
 mod op_code_test;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "parallel")]
+mod parallel_test;
+pub mod replay;
+mod replay_test;
 pub mod symbol_table;
 mod symbol_table_test;
 pub mod vm;
 mod vm_function_test;
+#[cfg(test)]
 mod vm_test;
+
+use parser::error::render;
+use parser::parse;
+
+use crate::compiler::{Bytecode, CompileError, CompileOptions, Compiler};
+
+// source to bytecode in one call, with the builtins predefined; parse errors one per line
+pub fn compile(source: &str) -> Result<Bytecode, CompileError> {
+    return compile_with_options(source, CompileOptions::default());
+}
+
+pub fn compile_with_options(
+    source: &str,
+    options: CompileOptions,
+) -> Result<Bytecode, CompileError> {
+    let program = parse(source).map_err(|errors| render(&errors))?;
+    let mut compiler = Compiler::new_with_options(options);
+    compiler.set_source(source);
+    return compiler.compile(&program);
+}
@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+
+use parser::ast::{BlockStatement, Expression, Literal, Statement, While};
+use parser::lexer::token::TokenKind;
+
+use crate::cse::{is_computation, is_pure, Candidate};
+
+// loop-invariant code motion for one `while`, see `CompileOptions::licm`: pure computations the
+// first turn reaches before anything that may fail are computed once before the loop
+pub struct Invariants {
+    pub condition: Vec<Expression>,
+    pub body: Vec<Expression>,
+}
+
+impl Invariants {
+    // `invariant` tells whether the names and builtins a computation reads keep their meaning
+    // for as long as the loop runs, the names the loop binds itself are ruled out here
+    pub fn find(w: &While, invariant: &mut dyn FnMut(&Candidate) -> bool) -> Invariants {
+        let mut bound = HashSet::new();
+        bound_in(&w.condition, &mut bound);
+        bound_in_block(&w.body, &mut bound);
+        let mut scan = Scan { bound, invariant, keys: HashSet::new(), found: vec![] };
+
+        scan.expr(&w.condition);
+        let condition = std::mem::take(&mut scan.found);
+        for stmt in w.body.body.iter() {
+            if !scan.stmt(stmt) {
+                break;
+            }
+        }
+        return Invariants { condition, body: scan.found };
+    }
+}
+
+struct Scan<'a> {
+    // names a `let`, a pattern or an assignment in the loop binds
+    bound: HashSet<String>,
+    invariant: &'a mut dyn FnMut(&Candidate) -> bool,
+    keys: HashSet<String>,
+    found: Vec<Expression>,
+}
+
+impl Scan<'_> {
+    // whether the statement can neither fail nor have an effect, the invariants in it moved out
+    fn stmt(&mut self, s: &Statement) -> bool {
+        match s {
+            Statement::Let(l) => return self.expr(&l.expr),
+            Statement::Expr(e) => return self.expr(e),
+            Statement::Return(r) => {
+                self.expr(&r.argument);
+                return false;
+            }
+            Statement::While(w) => {
+                self.expr(&w.condition);
+                return false;
+            }
+            Statement::Import(_) | Statement::Break(_) => return false,
+        }
+    }
+
+    // the same for an expression, its operands are looked at in the order they're evaluated
+    fn expr(&mut self, e: &Expression) -> bool {
+        if is_computation(e) && is_pure(e) {
+            let candidate = Candidate::of(e);
+            let bound = &self.bound;
+            if !candidate.names.iter().any(|n| bound.contains(n)) && (self.invariant)(&candidate) {
+                if self.keys.insert(e.to_string()) {
+                    self.found.push(e.clone());
+                }
+                return true;
+            }
+        }
+
+        match e {
+            Expression::IDENTIFIER(_) | Expression::FUNCTION(_) => return true,
+            Expression::LITERAL(Literal::Array(a)) => {
+                return a.elements.iter().all(|e| self.expr(e))
+            }
+            // a key may not be hashable
+            Expression::LITERAL(Literal::Hash(_)) => return false,
+            Expression::LITERAL(_) => return true,
+            Expression::PREFIX(p) => {
+                let settled = self.expr(&p.operand);
+                return settled && p.op.kind == TokenKind::BANG;
+            }
+            // any two values can be compared for equality
+            Expression::INFIX(i) => {
+                let settled = self.expr(&i.left) && self.expr(&i.right);
+                return settled && matches!(i.op.kind, TokenKind::EQ | TokenKind::NotEq);
+            }
+            Expression::Index(i) => {
+                let _ = self.expr(&i.object) && self.expr(&i.index);
+                return false;
+            }
+            Expression::FunctionCall(c) => {
+                let _ = self.expr(&c.callee) && c.arguments.iter().all(|a| self.expr(a));
+                return false;
+            }
+            // what follows a branch may not be reached
+            Expression::IF(i) => {
+                self.expr(&i.condition);
+                return false;
+            }
+            Expression::Match(m) => {
+                self.expr(&m.subject);
+                return false;
+            }
+            Expression::Assign(a) => {
+                self.expr(&a.value);
+                return false;
+            }
+        }
+    }
+}
+
+fn bound_in_block(block: &BlockStatement, bound: &mut HashSet<String>) {
+    for stmt in block.body.iter() {
+        match stmt {
+            Statement::Let(l) => {
+                bound.insert(l.identifier.kind.to_string());
+                bound_in(&l.expr, bound);
+            }
+            Statement::Return(r) => bound_in(&r.argument, bound),
+            Statement::Expr(e) => bound_in(e, bound),
+            Statement::Import(_) | Statement::Break(_) => {}
+            Statement::While(w) => {
+                bound_in(&w.condition, bound);
+                bound_in_block(&w.body, bound);
+            }
+        }
+    }
+}
+
+// nested functions included, which is more than needed
+fn bound_in(e: &Expression, bound: &mut HashSet<String>) {
+    match e {
+        Expression::IDENTIFIER(_) => {}
+        Expression::LITERAL(Literal::Array(a)) => {
+            a.elements.iter().for_each(|e| bound_in(e, bound))
+        }
+        Expression::LITERAL(Literal::Hash(h)) => h.elements.iter().for_each(|(k, v)| {
+            bound_in(k, bound);
+            bound_in(v, bound);
+        }),
+        Expression::LITERAL(_) => {}
+        Expression::PREFIX(p) => bound_in(&p.operand, bound),
+        Expression::INFIX(i) => {
+            bound_in(&i.left, bound);
+            bound_in(&i.right, bound);
+        }
+        Expression::IF(i) => {
+            bound_in(&i.condition, bound);
+            bound_in_block(&i.consequent, bound);
+            if let Some(alternate) = &i.alternate {
+                bound_in_block(alternate, bound);
+            }
+        }
+        Expression::FUNCTION(f) => bound_in_block(&f.body, bound),
+        Expression::FunctionCall(c) => {
+            bound_in(&c.callee, bound);
+            c.arguments.iter().for_each(|a| bound_in(a, bound));
+        }
+        Expression::Index(i) => {
+            bound_in(&i.object, bound);
+            bound_in(&i.index, bound);
+        }
+        Expression::Match(m) => {
+            bound_in(&m.subject, bound);
+            for arm in m.arms.iter() {
+                bound.extend(arm.pattern.names().iter().map(|id| id.name.clone()));
+                arm.guard.iter().for_each(|g| bound_in(g, bound));
+                bound_in(&arm.body, bound);
+            }
+        }
+        Expression::Assign(a) => {
+            bound.insert(a.name.name.clone());
+            bound_in(&a.value, bound);
+        }
+    }
+}
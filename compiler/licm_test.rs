@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use object::Object;
+    use parser::parse;
+
+    use crate::compiler::{Bytecode, CompileOptions, Compiler};
+    use crate::op_code::Opcode::*;
+    use crate::op_code::{concat_instructions, make_instructions};
+    use crate::vm::VM;
+
+    fn compile(input: &str, licm: bool) -> Bytecode {
+        return compile_with(input, CompileOptions { licm, ..Default::default() });
+    }
+
+    fn compile_with(input: &str, options: CompileOptions) -> Bytecode {
+        let mut compiler = Compiler::new_with_options(options);
+        return compiler.compile(&parse(input).unwrap()).unwrap();
+    }
+
+    fn run(bytecode: Bytecode) -> Result<Object, String> {
+        let mut vm = VM::new(bytecode);
+        vm.run()?;
+        return Ok((*vm.last_popped_stack_elm().unwrap()).clone());
+    }
+
+    #[test]
+    fn test_invariants_of_the_condition_are_computed_before_the_loop() {
+        let input = "let n = 2; let i = 0; while (i < n * 3) { i = i + 1; }";
+        let expected = concat_instructions(&vec![
+            make_instructions(OpConst, &[0]),
+            make_instructions(OpSetGlobal, &[0]),
+            make_instructions(OpConst, &[1]),
+            make_instructions(OpSetGlobal, &[1]),
+            make_instructions(OpGetGlobal, &[0]),
+            make_instructions(OpConst, &[2]),
+            make_instructions(OpMul, &[]),
+            make_instructions(OpSetGlobal, &[2]),
+            make_instructions(OpGetGlobal, &[1]),
+            make_instructions(OpGetGlobal, &[2]),
+            make_instructions(OpLessThan, &[]),
+            make_instructions(OpJumpNotTruthy, &[49]),
+            make_instructions(OpGetGlobal, &[1]),
+            make_instructions(OpConst, &[3]),
+            make_instructions(OpAdd, &[]),
+            make_instructions(OpSetGlobal, &[1]),
+            make_instructions(OpGetGlobal, &[1]),
+            make_instructions(OpPop, &[]),
+            make_instructions(OpJump, &[22]),
+        ]);
+        assert_eq!(compile(input, true).instructions.string(), expected.string());
+    }
+
+    #[test]
+    fn test_invariants_of_the_body_are_computed_once_the_condition_holds() {
+        let input = "let n = 2; let s = 0; while (s < 10) { s = s + n * 4; }";
+        let expected = concat_instructions(&vec![
+            make_instructions(OpConst, &[0]),
+            make_instructions(OpSetGlobal, &[0]),
+            make_instructions(OpConst, &[1]),
+            make_instructions(OpSetGlobal, &[1]),
+            // the first turn
+            make_instructions(OpGetGlobal, &[1]),
+            make_instructions(OpConst, &[2]),
+            make_instructions(OpLessThan, &[]),
+            make_instructions(OpJumpNotTruthy, &[62]),
+            make_instructions(OpGetGlobal, &[0]),
+            make_instructions(OpConst, &[3]),
+            make_instructions(OpMul, &[]),
+            make_instructions(OpSetGlobal, &[2]),
+            make_instructions(OpJump, &[45]),
+            // the next ones
+            make_instructions(OpGetGlobal, &[1]),
+            make_instructions(OpConst, &[4]),
+            make_instructions(OpLessThan, &[]),
+            make_instructions(OpJumpNotTruthy, &[62]),
+            make_instructions(OpGetGlobal, &[1]),
+            make_instructions(OpGetGlobal, &[2]),
+            make_instructions(OpAdd, &[]),
+            make_instructions(OpSetGlobal, &[1]),
+            make_instructions(OpGetGlobal, &[1]),
+            make_instructions(OpPop, &[]),
+            make_instructions(OpJump, &[35]),
+        ]);
+        assert_eq!(compile(input, true).instructions.string(), expected.string());
+    }
+
+    #[test]
+    fn test_what_the_loop_may_change_stays_in_it() {
+        let tests = vec![
+            // a function the loop calls sets the global
+            "let n = 1; let bump = fn() { n = n + 1 }; let s = 0; let i = 0; \
+            while (i < 3) { s = s + n * 10; bump(); i = i + 1; } s",
+            // the body binds it again
+            "let n = 1; let s = 0; let i = 0; \
+            while (i < 3) { s = s + n * 10; let n = n + 1; i = i + 1; } s",
+            // so does an arm of a match
+            "let n = 1; let s = 0; let i = 0; \
+            while (i < 3) { s = s + n * 10; match (i + 5) { n => n }; i = i + 1; } s",
+            "let xs = [1, 2]; let s = 0; let i = 0; \
+            while (i < 2) { s = s + len(xs); let len = fn(a) { 100 }; i = i + 1; } s",
+            // a parameter of a function in the loop is another name
+            "let k = 3; let s = 0; let i = 0; \
+            while (i < 2) { s = s + k * 2 + fn(k) { k * 2 }(i); i = i + 1; } s",
+            "let f = fn(n) { let i = 0; let s = 0; while (i < n * 2) { s = s + n * 3; i = i + 1; } s }; \
+            f(4) + f(5)",
+            // nested loops, the inner one's invariant is the outer one's too
+            "let n = 3; let s = 0; let i = 0; \
+            while (i < n) { let j = 0; while (j < n * 2) { s = s + n * 5; j = j + 1; } i = i + 1; } s",
+            // a loop that's left at once
+            "let n = 4; let s = 0; while (true) { s = n * 2; break; } s",
+        ];
+        for input in tests {
+            let expected = run(compile(input, false));
+            assert_eq!(run(compile(input, true)), expected, "{}", input);
+            // with the other optimizations too
+            let optimized = compile_with(input, CompileOptions::from_opt_level(2));
+            assert_eq!(run(optimized), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_a_failing_invariant_fails_where_it_did() {
+        let tests = vec![
+            // the body never runs
+            (
+                "let n = \"a\"; let i = 0; while (i > 0) { let x = n - 1; } 5",
+                Ok(Object::Integer(5)),
+            ),
+            // the condition fails before it's reached
+            (
+                "let n = 9223372036854775807; let s = \"a\"; while (s > 0) { let x = n * 2; }",
+                Err("can't compare string and integer".to_string()),
+            ),
+            // with big integers nothing overflows, and the loop never ends
+            #[cfg(not(feature = "bigint"))]
+            (
+                "let n = 9223372036854775807; let i = 0; while (i < n * 2) { i = i + 1; }",
+                Err("integer overflow: 9223372036854775807 * 2".to_string()),
+            ),
+        ];
+        for (input, expected) in tests {
+            let first_line = |r: Result<Object, String>| {
+                r.map_err(|e| e.lines().next().unwrap_or("").to_string())
+            };
+            let result = first_line(run(compile(input, true)));
+            assert_eq!(result, first_line(run(compile(input, false))), "{}", input);
+            match (&result, &expected) {
+                (Err(e), Err(prefix)) => assert!(e.starts_with(prefix), "{}: {}", input, e),
+                _ => assert_eq!(result, expected, "{}", input),
+            }
+        }
+    }
+}
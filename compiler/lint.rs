@@ -0,0 +1,95 @@
+use object::builtins::BuiltIns;
+use parser::ast::{is_meant_unused, BlockStatement, Expression, Literal, Program, Statement};
+
+// Warnings about programs that compile but probably don't do what was meant. Each is a message
+// with the line and column it's about; whoever shows them adds the file.
+
+// a `let` or `match` binding the name of a builtin hides it from there on; parameters and `_`
+// names aren't reported
+pub fn shadowed_builtins(program: &Program) -> Vec<String> {
+    let mut warnings = vec![];
+    visit_block(&program.body, &mut warnings);
+    return warnings;
+}
+
+fn visit_block(body: &[Statement], warnings: &mut Vec<String>) {
+    for stmt in body.iter() {
+        match stmt {
+            Statement::Let(l) => {
+                let name = l.identifier.kind.to_string();
+                if !is_meant_unused(&name) && BuiltIns.iter().any(|b| b.0 == name) {
+                    let at = l.identifier.position;
+                    warnings.push(format!("`let {}` at {} shadows the builtin {}", name, at, name));
+                }
+                visit(&l.expr, warnings);
+            }
+            Statement::Return(r) => visit(&r.argument, warnings),
+            Statement::Expr(e) => visit(e, warnings),
+            Statement::Import(_) | Statement::Break(_) => {}
+            Statement::While(w) => {
+                visit(&w.condition, warnings);
+                visit_block(&w.body.body, warnings);
+            }
+        }
+    }
+}
+
+fn visit(expr: &Expression, warnings: &mut Vec<String>) {
+    match expr {
+        Expression::IDENTIFIER(_) => {}
+        Expression::Assign(a) => visit(&a.value, warnings),
+        Expression::LITERAL(Literal::Array(a)) => {
+            for e in a.elements.iter() {
+                visit(e, warnings);
+            }
+        }
+        Expression::LITERAL(Literal::Hash(h)) => {
+            for (k, v) in h.elements.iter() {
+                visit(k, warnings);
+                visit(v, warnings);
+            }
+        }
+        Expression::LITERAL(_) => {}
+        Expression::PREFIX(p) => visit(&p.operand, warnings),
+        Expression::INFIX(i) => {
+            visit(&i.left, warnings);
+            visit(&i.right, warnings);
+        }
+        Expression::IF(i) => {
+            visit(&i.condition, warnings);
+            visit_block(&i.consequent.body, warnings);
+            if let Some(BlockStatement { body, .. }) = &i.alternate {
+                visit_block(body, warnings);
+            }
+        }
+        Expression::FUNCTION(f) => visit_block(&f.body.body, warnings),
+        Expression::FunctionCall(c) => {
+            visit(&c.callee, warnings);
+            for a in c.arguments.iter() {
+                visit(a, warnings);
+            }
+        }
+        Expression::Index(i) => {
+            visit(&i.object, warnings);
+            visit(&i.index, warnings);
+        }
+        Expression::Match(m) => {
+            visit(&m.subject, warnings);
+            for arm in m.arms.iter() {
+                for name in arm.pattern.names() {
+                    if BuiltIns.iter().any(|b| b.0 == name.name) {
+                        let at = name.position;
+                        warnings.push(format!(
+                            "the pattern binding `{}` at {} shadows the builtin {}",
+                            name, at, name
+                        ));
+                    }
+                }
+                if let Some(guard) = &arm.guard {
+                    visit(guard, warnings);
+                }
+                visit(&arm.body, warnings);
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(test)]
+mod tests {
+    use parser::ast::Node;
+    use parser::parse;
+
+    use crate::lint::shadowed_builtins;
+
+    fn warnings(input: &str) -> Vec<String> {
+        match parse(input).unwrap() {
+            Node::Program(p) => return shadowed_builtins(&p),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_shadowed_builtins() {
+        assert_eq!(
+            warnings("let len = fn(x) { 0 };\nlen([1, 2])"),
+            vec!["`let len` at line 1, column 5 shadows the builtin len"]
+        );
+        let nested = "let f = fn() {\n  if (true) { let puts = 1; puts }\n};";
+        assert_eq!(
+            warnings(nested),
+            vec!["`let puts` at line 2, column 19 shadows the builtin puts"]
+        );
+        assert_eq!(
+            warnings("match (xs) { [x, ...rest] => rest }"),
+            vec!["the pattern binding `rest` at line 1, column 21 shadows the builtin rest"]
+        );
+        // parameters, and names that only look like builtins, are fine
+        assert!(warnings("let f = fn(len) { len }; let length = 1; f(length)").is_empty());
+        // nor is `_`, which binds nothing
+        assert!(warnings("let _ = puts(1); match (1) { _ => 2 }").is_empty());
+    }
+}
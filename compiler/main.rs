@@ -1,19 +1,82 @@
 use compiler::compiler::Compiler;
 use compiler::vm::VM;
 
-use compiler::symbol_table::SymbolTable;
-use object::Object;
+use object::interrupt;
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::io::stdin;
 use std::io::{self, Write};
-use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 
-use parser::parse;
+use parser::{is_quiet, parse};
+
+// Counts allocations for `:timing`. There's no collector to report on: values are reference
+// counted and freed as soon as the last reference goes, so frees are the closest thing.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static FREES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        return System.alloc(layout);
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        FREES.fetch_add(1, Ordering::Relaxed);
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct Timing {
+    start: Instant,
+    allocations: usize,
+    allocated: usize,
+    frees: usize,
+}
+
+impl Timing {
+    fn start() -> Timing {
+        return Timing {
+            start: Instant::now(),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            allocated: ALLOCATED.load(Ordering::Relaxed),
+            frees: FREES.load(Ordering::Relaxed),
+        };
+    }
+
+    fn report(&self, executed: u64) -> String {
+        return format!(
+            "time {:?}, {} instructions, {} allocations ({} bytes), {} frees",
+            self.start.elapsed(),
+            executed,
+            ALLOCATIONS.load(Ordering::Relaxed) - self.allocations,
+            ALLOCATED.load(Ordering::Relaxed) - self.allocated,
+            FREES.load(Ordering::Relaxed) - self.frees,
+        );
+    }
+}
 
 fn main() {
     println!("Welcome to monkey compiler by gengjiawen");
+    // Top-level `let`s are globals, kept for the inputs after, and `let`s in functions are locals.
+    // An input that doesn't compile binds nothing.
     let mut constants = vec![];
-    let mut symbol_table = SymbolTable::new();
-    let mut globals = vec![Rc::new(Object::Null); compiler::vm::GLOBAL_SIZE];
+    let mut symbol_table = Compiler::new().symbol_table;
+    let mut globals = vec![];
+    let mut timing = false;
+    ctrlc::set_handler(|| {
+        if !interrupt::request() {
+            std::process::exit(130);
+        }
+    })
+    .expect("can't handle Ctrl-C");
     loop {
         print!("> ");
         io::stdout().flush().unwrap();
@@ -25,6 +88,15 @@ fn main() {
             std::process::exit(0);
         }
 
+        if let Some(command) = input.trim().strip_prefix(':') {
+            match command.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["timing", "on"] => timing = true,
+                ["timing", "off"] => timing = false,
+                _ => println!("unknown command :{}, try :timing on or :timing off", command),
+            }
+            continue;
+        }
+
         let program = match parse(&input) {
             Ok(x) => x,
             Err(e) => {
@@ -33,21 +105,30 @@ fn main() {
             }
         };
 
-        let mut compiler = Compiler::new_with_state(symbol_table, constants);
+        let started = Timing::start();
+        let mut compiler = Compiler::new_with_state(symbol_table.clone(), constants.clone());
+        compiler.set_source(&input);
 
         match compiler.compile(&program) {
             Ok(bytecodes) => {
                 let mut vm = VM::new_with_global_store(bytecodes, globals);
-                vm.run();
-                println!("{}", vm.last_popped_stack_elm().unwrap());
+                match interrupt::run(|| vm.run()) {
+                    Ok(()) if !is_quiet(&input) => {
+                        println!("{}", vm.last_popped_stack_elm().unwrap())
+                    }
+                    Ok(()) => {}
+                    Err(e) => println!("{}", e),
+                }
+                if timing {
+                    println!("{}", started.report(vm.executed));
+                }
                 globals = vm.globals;
+                symbol_table = compiler.symbol_table;
+                constants = compiler.constants;
             }
             Err(e) => {
                 println!("{}", e);
             }
         };
-
-        symbol_table = compiler.symbol_table;
-        constants = compiler.constants;
     }
 }
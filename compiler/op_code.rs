@@ -1,9 +1,7 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
 
-use byteorder;
-use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
-
-use strum::{EnumCount, EnumIter};
+use strum::{EnumCount, EnumIter, FromRepr};
 
 // why not type, see https://stackoverflow.com/a/35569079/1713757
 #[derive(Hash, Eq, Debug, Clone, PartialEq, PartialOrd)]
@@ -13,11 +11,14 @@ pub struct Instructions {
 
 pub struct OpcodeDefinition {
     pub(crate) name: &'static str,
-    operand_width: Vec<i32>,
+    pub(crate) operand_width: &'static [usize],
 }
 
+// no instruction takes more operands than this, so they can be decoded into a fixed-size array
+pub const MAX_OPERANDS: usize = 2;
+
 #[repr(u8)]
-#[derive(Debug, Hash, Eq, Clone, Copy, PartialEq, EnumCount, EnumIter)]
+#[derive(Debug, Hash, Eq, Clone, Copy, PartialEq, EnumCount, EnumIter, FromRepr)]
 pub enum Opcode {
     OpConst,
     OpAdd,
@@ -49,140 +50,192 @@ pub enum Opcode {
     OpClosure,
     OpGetFree,
     OpCurrentClosure,
+    OpClosureRef,
+    OpGetOuter,
+    OpAddInt,
+    OpSubInt,
+    OpMulInt,
+    OpDivInt,
+    OpTakeGlobal,
+    OpTakeLocal,
+    OpMatchArray,
+    OpMatchHash,
+    OpArrayRest,
+    // last, so the bytes of the other opcodes stay what bundles already hold
+    OpLessThan,
 }
 
 lazy_static! {
     pub static ref DEFINITIONS: HashMap<Opcode, OpcodeDefinition> = {
         let mut m = HashMap::new();
-        m.insert(Opcode::OpConst, OpcodeDefinition { name: "OpConst", operand_width: vec![2] });
-        m.insert(Opcode::OpAdd, OpcodeDefinition { name: "OpAdd", operand_width: vec![] });
-        m.insert(Opcode::OpPop, OpcodeDefinition { name: "OpPop", operand_width: vec![] });
-        m.insert(Opcode::OpSub, OpcodeDefinition { name: "OpSub", operand_width: vec![] });
-        m.insert(Opcode::OpMul, OpcodeDefinition { name: "OpMul", operand_width: vec![] });
-        m.insert(Opcode::OpDiv, OpcodeDefinition { name: "OpDiv", operand_width: vec![] });
-        m.insert(Opcode::OpTrue, OpcodeDefinition { name: "OpTrue", operand_width: vec![] });
-        m.insert(Opcode::OpFalse, OpcodeDefinition { name: "OpFalse", operand_width: vec![] });
-        m.insert(Opcode::OpEqual, OpcodeDefinition { name: "OpEqual", operand_width: vec![] });
+        m.insert(Opcode::OpConst, OpcodeDefinition { name: "OpConst", operand_width: &[2] });
+        m.insert(Opcode::OpAdd, OpcodeDefinition { name: "OpAdd", operand_width: &[] });
+        m.insert(Opcode::OpPop, OpcodeDefinition { name: "OpPop", operand_width: &[] });
+        m.insert(Opcode::OpSub, OpcodeDefinition { name: "OpSub", operand_width: &[] });
+        m.insert(Opcode::OpMul, OpcodeDefinition { name: "OpMul", operand_width: &[] });
+        m.insert(Opcode::OpDiv, OpcodeDefinition { name: "OpDiv", operand_width: &[] });
+        m.insert(Opcode::OpTrue, OpcodeDefinition { name: "OpTrue", operand_width: &[] });
+        m.insert(Opcode::OpFalse, OpcodeDefinition { name: "OpFalse", operand_width: &[] });
+        m.insert(Opcode::OpEqual, OpcodeDefinition { name: "OpEqual", operand_width: &[] });
         m.insert(
             Opcode::OpNotEqual,
-            OpcodeDefinition { name: "OpNotEqual", operand_width: vec![] },
+            OpcodeDefinition { name: "OpNotEqual", operand_width: &[] },
         );
         m.insert(
             Opcode::OpGreaterThan,
-            OpcodeDefinition { name: "OpGreatThan", operand_width: vec![] },
+            OpcodeDefinition { name: "OpGreatThan", operand_width: &[] },
+        );
+        m.insert(
+            Opcode::OpLessThan,
+            OpcodeDefinition { name: "OpLessThan", operand_width: &[] },
         );
-        m.insert(Opcode::OpMinus, OpcodeDefinition { name: "OpMinus", operand_width: vec![] });
-        m.insert(Opcode::OpBang, OpcodeDefinition { name: "OpBang", operand_width: vec![] });
+        m.insert(Opcode::OpMinus, OpcodeDefinition { name: "OpMinus", operand_width: &[] });
+        m.insert(Opcode::OpBang, OpcodeDefinition { name: "OpBang", operand_width: &[] });
         m.insert(
             Opcode::OpJumpNotTruthy,
-            OpcodeDefinition { name: "OpJumpNotTruthy", operand_width: vec![2] },
+            OpcodeDefinition { name: "OpJumpNotTruthy", operand_width: &[2] },
         );
-        m.insert(Opcode::OpJump, OpcodeDefinition { name: "OpJump", operand_width: vec![2] });
-        m.insert(Opcode::OpNull, OpcodeDefinition { name: "OpNull", operand_width: vec![] });
+        m.insert(Opcode::OpJump, OpcodeDefinition { name: "OpJump", operand_width: &[2] });
+        m.insert(Opcode::OpNull, OpcodeDefinition { name: "OpNull", operand_width: &[] });
         m.insert(
             Opcode::OpGetGlobal,
-            OpcodeDefinition { name: "OpGetGlobal", operand_width: vec![2] },
+            OpcodeDefinition { name: "OpGetGlobal", operand_width: &[2] },
         );
         m.insert(
             Opcode::OpSetGlobal,
-            OpcodeDefinition { name: "OpSetGlobal", operand_width: vec![2] },
+            OpcodeDefinition { name: "OpSetGlobal", operand_width: &[2] },
         );
-        m.insert(Opcode::OpArray, OpcodeDefinition { name: "OpArray", operand_width: vec![2] });
-        m.insert(Opcode::OpHash, OpcodeDefinition { name: "OpHash", operand_width: vec![2] });
-        m.insert(Opcode::OpIndex, OpcodeDefinition { name: "OpIndex", operand_width: vec![] });
-        m.insert(Opcode::OpCall, OpcodeDefinition { name: "OpCall", operand_width: vec![1] });
-        m.insert(Opcode::OpReturn, OpcodeDefinition { name: "OpReturn", operand_width: vec![] });
+        m.insert(Opcode::OpArray, OpcodeDefinition { name: "OpArray", operand_width: &[2] });
+        m.insert(Opcode::OpHash, OpcodeDefinition { name: "OpHash", operand_width: &[2] });
+        m.insert(Opcode::OpIndex, OpcodeDefinition { name: "OpIndex", operand_width: &[] });
+        m.insert(Opcode::OpCall, OpcodeDefinition { name: "OpCall", operand_width: &[1] });
+        m.insert(Opcode::OpReturn, OpcodeDefinition { name: "OpReturn", operand_width: &[] });
         m.insert(
             Opcode::OpReturnValue,
-            OpcodeDefinition { name: "OpReturnValue", operand_width: vec![] },
+            OpcodeDefinition { name: "OpReturnValue", operand_width: &[] },
         );
         m.insert(
             Opcode::OpGetLocal,
-            OpcodeDefinition { name: "OpGetLocal", operand_width: vec![1] },
+            OpcodeDefinition { name: "OpGetLocal", operand_width: &[1] },
         );
         m.insert(
             Opcode::OpSetLocal,
-            OpcodeDefinition { name: "OpSetLocal", operand_width: vec![1] },
+            OpcodeDefinition { name: "OpSetLocal", operand_width: &[1] },
         );
         m.insert(
             Opcode::OpGetBuiltin,
-            OpcodeDefinition { name: "OpGetBuiltin", operand_width: vec![1] },
+            OpcodeDefinition { name: "OpGetBuiltin", operand_width: &[1] },
         );
         m.insert(
             Opcode::OpClosure,
-            OpcodeDefinition { name: "OpClosure", operand_width: vec![2, 1] },
+            OpcodeDefinition { name: "OpClosure", operand_width: &[2, 1] },
         );
         m.insert(
             Opcode::OpGetFree,
-            OpcodeDefinition { name: "OpGetFree", operand_width: vec![1] },
+            OpcodeDefinition { name: "OpGetFree", operand_width: &[1] },
         );
         m.insert(
             Opcode::OpCurrentClosure,
-            OpcodeDefinition { name: "OpCurrentClosure", operand_width: vec![] },
+            OpcodeDefinition { name: "OpCurrentClosure", operand_width: &[] },
+        );
+        // non-escaping closures: created without copying free variables, which they read from
+        // the locals of the creating frame
+        m.insert(
+            Opcode::OpClosureRef,
+            OpcodeDefinition { name: "OpClosureRef", operand_width: &[2] },
+        );
+        m.insert(
+            Opcode::OpGetOuter,
+            OpcodeDefinition { name: "OpGetOuter", operand_width: &[1] },
+        );
+        // arithmetic on operands the compiler knows are integers, see `CompileOptions::int_ops`
+        m.insert(Opcode::OpAddInt, OpcodeDefinition { name: "OpAddInt", operand_width: &[] });
+        m.insert(Opcode::OpSubInt, OpcodeDefinition { name: "OpSubInt", operand_width: &[] });
+        m.insert(Opcode::OpMulInt, OpcodeDefinition { name: "OpMulInt", operand_width: &[] });
+        m.insert(Opcode::OpDivInt, OpcodeDefinition { name: "OpDivInt", operand_width: &[] });
+        // `OpGetGlobal` and `OpGetLocal` leaving null behind, see `Compiler::compile_handover`
+        m.insert(
+            Opcode::OpTakeGlobal,
+            OpcodeDefinition { name: "OpTakeGlobal", operand_width: &[2] },
+        );
+        m.insert(
+            Opcode::OpTakeLocal,
+            OpcodeDefinition { name: "OpTakeLocal", operand_width: &[1] },
+        );
+        // the tests and the rest binding of `match` patterns, see `Compiler::compile_match`
+        m.insert(
+            Opcode::OpMatchArray,
+            OpcodeDefinition { name: "OpMatchArray", operand_width: &[2, 1] },
+        );
+        m.insert(
+            Opcode::OpMatchHash,
+            OpcodeDefinition { name: "OpMatchHash", operand_width: &[] },
+        );
+        m.insert(
+            Opcode::OpArrayRest,
+            OpcodeDefinition { name: "OpArrayRest", operand_width: &[2] },
         );
         return m;
     };
 }
 
-pub fn make_instructions(op: Opcode, operands: &Vec<usize>) -> Instructions {
-    let mut instructions = Vec::new();
-    instructions.push(op as u8);
-    let widths = &DEFINITIONS.get(&op).unwrap().operand_width;
-
-    for (o, w) in operands.into_iter().zip(widths) {
-        match w {
-            2 => {
-                instructions.write_u16::<BigEndian>(*o as u16).unwrap();
-            }
-            1 => {
-                instructions.write_u8(*o as u8).unwrap();
-            }
-            _ => {
-                panic!("unsupported operand width {}", w)
-            }
-        }
-    }
-
-    return Instructions { data: instructions };
+pub fn make_instructions(op: Opcode, operands: &[usize]) -> Instructions {
+    let mut instructions = Instructions { data: Vec::with_capacity(1 + 2 * MAX_OPERANDS) };
+    instructions.push(op, operands);
+    return instructions;
 }
 
-pub fn read_operands(def: &OpcodeDefinition, ins: &[u8]) -> (Vec<usize>, usize) {
-    let mut operands = Vec::with_capacity(def.operand_width.len());
+// operands are big-endian and unaligned, read straight from the instruction bytes
+pub fn read_operands(def: &OpcodeDefinition, ins: &[u8]) -> ([usize; MAX_OPERANDS], usize) {
+    let mut operands = [0; MAX_OPERANDS];
     let mut offset = 0;
 
-    for w in &def.operand_width {
+    for (i, w) in def.operand_width.iter().enumerate() {
         match w {
-            2 => {
-                operands.push(BigEndian::read_u16(&ins[offset..offset + 2]) as usize);
-                offset = offset + 2;
-            }
-            1 => {
-                operands.push(ins[offset] as usize);
-                offset = offset + 1;
-            }
-            0 => {}
+            2 => operands[i] = read_u16(ins, offset),
+            1 => operands[i] = read_u8(ins, offset),
             _ => {
                 panic!("unsupported operand width {} for read", w)
             }
         }
+        offset += w;
     }
 
     return (operands, offset);
 }
 
+#[inline(always)]
+pub fn read_u16(ins: &[u8], pos: usize) -> usize {
+    return u16::from_be_bytes([ins[pos], ins[pos + 1]]) as usize;
+}
+
+#[inline(always)]
+pub fn read_u8(ins: &[u8], pos: usize) -> usize {
+    return ins[pos] as usize;
+}
+
 pub fn concat_instructions(expected: &Vec<Instructions>) -> Instructions {
     let mut out = Instructions { data: vec![] };
 
     for instruction in expected {
-        out = out.merge_instructions(instruction)
+        out.data.extend_from_slice(&instruction.data);
     }
 
     return out;
 }
 
+// a byte read back from a bundle can be anything, see `bundle::decode`
+impl TryFrom<u8> for Opcode {
+    type Error = String;
+
+    fn try_from(op: u8) -> Result<Self, Self::Error> {
+        return Opcode::from_repr(op).ok_or_else(|| format!("unknown opcode {:#04x}", op));
+    }
+}
+
+// for instructions the compiler made or `bundle::decode` checked, which only hold opcodes
 pub fn cast_u8_to_opcode(op: u8) -> Opcode {
-    // https://stackoverflow.com/a/42382144/1713757
-    return unsafe { ::std::mem::transmute(op) };
+    return Opcode::try_from(op).unwrap_or_else(|e| panic!("{}", e));
 }
 
 impl Instructions {
@@ -196,18 +249,19 @@ impl Instructions {
 
             let definition = DEFINITIONS.get(&opcode).unwrap();
             let (operands, read_size) = read_operands(definition, &self.data[i + 1..]);
-            ret.push_str(&format!("{:04} {}\n", i, Self::fmt_instructions(definition, &operands)));
-            i = i + 1 + read_size;
+            let operands = &operands[..definition.operand_width.len()];
+            ret.push_str(&format!("{:04} {}\n", i, Self::fmt_instructions(definition, operands)));
+            i += 1 + read_size;
         }
 
         return ret;
     }
 
-    fn fmt_instructions(def: &OpcodeDefinition, operands: &Vec<usize>) -> String {
+    fn fmt_instructions(def: &OpcodeDefinition, operands: &[usize]) -> String {
         match def.operand_width.len() {
             2 => format!("{} {} {}", def.name, operands[0], operands[1]),
             1 => format!("{} {}", def.name, operands[0]),
-            0 => format!("{}", def.name),
+            0 => def.name.to_string(),
             _ => {
                 panic!("unsupported operand width {}", def.operand_width.len());
             }
@@ -215,13 +269,26 @@ impl Instructions {
     }
 
     pub fn merge_instructions(&self, other: &Instructions) -> Instructions {
-        let ins = vec![self, other];
-        // Maybe extend_from_slice, but I have not make it work
-        // https://stackoverflow.com/a/69578632/1713757
-        return Instructions {
-            data: ins
-                .iter()
-                .fold(vec![], |sum, &i| [sum.as_slice(), i.data.as_slice()].concat()),
-        };
+        let mut data = Vec::with_capacity(self.data.len() + other.data.len());
+        data.extend_from_slice(&self.data);
+        data.extend_from_slice(&other.data);
+        return Instructions { data };
+    }
+
+    // appends one instruction in place and returns its position
+    pub fn push(&mut self, op: Opcode, operands: &[usize]) -> usize {
+        let pos = self.data.len();
+        self.data.push(op as u8);
+        let widths = DEFINITIONS.get(&op).unwrap().operand_width;
+        for (o, w) in operands.iter().zip(widths) {
+            match w {
+                2 => self.data.extend_from_slice(&(*o as u16).to_be_bytes()),
+                1 => self.data.push(*o as u8),
+                _ => {
+                    panic!("unsupported operand width {}", w)
+                }
+            }
+        }
+        return pos;
     }
 }
@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::panic;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
+
+use object::builtins::BuiltIns;
+use object::{Closure, CompiledFunction, Object};
+
+use crate::bundle::{decode_values, encode_values};
+use crate::compiler::Bytecode;
+use crate::op_code::{cast_u8_to_opcode, read_operands, Instructions, Opcode, DEFINITIONS};
+use crate::vm::{VMError, VM};
+
+// `pmap(a, f)` on worker threads, each given `f` and its chunk of `a` as a bundle, see `bundle`;
+// `None` when `f` reads globals, prints or can't be put in a bundle
+pub fn map(
+    constants: &[Rc<Object>],
+    f: &Object,
+    elements: &VecDeque<Rc<Object>>,
+    threads: usize,
+) -> Option<Result<Rc<Object>, VMError>> {
+    let closure = match f {
+        Object::ClosureObj(closure) if closure.frame.is_none() => closure,
+        _ => return None,
+    };
+    if threads < 2 || elements.len() < 2 || closure.func.num_parameters != 1 || closure.func.rest {
+        return None;
+    }
+    if !portable(f, constants) || !elements.iter().all(|e| portable(e, constants)) {
+        return None;
+    }
+    let mut carried = constants.to_vec();
+    carried.push(Rc::new(Object::CompiledFunction(Rc::clone(&closure.func))));
+    carried.extend(closure.free.iter().cloned());
+    let program = Arc::new(encode_values(carried).ok()?);
+    let function = constants.len();
+
+    let elements: Vec<Rc<Object>> = elements.iter().cloned().collect();
+    let mut chunks = vec![];
+    for chunk in elements.chunks(elements.len().div_ceil(threads)) {
+        chunks.push(encode_values(chunk.to_vec()).ok()?);
+    }
+    let mut workers = vec![];
+    for chunk in chunks {
+        let program = Arc::clone(&program);
+        match thread::Builder::new().spawn(move || work(&program, function, &chunk)) {
+            Ok(worker) => workers.push(worker),
+            // no threads here, as on wasm
+            Err(_) => return None,
+        }
+    }
+
+    let mut results = VecDeque::with_capacity(elements.len());
+    for worker in workers {
+        match worker.join().unwrap_or_else(|e| panic::resume_unwind(e)) {
+            Ok(chunk) => match decode_values(&chunk) {
+                Ok(values) => results.extend(values),
+                Err(e) => return Some(Err(e)),
+            },
+            Err(Failure::Error(e)) => return Some(Err(e)),
+            Err(Failure::Unsendable) => return None,
+        }
+    }
+    return Some(Ok(Rc::new(Object::Array(results))));
+}
+
+enum Failure {
+    Error(VMError),
+    // a result a bundle can't carry
+    Unsendable,
+}
+
+// the results of calling the function at `function` in `program` with each value in `chunk`
+fn work(program: &[u8], function: usize, chunk: &[u8]) -> Result<Vec<u8>, Failure> {
+    let constants = decode_values(program).map_err(Failure::Error)?;
+    let func = match &*constants[function] {
+        Object::CompiledFunction(func) => Rc::clone(func),
+        o => unreachable!("packed a function, got {}", o),
+    };
+    let free = constants[function + 1..].to_vec();
+    let closure = Rc::new(Object::ClosureObj(Closure { func, free, frame: None }));
+    let instructions = Instructions { data: vec![] };
+    let mut vm = VM::new(Bytecode { instructions, constants, num_globals: 0 });
+    vm.set_threads(1);
+
+    let mut results = vec![];
+    for element in decode_values(chunk).map_err(Failure::Error)? {
+        match vm.call(Rc::clone(&closure), &[element]) {
+            Ok(result) => results.push(result),
+            // the worker's frames mean nothing to whoever called `pmap`
+            Err(e) => return Err(Failure::Error(e.lines().next().unwrap_or("").to_string())),
+        }
+    }
+    return encode_values(results).map_err(|_| Failure::Unsendable);
+}
+
+// whether the functions in `value` read no globals and print nothing
+fn portable(value: &Object, constants: &[Rc<Object>]) -> bool {
+    match value {
+        Object::ClosureObj(closure) => {
+            return sendable(&closure.func, constants)
+                && closure.free.iter().all(|free| portable(free, constants));
+        }
+        Object::Builtin(builtin) => {
+            let name = BuiltIns
+                .iter()
+                .find(|(_, b)| std::ptr::fn_addr_eq(*b, *builtin));
+            return !matches!(name, Some(("puts", _)) | Some(("print", _)));
+        }
+        Object::Memo(memo) => return portable(&memo.function, constants),
+        Object::Partial(partial) => {
+            return portable(&partial.function, constants)
+                && partial.arguments.iter().all(|a| portable(a, constants));
+        }
+        Object::Array(elements) => return elements.iter().all(|e| portable(e, constants)),
+        Object::Hash(pairs) => {
+            return pairs
+                .iter()
+                .all(|(k, v)| portable(k, constants) && portable(v, constants));
+        }
+        _ => return true,
+    }
+}
+
+// whether `func`, and the functions it makes, read no globals and print nothing
+fn sendable(func: &CompiledFunction, constants: &[Rc<Object>]) -> bool {
+    let ins = &func.instructions;
+    let mut ip = 0;
+    while ip < ins.len() {
+        let op = cast_u8_to_opcode(ins[ip]);
+        let (operands, read) = read_operands(&DEFINITIONS[&op], &ins[ip + 1..]);
+        match op {
+            Opcode::OpGetGlobal | Opcode::OpSetGlobal | Opcode::OpTakeGlobal => return false,
+            Opcode::OpGetBuiltin if ["puts", "print"].contains(&BuiltIns[operands[0]].0) => {
+                return false
+            }
+            Opcode::OpClosure | Opcode::OpClosureRef => match &*constants[operands[0]] {
+                Object::CompiledFunction(inner) if sendable(inner, constants) => {}
+                _ => return false,
+            },
+            _ => {}
+        }
+        ip += 1 + read;
+    }
+    return true;
+}
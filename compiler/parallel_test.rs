@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    use object::Object;
+
+    use crate::compile;
+    use crate::parallel::map;
+    use crate::vm::VM;
+
+    // `pmap`'s function from the value of `input`, over 1 to 100 on 4 threads
+    fn map_over(input: &str) -> Option<String> {
+        let bytecode = compile(input).unwrap();
+        let constants = bytecode.constants.clone();
+        let mut vm = VM::new(bytecode);
+        vm.run().unwrap();
+        let f = vm.last_popped_stack_elm().unwrap();
+        let elements: VecDeque<_> = (1..=100).map(|i| Rc::new(Object::Integer(i))).collect();
+        return map(&constants, &f, &elements, 4).map(|result| match result {
+            Ok(value) => value.to_string(),
+            Err(e) => e,
+        });
+    }
+
+    #[test]
+    fn test_worker_threads() {
+        let squares: Vec<String> = (1..=100).map(|i: i64| (i * i).to_string()).collect();
+        let squares = format!("[{}]", squares.join(", "));
+        assert_eq!(map_over("fn(x) { x * x }"), Some(squares));
+        let tens = map_over("let add = fn(k) { fn(x) { [x + k, \"s\"] } }; add(10)").unwrap();
+        assert!(tens.starts_with("[[11, s], [12, s], "));
+        assert!(tens.ends_with("[110, s]]"));
+        // holds a closure, returns one
+        let held = map_over("let f = fn(g) { fn(x) { g(x) } }; f(fn(x) { x + 1 })").unwrap();
+        assert!(held.starts_with("[2, 3, "));
+        assert!(map_over("fn(x) { fn() { x } }").is_some());
+        let error = map_over("fn(x) { if (x > 50) { x / 0 } else { x } }");
+        assert_eq!(error, Some("division by zero".to_string()));
+    }
+
+    #[test]
+    fn test_on_the_programs_thread() {
+        // reads a global
+        assert_eq!(map_over("let k = 2; fn(x) { x * k }"), None);
+        let calls_global = "let double = fn(x) { x * 2 }; fn(x) { double(x) }";
+        assert_eq!(map_over(calls_global), None);
+        // prints
+        assert_eq!(map_over("fn(x) { puts(x) }"), None);
+        // holds a closure that reads a global, or prints
+        let held = "let k = 2; let f = fn(g) { fn(x) { g(x) } }; f(fn(x) { x * k })";
+        assert_eq!(map_over(held), None);
+        assert_eq!(map_over("let f = fn(g) { fn(x) { g(x) } }; f(puts)"), None);
+        assert_eq!(map_over("fn(a, b) { a }"), None);
+    }
+}
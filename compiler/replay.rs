@@ -0,0 +1,154 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::compiler::Bytecode;
+use crate::event::{Event, Scope};
+use crate::vm::{VMError, VM};
+
+// runs longer than this many instructions are only recorded up to it
+pub const MAX_STEPS: usize = 1_000_000;
+// a snapshot is kept every this many steps, so going back replays at most that many
+const CHECKPOINT_INTERVAL: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSnapshot {
+    pub function: String,
+    pub base_pointer: usize,
+}
+
+// The VM's stack, frames and globals as the events left them, values in their printed form.
+// Locals a call reserved but didn't bind yet are empty strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub stack: Vec<String>,
+    pub frames: Vec<FrameSnapshot>,
+    pub globals: BTreeMap<usize, String>,
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        let main = FrameSnapshot { function: "<main>".to_string(), base_pointer: 0 };
+        return Snapshot { stack: vec![], frames: vec![main], globals: BTreeMap::new() };
+    }
+}
+
+impl Snapshot {
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::Step { .. } => {}
+            Event::Push { slot, value } => {
+                self.stack.truncate(*slot);
+                self.stack.push(value.clone());
+            }
+            Event::Pop { sp } => self.stack.truncate(*sp),
+            Event::Enter { function, base_pointer, sp } => {
+                self.stack.resize(*sp, String::new());
+                self.frames.push(FrameSnapshot {
+                    function: function.clone(),
+                    base_pointer: *base_pointer,
+                });
+            }
+            Event::Exit { sp, .. } => {
+                self.frames.pop();
+                self.stack.truncate(*sp);
+            }
+            Event::Bind { scope: Scope::Global, index, value } => {
+                self.globals.insert(*index, value.clone());
+            }
+            Event::Bind { scope: Scope::Local, index, value } => {
+                let base = self.frames.last().map_or(0, |f| f.base_pointer);
+                self.stack[base + index] = value.clone();
+            }
+        }
+    }
+}
+
+// a finished run, any state of it rebuilt from its events; position `n` is right before the `n`th
+// instruction
+pub struct Recording {
+    events: Vec<Event>,
+    // where each instruction's events start
+    steps: Vec<usize>,
+    // the state at positions 0, CHECKPOINT_INTERVAL, 2 * CHECKPOINT_INTERVAL...
+    checkpoints: Vec<Snapshot>,
+    pub truncated: bool,
+    // how the run ended
+    pub result: Result<(), VMError>,
+}
+
+impl Recording {
+    pub fn record(bytecode: Bytecode) -> Recording {
+        let events = Rc::new(RefCell::new(vec![]));
+        let steps = Rc::new(RefCell::new(0));
+        let mut vm = VM::new(bytecode);
+        let (recorded, counted) = (Rc::clone(&events), Rc::clone(&steps));
+        vm.set_observer(move |e| {
+            if matches!(e, Event::Step { .. }) {
+                *counted.borrow_mut() += 1;
+            }
+            if *counted.borrow() <= MAX_STEPS {
+                recorded.borrow_mut().push(e);
+            }
+        });
+        let result = vm.run();
+        let truncated = *steps.borrow() > MAX_STEPS;
+        return Recording::new(events.take(), truncated, result);
+    }
+
+    pub fn new(events: Vec<Event>, truncated: bool, result: Result<(), VMError>) -> Recording {
+        let mut steps = vec![];
+        let mut checkpoints = vec![];
+        let mut state = Snapshot::default();
+        for (i, e) in events.iter().enumerate() {
+            if let Event::Step { .. } = e {
+                if steps.len() % CHECKPOINT_INTERVAL == 0 {
+                    checkpoints.push(state.clone());
+                }
+                steps.push(i);
+            }
+            state.apply(e);
+        }
+        return Recording { events, steps, checkpoints, truncated, result };
+    }
+
+    // the number of instructions run
+    pub fn len(&self) -> usize {
+        return self.steps.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.steps.is_empty();
+    }
+
+    // the instruction about to run at `position`
+    pub fn step(&self, position: usize) -> Option<&Event> {
+        return self.steps.get(position).map(|&i| &self.events[i]);
+    }
+
+    // the events of the instruction at `position`, its step event first
+    pub fn events_of(&self, position: usize) -> &[Event] {
+        return &self.events[self.first_event(position)..self.first_event(position + 1)];
+    }
+
+    pub fn state_at(&self, position: usize) -> Snapshot {
+        let position = position.min(self.len());
+        let checkpoint = (position / CHECKPOINT_INTERVAL).min(self.checkpoints.len().max(1) - 1);
+        let mut state = match self.checkpoints.get(checkpoint) {
+            Some(s) => s.clone(),
+            None => Snapshot::default(),
+        };
+        let start = self.first_event(checkpoint * CHECKPOINT_INTERVAL);
+        for e in self.events[start..self.first_event(position)].iter() {
+            state.apply(e);
+        }
+        return state;
+    }
+
+    fn first_event(&self, position: usize) -> usize {
+        match self.steps.get(position) {
+            Some(&i) => return i,
+            None => return self.events.len(),
+        }
+    }
+}
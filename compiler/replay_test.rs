@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::replay::{Recording, Snapshot};
+
+    fn record(input: &str) -> Recording {
+        return Recording::record(crate::compile(input).unwrap());
+    }
+
+    #[test]
+    fn test_state_at() {
+        let recording = record("let a = 1; let f = fn(x) { let y = x + a; y }; f(2);");
+        assert_eq!(recording.result, Ok(()));
+        assert!(!recording.truncated);
+
+        // right before `y` is bound, `x` is on the stack and `y` not set yet
+        let set_y = (0..recording.len())
+            .find(|&n| matches!(recording.step(n), Some(Event::Step { op: "OpSetLocal", .. })))
+            .unwrap();
+        let state = recording.state_at(set_y);
+        assert_eq!(state.frames.len(), 2);
+        assert_eq!(state.frames[1].function, "f");
+        let base = state.frames[1].base_pointer;
+        assert_eq!(state.stack[base..], ["2".to_string(), String::new(), "3".to_string()]);
+
+        let state = recording.state_at(set_y + 1);
+        assert_eq!(state.stack[base..], ["2".to_string(), "3".to_string()]);
+
+        let end = recording.state_at(recording.len());
+        assert!(end.stack.is_empty());
+        assert_eq!(end.frames.len(), 1);
+        assert_eq!(end.globals.get(&0).map(String::as_str), Some("1"));
+    }
+
+    #[test]
+    fn test_checkpoints_match_full_replay() {
+        let recording = record(
+            "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(10);",
+        );
+        assert!(recording.len() > 1000);
+
+        let mut state = Snapshot::default();
+        for n in 0..=recording.len() {
+            assert_eq!(recording.state_at(n), state, "at step {}", n);
+            for e in recording.events_of(n) {
+                state.apply(e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_run() {
+        let recording = record("let f = fn(x) { x + true }; f(1);");
+        assert!(recording.result.as_ref().unwrap_err().contains("in f"));
+        // the last state is the one the error happened in
+        let state = recording.state_at(recording.len());
+        assert_eq!(state.frames.last().unwrap().function, "f");
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::rc::Rc;
 
+use parser::lexer::token::Span;
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum SymbolScope {
     LOCAL,
@@ -21,17 +23,31 @@ pub struct Symbol {
 pub struct SymbolTable {
     pub outer: Option<Rc<SymbolTable>>,
     symbols: HashMap<String, Rc<Symbol>>,
+    // where each name was bound in the source, for names defined with `define_at`
+    spans: HashMap<String, Span>,
     pub free_symbols: Vec<Rc<Symbol>>,
     pub num_definitions: usize,
 }
 
 impl SymbolTable {
     pub fn new() -> SymbolTable {
-        SymbolTable { symbols: HashMap::new(), free_symbols: vec![], num_definitions: 0, outer: None }
+        SymbolTable {
+            symbols: HashMap::new(),
+            spans: HashMap::new(),
+            free_symbols: vec![],
+            num_definitions: 0,
+            outer: None,
+        }
     }
 
     pub fn new_enclosed_symbol_table(outer: SymbolTable) -> SymbolTable {
-        SymbolTable { symbols: HashMap::new(), free_symbols: vec![], num_definitions: 0, outer: Some(Rc::new(outer)) }
+        SymbolTable {
+            symbols: HashMap::new(),
+            spans: HashMap::new(),
+            free_symbols: vec![],
+            num_definitions: 0,
+            outer: Some(Rc::new(outer)),
+        }
     }
 
     pub fn define(&mut self, name: String) -> Rc<Symbol> {
@@ -44,15 +60,99 @@ impl SymbolTable {
 
         self.num_definitions += 1;
         self.symbols.insert(name.clone(), Rc::clone(&symbol));
+        self.spans.remove(&name);
+        return symbol;
+    }
+
+    // a slot nothing reads by name, for a `_` parameter, whose argument is still passed in it
+    pub fn define_slot(&mut self) {
+        self.num_definitions += 1;
+    }
+
+    pub fn define_at(&mut self, name: String, span: Span) -> Rc<Symbol> {
+        let symbol = self.define(name.clone());
+        self.spans.insert(name, span);
         return symbol;
     }
 
-    pub fn resolve(&self, name: String) -> Option<Rc<Symbol>> {
-        let symbol = self.symbols.get(&name);
-        if symbol.is_none() && self.outer.is_some() {
-            return self.outer.as_ref().unwrap().resolve(name);
+    // `define_at`, keeping the slot of a global or local this table already binds: a `let` in a
+    // loop runs on every turn and has to write where the code before it reads
+    pub fn rebind_at(&mut self, name: String, span: Span) -> Rc<Symbol> {
+        match self.symbols.get(&name) {
+            Some(s) if s.scope == SymbolScope::Global || s.scope == SymbolScope::LOCAL => {
+                let symbol = Rc::clone(s);
+                self.spans.insert(name, span);
+                return symbol;
+            }
+            _ => return self.define_at(name, span),
+        }
+    }
+
+    // every name this table binds, sorted, with the span of its latest definition; temporaries,
+    // starting with `$`, are left out
+    pub fn definitions(&self) -> Vec<(Rc<Symbol>, Option<Span>)> {
+        let mut definitions: Vec<(Rc<Symbol>, Option<Span>)> = self
+            .symbols
+            .iter()
+            .filter(|(name, _)| !name.starts_with('$'))
+            .map(|(name, symbol)| (Rc::clone(symbol), self.spans.get(name).cloned()))
+            .collect();
+        definitions.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+        return definitions;
+    }
+
+    // binds the symbol of `from` to `to` instead, keeping its scope and index
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<Rc<Symbol>, String> {
+        if self.symbols.contains_key(to) {
+            return Err(format!("'{}' is already defined", to));
+        }
+        let old = match self.symbols.remove(from) {
+            Some(symbol) => symbol,
+            None => return Err(format!("'{}' is not defined", from)),
+        };
+
+        let symbol =
+            Rc::new(Symbol { name: to.to_string(), scope: old.scope.clone(), index: old.index });
+        self.symbols.insert(to.to_string(), Rc::clone(&symbol));
+        if let Some(span) = self.spans.remove(from) {
+            self.spans.insert(to.to_string(), span);
+        }
+        return Ok(symbol);
+    }
+
+    // a local of an enclosing function becomes a free symbol of every table in between
+    pub fn resolve(&mut self, name: String) -> Option<Rc<Symbol>> {
+        if let Some(symbol) = self.symbols.get(&name) {
+            return Some(Rc::clone(symbol));
+        }
+        let outer = Rc::make_mut(self.outer.as_mut()?);
+        let symbol = outer.resolve(name)?;
+        if symbol.scope == SymbolScope::Global || symbol.scope == SymbolScope::Builtin {
+            return Some(symbol);
+        }
+        return Some(self.define_free(symbol));
+    }
+
+    // `resolve` without capturing anything: a name of this table or a global
+    pub fn lookup(&self, name: &str) -> Option<Rc<Symbol>> {
+        if let Some(symbol) = self.symbols.get(name) {
+            return Some(Rc::clone(symbol));
+        }
+        let symbol = self.outer.as_ref()?.lookup(name)?;
+        if symbol.scope == SymbolScope::Global {
+            return Some(symbol);
+        }
+        return None;
+    }
+
+    // every name this table and the tables around it bind, builtins included, for suggestions
+    pub fn names(&self) -> Vec<String> {
+        let own = self.symbols.keys().filter(|name| !name.starts_with('$'));
+        let mut names: Vec<String> = own.cloned().collect();
+        if let Some(outer) = &self.outer {
+            names.extend(outer.names());
         }
-        return symbol.cloned();
+        return names;
     }
 
     pub fn define_builtin(&mut self, index: usize, name: String) -> Rc<Symbol> {
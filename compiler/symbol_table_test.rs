@@ -1,5 +1,8 @@
 #[cfg(test)]
 mod tests {
+    use parser::lexer::token::Span;
+
+    use crate::compiler::Compiler;
     use crate::symbol_table::{SymbolScope, SymbolTable};
     #[test]
     fn test_define() {
@@ -16,4 +19,51 @@ mod tests {
         let symbol = symbol_table.define("x".to_string());
         assert_eq!(symbol_table.resolve("x".to_string()), Some(symbol));
     }
+
+    #[test]
+    fn test_resolve_free() {
+        let mut global = SymbolTable::new();
+        global.define("a".to_string());
+        let mut first = SymbolTable::new_enclosed_symbol_table(global);
+        first.define("b".to_string());
+        let mut second = SymbolTable::new_enclosed_symbol_table(first);
+        second.define("c".to_string());
+
+        assert_eq!(second.resolve("a".to_string()).unwrap().scope, SymbolScope::Global);
+        assert_eq!(second.resolve("c".to_string()).unwrap().scope, SymbolScope::LOCAL);
+        let b = second.resolve("b".to_string()).unwrap();
+        assert_eq!((b.scope.clone(), b.index), (SymbolScope::Free, 0));
+        assert_eq!(second.free_symbols[0].scope, SymbolScope::LOCAL);
+        assert_eq!(second.resolve("d".to_string()), None);
+    }
+
+    #[test]
+    fn test_definitions_and_rename() {
+        let mut compiler = Compiler::new();
+        compiler
+            .compile(&parser::parse("let b = 1;\nlet a = b + 1;").unwrap())
+            .unwrap();
+        let table = &mut compiler.symbol_table;
+        let globals: Vec<(String, usize, Option<Span>)> = table
+            .definitions()
+            .into_iter()
+            .filter(|(s, _)| s.scope == SymbolScope::Global)
+            .map(|(s, span)| (s.name.clone(), s.index, span))
+            .collect();
+        assert_eq!(
+            globals,
+            vec![
+                ("a".to_string(), 1, Some(Span { start: 15, end: 16 })),
+                ("b".to_string(), 0, Some(Span { start: 4, end: 5 })),
+            ]
+        );
+        assert!(table.definitions().iter().any(|(s, _)| s.name == "len"));
+
+        let renamed = table.rename("a", "total").unwrap();
+        assert_eq!((renamed.scope.clone(), renamed.index), (SymbolScope::Global, 1));
+        assert_eq!(table.resolve("total".to_string()), Some(renamed));
+        assert_eq!(table.resolve("a".to_string()), None);
+        assert!(table.rename("a", "c").is_err());
+        assert!(table.rename("b", "total").is_err());
+    }
 }
@@ -0,0 +1,47 @@
+// Its own test binary: the interrupt flag is process wide, and would cancel whatever tests ran
+// alongside this one.
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+use compiler::vm::VM;
+use object::environment::Env;
+use object::interrupt;
+use parser::parse;
+
+// far longer than the test should take
+const SLOW: &str = "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(60)";
+
+// what the repls' Ctrl-C handler does, once something is running
+fn press_ctrl_c() -> thread::JoinHandle<()> {
+    return thread::spawn(|| {
+        while !interrupt::request() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    });
+}
+
+#[test]
+fn test_interrupt() {
+    assert!(!interrupt::request(), "nothing to cancel at the prompt");
+
+    let ctrl_c = press_ctrl_c();
+    let mut vm = VM::new(compiler::compile(SLOW).unwrap());
+    let error = interrupt::run(|| vm.run()).unwrap_err();
+    assert_eq!(error.lines().next(), Some("interrupted"));
+    ctrl_c.join().unwrap();
+
+    // the next run isn't cancelled
+    let mut vm = VM::new_with_global_store(compiler::compile("1 + 1").unwrap(), vm.globals);
+    interrupt::run(|| vm.run()).unwrap();
+    assert_eq!(vm.last_popped_stack_elm().unwrap().to_string(), "2");
+
+    let env: Env = Rc::new(RefCell::new(Default::default()));
+    let ctrl_c = press_ctrl_c();
+    let error = interrupt::run(|| interpreter::eval(parse(SLOW).unwrap(), &env)).unwrap_err();
+    assert_eq!(error, "interrupted");
+    ctrl_c.join().unwrap();
+    let fib = interrupt::run(|| interpreter::eval(parse("fib(10)").unwrap(), &env));
+    assert_eq!(fib.unwrap().to_string(), "55");
+}
@@ -1,21 +1,49 @@
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::mem;
 use std::rc::Rc;
 
-use byteorder::{BigEndian, ByteOrder};
-use object::builtins::BuiltIns;
-
-use object::{BuiltinFunc, Closure, CompiledFunction, Object};
+use object::builtins::{calling, BuiltIns};
+use object::interrupt;
+use object::matching;
+use object::number::{self, Arithmetic};
+use object::order;
+#[cfg(feature = "parallel")]
+use object::parallel::pmap;
+
+use object::chars;
+use object::memo::Memo;
+use object::partial::Partial;
+use object::singleton;
+use object::{arity_error, BuiltinFunc, Closure, Object};
 use object::Object::ClosureObj;
 
 use crate::compiler::Bytecode;
+use crate::event::{Event, Scope};
 use crate::frame::Frame;
-use crate::op_code::{cast_u8_to_opcode, Opcode};
+use crate::op_code::{make_instructions, read_u16, read_u8, Opcode, DEFINITIONS};
+#[cfg(feature = "parallel")]
+use crate::{concurrent, parallel};
 
 const STACK_SIZE: usize = 2048;
+// the most globals a program can have, global indexes are two byte operands
 pub const GLOBAL_SIZE: usize = 65536;
 const MAX_FRAMES: usize = 1024;
 
+#[cfg(feature = "parallel")]
+fn default_threads() -> usize {
+    return std::thread::available_parallelism().map_or(1, |n| n.get());
+}
+
+#[cfg(not(feature = "parallel"))]
+fn default_threads() -> usize {
+    return 1;
+}
+
+pub type VMError = String;
+
 pub struct VM {
     constants: Vec<Rc<Object>>,
 
@@ -23,9 +51,15 @@ pub struct VM {
     sp: usize, // stack pointer. Always point to the next value. Top of the stack is stack[sp -1]
 
     pub globals: Vec<Rc<Object>>,
+    // instructions run so far
+    pub executed: u64,
 
     frames: Vec<Frame>,
     frame_index: usize,
+
+    observer: Option<Box<dyn FnMut(Event)>>,
+    // how many threads `pmap` runs on, see `parallel`
+    threads: usize,
 }
 
 impl VM {
@@ -33,8 +67,15 @@ impl VM {
         // it's rust, it's verbose. You can't just grow your vector size.
         let empty_frame = Frame::new(
             Closure {
-                func: Rc::from(object::CompiledFunction { instructions: vec![], num_locals: 0, num_parameters: 0 }),
-                free: vec![]
+                func: Rc::from(object::CompiledFunction {
+                    instructions: vec![],
+                    num_locals: 0,
+                    num_parameters: 0,
+                    rest: false,
+                    name: String::new(),
+                }),
+                free: vec![],
+                frame: None,
             },
             0,
         );
@@ -43,241 +84,419 @@ impl VM {
             instructions: bytecode.instructions.data,
             num_locals: 0,
             num_parameters: 0,
+            rest: false,
+            name: "<main>".to_string(),
         });
-        let main_closure = Closure {func: main_fn, free: vec![] };
+        let main_closure = Closure { func: main_fn, free: vec![], frame: None };
         let main_frame = Frame::new(main_closure, 0);
         let mut frames = vec![empty_frame; MAX_FRAMES];
         frames[0] = main_frame;
 
         return VM {
             constants: bytecode.constants,
-            stack: vec![Rc::new(Object::Null); STACK_SIZE],
+            stack: vec![singleton::null(); STACK_SIZE],
             sp: 0,
-            globals: vec![Rc::new(Object::Null); GLOBAL_SIZE],
+            globals: vec![singleton::null(); bytecode.num_globals],
+            executed: 0,
             frames,
             frame_index: 1,
+            observer: None,
+            threads: default_threads(),
         };
     }
 
-    pub fn new_with_global_store(bytecode: Bytecode, globals: Vec<Rc<Object>>) -> VM {
-        let mut vm = VM::new(bytecode);
+    // `globals` are those of earlier runs, made room in for the ones `bytecode` adds
+    pub fn new_with_global_store(bytecode: Bytecode, mut globals: Vec<Rc<Object>>) -> VM {
+        if globals.len() < bytecode.num_globals {
+            globals.resize(bytecode.num_globals, singleton::null());
+        }
+        let mut vm = VM::new(Bytecode { num_globals: 0, ..bytecode });
         vm.globals = globals;
         return vm;
     }
 
-    pub fn run(&mut self) {
-        let mut ip = 0;
-        let mut ins: Vec<u8>;
-        while self.current_frame().ip
-            < self.current_frame().instructions().data.clone().len() as i32 - 1
+    // Calls `callee` with `args`, against the globals and constants this vm was made with, for
+    // hosts calling into a script that already ran.
+    pub fn call(&mut self, callee: Rc<Object>, args: &[Rc<Object>]) -> Result<Rc<Object>, VMError> {
+        if args.len() > u8::MAX as usize {
+            return Err(format!("too many arguments: {}, at most {}", args.len(), u8::MAX));
+        }
+        let mut instructions = make_instructions(Opcode::OpCall, &[args.len()]).data;
+        instructions.extend(make_instructions(Opcode::OpPop, &[]).data);
+        let main_fn = Rc::from(object::CompiledFunction {
+            instructions,
+            num_locals: 0,
+            num_parameters: 0,
+            rest: false,
+            name: "<main>".to_string(),
+        });
+        self.frames[0] = Frame::new(Closure { func: main_fn, free: vec![], frame: None }, 0);
+        self.frame_index = 1;
+        self.set_sp(0);
+        self.push(callee)?;
+        for arg in args {
+            self.push(Rc::clone(arg))?;
+        }
+        self.run()?;
+        match self.last_popped_stack_elm() {
+            Some(value) => return Ok(value),
+            None => return Ok(singleton::null()),
+        }
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    // `observer` sees every instruction and every change to the stack, the frames and the globals
+    pub fn set_observer(&mut self, observer: impl FnMut(Event) + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    // the event is only built when someone is watching
+    #[inline(always)]
+    fn observe(&mut self, event: impl FnOnce(&VM) -> Event) {
+        if let Some(mut observer) = self.observer.take() {
+            observer(event(self));
+            self.observer = Some(observer);
+        }
+    }
+
+    // runtime errors name the function they happened in and its callers, innermost first
+    pub fn run(&mut self) -> Result<(), VMError> {
+        return self.execute(0).map_err(|e| self.trace(e));
+    }
+
+    fn trace(&self, error: VMError) -> VMError {
+        let mut message = error;
+        for frame in self.frames[..self.frame_index].iter().rev() {
+            message.push_str(&format!("\n    in {}", frame.cl.func.name));
+        }
+        return message;
+    }
+
+    // runs until the frames above `depth` return
+    fn execute(&mut self, depth: usize) -> Result<(), VMError> {
+        while self.frame_index > depth
+            && self.current_frame().ip < self.current_frame().cl.func.instructions.len() as i32 - 1
         {
             self.current_frame().ip += 1;
-            ip = self.current_frame().ip as usize;
-            ins = self.current_frame().instructions().data.clone();
-
-            let op: u8 = *ins.get(ip).unwrap();
-            let opcode = cast_u8_to_opcode(op);
+            let ip = self.current_frame().ip as usize;
+            // holding the function keeps its instructions alive while frames are pushed and popped
+            let func = Rc::clone(&self.current_frame().cl.func);
+            let ins = func.instructions.as_slice();
+
+            let op: u8 = ins[ip];
+            let opcode = Opcode::try_from(op)?;
+            self.executed += 1;
+            self.observe(|_| Event::Step {
+                function: func.name.clone(),
+                ip,
+                op: DEFINITIONS[&opcode].name,
+            });
 
             match opcode {
                 Opcode::OpConst => {
-                    let const_index = BigEndian::read_u16(&ins[ip + 1..ip + 3]) as usize;
+                    let const_index = read_u16(ins, ip + 1);
                     self.current_frame().ip += 2;
-                    self.push(Rc::clone(&self.constants[const_index]))
+                    self.push(Rc::clone(&self.constants[const_index]))?;
                 }
                 Opcode::OpAdd | Opcode::OpSub | Opcode::OpMul | Opcode::OpDiv => {
-                    self.execute_binary_operation(opcode);
+                    self.execute_binary_operation(opcode)?;
+                }
+                Opcode::OpAddInt | Opcode::OpSubInt | Opcode::OpMulInt | Opcode::OpDivInt => {
+                    self.execute_integer_operation(opcode)?;
                 }
                 Opcode::OpPop => {
                     self.pop();
                 }
                 Opcode::OpTrue => {
-                    self.push(Rc::new(Object::Boolean(true)));
+                    self.push(singleton::boolean(true))?;
                 }
                 Opcode::OpFalse => {
-                    self.push(Rc::new(Object::Boolean(false)));
+                    self.push(singleton::boolean(false))?;
                 }
-                Opcode::OpEqual | Opcode::OpNotEqual | Opcode::OpGreaterThan => {
-                    self.execute_comparison(opcode);
+                Opcode::OpEqual
+                | Opcode::OpNotEqual
+                | Opcode::OpGreaterThan
+                | Opcode::OpLessThan => {
+                    self.execute_comparison(opcode)?;
                 }
                 Opcode::OpMinus => {
-                    self.execute_minus_operation(opcode);
+                    self.execute_minus_operation()?;
                 }
                 Opcode::OpBang => {
-                    self.execute_bang_operation();
+                    self.execute_bang_operation()?;
                 }
                 Opcode::OpJump => {
-                    let pos = BigEndian::read_u16(&ins[ip + 1..ip + 3]) as usize;
+                    let pos = read_u16(ins, ip + 1);
+                    // only loops jump back
+                    if pos <= ip {
+                        interrupt::check()?;
+                    }
                     self.current_frame().ip = pos as i32 - 1;
                 }
                 Opcode::OpJumpNotTruthy => {
-                    let pos = BigEndian::read_u16(&ins[ip + 1..ip + 3]) as usize;
+                    let pos = read_u16(ins, ip + 1);
                     self.current_frame().ip += 2;
                     let condition = self.pop();
-                    if !self.is_truthy(condition) {
+                    if !condition.is_truthy() {
                         self.current_frame().ip = pos as i32 - 1;
                     }
                 }
                 Opcode::OpNull => {
-                    self.push(Rc::new(Object::Null));
+                    self.push(singleton::null())?;
                 }
                 Opcode::OpGetGlobal => {
-                    let global_index = BigEndian::read_u16(&ins[ip + 1..ip + 3]) as usize;
+                    let global_index = read_u16(ins, ip + 1);
                     self.current_frame().ip += 2;
-                    self.push(Rc::clone(&self.globals[global_index]));
+                    self.push(Rc::clone(&self.globals[global_index]))?;
                 }
                 Opcode::OpSetGlobal => {
-                    let global_index = BigEndian::read_u16(&ins[ip + 1..ip + 3]) as usize;
+                    let global_index = read_u16(ins, ip + 1);
                     self.current_frame().ip += 2;
                     self.globals[global_index] = self.pop();
+                    self.observe(|vm| Event::Bind {
+                        scope: Scope::Global,
+                        index: global_index,
+                        value: vm.globals[global_index].to_string(),
+                    });
                 }
                 Opcode::OpArray => {
-                    let count = BigEndian::read_u16(&ins[ip + 1..ip + 3]) as usize;
+                    let count = read_u16(ins, ip + 1);
                     self.current_frame().ip += 2;
                     let elements = self.build_array(self.sp - count, self.sp);
-                    self.sp = self.sp - count;
-                    self.push(Rc::new(Object::Array(elements)));
+                    self.set_sp(self.sp - count);
+                    self.push(Rc::new(Object::Array(elements.into())))?;
                 }
                 Opcode::OpHash => {
-                    let count = BigEndian::read_u16(&ins[ip + 1..ip + 3]) as usize;
+                    let count = read_u16(ins, ip + 1);
                     self.current_frame().ip += 2;
-                    let elements = self.build_hash(self.sp - count, self.sp);
-                    self.sp = self.sp - count;
-                    self.push(Rc::new(Object::Hash(elements)));
+                    let elements = self.build_hash(self.sp - count, self.sp)?;
+                    self.set_sp(self.sp - count);
+                    self.push(Rc::new(Object::Hash(elements)))?;
                 }
                 Opcode::OpIndex => {
                     let index = self.pop();
                     let left = self.pop();
-                    self.execute_index_operation(left, index);
+                    self.execute_index_operation(left, index)?;
                 }
                 Opcode::OpReturnValue => {
                     let return_value = self.pop();
                     let frame = self.pop_frame();
-                    self.sp = frame.base_pointer - 1;
-                    self.push(return_value);
+                    self.exit_frame(&frame);
+                    self.push(return_value)?;
                 }
                 Opcode::OpReturn => {
                     let frame = self.pop_frame();
-                    self.sp = frame.base_pointer - 1;
-                    self.push(Rc::new(object::Object::Null));
+                    self.exit_frame(&frame);
+                    self.push(Rc::new(object::Object::Null))?;
                 }
                 Opcode::OpCall => {
-                    let num_args = ins[ip + 1] as usize;
+                    interrupt::check()?;
+                    let num_args = read_u8(ins, ip + 1);
                     self.current_frame().ip += 1;
-                    self.execute_call(num_args);
+                    self.execute_call(num_args)?;
                 }
                 Opcode::OpSetLocal => {
-                    let local_index = ins[ip + 1] as usize;
+                    let local_index = read_u8(ins, ip + 1);
                     self.current_frame().ip += 1;
                     let base = self.current_frame().base_pointer;
                     self.stack[base + local_index] = self.pop();
+                    self.observe(|vm| Event::Bind {
+                        scope: Scope::Local,
+                        index: local_index,
+                        value: vm.stack[base + local_index].to_string(),
+                    });
                 }
                 Opcode::OpGetLocal => {
-                    let local_index = ins[ip + 1] as usize;
+                    let local_index = read_u8(ins, ip + 1);
                     self.current_frame().ip += 1;
                     let base = self.current_frame().base_pointer;
-                    self.push(Rc::clone(&self.stack[base + local_index]));
+                    self.push(Rc::clone(&self.stack[base + local_index]))?;
                 }
                 Opcode::OpGetBuiltin => {
-                    let built_index = ins[ip + 1] as usize;
+                    let built_index = read_u8(ins, ip + 1);
                     self.current_frame().ip += 1;
                     let definition = BuiltIns.get(built_index).unwrap().1;
-                    self.push(Rc::new(Object::Builtin(definition)));
+                    self.push(Rc::new(Object::Builtin(definition)))?;
                 }
                 Opcode::OpClosure => {
-                    let const_index = BigEndian::read_u16(&ins[ip + 1..ip + 3]) as usize;
-                    let num_free = ins[ip + 3] as usize;
+                    let const_index = read_u16(ins, ip + 1);
+                    let num_free = read_u8(ins, ip + 3);
                     self.current_frame().ip += 3;
-                    self.push_closure(const_index, num_free);
+                    self.push_closure(const_index, num_free)?;
                 }
                 Opcode::OpGetFree => {
-                    let free_index = ins[ip + 1] as usize;
+                    let free_index = read_u8(ins, ip + 1);
                     self.current_frame().ip += 1;
                     let current_closure = self.current_frame().cl.clone();
-                    self.push(current_closure.free[free_index].clone());
+                    self.push(current_closure.free[free_index].clone())?;
                 }
                 Opcode::OpCurrentClosure => {
                     let current_closure = self.current_frame().cl.clone();
-                    self.push(Rc::new(Object::ClosureObj(current_closure)));
+                    self.push(Rc::new(Object::ClosureObj(current_closure)))?;
+                }
+                Opcode::OpClosureRef => {
+                    let const_index = read_u16(ins, ip + 1);
+                    self.current_frame().ip += 2;
+                    let base = self.current_frame().base_pointer;
+                    self.push_closure_ref(const_index, base)?;
+                }
+                Opcode::OpGetOuter => {
+                    let local_index = read_u8(ins, ip + 1);
+                    self.current_frame().ip += 1;
+                    let base = match self.current_frame().cl.frame {
+                        Some(base) => base,
+                        None => return Err("OpGetOuter outside a non-escaping closure".to_string()),
+                    };
+                    self.push(Rc::clone(&self.stack[base + local_index]))?;
+                }
+                Opcode::OpTakeGlobal => {
+                    let global_index = read_u16(ins, ip + 1);
+                    self.current_frame().ip += 2;
+                    let slot = &mut self.globals[global_index];
+                    let value = mem::replace(slot, singleton::null());
+                    self.push(value)?;
+                }
+                Opcode::OpTakeLocal => {
+                    let local_index = read_u8(ins, ip + 1);
+                    self.current_frame().ip += 1;
+                    let base = self.current_frame().base_pointer;
+                    let slot = &mut self.stack[base + local_index];
+                    let value = mem::replace(slot, singleton::null());
+                    self.push(value)?;
+                }
+                Opcode::OpMatchArray => {
+                    let len = read_u16(ins, ip + 1);
+                    let rest = read_u8(ins, ip + 3) == 1;
+                    self.current_frame().ip += 3;
+                    let value = self.pop();
+                    let fits = matching::array_fits(&value, len, rest);
+                    self.push(singleton::boolean(fits))?;
+                }
+                Opcode::OpMatchHash => {
+                    let key = self.pop();
+                    let value = self.pop();
+                    let fits = matching::hash_has(&value, &key);
+                    self.push(singleton::boolean(fits))?;
+                }
+                Opcode::OpArrayRest => {
+                    let from = read_u16(ins, ip + 1);
+                    self.current_frame().ip += 2;
+                    let value = self.pop();
+                    self.push(matching::array_rest(&value, from))?;
                 }
             }
         }
+        return Ok(());
     }
 
-    fn execute_binary_operation(&mut self, opcode: Opcode) {
+    fn execute_binary_operation(&mut self, opcode: Opcode) -> Result<(), VMError> {
         let right = self.pop();
-        let left = self.pop();
-        match (left.borrow(), right.borrow()) {
-            (Object::Integer(l), Object::Integer(r)) => {
-                let result = match opcode {
-                    Opcode::OpAdd => l + r,
-                    Opcode::OpSub => l - r,
-                    Opcode::OpMul => l * r,
-                    Opcode::OpDiv => l / r,
-                    _ => panic!("Unknown opcode for int"),
-                };
-                self.push(Rc::from(Object::Integer(result)));
-            }
-            (Object::String(l), Object::String(r)) => {
-                let result = match opcode {
-                    Opcode::OpAdd => l.to_string() + &r.to_string(),
-                    _ => panic!("Unknown opcode for string"),
-                };
-                self.push(Rc::from(Object::String(result)));
-            }
-            _ => {
-                panic!("unsupported add for those types")
+        // the left of `a + b + c` is the string `a + b` made, that nothing else holds: `c` is
+        // added to it in place, so a chain of them, as a template string is, copies each once
+        if let (Opcode::OpAdd, Object::String(r)) = (opcode, &*right) {
+            let mut left = mem::replace(&mut self.stack[self.sp - 1], Rc::clone(&right));
+            if let Some(Object::String(l)) = Rc::get_mut(&mut left) {
+                l.push_str(r);
+                self.set_sp(self.sp - 1);
+                return self.push(left);
             }
+            self.stack[self.sp - 1] = left;
         }
+        let left = self.pop();
+        return self.binary_operation(opcode, &left, &right);
     }
 
-    fn execute_comparison(&mut self, opcode: Opcode) {
+    // Operands the compiler knows are integers: two `i64`s that don't overflow are done here,
+    // anything else, big integers or division by zero, the generic way.
+    fn execute_integer_operation(&mut self, opcode: Opcode) -> Result<(), VMError> {
         let right = self.pop();
         let left = self.pop();
-        match (left.borrow(), right.borrow()) {
-            (Object::Integer(l), Object::Integer(r)) => {
-                let result = match opcode {
-                    Opcode::OpEqual => l == r,
-                    Opcode::OpNotEqual => l != r,
-                    Opcode::OpGreaterThan => l > r,
-                    _ => panic!("Unknown opcode for comparing int"),
-                };
-                self.push(Rc::from(Object::Boolean(result)));
-            }
-            (Object::Boolean(l), Object::Boolean(r)) => {
-                let result = match opcode {
-                    Opcode::OpEqual => l == r,
-                    Opcode::OpNotEqual => l != r,
-                    _ => panic!("Unknown opcode for comparing boolean"),
-                };
-                self.push(Rc::from(Object::Boolean(result)));
-            }
-            _ => {
-                panic!("unsupported comparison for those types")
+        if let (Object::Integer(l), Object::Integer(r)) = (&*left, &*right) {
+            let result = match opcode {
+                Opcode::OpAddInt => l.checked_add(*r),
+                Opcode::OpSubInt => l.checked_sub(*r),
+                Opcode::OpMulInt => l.checked_mul(*r),
+                _ => l.checked_div(*r),
+            };
+            if let Some(i) = result {
+                return self.push(singleton::integer(i));
             }
         }
+        let generic = match opcode {
+            Opcode::OpAddInt => Opcode::OpAdd,
+            Opcode::OpSubInt => Opcode::OpSub,
+            Opcode::OpMulInt => Opcode::OpMul,
+            _ => Opcode::OpDiv,
+        };
+        return self.binary_operation(generic, &left, &right);
     }
 
-    fn execute_minus_operation(&mut self, opcode: Opcode) {
-        let operand = self.pop();
-        match operand.borrow() {
-            Object::Integer(l) => {
-                self.push(Rc::from(Object::Integer(-*l)));
+    fn binary_operation(
+        &mut self,
+        opcode: Opcode,
+        left: &Object,
+        right: &Object,
+    ) -> Result<(), VMError> {
+        let op = match opcode {
+            Opcode::OpAdd => Arithmetic::Add,
+            Opcode::OpSub => Arithmetic::Sub,
+            Opcode::OpMul => Arithmetic::Mul,
+            Opcode::OpDiv => Arithmetic::Div,
+            _ => return Err(format!("unknown arithmetic {:?}", opcode)),
+        };
+        match (left, right) {
+            (l, r) if number::is_number(l) && number::is_number(r) => {
+                self.push(singleton::share(number::arithmetic(op, l, r)?))?;
+            }
+            (Object::String(l), Object::String(r)) if op == Arithmetic::Add => {
+                self.push(Rc::from(Object::String([l.as_str(), r.as_str()].concat())))?;
+            }
+            (Object::String(_), Object::Char(_)) | (Object::Char(_), Object::String(_))
+                if op == Arithmetic::Add =>
+            {
+                self.push(Rc::new(Object::String(chars::join(left, right).unwrap())))?;
             }
             _ => {
-                panic!("unsupported types for negation {:?}", opcode)
+                return Err(number::unsupported(op, left, right));
             }
         }
+        return Ok(());
+    }
+
+    fn execute_comparison(&mut self, opcode: Opcode) -> Result<(), VMError> {
+        let right = self.pop();
+        let left = self.pop();
+        let result = match opcode {
+            // any two values, see `object::equality`
+            Opcode::OpEqual => left == right,
+            Opcode::OpNotEqual => left != right,
+            Opcode::OpGreaterThan => order::compare(&left, &right)? == Ordering::Greater,
+            Opcode::OpLessThan => order::compare(&left, &right)? == Ordering::Less,
+            _ => return Err(format!("unknown comparison {:?}", opcode)),
+        };
+        self.push(singleton::boolean(result))?;
+        return Ok(());
     }
-    fn execute_bang_operation(&mut self) {
+
+    fn execute_minus_operation(&mut self) -> Result<(), VMError> {
         let operand = self.pop();
         match operand.borrow() {
-            Object::Boolean(l) => {
-                self.push(Rc::from(Object::Boolean(!*l)));
+            l if number::is_number(l) => {
+                self.push(singleton::share(number::negate(l)?))?;
             }
             _ => {
-                self.push(Rc::from(Object::Boolean(false)));
+                return Err(number::unsupported_negation(&operand));
             }
         }
+        return Ok(());
+    }
+    fn execute_bang_operation(&mut self) -> Result<(), VMError> {
+        let operand = self.pop();
+        self.push(singleton::boolean(!operand.is_truthy()))?;
+        return Ok(());
     }
 
     pub fn last_popped_stack_elm(&self) -> Option<Rc<Object>> {
@@ -286,22 +505,25 @@ impl VM {
 
     fn pop(&mut self) -> Rc<Object> {
         let o = Rc::clone(&self.stack[self.sp - 1]);
-        self.sp -= 1;
+        self.set_sp(self.sp - 1);
         return o;
     }
 
-    fn push(&mut self, o: Rc<Object>) {
+    fn push(&mut self, o: Rc<Object>) -> Result<(), VMError> {
         if self.sp >= STACK_SIZE {
-            panic!("Stack overflow");
+            return Err("Stack overflow".to_string());
         };
         self.stack[self.sp] = o;
         self.sp += 1;
+        self.observe(|vm| Event::Push { slot: vm.sp - 1, value: vm.stack[vm.sp - 1].to_string() });
+        return Ok(());
     }
-    fn is_truthy(&self, condition: Rc<Object>) -> bool {
-        match condition.borrow() {
-            Object::Boolean(b) => *b,
-            Object::Null => false,
-            _ => true,
+
+    // drops everything above `sp`
+    fn set_sp(&mut self, sp: usize) {
+        if sp != self.sp {
+            self.sp = sp;
+            self.observe(|vm| Event::Pop { sp: vm.sp });
         }
     }
     fn build_array(&self, start: usize, end: usize) -> Vec<Rc<Object>> {
@@ -312,61 +534,79 @@ impl VM {
         return elements;
     }
 
-    fn build_hash(&self, start: usize, end: usize) -> HashMap<Rc<Object>, Rc<Object>> {
+    fn build_hash(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<HashMap<Rc<Object>, Rc<Object>>, VMError> {
         let mut elements = HashMap::new();
         for i in (start..end).step_by(2) {
             let key = Rc::clone(&self.stack[i]);
             let value = Rc::clone(&self.stack[i + 1]);
+            if elements.contains_key(&key) {
+                return Err(format!("key {} is in the hash twice", key));
+            }
             elements.insert(key, value);
         }
-        return elements;
+        return Ok(elements);
     }
 
-    fn execute_index_operation(&mut self, left: Rc<Object>, index: Rc<Object>) {
+    fn execute_index_operation(&mut self, left: Rc<Object>, index: Rc<Object>) -> Result<(), VMError> {
         match (left.borrow(), index.borrow()) {
             (Object::Array(l), Object::Integer(i)) => {
-                self.execute_array_index(l, *i);
+                self.execute_array_index(l, *i)?;
             }
             (Object::Hash(l), _) => {
-                self.execute_hash_index(l, index);
+                self.execute_hash_index(l, index)?;
             }
             _ => {
-                panic!("unsupported index operation for those types")
+                return Err(format!("index operator not supported for {}", left));
             }
         }
+        return Ok(());
     }
 
-    fn execute_array_index(&mut self, array: &Vec<Rc<Object>>, index: i64) {
+    fn execute_array_index(
+        &mut self,
+        array: &VecDeque<Rc<Object>>,
+        index: i64,
+    ) -> Result<(), VMError> {
         if index < array.len() as i64 && index >= 0 {
-            self.push(Rc::clone(&array[index as usize]));
+            self.push(Rc::clone(&array[index as usize]))?;
         } else {
-            self.push(Rc::new(Object::Null));
+            self.push(singleton::null())?;
         }
+        return Ok(());
     }
 
-    fn execute_hash_index(&mut self, hash: &HashMap<Rc<Object>, Rc<Object>>, index: Rc<Object>) {
+    fn execute_hash_index(&mut self, hash: &HashMap<Rc<Object>, Rc<Object>>, index: Rc<Object>) -> Result<(), VMError> {
         match &*index {
-            Object::Integer(_) | Object::Boolean(_) | Object::String(_) => match hash.get(&index) {
+            key if key.is_hashable() => match hash.get(&index) {
                 Some(el) => {
-                    self.push(Rc::clone(el));
+                    self.push(Rc::clone(el))?;
                 }
                 None => {
-                    self.push(Rc::new(Object::Null));
+                    self.push(singleton::null())?;
                 }
             },
             _ => {
-                panic!("unsupported hash index operation for those types {}", index)
+                return Err(format!("unsupported hash index operation for those types {}", index));
             }
         }
+        return Ok(());
     }
 
     fn current_frame(&mut self) -> &mut Frame {
         &mut self.frames[self.frame_index - 1]
     }
 
-    fn push_frame(&mut self, frame: Frame) {
+    fn push_frame(&mut self, frame: Frame) -> Result<(), VMError> {
+        if self.frame_index >= MAX_FRAMES {
+            return Err(format!("call stack overflow: more than {} nested calls", MAX_FRAMES));
+        }
         self.frames[self.frame_index] = frame;
         self.frame_index += 1;
+        return Ok(());
     }
 
     fn pop_frame(&mut self) -> Frame {
@@ -374,56 +614,186 @@ impl VM {
         return self.frames[self.frame_index].clone();
     }
 
-    fn execute_call(&mut self, num_args: usize) {
+    // drops the frame's locals and arguments, and the callee below them
+    fn exit_frame(&mut self, frame: &Frame) {
+        self.sp = frame.base_pointer - 1;
+        self.observe(|vm| Event::Exit { function: frame.cl.func.name.clone(), sp: vm.sp });
+    }
+
+    fn execute_call(&mut self, num_args: usize) -> Result<(), VMError> {
         let callee = &*self.stack[self.sp - 1 - num_args];
         match callee {
             Object::ClosureObj(cf) => {
-                self.call_closure(cf.clone(), num_args);
+                self.call_closure(cf.clone(), num_args)?;
             }
             Object::Builtin(bt) => {
-                self.call_builtin(bt.clone(), num_args);
+                self.call_builtin(*bt, num_args)?;
             }
-            _ => {
-                panic!("calling non-closure")
+            Object::Memo(memo) => {
+                let memo = Rc::clone(memo);
+                self.call_memo(&memo, num_args)?;
+            }
+            Object::Partial(partial) => {
+                let partial = Rc::clone(partial);
+                self.call_partial(&partial, num_args)?;
+            }
+            f => {
+                return Err(format!("expected {} to be a function", f));
             }
         }
+        return Ok(());
     }
-    fn call_closure(&mut self, cl: Closure, num_args: usize) {
-        if cl.func.num_parameters != num_args {
-            panic!("wrong number of arguments: want={}, got={}", cl.func.num_parameters, num_args);
+    fn call_closure(&mut self, cl: Closure, mut num_args: usize) -> Result<(), VMError> {
+        if let Some(e) = arity_error(cl.func.num_parameters, cl.func.rest, num_args) {
+            return Err(e);
+        }
+        // the arguments from the last parameter's on are one array in its slot
+        if cl.func.rest {
+            let from = self.sp - num_args + cl.func.num_parameters - 1;
+            let rest = self.stack[from..self.sp]
+                .iter_mut()
+                .map(|arg| mem::replace(arg, singleton::null()));
+            let rest = Object::Array(rest.collect());
+            self.set_sp(from);
+            self.push(Rc::new(rest))?;
+            num_args = cl.func.num_parameters;
         }
 
         let frame = Frame::new(cl.clone(), self.sp - num_args);
         self.sp = frame.base_pointer + cl.func.num_locals;
-        self.push_frame(frame);
+        self.push_frame(frame)?;
+        self.observe(|vm| Event::Enter {
+            function: cl.func.name.clone(),
+            base_pointer: vm.sp - cl.func.num_locals,
+            sp: vm.sp,
+        });
+        return Ok(());
     }
 
-    fn call_builtin(&mut self, bt: BuiltinFunc, num_args: usize) {
+    fn call_builtin(&mut self, bt: BuiltinFunc, num_args: usize) -> Result<(), VMError> {
+        // moved off the stack, a builtin given the only reference to a value may change it
+        let args: Vec<Rc<Object>> = self.stack[self.sp - num_args..self.sp]
+            .iter_mut()
+            .map(|arg| mem::replace(arg, singleton::null()))
+            .collect();
+        self.set_sp(self.sp - num_args - 1);
+        if let Some(result) = self.on_threads(bt, &args) {
+            self.push(result?)?;
+            return Ok(());
+        }
+        let result = match calling(bt) {
+            Some(calling) => calling(args, &mut |f, args| self.call_nested(f, args))?,
+            None => bt(args),
+        };
+        self.push(result)?;
+        return Ok(());
+    }
+
+    // the builtins that run on other threads, `None` for the rest, see `concurrent`. `pmap`
+    // does if it can, unless watched, which would miss the calls there
+    #[cfg(feature = "parallel")]
+    fn on_threads(
+        &self,
+        bt: BuiltinFunc,
+        args: &[Rc<Object>],
+    ) -> Option<Result<Rc<Object>, VMError>> {
+        if !std::ptr::fn_addr_eq(bt, pmap as BuiltinFunc) {
+            return concurrent::call(bt, args, &self.constants, &self.globals);
+        }
+        if self.observer.is_some() {
+            return None;
+        }
+        match args {
+            [array, f] => match &**array {
+                Object::Array(elements) => {
+                    return parallel::map(&self.constants, f, elements, self.threads)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn on_threads(&self, _: BuiltinFunc, _: &[Rc<Object>]) -> Option<Result<Rc<Object>, VMError>> {
+        return None;
+    }
+
+    // what `memo` remembers for the arguments on the stack, or its function called with them
+    // and run until it returns, see `object::memo`
+    fn call_memo(&mut self, memo: &Memo, num_args: usize) -> Result<(), VMError> {
         let args = self.stack[self.sp - num_args..self.sp].to_vec();
-        let result = bt(args);
-        self.sp = self.sp - num_args - 1;
-        self.push(result);
+        self.set_sp(self.sp - num_args - 1);
+        let result = match memo.recall(&args) {
+            Some(result) => result,
+            None => {
+                let result = self.call_nested(&memo.function, args.clone())?;
+                memo.remember(args, &result);
+                result
+            }
+        };
+        self.push(result)?;
+        return Ok(());
     }
 
-    fn push_closure(&mut self, const_index: usize, num_free: usize) {
-        match &*self.constants[const_index] {
+    // the function of `partial` called in its place, with the arguments it binds and those on the
+    // stack, see `object::partial`
+    fn call_partial(&mut self, partial: &Partial, num_args: usize) -> Result<(), VMError> {
+        let args = partial.arguments(&self.stack[self.sp - num_args..self.sp])?;
+        self.set_sp(self.sp - num_args - 1);
+        self.push(Rc::clone(&partial.function))?;
+        for arg in args.iter() {
+            self.push(Rc::clone(arg))?;
+        }
+        return self.execute_call(args.len());
+    }
+
+    // `callee` called with `args` and run until it returns, for a builtin calling a function
+    fn call_nested(
+        &mut self,
+        callee: &Rc<Object>,
+        args: Vec<Rc<Object>>,
+    ) -> Result<Rc<Object>, VMError> {
+        let (depth, num_args) = (self.frame_index, args.len());
+        self.push(Rc::clone(callee))?;
+        for arg in args {
+            self.push(arg)?;
+        }
+        self.execute_call(num_args)?;
+        self.execute(depth)?;
+        return Ok(self.pop());
+    }
+
+    fn push_closure(&mut self, const_index: usize, num_free: usize) -> Result<(), VMError> {
+        let constant = Rc::clone(&self.constants[const_index]);
+        match &*constant {
             Object::CompiledFunction(f) => {
-                let mut free = Vec::with_capacity(num_free);
-                for i in 0..num_free {
-                    let f = self.stack[self.sp - num_free + i].clone();
-                    free[i] = f;
-                }
-                self.sp = self.sp - num_free;
+                let free = self.stack[self.sp - num_free..self.sp].to_vec();
+                self.set_sp(self.sp - num_free);
                 let closure = ClosureObj (Closure {
                     func: f.clone(),
                     free,
+                    frame: None,
                 });
-                self.push(Rc::new(closure));
+                self.push(Rc::new(closure))?;
             }
             o => {
-                panic!("not a function {}", o);
+                return Err(format!("not a function {}", o));
             }
         }
+        return Ok(());
+    }
 
+    fn push_closure_ref(&mut self, const_index: usize, base: usize) -> Result<(), VMError> {
+        match &*self.constants[const_index] {
+            Object::CompiledFunction(f) => {
+                let closure = Closure { func: f.clone(), free: vec![], frame: Some(base) };
+                self.push(Rc::new(ClosureObj(closure)))?;
+            }
+            o => {
+                return Err(format!("not a function {}", o));
+            }
+        }
+        return Ok(());
     }
 }
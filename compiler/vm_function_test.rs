@@ -1,5 +1,6 @@
 #[cfg(test)]
 mod tests {
+    use crate::vm::VM;
     use crate::vm_test::{run_vm_tests, VmTestCase};
     use object::Object;
     use std::rc::Rc;
@@ -170,17 +171,105 @@ mod tests {
             VmTestCase { input: "last([]);", expected: Object::Null },
             VmTestCase {
                 input: "rest([1, 2, 3]);",
-                expected: Object::Array(vec![
-                    Rc::from(Object::Integer(2)),
-                    Rc::from(Object::Integer(3)),
-                ]),
+                expected: Object::Array(
+                    vec![Rc::from(Object::Integer(2)), Rc::from(Object::Integer(3))].into(),
+                ),
             },
             VmTestCase { input: "rest([]);", expected: Object::Null },
             VmTestCase {
                 input: "push([], 1);",
-                expected: Object::Array(vec![Rc::from(Object::Integer(1))]),
+                expected: Object::Array(vec![Rc::from(Object::Integer(1))].into()),
+            },
+        ];
+        run_vm_tests(tests);
+    }
+
+    #[test]
+    fn test_closures() {
+        let tests = vec![
+            VmTestCase {
+                input: "let newClosure = fn(a) { fn() { a; }; }; \
+                    let closure = newClosure(99); \
+                    closure();",
+                expected: Object::Integer(99),
+            },
+            VmTestCase {
+                input: "let newAdderOuter = fn(a, b) { \
+                        let c = a + b; \
+                        fn(d) { let e = d + c; fn(f) { e + f; }; }; \
+                    }; \
+                    let newAdderInner = newAdderOuter(1, 2); \
+                    let adder = newAdderInner(3); \
+                    adder(8);",
+                expected: Object::Integer(14),
+            },
+            VmTestCase {
+                input: "let wrapper = fn() { \
+                        let countDown = fn(x) { if (x == 0) { return 0; } else { countDown(x - 1); } }; \
+                        countDown(1); \
+                    }; \
+                    wrapper();",
+                expected: Object::Integer(0),
+            },
+        ];
+
+        run_vm_tests(tests);
+    }
+
+    #[test]
+    fn test_non_escaping_closures() {
+        let tests = vec![
+            VmTestCase {
+                input: "let map = fn(arr, f) { \
+                        if (len(arr) == 0) { [] } else { let head = f(first(arr)); push(map(rest(arr), f), head) } \
+                    }; \
+                    let scale = fn(arr, k) { map(arr, fn(x) { x * k }) }; \
+                    scale([1, 2, 3], 10);",
+                expected: Object::Array(vec![
+                    Rc::new(Object::Integer(30)),
+                    Rc::new(Object::Integer(20)),
+                    Rc::new(Object::Integer(10)),
+                ].into()),
+            },
+            VmTestCase {
+                input: "let g = fn(k) { let add = fn(x) { x + k }; add(1) + add(2) }; g(10);",
+                expected: Object::Integer(23),
+            },
+            // the innermost call captures from two frames up, so it copies
+            VmTestCase {
+                input: "let outer = fn(a) { fn(b) { fn(c) { a + b + c }(3) } }; let inner = outer(1); inner(2);",
+                expected: Object::Integer(6),
+            },
+            VmTestCase {
+                input: "let f = fn(a) { let g = fn() { fn() { a } }; let h = g(); h() }; f(5);",
+                expected: Object::Integer(5),
             },
         ];
+
         run_vm_tests(tests);
     }
+
+    #[test]
+    fn test_runtime_error_trace() {
+        let run = |input: &str| VM::new(crate::compile(input).unwrap()).run().unwrap_err();
+
+        let input = "let add = fn(a, b) { a + b };\n\
+                     let twice = fn(x) {\n\
+                         fn(y) { add(y, true) }(x)\n\
+                     };\n\
+                     twice(1);";
+        assert_eq!(
+            run(input),
+            "can't apply + to 1 and true\n    in add\n    in fn@3\n    in twice\n    in <main>"
+        );
+
+        assert_eq!(
+            run("let f = fn(a) { a }; f();"),
+            "wrong number of arguments: want=1, got=0\n    in <main>"
+        );
+
+        let err = run("let f = fn(n) { f(n + 1) }; f(0);");
+        assert!(err.contains("overflow"), "{}", err);
+        assert!(err.ends_with("    in f\n    in f\n    in <main>"), "{}", err);
+    }
 }
@@ -1,4 +1,3 @@
-use std::io::{self, Write};
 use crate::compiler::Compiler;
 use crate::compiler_test::test_constants;
 use crate::vm::VM;
@@ -15,22 +14,24 @@ pub fn run_vm_tests(tests: Vec<VmTestCase>) {
         let program = parse(t.input).unwrap();
         let mut compiler = Compiler::new();
         let bytecodes = compiler.compile(&program).unwrap();
-        let s = bytecodes.instructions.string();
         println!("ins {} for input {}", bytecodes.instructions.string(), t.input);
         let mut vm = VM::new(bytecodes);
-        vm.run();
+        vm.run().unwrap();
         let got = vm.last_popped_stack_elm().unwrap();
         let expected_argument = t.expected;
-        test_constants(&vec![expected_argument], &vec![got]);
+        test_constants(&[expected_argument], &[got]);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use object::Object;
-    use std::collections::HashMap;
+    use std::collections::{HashMap, VecDeque};
     use std::rc::Rc;
 
+    use crate::compiler::{CompileOptions, Compiler};
+    use crate::symbol_table::SymbolTable;
+    use crate::vm::{VMError, GLOBAL_SIZE, VM};
     use crate::vm_test::{run_vm_tests, VmTestCase};
 
     #[test]
@@ -129,6 +130,47 @@ mod tests {
         run_vm_tests(tests);
     }
 
+    #[test]
+    fn test_executed_instructions() {
+        // two constants, the add and the pop
+        let mut vm = VM::new(crate::compile("1 + 2").unwrap());
+        vm.run().unwrap();
+        assert_eq!(vm.executed, 4);
+
+        // a second call adds loading `f` and its argument, the call, the body's two and the pop
+        let source = "let f = fn(x) { x }; f(1); f(2);";
+        let mut once = VM::new(crate::compile("let f = fn(x) { x }; f(1);").unwrap());
+        once.run().unwrap();
+        let mut twice = VM::new(crate::compile(source).unwrap());
+        twice.run().unwrap();
+        assert_eq!(twice.executed - once.executed, 6);
+    }
+
+    #[test]
+    fn test_globals_sized_by_the_program() {
+        let bytecode = crate::compile("let a = 1; let f = fn() { let b = 2; b }; f()").unwrap();
+        assert_eq!(VM::new(bytecode).globals.len(), 2);
+
+        // like the repl, each input keeps the globals of the ones before and adds its own
+        let mut compiler = Compiler::new();
+        let first = parser::parse("let a = 1;").unwrap();
+        let mut vm = VM::new(compiler.compile(&first).unwrap());
+        vm.run().unwrap();
+        let mut compiler = Compiler::new_with_state(compiler.symbol_table, compiler.constants);
+        let second = parser::parse("let b = a + 1; b").unwrap();
+        let mut vm = VM::new_with_global_store(compiler.compile(&second).unwrap(), vm.globals);
+        vm.run().unwrap();
+        assert_eq!(vm.globals.len(), 2);
+        assert_eq!(vm.last_popped_stack_elm().unwrap().to_string(), "2");
+
+        let mut symbol_table = SymbolTable::new();
+        symbol_table.num_definitions = GLOBAL_SIZE;
+        let mut compiler = Compiler::new_with_state(symbol_table, vec![]);
+        let program = parser::parse("let x = 1;").unwrap();
+        let error = compiler.compile(&program).unwrap_err();
+        assert_eq!(error, "too many globals: 65537, at most 65536");
+    }
+
     #[test]
     fn test_strings() {
         let tests = vec![
@@ -141,6 +183,10 @@ mod tests {
                 input: "\"mon\" + \"key\" + \"banana\"",
                 expected: Object::String("monkeybanana".to_string()),
             },
+            VmTestCase {
+                input: r#""say \"hi\"\n""#,
+                expected: Object::String("say \"hi\"\n".to_string()),
+            },
         ];
 
         run_vm_tests(tests);
@@ -152,7 +198,7 @@ mod tests {
             let array = vec
                 .iter()
                 .map(|i| Rc::new(Object::Integer(*i)))
-                .collect::<Vec<Rc<Object>>>();
+                .collect::<VecDeque<Rc<Object>>>();
             return Object::Array(array);
         }
         let tests = vec![
@@ -206,4 +252,267 @@ mod tests {
 
         run_vm_tests(tests);
     }
+
+    #[test]
+    fn test_decimals() {
+        let decimal = |s: &str| Object::Decimal(s.parse().unwrap());
+        let tests: Vec<VmTestCase> = vec![
+            VmTestCase { input: "1.50d", expected: decimal("1.50") },
+            VmTestCase { input: "0.1d + 0.2d", expected: decimal("0.3") },
+            VmTestCase { input: "-1.25d * 4", expected: decimal("-5") },
+            VmTestCase { input: "10 / 4d", expected: decimal("2.5") },
+            VmTestCase { input: "1.5d < 2", expected: Object::Boolean(true) },
+            VmTestCase { input: "2 != 2.00d", expected: Object::Boolean(false) },
+            VmTestCase { input: r#"decimal("19.99") * 3"#, expected: decimal("59.97") },
+        ];
+
+        run_vm_tests(tests);
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let run = |input| -> Result<String, VMError> {
+            let mut vm = VM::new(crate::compile(input).unwrap());
+            vm.run()?;
+            return Ok(vm.last_popped_stack_elm().unwrap().to_string());
+        };
+        assert_eq!(run("1 / 0").unwrap_err(), "division by zero\n    in <main>");
+        assert_eq!(run("-(0 - 9223372036854775807)").unwrap(), "9223372036854775807");
+        #[cfg(not(feature = "bigint"))]
+        assert_eq!(
+            run("9223372036854775807 * 2").unwrap_err(),
+            "integer overflow: 9223372036854775807 * 2\n    in <main>"
+        );
+        #[cfg(feature = "bigint")]
+        {
+            assert_eq!(run("9223372036854775807 * 2").unwrap(), "18446744073709551614");
+            assert_eq!(run("-(9223372036854775807 + 1) - 1 < 0").unwrap(), "true");
+            let fact = "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(30)";
+            assert_eq!(run(fact).unwrap(), "265252859812191058636308480000000");
+        }
+    }
+
+    // a table of `conformance`, errors compared by their first line
+    fn apply_conformance(cases: &[(&str, &str)]) {
+        // opt level 1 folds each example at compile time, with the evaluator, and 2 reuses
+        // computations and inlines calls
+        for level in 0..=2 {
+            for (input, expected) in cases.iter() {
+                let options = CompileOptions::from_opt_level(level);
+                let mut vm = VM::new(crate::compile_with_options(input, options).unwrap());
+                let got = match vm.run() {
+                    Ok(()) => vm.last_popped_stack_elm().unwrap().to_string(),
+                    Err(e) => e.lines().next().unwrap().to_string(),
+                };
+                assert_eq!(&got, expected, "{} at -O{}", input, level);
+            }
+        }
+    }
+
+    #[test]
+    fn test_integer_opcodes() {
+        // the fast path gives way to the generic one past `i64` and on division by zero
+        let cases = [
+            ("let f = fn() { let a = 2; a * a + 1 }; f()", "5"),
+            ("let f = fn() { let a = -7; a / 2 }; f()", "-3"),
+            ("let f = fn() { let a = 7; let b = 0; a / b }; f()", "division by zero"),
+            ("let x = -9223372036854775807 - 1; x - 0", "-9223372036854775808"),
+            #[cfg(not(feature = "bigint"))]
+            ("let x = 9223372036854775807; x + 1", "integer overflow: 9223372036854775807 + 1"),
+            #[cfg(feature = "bigint")]
+            ("let x = 9223372036854775807; x + 1", "9223372036854775808"),
+            #[cfg(feature = "bigint")]
+            ("let x = 9223372036854775807; let y = x * x; y / x - x", "0"),
+        ];
+        apply_conformance(&cases);
+    }
+
+    #[test]
+    fn test_numeric_tower() {
+        apply_conformance(conformance::TOWER);
+    }
+
+    #[test]
+    fn test_ordering() {
+        apply_conformance(conformance::ORDERING);
+    }
+
+    #[test]
+    fn test_equality() {
+        apply_conformance(conformance::EQUALITY);
+    }
+
+    #[test]
+    fn test_truthiness() {
+        apply_conformance(conformance::TRUTHINESS);
+    }
+
+    #[test]
+    fn test_aliasing() {
+        apply_conformance(conformance::ALIASING);
+    }
+
+    #[test]
+    fn test_assignments() {
+        apply_conformance(conformance::ASSIGNMENTS);
+    }
+
+    #[test]
+    fn test_cells() {
+        conformance::define_cells();
+        apply_conformance(conformance::CELLS);
+        // no update lost between threads
+        #[cfg(feature = "parallel")]
+        apply_conformance(&[(
+            "let c = cell(\"hits\"); cell_set(c, 0);
+             let count = fn() { for (let i = 0; i < 100; i += 1) { cell_add(c, 1); } };
+             let done = [spawn(count), spawn(count), spawn(count), spawn(count)];
+             for (let i = 0; i < 4; i += 1) { recv(done[i]); }
+             cell_get(c)",
+            "400",
+        )]);
+    }
+
+    #[test]
+    fn test_memos() {
+        apply_conformance(conformance::MEMOS);
+    }
+
+    #[test]
+    fn test_results() {
+        apply_conformance(conformance::RESULTS);
+    }
+
+    #[test]
+    fn test_requires() {
+        apply_conformance(conformance::REQUIRES);
+    }
+
+    #[test]
+    fn test_partials() {
+        apply_conformance(conformance::PARTIALS);
+    }
+
+    #[test]
+    fn test_compositions() {
+        apply_conformance(conformance::COMPOSITIONS);
+    }
+
+    #[test]
+    fn test_chars() {
+        apply_conformance(conformance::CHARS);
+    }
+
+    #[test]
+    fn test_sections() {
+        apply_conformance(conformance::SECTIONS);
+    }
+
+    #[test]
+    fn test_builders() {
+        apply_conformance(conformance::BUILDERS);
+    }
+
+    #[test]
+    fn test_reassignments() {
+        apply_conformance(conformance::REASSIGNMENTS);
+    }
+
+    #[test]
+    fn test_ternaries() {
+        apply_conformance(conformance::TERNARIES);
+    }
+
+    #[test]
+    fn test_returns() {
+        apply_conformance(conformance::RETURNS);
+    }
+
+    #[test]
+    fn test_declarations() {
+        apply_conformance(conformance::DECLARATIONS);
+    }
+
+    #[test]
+    fn test_keys() {
+        apply_conformance(conformance::KEYS);
+    }
+
+    #[test]
+    fn test_variadics() {
+        apply_conformance(conformance::VARIADICS);
+    }
+
+    #[test]
+    fn test_records() {
+        apply_conformance(conformance::RECORDS);
+    }
+
+    #[test]
+    fn test_throwaway() {
+        apply_conformance(conformance::THROWAWAY);
+    }
+
+    #[test]
+    fn test_matches() {
+        apply_conformance(conformance::MATCHES);
+    }
+
+    #[test]
+    fn test_loops() {
+        apply_conformance(conformance::LOOPS);
+    }
+
+    #[test]
+    fn test_deque() {
+        apply_conformance(conformance::DEQUE);
+    }
+
+    #[test]
+    fn test_search() {
+        apply_conformance(conformance::SEARCH);
+    }
+
+    #[test]
+    fn test_iteration() {
+        apply_conformance(conformance::ITERATION);
+    }
+
+    #[test]
+    fn test_parallel() {
+        apply_conformance(conformance::PARALLEL);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_channels() {
+        apply_conformance(conformance::CHANNELS);
+    }
+
+    #[test]
+    fn test_updates() {
+        apply_conformance(conformance::UPDATES);
+    }
+
+    #[test]
+    fn test_frozen() {
+        apply_conformance(conformance::FROZEN);
+    }
+
+    #[test]
+    fn test_templates() {
+        apply_conformance(conformance::TEMPLATES);
+    }
+
+    #[test]
+    fn test_feature_registry() {
+        for f in object::features::CONSTRUCTS.iter() {
+            let got = crate::compile(f.example).and_then(|bytecode| {
+                let mut vm = VM::new(bytecode);
+                vm.run()?;
+                return Ok(vm.last_popped_stack_elm().unwrap().to_string());
+            });
+            assert_eq!(got.as_deref() == Ok(f.expected), f.vm, "{}: {:?}", f.name, got);
+        }
+    }
 }
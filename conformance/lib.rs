@@ -0,0 +1,961 @@
+// the language's policies as examples, each run by the interpreter and the vm tests
+
+use object::cell::{define, get, Access, Shared};
+
+// the numeric tower, see `object::number`
+pub static TOWER: &[(&str, &str)] = &[
+    // integers stay integers, division truncates toward zero
+    ("7 / 2", "3"),
+    ("-7 / 2", "-3"),
+    ("1 / 0", "division by zero"),
+    // `div` is floor division, rounding toward negative infinity
+    ("div(7, 2)", "3"),
+    ("div(-7, 2)", "-4"),
+    ("div(7, -2)", "-4"),
+    ("div(-7, -2)", "3"),
+    ("div(-8, 2)", "-4"),
+    ("div(1, 0)", "division by zero"),
+    ("div(7.5d, 2)", "3"),
+    ("div(-7.5d, 2)", "-4"),
+    ("div(1, 0.0d)", "division by zero"),
+    ("div(\"7\", 2)", "can't apply div to 7 and 2"),
+    // an operator that doesn't take its operands is named, with them
+    ("\"a\" - 1", "can't apply - to a and 1"),
+    ("\"a\" * \"b\"", "can't apply * to a and b"),
+    ("[1] + [2]", "can't apply + to [1] and [2]"),
+    ("true / 2", "can't apply / to true and 2"),
+    ("\"a\" + 1", "can't apply + to a and 1"),
+    ("-\"a\"", "can't apply - to a"),
+    // a decimal on either side makes it a decimal operation
+    ("7 / 2d", "3.50"),
+    ("2 * 1.50d", "3.00"),
+    ("1.50d - 1", "0.50"),
+    ("-(1 - 1.5d)", "0.5"),
+    ("1d / 0", "division by zero"),
+    // equality is by value, across types and scales
+    ("2 == 2.00d", "true"),
+    ("2.50d == 2.5d", "true"),
+    ("2 != 2.01d", "true"),
+    ("0.1d + 0.2d == 0.3d", "true"),
+    // and so is ordering
+    ("1 < 1.01d", "true"),
+    ("0.99d > 1", "false"),
+    ("-0.5d < 0", "true"),
+    // numbers equal by value are the same hash key
+    ("{2: \"two\"}[2.00d]", "two"),
+    ("{2.0d: \"two\"}[2]", "two"),
+    ("{1.5d: \"a\"}[1.50d]", "a"),
+    ("{1: \"a\"}[1.1d]", "null"),
+    // the smallest integer can be written
+    ("-9223372036854775808 == -9223372036854775807 - 1", "true"),
+    #[cfg(not(feature = "bigint"))]
+    ("-9223372036854775808 - 1", "integer overflow: -9223372036854775808 - 1"),
+    #[cfg(not(feature = "bigint"))]
+    ("(-9223372036854775807 - 1) / -1", "integer overflow: -9223372036854775808 / -1"),
+    #[cfg(feature = "bigint")]
+    ("(-9223372036854775807 - 1) / -1", "9223372036854775808"),
+    #[cfg(feature = "bigint")]
+    ("div(-9223372036854775807 * 3, 2)", "-13835058055282163711"),
+    #[cfg(feature = "bigint")]
+    ("9223372036854775807 + 1 == 9223372036854775808d", "true"),
+    #[cfg(feature = "bigint")]
+    ("9223372036854775807 * 2 > 9223372036854775807.5d", "true"),
+    #[cfg(feature = "bigint")]
+    ("{9223372036854775807 + 1: 1}[9223372036854775808.0d]", "1"),
+];
+
+// what `<` and `>` order, see `object::order`
+pub static ORDERING: &[(&str, &str)] = &[
+    ("1 < 2", "true"),
+    ("1.5d > 1", "true"),
+    // strings
+    ("\"apple\" < \"banana\"", "true"),
+    ("\"apple\" < \"app\"", "false"),
+    ("\"app\" < \"apple\"", "true"),
+    ("\"Zebra\" < \"apple\"", "true"),
+    ("\"b\" > \"abc\"", "true"),
+    ("\"same\" > \"same\"", "false"),
+    ("\"\" < \"a\"", "true"),
+    // arrays
+    ("[1, 2] < [1, 3]", "true"),
+    ("[1, 2] < [1, 2, 0]", "true"),
+    ("[2] > [1, 9]", "true"),
+    ("[] < [[]]", "true"),
+    ("[\"a\", 2] < [\"a\", 10]", "true"),
+    ("[[1, 2], 3] > [[1, 1], 4]", "true"),
+    // elements past the first difference aren't compared
+    ("[1, \"a\"] < [2, 3]", "true"),
+    // nothing else is ordered
+    ("\"1\" < 2", "can't compare string and integer"),
+    ("[1] > 1", "can't compare array and integer"),
+    ("[1, \"a\"] < [1, 2]", "can't compare string and integer"),
+    ("true > false", "can't compare boolean and boolean"),
+    ("{} < {}", "can't compare hash and hash"),
+    // the left operand is evaluated first
+    ("(1 / 0) < (\"a\" - 1)", "division by zero"),
+];
+
+// what `==` and `!=` mean, see `object::equality`
+pub static EQUALITY: &[(&str, &str)] = &[
+    ("1 == 1.0d", "true"),
+    ("\"a\" == \"a\"", "true"),
+    ("true == true", "true"),
+    ("first([]) == first([])", "true"),
+    // arrays
+    ("[1, 2] == [1, 2]", "true"),
+    ("[1, [2, \"3\"]] == [1, [2, \"3\"]]", "true"),
+    ("[1, 2] == [2, 1]", "false"),
+    ("[1] == [1, 1]", "false"),
+    ("[] == []", "true"),
+    ("[1, 2.50d] == [1.0d, 2.5d]", "true"),
+    ("[1, 2] != [1, 2]", "false"),
+    // hashes
+    ("{\"a\": 1, \"b\": [2]} == {\"b\": [2], \"a\": 1}", "true"),
+    ("{\"a\": 1} == {\"a\": 2}", "false"),
+    ("{\"a\": 1} == {\"a\": 1, \"b\": 2}", "false"),
+    ("{1: \"one\"} == {1.00d: \"one\"}", "true"),
+    ("{} == {}", "true"),
+    // functions
+    ("let f = fn(x) { x }; f == f", "true"),
+    ("let f = fn(x) { x }; let g = f; [f, 1] == [g, 1]", "true"),
+    ("fn(x) { x } == fn(x) { x }", "false"),
+    ("let make = fn() { fn() { 1 } }; make() == make()", "false"),
+    ("len == len", "true"),
+    ("len != first", "true"),
+    // different kinds
+    ("1 == \"1\"", "false"),
+    ("0 == false", "false"),
+    ("[] == {}", "false"),
+    ("[] != first([])", "true"),
+    ("len == fn(x) { len(x) }", "false"),
+];
+
+// what `if` and `!` take for true: only `false` and null are falsy
+pub static TRUTHINESS: &[(&str, &str)] = &[
+    ("if (true) { 1 } else { 2 }", "1"),
+    ("if (false) { 1 } else { 2 }", "2"),
+    ("if (first([])) { 1 } else { 2 }", "2"),
+    ("if (0) { 1 } else { 2 }", "1"),
+    ("if (0.00d) { 1 } else { 2 }", "1"),
+    ("if (\"\") { 1 } else { 2 }", "1"),
+    ("if ([]) { 1 } else { 2 }", "1"),
+    ("if ({}) { 1 } else { 2 }", "1"),
+    ("if (len) { 1 } else { 2 }", "1"),
+    ("if (fn() { false }) { 1 } else { 2 }", "1"),
+    // `!` is the opposite
+    ("!true", "false"),
+    ("!false", "true"),
+    ("!first([])", "true"),
+    ("!0", "false"),
+    ("!0.00d", "false"),
+    ("!\"\"", "false"),
+    ("![]", "false"),
+    ("!{}", "false"),
+    ("!len", "false"),
+    ("!!first([])", "false"),
+    ("!!0", "true"),
+];
+
+// values are shared, never copied, see `object::aliasing`
+pub static ALIASING: &[(&str, &str)] = &[
+    ("let a = [1, [2]]; let b = a; let a = push(a, 3); b", "[1, [2]]"),
+    ("let a = [1]; let b = push(a, 2); a", "[1]"),
+    // the value of a `let` sees the binding it replaces
+    ("let a = [1]; let a = push(a, 2); a", "[1, 2]"),
+    ("let f = fn() { let a = [1]; let a = push(a, 2); a }; f()", "[1, 2]"),
+    ("let h = {\"k\": [1]}; let c = clone(h); c == h", "true"),
+    ("clone([1, [2, [\"three\"]]])", "[1, [2, [three]]]"),
+    ("clone({}) == {}", "true"),
+    ("clone(\"s\")", "s"),
+    // functions are shared, by assignment and by `clone`
+    ("let f = fn() { 1 }; let g = f; g == f", "true"),
+    ("let f = fn() { 1 }; clone([f])[0] == f", "true"),
+    ("let f = fn() { 1 }; clone(f) == f", "true"),
+    ("clone()", "builtin clone expected 1 argument, got 0"),
+];
+
+// `x += v` is `x = x + v`, with `-=`, `*=` and `/=` too, and `a[i] += v` updates an element
+pub static ASSIGNMENTS: &[(&str, &str)] = &[
+    ("let x = 5; x += 2; x", "7"),
+    ("let x = 5; x -= 2; x *= 4; x /= 3; x", "4"),
+    ("let s = \"mon\"; s += \"key\"; s", "monkey"),
+    ("let a = [1, 2]; a[1] += 10; a", "[1, 12]"),
+    ("let h = {\"n\": 2}; h[\"n\"] *= 5; h", "[n: 10]"),
+    ("let m = [[1, 2]]; m[0][1] -= 2; m", "[[1, 0]]"),
+    // in loops
+    ("let s = 0; for (let i = 0; i < 4; i += 1) { s += i; } s", "6"),
+    ("let n = 1; while (n < 100) { n *= 3; } n", "243"),
+    // sets the name already bound
+    ("let x = 1; let f = fn() { x += 1; x }; [f(), x]", "[2, 2]"),
+    ("let c = 0; let f = fn() { c += 1; c }; f(); f(); c", "2"),
+    ("let xs = [1, 2]; let f = fn() { xs[0] += 5; }; f(); xs", "[6, 2]"),
+    ("let a = [1]; let b = a; a[0] += 1; [a, b]", "[[2], [1]]"),
+    ("let t = 1; let f = fn() { let t = 10; t += 1; t }; [f(), t]", "[11, 1]"),
+    // errors
+    ("let x = 1; x /= 0; x", "division by zero"),
+    ("let a = [1, 0]; a[0] /= a[1]; a", "division by zero"),
+];
+
+// cells the host shares between scripts, see `object::cell`, with those `define_cells` defines
+pub static CELLS: &[(&str, &str)] = &[
+    ("cell_get(cell(\"motd\"))", "hello"),
+    ("cell(\"motd\")", "[cell motd]"),
+    ("let c = cell(\"hits\"); cell_set(c, 0); cell_add(c, 2); cell_add(c, 3)", "5"),
+    ("let c = cell(\"hits\"); cell_set(c, 1); cell_add(c, -1); cell_get(c)", "0"),
+    ("let c = cell(\"hits\"); cell_set(c, 1); [cell_swap(c, 1, 7), cell_swap(c, 1, 8), cell_get(c)]", "[true, false, 7]"),
+    ("let c = cell(\"hits\"); cell_set(c, \"off\"); cell_get(c)", "off"),
+    ("[cell(\"hits\") == cell(\"hits\"), cell(\"hits\") == cell(\"motd\")]", "[true, false]"),
+    // a handle, not a copy
+    ("let a = cell(\"hits\"); let b = cell(\"hits\"); cell_set(a, 4); cell_get(b)", "4"),
+    // errors
+    ("cell(\"nope\")", "no cell named nope, the host defines them"),
+    ("cell_set(cell(\"motd\"), \"bye\")", "cell motd is read-only"),
+    ("cell_set(cell(\"hits\"), [1])", "a cell holds an integer or a string, got array"),
+    ("let c = cell(\"hits\"); cell_set(c, \"a\"); cell_add(c, 1)", "builtin cell_add not supported for a cell holding string"),
+    ("let c = cell(\"hits\"); cell_set(c, 9223372036854775807); cell_add(c, 1)", "integer overflow: 9223372036854775807 + 1"),
+    ("cell_get(1)", "builtin cell_get not supported for type integer"),
+    ("cell(1)", "builtin cell expected a name, got integer"),
+    ("cell_add(cell(\"hits\"))", "builtin cell_add expected 2 arguments, got 1"),
+];
+
+pub fn define_cells() {
+    define("motd", Shared::String("hello".to_string()), Access::Read);
+    if get("hits").is_none() {
+        define("hits", Shared::Integer(0), Access::Write);
+    }
+}
+
+// see `object::memo`
+pub static MEMOS: &[(&str, &str)] = &[
+    ("let sq = memo(fn(x) { x * x }); [sq(3), sq(3), sq(4)]", "[9, 9, 16]"),
+    (
+        "let sq = memo(fn(x) { x * x }); sq(3); sq(3); sq(4);
+         let s = cache_stats(sq); [s[\"hits\"], s[\"misses\"], s[\"size\"], s[\"limit\"]]",
+        "[1, 2, 2, 1024]",
+    ),
+    (
+        "let fib = memo(fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } });
+         [fib(90), cache_stats(fib)[\"misses\"]]",
+        "[2880067194370816120, 91]",
+    ),
+    (
+        "let add = memo(fn(a, b) { a + b }); [add(1, 2), add(2, 1), cache_stats(add)[\"hits\"]]",
+        "[3, 3, 0]",
+    ),
+    ("let f = memo(len); [f(\"ab\"), f(\"ab\"), cache_stats(f)[\"hits\"]]", "[2, 2, 1]"),
+    // the limit
+    (
+        "let f = memo(fn(x) { x }, 2); f(1); f(2); f(3); f(1);
+         let s = cache_stats(f); [s[\"size\"], s[\"misses\"]]",
+        "[2, 4]",
+    ),
+    // arguments that aren't hashable aren't remembered
+    ("let f = memo(fn(a) { len(a) }); f([1]); f([1]); cache_stats(f)[\"misses\"]", "2"),
+    ("let f = memo(fn(x) { x }); [f == f, f == memo(fn(x) { x })]", "[true, false]"),
+    ("memo(fn(x) { x })", "[memoized function]"),
+    // errors
+    ("let f = memo(fn(x) { 10 / x }); f(0)", "division by zero"),
+    ("let f = memo(fn(x) { x }); f(1, 2)", "wrong number of arguments: want=1, got=2"),
+    ("memo(1)", "builtin memo expected a function, got integer"),
+    ("memo(fn(x) { x }, 0)", "builtin memo expected a limit of 1 or more, got 0"),
+    ("cache_stats(fn(x) { x })", "builtin cache_stats expected a memoized function, got function"),
+    ("memo()", "builtin memo expected 1 or 2 arguments, got 0"),
+];
+
+// see `object::result`
+pub static RESULTS: &[(&str, &str)] = &[
+    ("ok(1)", "[ok: 1]"),
+    ("err(\"no such user\")", "[err: no such user]"),
+    ("[is_ok(ok(1)), is_ok(err(\"e\"))]", "[true, false]"),
+    ("[unwrap_or(ok(1), 0), unwrap_or(err(\"e\"), 0)]", "[1, 0]"),
+    ("ok(2) == {\"ok\": 2}", "true"),
+    ("is_ok({\"err\": \"built by hand\"})", "false"),
+    ("err({\"code\": 404})[\"err\"][\"code\"]", "404"),
+    ("ok(ok(1))[\"ok\"][\"ok\"]", "1"),
+    (
+        "let parse_digit = fn(s) {
+             let digits = {\"0\": 0, \"1\": 1, \"2\": 2};
+             if (len(s) != 1) { return err(\"want one digit, got \" + s); }
+             ok(digits[s])
+         };
+         [unwrap_or(parse_digit(\"2\"), -1), unwrap_or(parse_digit(\"12\"), -1),
+          parse_digit(\"12\")[\"err\"]]",
+        "[2, -1, want one digit, got 12]",
+    ),
+    // errors
+    ("is_ok(1)", "builtin is_ok expected a result, got integer"),
+    ("unwrap_or({\"ok\": 1, \"err\": 2}, 0)", "builtin unwrap_or expected a result, got hash"),
+    ("unwrap_or(ok(1))", "builtin unwrap_or expected 2 arguments, got 1"),
+    ("ok()", "builtin ok expected 1 argument, got 0"),
+];
+
+// see `object::require`
+pub static REQUIRES: &[(&str, &str)] = &[
+    ("require(true, \"never\")", "null"),
+    ("require(1 > 0)", "null"),
+    // truthy as `if` takes it, see `truthiness`
+    ("require(0, \"zero is truthy\")", "null"),
+    (
+        "let sqrt = fn(n) { require(n > -1, \"sqrt of a negative number\"); n };
+         sqrt(4)",
+        "4",
+    ),
+    // errors
+    ("require(false, \"n is negative\")", "requirement failed: n is negative"),
+    ("require(first([]))", "requirement failed"),
+    (
+        "let head = fn(xs, name) { require(len(xs) > 0, fn() { name + \" is empty\" }); xs[0] };
+         head([], \"queue\")",
+        "requirement failed: queue is empty",
+    ),
+    (
+        "let sqrt = fn(n) { require(n > -1, \"sqrt of a negative number\"); n };
+         sqrt(-4); puts(\"not reached\")",
+        "requirement failed: sqrt of a negative number",
+    ),
+    ("require(false, 42)", "requirement failed: 42"),
+    ("require()", "builtin require expected 1 or 2 arguments, got 0"),
+];
+
+// see `object::partial`
+pub static PARTIALS: &[(&str, &str)] = &[
+    ("let add = fn(a, b) { a + b }; let inc = partial(add, 1); inc(2)", "3"),
+    ("let sub = fn(a, b) { a - b }; let dec = partial(sub, _, 1); dec(5)", "4"),
+    ("let f = fn(a, b, c) { [a, b, c] }; let g = partial(f, _, 2, _); g(1, 3)", "[1, 2, 3]"),
+    ("let f = fn(a, b, c) { [a, b, c] }; let g = partial(f, _, 2); g(1, 3)", "[1, 2, 3]"),
+    ("let f = fn(a, b, c) { [a, b, c] }; let g = partial(partial(f, 1), 2); g(3)", "[1, 2, 3]"),
+    ("let f = fn(a, b) { a * b }; let g = partial(f, 2, 3); g()", "6"),
+    ("let g = partial(push, [1]); g(2)", "[1, 2]"),
+    ("let sq = memo(fn(x, y) { x * y }); let g = partial(sq, 3); g(3)", "9"),
+    // composing
+    (
+        "let map = fn(f, xs) { let out = []; for (let i = 0; i < len(xs); i = i + 1) { let out = push(out, f(xs[i])); } out };
+         let double_all = partial(map, fn(x) { x * 2 });
+         double_all([1, 2, 3])",
+        "[2, 4, 6]",
+    ),
+    ("let p = partial(len, _); [p == p, p == partial(len, _)]", "[true, false]"),
+    ("partial(len)", "[partial function]"),
+    // errors
+    ("let f = fn(a, b) { a + b }; let g = partial(f, 1); g(2, 3)", "wrong number of arguments: want=2, got=3"),
+    ("let f = fn(a, b) { a + b }; let g = partial(f, _, _); g(1)", "partial function expected at least 2 arguments, got 1"),
+    ("partial(1, 2)", "builtin partial expected a function, got integer"),
+    ("partial()", "builtin partial expected a function and arguments, got none"),
+    ("_(1)", "`_` is a hole for `partial`, not a function"),
+    ("let p = partial(1); puts(\"not reached\")", "builtin partial expected a function, got integer"),
+];
+
+// `f >> g` is `fn(x) { g(f(x)) }`, with `f` and `g` evaluated once
+pub static COMPOSITIONS: &[(&str, &str)] = &[
+    ("let inc = fn(x) { x + 1 }; let double = fn(x) { x * 2 }; let h = inc >> double; h(3)", "8"),
+    ("let inc = fn(x) { x + 1 }; let double = fn(x) { x * 2 }; let h = double >> inc; h(3)", "7"),
+    ("let inc = fn(x) { x + 1 }; let h = inc >> inc >> inc; h(0)", "3"),
+    ("let h = rest >> first; h([1, 2, 3])", "2"),
+    ("let h = partial(push, [0]) >> len; h(9)", "2"),
+    ("let inc = fn(x) { x + 1 }; let h = fn(x) { [x] } >> first >> inc; h(1)", "2"),
+    // `f` and `g` as they were when composed
+    ("let f = first; let h = f >> len; let f = last; h([[1], [1, 2]])", "1"),
+    ("let make = fn(k) { fn(x) { x * k } }; let h = make(2) >> make(3); h(1)", "6"),
+    // errors
+    (
+        "let inc = fn(x) { x + 1 }; let h = inc >> inc; h(1, 2)",
+        "wrong number of arguments: want=1, got=2",
+    ),
+];
+
+// see `object::chars`
+pub static CHARS: &[(&str, &str)] = &[
+    ("'a'", "a"),
+    ("['\\n' == chr(10), '\\'' == chr(39), '\\u{e9}' == chr(233)]", "[true, true, true]"),
+    ("[ord('a'), ord('\u{e9}'), ord('\u{1F600}')]", "[97, 233, 128512]"),
+    ("chr(97)", "a"),
+    ("chars(\"h\u{e9}!\")", "[h, \u{e9}, !]"),
+    ("chars(\"\")", "[]"),
+    ("['a' == 'a', 'a' == \"a\", 'a' < 'b', 'B' < 'a']", "[true, false, true, true]"),
+    ("[\"ab\" + 'c', 'a' + \"bc\", \"\" + 'x' + 'y']", "[abc, abc, xy]"),
+    ("{'a': 1}['a']", "1"),
+    ("match ('b') { 'a' => 1, 'b' => 2, _ => 3 }", "2"),
+    (
+        "let upper = fn(s) {
+             let out = \"\"; let cs = chars(s);
+             for (let i = 0; i < len(cs); i = i + 1) {
+                 let c = cs[i];
+                 if (c > '`') { if (c < '{') { let c = chr(ord(c) - 32); } }
+                 let out = out + c;
+             }
+             out
+         };
+         upper(\"monkey 1\")",
+        "MONKEY 1",
+    ),
+    // errors
+    ("'a' < \"b\"", "can't compare char and string"),
+    ("chr(55296)", "builtin chr expected a code point, got 55296"),
+    ("chr(-1)", "builtin chr expected a code point, got -1"),
+    ("ord(\"a\")", "builtin ord expected a char, got string"),
+    ("chars('a')", "builtin chars expected a string, got char"),
+    ("ord()", "builtin ord expected 1 argument, got 0"),
+    ("let c = chr(-1); puts(\"not reached\")", "builtin chr expected a code point, got -1"),
+];
+
+// `(+)` is `fn(a, b) { a + b }`, for each of `+ - * / == != < >`
+pub static SECTIONS: &[(&str, &str)] = &[
+    ("let add = (+); add(1, 2)", "3"),
+    ("let f = (-); let g = (/); [f(7, 2), g(7, 2), (-2)]", "[5, 3, -2]"),
+    ("let eq = (==); let ne = (!=); [eq(1, 1), ne(1, 1)]", "[true, false]"),
+    ("let lt = (<); let gt = (>); [lt(1, 2), gt(1, 2)]", "[true, false]"),
+    ("let cat = (+); cat(\"mon\", \"key\")", "monkey"),
+    (
+        "let fold = fn(f, acc, xs) {
+             for (let i = 0; i < len(xs); i = i + 1) { let acc = f(acc, xs[i]); }
+             acc
+         };
+         [fold((+), 0, [1, 2, 3, 4]), fold((*), 1, [1, 2, 3, 4])]",
+        "[10, 24]",
+    ),
+    ("sorted_insert([1, 3, 5], 4, (-))", "[1, 3, 4, 5]"),
+    ("let double = partial((*), 2); double(21)", "42"),
+    // errors
+    ("let add = (+); add(1)", "wrong number of arguments: want=2, got=1"),
+    ("let div = (/); div(1, 0)", "division by zero"),
+];
+
+// `append` builds a string in place, see `object::builder`
+pub static BUILDERS: &[(&str, &str)] = &[
+    ("append(\"mon\", \"key\")", "monkey"),
+    ("append(\"a\", 'b')", "ab"),
+    (
+        "let s = \"\"; for (let i = 0; i < 5; i = i + 1) { let s = append(s, \"ab\"); } s",
+        "ababababab",
+    ),
+    (
+        "let csv = fn(xs) {
+             let s = \"\";
+             for (let i = 0; i < len(xs); i = i + 1) {
+                 if (i > 0) { let s = append(s, ','); }
+                 let s = append(s, xs[i]);
+             }
+             s
+         };
+         csv([\"a\", \"b\", \"c\"])",
+        "a,b,c",
+    ),
+    (
+        "let s = \"\"; for (let i = 0; i < 1000; i = i + 1) { let s = append(s, \"x\"); } len(s)",
+        "1000",
+    ),
+    // what else holds the string keeps it as it was
+    ("let a = \"a\"; let b = a; let a = append(a, \"b\"); [a, b]", "[ab, a]"),
+    ("let f = fn(s) { let s = append(s, \"b\"); s }; let s = \"a\"; [f(s), s]", "[ab, a]"),
+    // nor does `+`, though a chain of them adds to the string it made
+    ("let a = \"x\" + \"y\"; let b = a + \"z\"; [a, b]", "[xy, xyz]"),
+    ("let f = fn(s) { s + \"!\" + \"!\" }; let s = \"a\" + \"b\"; [f(s), s]", "[ab!!, ab]"),
+    // errors
+    ("append(\"a\", 1)", "builtin append expected a string or a char to append, got integer"),
+    ("append([], \"a\")", "builtin append not supported for type array"),
+    ("append(\"a\")", "builtin append expected 2 arguments, got 1"),
+];
+
+// `x = v` sets the `x` already bound, a global from a function too, and is `v`; a name
+// bound nowhere, by a builtin or by a function around it can't be assigned
+pub static REASSIGNMENTS: &[(&str, &str)] = &[
+    ("let x = 1; x = x + 1; x", "2"),
+    ("let z = 0; z = 42", "42"),
+    ("let x = 1; let y = x = 5; [x, y]", "[5, 5]"),
+    ("let a = 1; let b = 2; a = b = 0; [a, b]", "[0, 0]"),
+    ("let n = 0; if ((n = 5) > 3) { n } else { 0 }", "5"),
+    ("let h = {}; h = set(h, \"a\", 1); h[\"a\"]", "1"),
+    // in loops
+    ("let i = 0; let s = \"\"; while (i < 3) { s = s + \"ab\"; i = i + 1; } s", "ababab"),
+    ("let sum = fn(n) { let t = 0; while (n > 0) { t = t + n; n = n - 1; } t }; sum(4)", "10"),
+    // a function changes the global, where `let` binds a local
+    ("let count = 0; let bump = fn() { count = count + 1; }; bump(); bump(); count", "2"),
+    ("let v = 1; let f = fn() { let v = 2; v = 3; v }; [f(), v]", "[3, 1]"),
+    ("let m = 1; let g = fn() { m = m + 1; m += 10; m }; [g(), m]", "[12, 12]"),
+    // what was worked out about a global before a call assigned it
+    ("let k = 1; let setk = fn() { k = 3; }; let a = k * k; setk(); [a, k * k]", "[1, 9]"),
+    ("let sq = fn(x) { x * x }; sq = fn(x) { x + x }; sq(5)", "10"),
+    // errors
+    ("let d = 1; d = d / 0; d", "division by zero"),
+];
+
+// `c ? a : b` is `if (c) { a } else { b }`, binding looser than all but `=` and grouping right
+pub static TERNARIES: &[(&str, &str)] = &[
+    ("true ? 1 : 2", "1"),
+    ("false ? 1 : 2", "2"),
+    ("let lo = fn(x, y) { x < y ? x : y }; [lo(1, 2), lo(4, 3)]", "[1, 3]"),
+    ("0 ? \"zero\" : \"falsy\"", "zero"),
+    ("first([]) ? 1 : 2", "2"),
+    ("1 + 2 > 2 ? 10 * 2 : 0", "20"),
+    ("[1 > 2 ? 1 : 2, 3]", "[2, 3]"),
+    // chains group to the right
+    (
+        "let sign = fn(n) { n < 0 ? -1 : n == 0 ? 0 : 1 }; [sign(-5), sign(0), sign(7)]",
+        "[-1, 0, 1]",
+    ),
+    (
+        "let grid = fn(a, b) { a ? b ? 1 : 2 : b ? 3 : 4 };
+         [grid(true, true), grid(true, false), grid(false, true), grid(false, false)]",
+        "[1, 2, 3, 4]",
+    ),
+    ("(true ? false : true) ? 1 : 2", "2"),
+    // only the side picked is evaluated
+    ("let t = 1; true ? t : 1 / 0", "1"),
+    ("let u = 0; let w = false ? (u = 5) : (u = 6); [u, w]", "[6, 6]"),
+    ("let v = 0; v = v > 0 ? v : 9; v", "9"),
+    // errors
+    ("true ? 1 / 0 : 1", "division by zero"),
+];
+
+// `return v` leaves the function it's in, and at the top level ends the program with `v`
+pub static RETURNS: &[(&str, &str)] = &[
+    (
+        "let early = fn(x) { if (x > 0) { return \"pos\"; } \"neg\" }; [early(1), early(-1)]",
+        "[pos, neg]",
+    ),
+    ("let find = fn(xs) { for (x in xs) { if (x > 2) { return x; } } -1 }; find([1, 5, 3])", "5"),
+    ("let inner = fn() { fn() { return 1; }() + 1 }; inner()", "2"),
+    // at the top level
+    ("return 10; 9", "10"),
+    ("9; return 2 * 5; 9", "10"),
+    ("let r = 3; if (r > 1) { return r * 2; } 0", "6"),
+    ("let w = 0; while (true) { let w = w + 1; if (w > 3) { return w * 10; } } 0", "40"),
+    ("match ([1]) { [one] => if (one == 1) { return \"one\"; } }; 9", "one"),
+    ("let after = 1; return after; let after = 2;", "1"),
+    // errors
+    ("return 1 / 0; 2", "division by zero"),
+];
+
+// `fn add(a, b) { a + b }` is `let add = fn(a, b) { a + b };`, the name bound first
+pub static DECLARATIONS: &[(&str, &str)] = &[
+    ("fn add(a, b) { a + b } add(1, 2)", "3"),
+    ("fn sub(a, b) { a - b }; sub(5, 2)", "3"),
+    ("fn fact(n) { if (n < 2) { 1 } else { n * fact(n - 1) } } fact(10)", "3628800"),
+    (
+        "fn total(xs) {
+             fn from(i) { if (i == len(xs)) { 0 } else { xs[i] + from(i + 1) } }
+             from(0)
+         }
+         total([1, 2, 3])",
+        "6",
+    ),
+    ("fn twice(f, x) { f(f(x)) } fn inc(x) { x + 1 } twice(inc, 1)", "3"),
+    ("fn again() { 1 } fn again() { 2 } again()", "2"),
+    ("fn(x) { x * 2 }(4)", "8"),
+    // errors
+    ("fn boom() { 1 / 0 } boom()", "division by zero"),
+];
+
+// a hash gives each key once, a key given twice is an error
+pub static KEYS: &[(&str, &str)] = &[
+    (
+        "let by = {\"1\": \"s\", 1: \"i\", '1': \"c\", true: \"b\"}; [by[\"1\"], by[1], by['1'], by[true]]",
+        "[s, i, c, b]",
+    ),
+    ("let i = 1; let pair = {i: \"x\", i + 1: \"y\"}; pair[2]", "y"),
+    // computed ones
+    ("let key = fn() { \"a\" }; {key(): 1, \"a\": 2}", "key a is in the hash twice"),
+    ("let n = 1; {n: \"x\", 1: \"y\"}", "key 1 is in the hash twice"),
+    (
+        "let dup = fn(x) { {x: 1, \"k\": 2} }; [dup(\"j\")[\"k\"], dup(\"k\")]",
+        "key k is in the hash twice",
+    ),
+    ("let same = [3, 3]; {same[0]: 1, same[1]: 2}", "key 3 is in the hash twice"),
+    // after every key and value
+    ("let kk = \"b\"; {kk: 1, kk: 1 / 0}", "division by zero"),
+];
+
+// `fn(first, ...rest)` takes the arguments past `first` as the array `rest`
+pub static VARIADICS: &[(&str, &str)] = &[
+    (
+        "let tail = fn(first, ...rest) { [first, rest] }; [tail(1, 2, 3), tail(1)]",
+        "[[1, [2, 3]], [1, []]]",
+    ),
+    ("let count = fn(...xs) { len(xs) }; [count(), count(\"a\"), count(1, [2], 3)]", "[0, 1, 3]"),
+    (
+        "let greet = fn(g) { fn(...names) { [g, names] } }; let hi = greet(\"hi\"); hi(\"a\", \"b\")",
+        "[hi, [a, b]]",
+    ),
+    ("let sum = fn(acc, ...ns) { for (n in ns) { acc += n; } acc }; sum(0, 5, 6, 7)", "18"),
+    ("pmap([1, 2, 3], fn(...x) { x })", "[[1], [2], [3]]"),
+    ("let pair = partial(fn(a, ...r) { [a, r] }, 1); pair(2, 3)", "[1, [2, 3]]"),
+    ("let arity = memo(fn(...xs) { len(xs) }); [arity(1, 2), arity(1, 2)]", "[2, 2]"),
+    // too few, or too many without one
+    ("let two = fn(a, b, ...more) { a }; two(1)", "wrong number of arguments: want>=2, got=1"),
+    ("let exact = fn(a, b) { a }; exact(1, 2, 3)", "wrong number of arguments: want=2, got=3"),
+];
+
+// `h.name` is `h["name"]`, `h.name(x)` calls what's there and `h.name = v;` sets it
+pub static RECORDS: &[(&str, &str)] = &[
+    ("let point = {\"x\": 1, \"y\": 2}; point.x + point.y", "3"),
+    ("let config = {\"db\": {\"port\": 5432}}; config.db.port", "5432"),
+    ("let config = {\"db\": {\"port\": 5432}}; config.db.port == config[\"db\"][\"port\"]", "true"),
+    ("let point = {\"x\": 1}; point.x = 5; point.x", "5"),
+    ("let point = {\"x\": 1}; point.z", "null"),
+    // methods
+    ("let counter = {\"step\": 2, \"bump\": fn(n) { n + 2 }}; counter.bump(counter.step)", "4"),
+    ("let shape = {\"area\": fn(w, h) { w * h }}; shape.area(3, 4) + 1", "13"),
+    ("let shapes = [{\"sides\": 3}, {\"sides\": 4}]; shapes[1].sides", "4"),
+    // only hashes have keys
+    ("let n = 3; n.x", "index operator not supported for 3"),
+    ("let record = {\"v\": 1}; record.v(2)", "expected 1 to be a function"),
+];
+
+// `_` binds nothing, as a name, a parameter or a pattern
+pub static THROWAWAY: &[(&str, &str)] = &[
+    ("let _ = 5; 1", "1"),
+    ("let n = 0; let _ = n = n + 1; n", "1"),
+    ("let second = fn(_, x) { x }; second(1, 2)", "2"),
+    ("let three = fn(_, _) { 3 }; three(1, 2)", "3"),
+    ("let three = fn(_, _) { 3 }; three(1)", "wrong number of arguments: want=2, got=1"),
+    ("let rest = fn(x, ..._) { x }; rest(1, 2, 3)", "1"),
+    ("let f = fn() { let _ = 4; }; f()", "null"),
+    ("let s = 0; for (_ in [1, 2, 3]) { let s = s + 1; } s", "3"),
+    ("match ([1, 2]) { [_, b] => b }", "2"),
+    // still the hole
+    ("let _ = 5; let sub = fn(a, b) { a - b }; let dec = partial(sub, _, 1); dec(5)", "4"),
+    // bound as any other
+    ("let _unused = 2; _unused * 3", "6"),
+];
+
+// how `match` picks an arm, see `object::matching`
+pub static MATCHES: &[(&str, &str)] = &[
+    ("match (1) { 0 => \"zero\", 1 => \"one\", _ => \"many\" }", "one"),
+    ("match (\"b\") { \"a\" => 1, \"b\" => 2, }", "2"),
+    ("match (2) { 2.00d => \"two\" }", "two"),
+    ("match (5) { 1 => 1 }", "null"),
+    ("match (-3) { x if x > 0 => \"positive\", 0 => \"zero\", _ => \"negative\" }", "negative"),
+    // arrays
+    ("match ([1, 2, 3]) { [] => 0, [first, ...rest] => [first, rest] }", "[1, [2, 3]]"),
+    ("match ([1]) { [a, b] => \"two\", [a] => \"one\" }", "one"),
+    ("match ([1, 2]) { [...all] => all }", "[1, 2]"),
+    ("match ([1, [2, 3]]) { [1, [_, z]] => z }", "3"),
+    ("match ([3, 4]) { [a, b] if a > b => \"down\", [a, b] => \"up\" }", "up"),
+    // hashes
+    ("match ({\"name\": \"ann\", \"age\": 30}) { {name: n} => n }", "ann"),
+    ("match ({\"age\": 3}) { {name: n} => n, _ => \"anonymous\" }", "anonymous"),
+    ("match ({\"point\": [1, 2]}) { {point: [x, y]} => x + y }", "3"),
+    ("match ({1: \"a\", true: \"b\"}) { {1: x, true: y} => x + y }", "ab"),
+    ("match (err(\"no\")) { {ok: v} => v, {err: e} => \"failed: \" + e }", "failed: no"),
+    // other values fit neither
+    ("match (\"str\") { [x] => 1, {a: b} => 2, _ => 3 }", "3"),
+    // the names outlive the `match`
+    ("let r = match ([1, 2]) { [a, b] => a + b }; [r, b]", "[3, 2]"),
+    (
+        "let sum = fn(a) { match (a) { [] => 0, [x, ...rest] => x + sum(rest) } };
+         sum([1, 2, 3, 4])",
+        "10",
+    ),
+    ("let f = fn(p) { match (p) { [x, y] => fn() { x * y } } }; let g = f([3, 4]); g()", "12"),
+    (
+        "let s = 0; for (let i = 0; i < 4; i = i + 1) {
+             let s = s + match (i) { 0 => 10, n if n > 2 => 100, _ => 1 };
+         } s",
+        "112",
+    ),
+    ("match (match (1) { 1 => [2] }) { [n] => match (n) { 2 => \"two\" } }", "two"),
+    // errors
+    ("match (0) { x => 1 / x }", "division by zero"),
+];
+
+// a loop's body is no scope of its own, a `let` in it binds around the loop, and
+// `for (x in xs)` goes through what `each(xs)` yields
+pub static LOOPS: &[(&str, &str)] = &[
+    ("let s = 0; for (let i = 0; i < 5; i = i + 1) { let s = s + i; } s", "10"),
+    ("let i = 0; while (i < 3) { let i = i + 1; } i", "3"),
+    ("let s = 5; while (false) { let s = 0; } s", "5"),
+    ("for (let i = 0; i < 3; i = i + 1) { let last = i; } last", "2"),
+    ("let c = 0; for (let i = 0; i < 6; i = i + 1) { if (i > 2) { let c = c + 1; } } c", "3"),
+    // in a function, and `return` leaves the loop with it
+    ("let f = fn(n) { let t = 1; for (let j = 1; j < n + 1; j = j + 1) { let t = t * j; } t }; f(5)", "120"),
+    ("let f = fn(n) { let k = 0; while (true) { if (k > n) { return k; } let k = k + 1; } }; f(3)", "4"),
+    ("let f = fn() { let c = 0; for (;;) { if (c == 3) { return c; } let c = c + 1; } }; f()", "3"),
+    // what's computed before the loop isn't reused once the loop rebinds what it reads
+    ("let a = 1; let n = a * 2; while (a * 2 < 10) { let n = n + a * 2; let a = a + 1; } n", "22"),
+    ("let i = 0; while (i < 3) { let i = i + 1; } let y = i * 2; y", "6"),
+    ("let sq = fn(x) { x * x }; let s = 0; for (let i = 0; i < 4; i = i + 1) { let s = s + sq(i); } s", "14"),
+    // `break`
+    ("let n = 0; while (true) { let n = n + 1; if (n == 5) { break; } } n", "5"),
+    ("let n = 0; for (;;) { for (;;) { break; } let n = n + 1; if (n > 2) { break; } } n", "3"),
+    ("let p = 0; outer: for (let i = 1; i < 9; i = i + 1) { for (let j = 1; j < 9; j = j + 1) { if (i * j == 12) { let p = [i, j]; break outer; } } } p", "[2, 6]"),
+    ("let f = fn() { let k = 0; a: while (true) { while (true) { let k = k + 1; if (k > 3) { break a; } break; } } k }; f()", "4"),
+    ("a: while (true) { a: while (true) { break a; } break; } 1", "1"),
+    // `for (x in xs)`
+    ("let s = 0; for (x in [1, 2, 3]) { let s = s + x; } s", "6"),
+    ("let ks = []; for (k in {\"a\": 1}) { let ks = push(ks, k); } ks", "[a]"),
+    ("let h = {1: 10, 2: 20}; let s = 0; for (k in h) { let s = s + k * h[k]; } s", "50"),
+    ("let cs = []; for (c in \"hey\") { let cs = push(cs, c); } cs", "[h, e, y]"),
+    ("let s = []; for (i in range(3)) { let s = push(s, i * i); } s", "[0, 1, 4]"),
+    ("for (x in [7, 8]) {} x", "8"),
+    ("let n = 0; for (x in []) { let n = n + 1; } n", "0"),
+    ("let p = []; for (a in [1, 2]) { for (b in [a, 3]) { let p = push(p, a * b); } } p", "[1, 3, 4, 6]"),
+    ("let f = fn(xs) { for (x in xs) { if (x > 1) { return x; } } }; f([0, 2, 5])", "2"),
+    ("let s = 0; for (x in [1, 2, 3, 4]) { if (x == 3) { break; } let s = s + x; } s", "3"),
+    ("let p = 0; rows: for (r in [1, 2]) { for (c in [1, 2]) { if (r * c == 2) { let p = [r, c]; break rows; } } } p", "[1, 2]"),
+    ("let fs = []; for (x in [1, 2]) { let fs = push(fs, fn() { x }); } let g = fs[0]; g()", "2"),
+    ("for (x in 5) {}", "builtin each not supported for type integer"),
+];
+
+// see `object::deque`
+pub static DEQUE: &[(&str, &str)] = &[
+    ("push([1, 2], 3)", "[1, 2, 3]"),
+    ("pop([1, 2, 3])", "[1, 2]"),
+    ("unshift([2, 3], 1)", "[1, 2, 3]"),
+    ("shift([1, 2, 3])", "[2, 3]"),
+    ("let q = [1]; let q = push(q, 2); let q = unshift(q, 0); let q = shift(q); q", "[1, 2]"),
+    // a breadth-first walk of a tree
+    (
+        "let tree = {1: [2, 3], 2: [4], 3: [], 4: []}; let q = [1]; let seen = [];
+         while (len(q) > 0) {
+             let node = first(q); let q = shift(q); let seen = push(seen, node);
+             let kids = tree[node];
+             for (let i = 0; i < len(kids); i = i + 1) { let q = push(q, kids[i]); }
+         }
+         seen",
+        "[1, 2, 3, 4]",
+    ),
+    ("let q = []; for (let i = 0; i < 3; i = i + 1) { let q = unshift(q, i); } q", "[2, 1, 0]"),
+    // what else holds the array keeps it as it was
+    ("let a = [1, 2]; let b = a; let a = pop(a); [a, b]", "[[1], [1, 2]]"),
+    ("let f = fn(a) { let a = push(a, 2); a }; let a = [1]; [f(a), a]", "[[1, 2], [1]]"),
+    ("let q = [1, 2]; for (let i = 0; i < 2; i = i + 1) { let q = push(q, q); } len(q)", "4"),
+    ("let q = [1]; let q = push(q, len(q)); q", "[1, 1]"),
+    // errors
+    ("pop([])", "builtin pop of an empty array"),
+    ("shift([])", "builtin shift of an empty array"),
+    ("push(1, 2)", "builtin push not supported for type integer"),
+    ("unshift([1])", "builtin unshift expected 2 arguments, got 1"),
+    ("let x = pop([]); puts(\"not reached\")", "builtin pop of an empty array"),
+    ("let q = shift([]); len(q)", "builtin shift of an empty array"),
+];
+
+// see `object::search`
+pub static SEARCH: &[(&str, &str)] = &[
+    ("bsearch([1, 3, 5, 7], 5)", "2"),
+    ("bsearch([1, 3, 5, 7], 4)", "null"),
+    ("bsearch([], 1)", "null"),
+    ("bsearch([1, 2, 2, 2, 3], 2)", "1"),
+    ("bsearch([\"ant\", \"bee\", \"cat\"], \"cat\")", "2"),
+    ("bsearch([1, 2.5d, 4], 2.5d)", "1"),
+    ("sorted_insert([1, 3, 5], 4)", "[1, 3, 4, 5]"),
+    ("sorted_insert([], 1)", "[1]"),
+    ("sorted_insert([1, 3], 0)", "[0, 1, 3]"),
+    ("sorted_insert([[1, 1], [2, 0]], [1, 5])", "[[1, 1], [1, 5], [2, 0]]"),
+    // a comparator, here sorting by descending length
+    ("let by_len = fn(a, b) { len(b) - len(a) }; bsearch([\"ccc\", \"bb\", \"a\"], \"xx\", by_len)", "1"),
+    ("sorted_insert([\"ccc\", \"a\"], \"bb\", fn(a, b) { len(b) - len(a) })", "[ccc, bb, a]"),
+    // equal elements keep the order they were inserted in
+    ("sorted_insert([[1, \"a\"]], [1, \"b\"], fn(a, b) { a[0] - b[0] })", "[[1, a], [1, b]]"),
+    (
+        "let xs = [3, 0, 5, 1, 4, 2]; let a = [];
+         for (let i = 0; i < len(xs); i = i + 1) { let a = sorted_insert(a, xs[i]); }
+         a",
+        "[0, 1, 2, 3, 4, 5]",
+    ),
+    ("let a = [1, 3]; let b = sorted_insert(a, 2); a", "[1, 3]"),
+    // errors
+    ("bsearch([1, 2], \"2\")", "can't compare integer and string"),
+    ("bsearch([1, 2], 2, fn(a, b) { a < b })", "a comparator must return an integer, got boolean"),
+    ("bsearch([1, 2], 2, fn(a, b) { a / 0 })", "division by zero"),
+    ("bsearch([1, 2], 2, 3)", "builtin bsearch expected a function to compare with, got integer"),
+    ("bsearch({}, 1)", "builtin bsearch not supported for type hash"),
+    ("sorted_insert([1])", "builtin sorted_insert expected 2 or 3 arguments, got 1"),
+];
+
+// see `object::iterator`
+pub static ITERATION: &[(&str, &str)] = &[
+    (
+        "let it = iter([1, 2]); [next(it)[\"value\"], next(it)[\"value\"], next(it)[\"done\"]]",
+        "[1, 2, true]",
+    ),
+    ("next(iter([]))[\"value\"]", "null"),
+    ("collect(iter(\"hey\"))", "[h, e, y]"),
+    ("collect({\"a\": 1})", "[[a, 1]]"),
+    ("collect(range(3))", "[0, 1, 2]"),
+    ("collect(range(2, 5))", "[2, 3, 4]"),
+    ("collect(range(10, 0, -3))", "[10, 7, 4, 1]"),
+    ("collect(range(5, 2))", "[]"),
+    ("collect(generate(1, fn(n) { if (n < 100) { [n, n * 3] } }))", "[1, 3, 9, 27, 81]"),
+    // lazy pipelines, over endless sequences too
+    ("collect(imap([1, 2, 3], fn(x) { x * x }))", "[1, 4, 9]"),
+    ("collect(ifilter(range(10), fn(x) { x / 3 * 3 == x }))", "[0, 3, 6, 9]"),
+    ("collect(take(imap(range(1000000000000), fn(x) { x * 2 }), 3))", "[0, 2, 4]"),
+    (
+        "let fib = generate([0, 1], fn(s) { [s[0], [s[1], s[0] + s[1]]] });
+         collect(take(ifilter(fib, fn(x) { x > 10 }), 4))",
+        "[13, 21, 34, 55]",
+    ),
+    ("let never = imap([1, 2], fn(x) { x + \"\" }); 1", "1"),
+    // an iterator is shared, and moved on by `next`
+    ("let it = range(3); let other = it; next(other); collect(it)", "[1, 2]"),
+    (
+        "let it = range(4); let firsts = collect(take(it, 2)); [firsts, collect(it)]",
+        "[[0, 1], [2, 3]]",
+    ),
+    ("let it = range(1); [it == it, range(1) == range(1)]", "[true, false]"),
+    ("range(2)", "[iterator]"),
+    // errors
+    ("next([1])", "builtin next not supported for type array"),
+    ("iter(1)", "builtin iter not supported for type integer"),
+    ("range(0, 10, 0)", "builtin range step can't be 0"),
+    ("collect(imap([1], fn(x) { x / 0 }))", "division by zero"),
+    (
+        "collect(generate(0, fn(n) { n }))",
+        "a generator must return [value, state] or null, got integer",
+    ),
+    ("take([1], -1)", "builtin take expected a count of 0 or more, got -1"),
+];
+
+// see `object::parallel`
+pub static PARALLEL: &[(&str, &str)] = &[
+    ("pmap([1, 2, 3], fn(x) { x * x })", "[1, 4, 9]"),
+    ("pmap([], fn(x) { x })", "[]"),
+    ("pmap([[1, 2], [3]], len)", "[2, 1]"),
+    ("let scale = fn(k) { pmap([1, 2, 3], fn(x) { x * k }) }; scale(10)", "[10, 20, 30]"),
+    (
+        "let fibs = fn(ns) {
+             pmap(ns, fn(n) { let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(n) })
+         };
+         fibs([10, 15, 20])",
+        "[55, 610, 6765]",
+    ),
+    // mapped on the program's thread, with the same result
+    ("let k = 2; pmap([1, 2], fn(x) { x * k })", "[2, 4]"),
+    ("len(pmap([1, 2], fn(x) { fn() { x } }))", "2"),
+    // the first element to fail gives the error
+    ("pmap([1, 0, \"a\"], fn(x) { 10 / x })", "division by zero"),
+    ("pmap([1], fn(a, b) { a })", "wrong number of arguments: want=2, got=1"),
+    ("pmap(1, fn(x) { x })", "builtin pmap not supported for type integer"),
+    ("pmap([1], 2)", "builtin pmap expected a function, got integer"),
+    ("pmap([1])", "builtin pmap expected 2 arguments, got 1"),
+];
+
+// run by the vm alone, see `object::channel`
+pub static CHANNELS: &[(&str, &str)] = &[
+    ("let ch = chan(); send(ch, 1); send(ch, [2]); [recv(ch), recv(ch)]", "[1, [2]]"),
+    ("recv(spawn(fn() { 6 * 7 }))", "42"),
+    (
+        "let ch = chan();
+         let square = fn(x) { x * x };
+         let workers = [spawn(fn() { send(ch, square(3)) }), spawn(fn() { send(ch, square(3)) })];
+         recv(workers[0]); recv(workers[1]);
+         recv(ch) + recv(ch)",
+        "18",
+    ),
+    // a pipeline of threads, 0 ends a stream
+    (
+        "let numbers = chan(); let squares = chan();
+         spawn(fn() { for (let i = 1; i < 4; i = i + 1) { send(numbers, i); } send(numbers, 0) });
+         spawn(fn() {
+             let go = fn() { let n = recv(numbers); if (n != 0) { send(squares, n * n); go() } };
+             go();
+             send(squares, 0)
+         });
+         let total = 0;
+         for (let n = recv(squares); n != 0; n = recv(squares)) { let total = total + n; }
+         total",
+        "14",
+    ),
+    // copies
+    ("let a = [1]; let done = spawn(fn() { push(a, 2) }); [recv(done), a]", "[[1, 2], [1]]"),
+    ("let ch = chan(); [ch == ch, ch == chan()]", "[true, false]"),
+    ("chan()", "[channel]"),
+    ("let f = fn(x) { x + 1 }; let ch = chan(); send(ch, f); let g = recv(ch); g(1)", "2"),
+    // errors
+    ("recv(spawn(fn() { 1 / 0 }))", "division by zero"),
+    ("spawn(fn(x) { x })", "wrong number of arguments: want=1, got=0"),
+    ("send(chan(), range(3))", "builtin send can't send an iterator"),
+    ("spawn(1)", "builtin spawn expected a function, got integer"),
+    ("recv(1)", "builtin recv not supported for type integer"),
+    ("send(chan())", "builtin send expected 2 arguments, got 1"),
+];
+
+// see `object::update`
+pub static UPDATES: &[(&str, &str)] = &[
+    ("set([1, 2, 3], 1, 5)", "[1, 5, 3]"),
+    ("insert([1, 3], 1, 2)", "[1, 2, 3]"),
+    ("insert([1], 1, 2)", "[1, 2]"),
+    ("remove([1, 2, 3], 0)", "[2, 3]"),
+    ("set({\"a\": 1}, \"a\", 2)", "[a: 2]"),
+    ("set({}, 1, true) == {1: true}", "true"),
+    ("remove({\"a\": 1, \"b\": 2}, \"a\")", "[b: 2]"),
+    ("remove({\"a\": 1}, \"b\")", "[a: 1]"),
+    // the value passed in is left as it was
+    ("let a = [1, 2]; let b = set(a, 0, 9); a", "[1, 2]"),
+    ("let h = {\"k\": 1}; let g = remove(h, \"k\"); h", "[k: 1]"),
+    // index assignment
+    ("let a = [1, 2, 3]; a[0] = 7; a", "[7, 2, 3]"),
+    ("let h = {}; h[\"k\"] = 1; h[\"k\"] = h[\"k\"] + 1; h", "[k: 2]"),
+    ("let m = [[1, 2], [3, 4]]; m[1][0] = 0; m", "[[1, 2], [0, 4]]"),
+    ("let h = {\"xs\": [1]}; h[\"xs\"][0] = 2; h[\"xs\"]", "[2]"),
+    ("let a = [1]; let b = a; a[0] = 2; b", "[1]"),
+    ("let a = [0, 0, 0]; for (let i = 0; i < 3; i = i + 1) { a[i] = i * i; } a", "[0, 1, 4]"),
+    ("let a = [1]; let f = fn() { a[0] = 2; a }; [f(), a]", "[[2], [2]]"),
+    ("let a = [1]; let f = fn(a) { a[0] = 2; a }; [f(a), a]", "[[2], [1]]"),
+    // errors
+    ("set([1], 1, 0)", "index 1 out of range for an array of length 1"),
+    ("insert([1], 2, 0)", "index 2 out of range for an array of length 1"),
+    ("remove([], 0)", "index 0 out of range for an array of length 0"),
+    ("set([1], -1, 0)", "index -1 out of range for an array of length 1"),
+    ("set([1], \"0\", 0)", "an array index must be an integer, got string"),
+    ("set({}, [1], 0)", "key [1] is not hashable"),
+    ("insert({}, 1, 0)", "builtin insert not supported for type hash"),
+    ("set(\"abc\", 0, \"x\")", "builtin set not supported for type string"),
+    ("remove([1])", "builtin remove expected 2 arguments, got 1"),
+    // stopping there
+    (
+        "let a = [1]; a[1] = 2; puts(\"not reached\")",
+        "index 1 out of range for an array of length 1",
+    ),
+    ("let a = [1]; a[\"0\"] = 2; a", "an array index must be an integer, got string"),
+    ("let m = [[1]]; m[0][3] = 2; m", "index 3 out of range for an array of length 1"),
+    ("let f = fn(a) { a[5] = 0; a }; f([1]); 0", "index 5 out of range for an array of length 1"),
+    ("let xs = [1]; let ys = remove(xs, 1); ys", "index 1 out of range for an array of length 1"),
+];
+
+// see `object::frozen`
+pub static FROZEN: &[(&str, &str)] = &[
+    ("freeze([1, 2])", "[1, 2]"),
+    ("let a = freeze([1, 2]); a[0] + len(a)", "3"),
+    ("let a = freeze([1]); let b = set(clone(a), 0, 2); [a, b]", "[[1], [2]]"),
+    // what an update makes from a value that isn't frozen isn't either
+    ("let a = freeze([1]); let b = push(clone(a), 2); push(b, 3)", "[1, 2, 3]"),
+    // errors
+    ("set(freeze([1]), 0, 2)", "builtin set can't change a frozen array"),
+    ("insert(freeze([1]), 0, 2)", "builtin insert can't change a frozen array"),
+    ("remove(freeze({\"k\": 1}), \"k\")", "builtin remove can't change a frozen hash"),
+    ("let q = freeze([1]); let q = push(q, 2); q", "builtin push can't change a frozen array"),
+    ("pop(freeze([1]))", "builtin pop can't change a frozen array"),
+    ("unshift(freeze([1]), 0)", "builtin unshift can't change a frozen array"),
+    ("shift(freeze([1]))", "builtin shift can't change a frozen array"),
+    ("sorted_insert(freeze([1, 3]), 2)", "builtin sorted_insert can't change a frozen array"),
+    ("append(freeze(\"a\"), \"b\")", "builtin append can't change a frozen string"),
+    ("let a = freeze([1]); let b = a; push(b, 2)", "builtin push can't change a frozen array"),
+    ("let a = freeze([1, 2]); a[0] = 5; a", "builtin set can't change a frozen array"),
+    // and what's in it
+    (
+        "let h = freeze({\"xs\": [1]}); push(h[\"xs\"], 2)",
+        "builtin push can't change a frozen array",
+    ),
+    (
+        "let h = freeze({\"xs\": [1]}); h[\"xs\"][0] = 2; h",
+        "builtin set can't change a frozen array",
+    ),
+    ("freeze()", "builtin freeze expected 1 argument, got 0"),
+];
+
+// see `object::template`
+pub static TEMPLATES: &[(&str, &str)] = &[
+    ("\"n=${1 + 2}\"", "n=3"),
+    ("let name = \"monkey\"; \"hello ${name}!\"", "hello monkey!"),
+    (
+        "\"${[1, \"a\"]} ${{\"k\": true}} ${'c'} ${1.50d} ${if (false) { 1 }}\"",
+        "[1, a] [k: true] c 1.50 null",
+    ),
+    ("let f = fn(x) { x * 2 }; \"${f(2)}${f(3)}\"", "46"),
+    ("\"a ${\"b ${1}\"}\"", "a b 1"),
+    ("string(12) + string(\"x\")", "12x"),
+    // what's interpolated fails as it would anywhere
+    ("\"${1 / 0}\"", "division by zero"),
+    ("string()", "builtin string expected 1 argument, got 0"),
+];
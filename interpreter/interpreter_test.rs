@@ -14,7 +14,7 @@ mod tests {
             match parse(input) {
                 Ok(node) => match eval(node, &env) {
                     Ok(evaluated) => assert_eq!(&format!("{}", evaluated), expected),
-                    Err(e) => assert_eq!(&format!("{}", e), expected),
+                    Err(e) => assert_eq!(&e, expected),
                 },
                 Err(e) => panic!("parse error: {}", e[0]),
             }
@@ -33,6 +33,47 @@ mod tests {
         apply_test(&test_case);
     }
 
+    #[test]
+    fn test_integer_overflow() {
+        let test_case = [
+            ("1 / 0", "division by zero"),
+            ("9223372036854775807 - 1 + 1", "9223372036854775807"),
+            #[cfg(not(feature = "bigint"))]
+            ("9223372036854775807 + 1", "integer overflow: 9223372036854775807 + 1"),
+            #[cfg(feature = "bigint")]
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            #[cfg(feature = "bigint")]
+            ("let big = 9223372036854775807 * 4; big / 2 == 9223372036854775807 * 2", "true"),
+            #[cfg(feature = "bigint")]
+            ("let big = 9223372036854775807 * 2; big - big + 1", "1"),
+            #[cfg(feature = "bigint")]
+            ("{9223372036854775807 * 2: 1}[9223372036854775807 + 9223372036854775807]", "1"),
+            #[cfg(feature = "bigint")]
+            (
+                "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(25)",
+                "15511210043330985984000000",
+            ),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_decimal_expressions() {
+        let test_case = [
+            ("1.50d", "1.50"),
+            ("0.1d + 0.2d == 0.3d", "true"),
+            ("-1.25d * 4", "-5.00"),
+            ("10 / 4d", "2.50"),
+            ("1.5d < 2", "true"),
+            ("2 == 2.00d", "true"),
+            ("1d / 0", "division by zero"),
+            (r#"decimal("19.99") * 3"#, "59.97"),
+            ("decimal(true)", "builtin decimal not supported for type true"),
+            ("{1.50d: 1}[1.5d]", "1"),
+        ];
+        apply_test(&test_case);
+    }
+
     #[test]
     fn test_boolean_expressions() {
         let test_case = [
@@ -150,6 +191,7 @@ mod tests {
             ("let add = fn(x, y) { x + y; }; add(5, 5);", "10"),
             ("let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));", "20"),
             ("fn(x) { x; }(5)", "5"),
+            ("fn(x, y) { x; }(5)", "wrong number of arguments: want=2, got=1"),
         ];
         apply_test(&test_case);
     }
@@ -160,6 +202,8 @@ mod tests {
             (r#""Hello" + " " + "World!""#, "Hello World!"),
             (r#""Hello" == "Hello""#, "true"),
             (r#""Hello" == "Hi""#, "false"),
+            (r#""line\n" + "\ttab""#, "line\n\ttab"),
+            (r#"len("\"\\\u{41}")"#, "3"),
         ];
         apply_test(&test_case);
     }
@@ -170,6 +214,8 @@ mod tests {
             (r#"len("")"#, "0"),
             (r#"len("four")"#, "4"),
             (r#"len("hello world")"#, "11"),
+            ("freeze([1, 2])", "[1, 2]"),
+            ("freeze()", "builtin freeze expected 1 argument, got 0"),
         ];
         apply_test(&test_case);
     }
@@ -231,4 +277,345 @@ mod tests {
         ];
         apply_test(&test_case);
     }
+
+    #[test]
+    fn test_numeric_tower() {
+        apply_test(conformance::TOWER);
+    }
+
+    #[test]
+    fn test_ordering() {
+        apply_test(conformance::ORDERING);
+    }
+
+    #[test]
+    fn test_equality() {
+        apply_test(conformance::EQUALITY);
+    }
+
+    #[test]
+    fn test_truthiness() {
+        apply_test(conformance::TRUTHINESS);
+    }
+
+    #[test]
+    fn test_aliasing() {
+        apply_test(conformance::ALIASING);
+    }
+
+    #[test]
+    fn test_assignments() {
+        apply_test(conformance::ASSIGNMENTS);
+    }
+
+    #[test]
+    fn test_cells() {
+        conformance::define_cells();
+        apply_test(conformance::CELLS);
+    }
+
+    #[test]
+    fn test_memos() {
+        apply_test(conformance::MEMOS);
+    }
+
+    #[test]
+    fn test_results() {
+        apply_test(conformance::RESULTS);
+    }
+
+    #[test]
+    fn test_requires() {
+        apply_test(conformance::REQUIRES);
+    }
+
+    #[test]
+    fn test_partials() {
+        apply_test(conformance::PARTIALS);
+    }
+
+    #[test]
+    fn test_compositions() {
+        apply_test(conformance::COMPOSITIONS);
+    }
+
+    #[test]
+    fn test_chars() {
+        apply_test(conformance::CHARS);
+    }
+
+    #[test]
+    fn test_sections() {
+        apply_test(conformance::SECTIONS);
+    }
+
+    #[test]
+    fn test_builders() {
+        apply_test(conformance::BUILDERS);
+    }
+
+    #[test]
+    fn test_reassignments() {
+        apply_test(conformance::REASSIGNMENTS);
+    }
+
+    #[test]
+    fn test_ternaries() {
+        apply_test(conformance::TERNARIES);
+    }
+
+    #[test]
+    fn test_returns() {
+        apply_test(conformance::RETURNS);
+    }
+
+    #[test]
+    fn test_declarations() {
+        apply_test(conformance::DECLARATIONS);
+    }
+
+    #[test]
+    fn test_keys() {
+        apply_test(conformance::KEYS);
+    }
+
+    #[test]
+    fn test_variadics() {
+        apply_test(conformance::VARIADICS);
+    }
+
+    #[test]
+    fn test_records() {
+        apply_test(conformance::RECORDS);
+    }
+
+    #[test]
+    fn test_throwaway() {
+        apply_test(conformance::THROWAWAY);
+    }
+
+    #[test]
+    fn test_matches() {
+        apply_test(conformance::MATCHES);
+    }
+
+    #[test]
+    fn test_loops() {
+        apply_test(conformance::LOOPS);
+    }
+
+    #[test]
+    fn test_deque() {
+        apply_test(conformance::DEQUE);
+    }
+
+    #[test]
+    fn test_search() {
+        apply_test(conformance::SEARCH);
+    }
+
+    #[test]
+    fn test_iteration() {
+        apply_test(conformance::ITERATION);
+    }
+
+    #[test]
+    fn test_parallel() {
+        apply_test(conformance::PARALLEL);
+    }
+
+    #[test]
+    fn test_channels_need_the_vm() {
+        let needs = "builtin spawn needs the vm, built with the `parallel` feature";
+        apply_test(&[("spawn(fn() { 1 })", needs)]);
+        let needs = "builtin chan needs the vm, built with the `parallel` feature";
+        apply_test(&[("chan()", needs)]);
+    }
+
+    #[test]
+    fn test_updates() {
+        apply_test(conformance::UPDATES);
+    }
+
+    #[test]
+    fn test_frozen() {
+        apply_test(conformance::FROZEN);
+    }
+
+    #[test]
+    fn test_templates() {
+        apply_test(conformance::TEMPLATES);
+    }
+
+    #[test]
+    fn test_feature_registry() {
+        for f in object::features::CONSTRUCTS.iter() {
+            let env: Env = Rc::new(RefCell::new(Default::default()));
+            let got = eval(parse(f.example).unwrap(), &env).map(|o| o.to_string());
+            assert_eq!(got.as_deref() == Ok(f.expected), f.evaluator, "{}: {:?}", f.name, got);
+        }
+    }
+
+    #[test]
+    fn test_isolated_children() {
+        fn run(source: &str, env: &Env) -> Result<String, String> {
+            return eval(parse(source).unwrap(), env).map(|o| o.to_string());
+        }
+        let prelude: Env = Rc::new(RefCell::new(Default::default()));
+        run("let greet = fn(n) { \"hi \" + n }; let secret = 42;", &prelude).unwrap();
+
+        let isolated = |capabilities: &[&str]| {
+            let child = Environment::new_isolated_child(&prelude, capabilities);
+            return Rc::new(RefCell::new(child));
+        };
+        let a = isolated(&["greet"]);
+        let b = isolated(&["greet", "secret"]);
+
+        // only the prelude names granted, builtins always
+        assert_eq!(run("greet(\"a\")", &a), Ok("hi a".to_string()));
+        assert_eq!(
+            run("secret", &a),
+            Err("unknown identifier secret at line 1, column 1".to_string())
+        );
+        assert_eq!(run("secret", &b), Ok("42".to_string()));
+        assert_eq!(run("len(\"abc\")", &a), Ok("3".to_string()));
+
+        // bindings stay in the plugin, shadowing the prelude without changing it
+        run("let greet = fn(n) { \"bye \" + n }; let mine = 1;", &a).unwrap();
+        assert_eq!(run("greet(\"a\")", &a), Ok("bye a".to_string()));
+        assert_eq!(run("greet(\"b\")", &b), Ok("hi b".to_string()));
+        assert_eq!(run("greet(\"p\")", &prelude), Ok("hi p".to_string()));
+        assert_eq!(run("mine", &b), Err("unknown identifier mine at line 1, column 1".to_string()));
+        assert_eq!(prelude.borrow().get("mine"), None);
+
+        // a function from the prelude still sees the prelude it was defined in
+        run("let reveal = fn() { secret };", &prelude).unwrap();
+        let c = isolated(&["reveal"]);
+        assert_eq!(run("reveal()", &c), Ok("42".to_string()));
+        // and a plugin's own functions see what the plugin sees
+        let hidden = Err("unknown identifier secret at line 1, column 16".to_string());
+        assert_eq!(run("let f = fn() { secret }; f()", &c), hidden);
+        // nor is a hidden name suggested
+        assert_eq!(
+            run("secrets", &c),
+            Err("unknown identifier secrets at line 1, column 1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let test_case = [
+            (
+                "let length = 3; lenght",
+                "unknown identifier lenght at line 1, column 17, did you mean 'length'?",
+            ),
+            ("lenn([1])", "unknown identifier lenn at line 1, column 1, did you mean 'len'?"),
+            (
+                "let f = fn(count) { cont }; f(1)",
+                "unknown identifier cont at line 1, column 21, did you mean 'count'?",
+            ),
+            // too far from every name to be a typo
+            ("let x = 1; y", "unknown identifier y at line 1, column 12"),
+            ("banana", "unknown identifier banana at line 1, column 1"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let run = |input: &str, env: &Env| match eval(parse(input).unwrap(), env) {
+            Ok(value) => Ok(value.to_string()),
+            Err(e) => Err(e),
+        };
+        let strict: Env = Rc::new(RefCell::new(Default::default()));
+        let lenient: Env = Rc::new(RefCell::new(Default::default()));
+        lenient.borrow_mut().set_lenient(true);
+
+        // a name is checked even where it's never evaluated
+        let input = "if (false) { nope }";
+        assert_eq!(run(input, &strict), Err("unknown identifier nope at line 1, column 14".into()));
+        assert_eq!(run(input, &lenient), Ok("null".to_string()));
+        // and a function can't read a global bound after it, as in the vm
+        let input = "let f = fn() { g() };\nlet g = fn() { 1 };\nf()";
+        assert_eq!(run(input, &strict), Err("unknown identifier g at line 1, column 16".into()));
+        assert_eq!(run(input, &lenient), Ok("1".to_string()));
+        // nothing ran, so nothing was bound
+        assert_eq!(strict.borrow().get("f"), None);
+        // a `break` needs its loop, found out when it's parsed in either
+        let input = "puts(1); while (true) { fn() { break; }(); }";
+        let errors = parse(input).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "`break` outside a loop at line 1, column 32");
+
+        // an assignment needs a name bound in its own function or a global, as in the vm
+        let test_case = [
+            ("y = 1", "assignment to undefined variable y at line 1, column 1"),
+            ("len = 1", "can't assign to the builtin len at line 1, column 1"),
+            (
+                "fn() { let n = 0; fn() { n = n + 1 }() }()",
+                "can't assign to n at line 1, column 26, a function around this one binds it",
+            ),
+            // and so does a compound or an index assignment
+            (
+                "fn() { let n = 0; fn() { n += 1 }() }()",
+                "can't assign to n at line 1, column 26, a function around this one binds it",
+            ),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(run(input, &strict), Err(error.to_string()));
+            assert_eq!(run(input, &lenient), Err(error.to_string()));
+        }
+        // the name before the value, which lenient mode only evaluates first
+        let test_case = [
+            ("q += 1", "assignment to undefined variable q at line 1, column 1"),
+            ("qs[0] = 1", "assignment to undefined variable qs at line 1, column 1"),
+            ("len += 1", "can't assign to the builtin len at line 1, column 1"),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(run(input, &strict), Err(error.to_string()));
+        }
+        // even where it's never evaluated
+        let input = "if (false) { nope = 1 }";
+        let error = "assignment to undefined variable nope at line 1, column 14";
+        assert_eq!(run(input, &strict), Err(error.to_string()));
+        assert_eq!(run(input, &lenient), Ok("null".to_string()));
+
+        let test_case = [
+            ("let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(5)", "120"),
+            ("let add = fn(a) { fn(b) { let sum = a + b; sum } }; let inc = add(1); inc(2)", "3"),
+            // globals of earlier inputs
+            ("fact(3) + inc(1)", "8"),
+        ];
+        for (input, expected) in test_case {
+            assert_eq!(run(input, &strict), Ok(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_read_only_environment() {
+        let config: Env = Rc::new(RefCell::new(Default::default()));
+        let limit = Rc::new(object::Object::Integer(3));
+        config.borrow_mut().set("limit".to_string(), limit);
+        config.borrow_mut().freeze();
+
+        let run = |source: &str| eval(parse(source).unwrap(), &config).map(|o| o.to_string());
+        assert_eq!(run("limit * 2"), Ok("6".to_string()));
+        let rebind = Err("can't bind limit, the environment is read-only".to_string());
+        assert_eq!(run("let limit = 10;"), rebind);
+        assert!(run("let other = 1;").is_err());
+        let assign = Err("can't assign to limit, the environment is read-only".to_string());
+        assert_eq!(run("limit = 10"), assign);
+        assert_eq!(run("fn() { limit = 10 }()"), assign);
+        assert_eq!(run("limit"), Ok("3".to_string()));
+        // functions bind their parameters in their own environment
+        assert_eq!(run("fn(x) { let y = x + limit; y }(1)"), Ok("4".to_string()));
+
+        // a plugin over a frozen prelude binds in its own store
+        let plugin = Environment::new_isolated_child(&config, &["limit"]);
+        let plugin: Env = Rc::new(RefCell::new(plugin));
+        let got = eval(parse("let limit = limit + 1; limit").unwrap(), &plugin);
+        assert_eq!(got.map(|o| o.to_string()), Ok("4".to_string()));
+        let got = eval(parse("fn() { limit = limit * 2 }(); limit").unwrap(), &plugin);
+        assert_eq!(got.map(|o| o.to_string()), Ok("8".to_string()));
+    }
 }
@@ -1,29 +1,58 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::rc::Rc;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::Ordering;
 
 use object::builtins::*;
+use object::chars;
+use object::decimal;
 use object::environment::*;
-use object::{EvalError, Object};
+use object::interrupt;
+use object::matching;
+use object::number::{self, Arithmetic};
+use object::order;
+use object::singleton;
+use object::{arity_error, BuiltinFunc, EvalError, HashMap, Object};
 use parser::ast::*;
 use parser::lexer::token::{Token, TokenKind};
 
 mod interpreter_test;
+pub mod strict;
 
+// Checks the names `node` reads before evaluating it, unless `env` is lenient, see `strict`.
 pub fn eval(node: Node, env: &Env) -> Result<Rc<Object>, EvalError> {
-    match node {
-        Node::Program(p) => eval_block_statements(&p.body, env),
-        Node::Statement(statements) => eval_statement(&statements, env),
-        Node::Expression(expression) => eval_expression(&expression, env),
+    if !env.borrow().is_lenient() {
+        match &node {
+            Node::Program(p) => strict::check(&p.body, env)?,
+            Node::Statement(s) => strict::check(core::slice::from_ref(s), env)?,
+            Node::Expression(e) => strict::check_expression(e, env)?,
+        }
+    }
+    let evaluated = match node {
+        Node::Program(p) => eval_block_statements(&p.body, env)?,
+        Node::Statement(statements) => eval_statement(&statements, env)?,
+        Node::Expression(expression) => eval_expression(&expression, env)?,
+    };
+    // lenient mode only finds out here
+    if let Object::Break(b) = &*evaluated {
+        return Err(b.misplaced());
     }
+    return Ok(evaluated);
 }
 
 fn eval_block_statements(statements: &Vec<Statement>, env: &Env) -> Result<Rc<Object>, EvalError> {
-    let mut result = Rc::new(Object::Null);
+    let mut result = singleton::null();
     for statement in statements {
         let val = eval_statement(statement, &Rc::clone(env))?;
         match *val {
-            Object::ReturnValue(_) => return Ok(val),
+            Object::ReturnValue(_) | Object::Break(_) => return Ok(val),
             _ => {
                 result = val;
             }
@@ -40,22 +69,47 @@ fn eval_statement(statement: &Statement, env: &Env) -> Result<Rc<Object>, EvalEr
             let val = eval_expression(argument, env)?;
             return Ok(Rc::new(Object::ReturnValue(val)));
         }
-        Statement::Let(Let { identifier: id, expr, .. }) => {
-            let val = eval_expression(expr, &Rc::clone(env))?;
+        Statement::Let(l @ Let { identifier: id, expr, .. }) => {
+            let val = match l.handover() {
+                Some(call) => eval_handover(call, env)?,
+                None => eval_expression(expr, &Rc::clone(env))?,
+            };
             let obj: Rc<Object> = Rc::clone(&val);
+            if l.is_throwaway() {
+                return Ok(singleton::null());
+            }
             if let TokenKind::IDENTIFIER { name } = &id.kind {
-                env.borrow_mut().set(name.clone(), obj);
+                if env.borrow().is_read_only() {
+                    return Err(format!("can't bind {}, the environment is read-only", name));
+                }
+                env.borrow_mut().set(name.to_string(), obj);
             }
-            return Ok(Rc::new(Object::Null));
+            return Ok(singleton::null());
         }
-    }
-}
-
-fn is_truthy(obj: &Object) -> bool {
-    match obj {
-        Object::Null => return false,
-        Object::Boolean(false) => return false,
-        _ => true,
+        Statement::Import(Import { path, .. }) => {
+            Err(format!("import \"{}\" must be resolved by the project loader", path))
+        }
+        Statement::While(While { label, condition, body, .. }) => loop {
+            spend(env)?;
+            let condition = eval_expression(condition, &Rc::clone(env))?;
+            if !condition.is_truthy() {
+                return Ok(singleton::null());
+            }
+            let result = eval_block_statements(&body.body, env)?;
+            match &*result {
+                Object::ReturnValue(_) => return Ok(result),
+                // the loop it names, or an outer one
+                Object::Break(b) => match (&b.label, label) {
+                    (Some(name), Some(label)) if name.name == label.name => {
+                        return Ok(singleton::null())
+                    }
+                    (Some(_), _) => return Ok(result),
+                    (None, _) => return Ok(singleton::null()),
+                },
+                _ => {}
+            }
+        },
+        Statement::Break(b) => Ok(Rc::new(Object::Break(b.clone()))),
     }
 }
 
@@ -73,18 +127,19 @@ fn eval_expression(expression: &Expression, env: &Env) -> Result<Rc<Object>, Eva
         }
         Expression::IF(IF { condition, consequent, alternate, .. }) => {
             let condition = eval_expression(condition, &Rc::clone(env))?;
-            if is_truthy(&condition) {
+            if condition.is_truthy() {
                 eval_block_statements(&(consequent.body), env)
             } else {
                 match alternate {
                     Some(alt) => eval_block_statements(&(alt.body), env),
-                    None => Ok(Rc::new(Object::Null)),
+                    None => Ok(singleton::null()),
                 }
             }
         }
-        Expression::IDENTIFIER(IDENTIFIER { name: id, .. }) => eval_identifier(&id, env),
-        Expression::FUNCTION(FunctionDeclaration { params, body, .. }) => {
-            return Ok(Rc::new(Object::Function(params.clone(), body.clone(), Rc::clone(env))));
+        Expression::IDENTIFIER(identifier) => eval_identifier(identifier, env),
+        Expression::FUNCTION(FunctionDeclaration { params, body, rest, .. }) => {
+            let env = Rc::clone(env);
+            return Ok(Rc::new(Object::Function(params.clone(), body.clone(), env, *rest)));
         }
         Expression::FunctionCall(FunctionCall { callee, arguments, .. }) => {
             let func = eval_expression(callee, &Rc::clone(env))?;
@@ -96,6 +151,96 @@ fn eval_expression(expression: &Expression, env: &Env) -> Result<Rc<Object>, Eva
             let index = eval_expression(index, env)?;
             eval_index_expression(&literal, &index)
         }
+        Expression::Match(m) => eval_match(m, env),
+        Expression::Assign(a) => eval_assign(a, env),
+    }
+}
+
+// `name = value`, see `Environment::assign`
+fn eval_assign(a: &Assign, env: &Env) -> Result<Rc<Object>, EvalError> {
+    let value = eval_expression(&a.value, env)?;
+    match env.borrow_mut().assign(&a.name.name, Rc::clone(&value)) {
+        Assigned::Set => return Ok(value),
+        Assigned::ReadOnly => {
+            return Err(format!("can't assign to {}, the environment is read-only", a.name))
+        }
+        Assigned::Enclosing => return Err(a.enclosing()),
+        Assigned::Unbound if BuiltIns.iter().any(|b| b.0 == a.name.name) => return Err(a.builtin()),
+        Assigned::Unbound => return Err(a.unbound()),
+    }
+}
+
+// the value of the first arm that fits, see `object::matching`
+fn eval_match(m: &Match, env: &Env) -> Result<Rc<Object>, EvalError> {
+    let subject = eval_expression(&m.subject, env)?;
+    for arm in m.arms.iter() {
+        let mut bindings = vec![];
+        if !fits(&arm.pattern, &subject, &mut bindings, env)? {
+            continue;
+        }
+        for (name, value) in bindings {
+            if env.borrow().is_read_only() {
+                return Err(format!("can't bind {}, the environment is read-only", name));
+            }
+            env.borrow_mut().set(name, value);
+        }
+        if let Some(guard) = &arm.guard {
+            if !eval_expression(guard, env)?.is_truthy() {
+                continue;
+            }
+        }
+        return eval_expression(&arm.body, env);
+    }
+    return Ok(singleton::null());
+}
+
+// whether `value` fits `pattern`, adding what it binds to `bindings`
+fn fits(
+    pattern: &Pattern,
+    value: &Rc<Object>,
+    bindings: &mut Vec<(String, Rc<Object>)>,
+    env: &Env,
+) -> Result<bool, EvalError> {
+    match pattern {
+        Pattern::Binding(id) => {
+            if !id.is_throwaway() {
+                bindings.push((id.name.clone(), Rc::clone(value)));
+            }
+            return Ok(true);
+        }
+        Pattern::Literal(literal) => {
+            return Ok(**value == *eval_literal(literal, env)?);
+        }
+        Pattern::Array { elements, rest, .. } => {
+            let long_enough = matching::array_fits(value, elements.len(), rest.is_some());
+            let array = match &**value {
+                Object::Array(array) if long_enough => array,
+                _ => return Ok(false),
+            };
+            for (element, value) in elements.iter().zip(array.iter()) {
+                if !fits(element, value, bindings, env)? {
+                    return Ok(false);
+                }
+            }
+            if let Some(rest) = rest.as_ref().filter(|r| !r.is_throwaway()) {
+                let rest_value = matching::array_rest(value, elements.len());
+                bindings.push((rest.name.clone(), rest_value));
+            }
+            return Ok(true);
+        }
+        Pattern::Hash { entries, .. } => {
+            for (key, pattern) in entries.iter() {
+                let key = eval_literal(key, env)?;
+                if !matching::hash_has(value, &key) {
+                    return Ok(false);
+                }
+                let entry = eval_index_expression(value, &key)?;
+                if !fits(pattern, &entry, bindings, env)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
     }
 }
 
@@ -103,44 +248,119 @@ fn eval_index_expression(left: &Rc<Object>, index: &Rc<Object>) -> Result<Rc<Obj
     match (&**left, &**index) {
         (Object::Array(arr), Object::Integer(idx)) => match arr.get(*idx as usize) {
             Some(obj) => return Ok(Rc::clone(obj)),
-            None => return Ok(Rc::new(Object::Null)),
+            None => return Ok(singleton::null()),
         },
         (Object::Hash(map), key) => {
             if !(key.is_hashable()) {
-                return Err(format!("not a valid hash key"));
+                return Err("not a valid hash key".to_string());
             }
 
             match map.get(key) {
                 Some(obj) => return Ok(Rc::clone(obj)),
-                None => return Ok(Rc::new(Object::Null)),
+                None => return Ok(singleton::null()),
             }
         }
         _ => return Err(format!("index operator not supported for {}", left)),
     }
 }
 
-fn apply_function(function: &Rc<Object>, args: &Vec<Rc<Object>>) -> Result<Rc<Object>, EvalError> {
+pub fn apply_function(function: &Rc<Object>, args: &[Rc<Object>]) -> Result<Rc<Object>, EvalError> {
     match &**function {
-        Object::Function(params, body, env) => {
-            let mut env = Environment::new_enclosed_environment(&env);
+        Object::Function(params, body, env, rest) => {
+            if let Some(e) = arity_error(params.len(), *rest, args.len()) {
+                return Err(e);
+            }
+            spend(env)?;
+            let mut env = Environment::new_enclosed_environment(env);
 
-            params.iter().enumerate().for_each(|(i, param)| {
-                env.set(param.name.clone(), args[i].clone());
+            let named = params
+                .iter()
+                .zip(args.iter())
+                .filter(|(param, _)| !param.is_throwaway());
+            named.for_each(|(param, arg)| {
+                env.set(param.name.clone(), arg.clone());
             });
+            // the last one takes what's left, from where it is on
+            if let (true, Some(last)) = (*rest, params.last().filter(|p| !p.is_throwaway())) {
+                let others = args[params.len() - 1..].iter().cloned().collect();
+                env.set(last.name.clone(), Rc::new(Object::Array(others)));
+            }
 
-            let evaluated = eval_block_statements(&body.body, &Rc::new(RefCell::new(env)))?;
-            return unwrap_return(evaluated);
+            let budget = env.budget();
+            if let Some(budget) = &budget {
+                budget.enter()?;
+            }
+            let evaluated = eval_block_statements(&body.body, &Rc::new(RefCell::new(env)));
+            if let Some(budget) = &budget {
+                budget.leave();
+            }
+            return unwrap_return(evaluated?);
+        }
+        Object::Builtin(b) => call_builtin(*b, args.to_vec()),
+        Object::Memo(memo) => {
+            if let Some(result) = memo.recall(args) {
+                return Ok(result);
+            }
+            let result = apply_function(&memo.function, args)?;
+            memo.remember(args.to_vec(), &result);
+            return Ok(result);
+        }
+        Object::Partial(partial) => {
+            return apply_function(&partial.function, &partial.arguments(args)?)
         }
-        Object::Builtin(b) => Ok(b(args.to_vec())),
         f => Err(format!("expected {} to be a function", f)),
     }
 }
 
+// at each call and each turn of a loop, where a program that doesn't finish spends its time
+fn spend(env: &Env) -> Result<(), EvalError> {
+    interrupt::check()?;
+    if let Some(budget) = env.borrow().budget() {
+        budget.spend()?;
+    }
+    return Ok(());
+}
+
+// The value of `let q = f(q, ...)`, calling a builtin `f` with the value of `q` taken out of
+// `env`, so it can change it in place, see `object::deque`
+fn eval_handover(call: &FunctionCall, env: &Env) -> Result<Rc<Object>, EvalError> {
+    let callee = eval_expression(&call.callee, env)?;
+    let (builtin, name) = match (&*callee, &call.arguments[0]) {
+        (Object::Builtin(b), Expression::IDENTIFIER(id)) if !env.borrow().is_read_only() => (b, id),
+        _ => return apply_function(&callee, &eval_expressions(&call.arguments, env)?),
+    };
+    let taken = env.borrow_mut().take(&name.name);
+    let value = match taken {
+        Some(value) => value,
+        None => return apply_function(&callee, &eval_expressions(&call.arguments, env)?),
+    };
+    let mut args = vec![value];
+    for argument in &call.arguments[1..] {
+        match eval_expression(argument, env) {
+            Ok(arg) => args.push(arg),
+            Err(e) => {
+                env.borrow_mut().set(name.name.clone(), args.swap_remove(0));
+                return Err(e);
+            }
+        }
+    }
+    return call_builtin(*builtin, args);
+}
+
+// a builtin calling the functions it's passed, if it does, by `apply_function`
+fn call_builtin(builtin: BuiltinFunc, args: Vec<Rc<Object>>) -> Result<Rc<Object>, EvalError> {
+    match calling(builtin) {
+        Some(calling) => return calling(args, &mut |f, args| apply_function(f, &args)),
+        None => return Ok(builtin(args)),
+    }
+}
+
 fn unwrap_return(obj: Rc<Object>) -> Result<Rc<Object>, EvalError> {
-    if let Object::ReturnValue(val) = &*obj {
-        Ok(Rc::clone(&val))
-    } else {
-        Ok(obj)
+    match &*obj {
+        Object::ReturnValue(val) => Ok(Rc::clone(val)),
+        // a function body is no loop
+        Object::Break(b) => Err(b.misplaced()),
+        _ => Ok(obj),
     }
 }
 
@@ -154,12 +374,12 @@ fn eval_expressions(exprs: &Vec<Expression>, env: &Env) -> Result<Vec<Rc<Object>
     Ok(list)
 }
 
-fn eval_identifier(identifier: &str, env: &Env) -> Result<Rc<Object>, EvalError> {
-    match env.borrow().get(identifier) {
+fn eval_identifier(identifier: &IDENTIFIER, env: &Env) -> Result<Rc<Object>, EvalError> {
+    match env.borrow().get(&identifier.name) {
         Some(obj) => Ok(obj.clone()),
-        None => match BuiltIns.iter().find(|&&b| b.0 == identifier) {
+        None => match BuiltIns.iter().find(|&&b| b.0 == identifier.name) {
             Some(obj) => Ok(Rc::new(Object::Builtin(obj.1))),
-            None => Err(format!("unknown identifier {}", identifier)),
+            None => Err(strict::unknown_identifier(identifier, &env.borrow().names())),
         },
     }
 }
@@ -173,83 +393,72 @@ fn eval_prefix(op: &Token, right: &Object) -> Result<Rc<Object>, EvalError> {
 }
 
 fn eval_prefix_bang(expr: &Object) -> Result<Rc<Object>, EvalError> {
-    match *expr {
-        Object::Null => Ok(Rc::new(Object::Boolean(true))),
-        Object::Boolean(b) => Ok(Rc::new(Object::Boolean(!b))),
-        _ => Ok(Rc::new(Object::Boolean(false))),
-    }
+    return Ok(singleton::boolean(!expr.is_truthy()));
 }
 
 fn eval_prefix_minus(expr: &Object) -> Result<Rc<Object>, EvalError> {
-    match *expr {
-        Object::Integer(i) => Ok(Rc::from(Object::Integer(-i))),
-        _ => Err(format!("can't apply prefix minus operator: {}", expr)),
+    if !number::is_number(expr) {
+        return Err(number::unsupported_negation(expr));
     }
+    return Ok(singleton::share(number::negate(expr)?));
 }
 
 fn eval_infix(op: &Token, left: &Object, right: &Object) -> Result<Rc<Object>, EvalError> {
+    let ordered = match op.kind {
+        TokenKind::LT => Some(order::compare(left, right)? == Ordering::Less),
+        TokenKind::GT => Some(order::compare(left, right)? == Ordering::Greater),
+        _ => None,
+    };
+    if let Some(ordered) = ordered {
+        return Ok(singleton::boolean(ordered));
+    }
+    // any two values, see `object::equality`
+    match op.kind {
+        TokenKind::EQ => return Ok(singleton::boolean(left == right)),
+        TokenKind::NotEq => return Ok(singleton::boolean(left != right)),
+        _ => {}
+    }
+    let op = arithmetic_of(op)?;
     match (left, right) {
-        (Object::Integer(left), Object::Integer(right)) => {
-            return eval_integer_infix(op, *left, *right);
+        (l, r) if number::is_number(l) && number::is_number(r) => {
+            return Ok(singleton::share(number::arithmetic(op, l, r)?));
         }
-        (Object::Boolean(left), Object::Boolean(right)) => {
-            return eval_boolean_infix(op, *left, *right);
+        (Object::String(l), Object::String(r)) if op == Arithmetic::Add => {
+            return Ok(Rc::from(Object::String([l.as_str(), r.as_str()].concat())));
         }
-        (Object::String(left), Object::String(right)) => {
-            return eval_string_infix(op, left.to_string(), right.to_string());
+        (Object::String(_), Object::Char(_)) | (Object::Char(_), Object::String(_))
+            if op == Arithmetic::Add =>
+        {
+            return Ok(Rc::new(Object::String(chars::join(left, right).unwrap())));
         }
-        _ => Err(format!("eval infix error for op: {}, left: {}, right: {}", op, left, right)),
+        _ => return Err(number::unsupported(op, left, right)),
     }
 }
 
-fn eval_integer_infix(op: &Token, left: i64, right: i64) -> Result<Rc<Object>, EvalError> {
-    let result = match &op.kind {
-        TokenKind::PLUS => Object::Integer(left + right),
-        TokenKind::MINUS => Object::Integer(left - right),
-        TokenKind::ASTERISK => Object::Integer(left * right),
-        TokenKind::SLASH => Object::Integer(left / right),
-        TokenKind::LT => Object::Boolean(left < right),
-        TokenKind::GT => Object::Boolean(left > right),
-        TokenKind::EQ => Object::Boolean(left == right),
-        TokenKind::NotEq => Object::Boolean(left != right),
-        op => return Err(format!("Invalid infix operator {} for int", op)),
-    };
-
-    Ok(Rc::from(result))
-}
-
-fn eval_boolean_infix(op: &Token, left: bool, right: bool) -> Result<Rc<Object>, EvalError> {
-    let result = match &op.kind {
-        TokenKind::EQ => Object::Boolean(left == right),
-        TokenKind::NotEq => Object::Boolean(left != right),
-        op => return Err(format!("Invalid infix operator for int: {}", op)),
-    };
-
-    Ok(Rc::from(result))
-}
-
-fn eval_string_infix(op: &Token, left: String, right: String) -> Result<Rc<Object>, EvalError> {
-    let result = match &op.kind {
-        TokenKind::EQ => Object::Boolean(left == right),
-        TokenKind::NotEq => Object::Boolean(left != right),
-        TokenKind::PLUS => Object::String(format!("{}{}", left, right)),
-        op => return Err(format!("Invalid infix {} operator for string", op)),
-    };
-
-    Ok(Rc::from(result))
+fn arithmetic_of(op: &Token) -> Result<Arithmetic, EvalError> {
+    match &op.kind {
+        TokenKind::PLUS => return Ok(Arithmetic::Add),
+        TokenKind::MINUS => return Ok(Arithmetic::Sub),
+        TokenKind::ASTERISK => return Ok(Arithmetic::Mul),
+        TokenKind::SLASH => return Ok(Arithmetic::Div),
+        op => return Err(format!("unknown infix operator {}", op)),
+    }
 }
 
 fn eval_literal(literal: &Literal, env: &Env) -> Result<Rc<Object>, EvalError> {
     match literal {
-        Literal::Integer(Integer { raw: i, .. }) => Ok(Rc::from(Object::Integer(*i))),
-        Literal::Boolean(Boolean { raw: b, .. }) => Ok(Rc::from(Object::Boolean(*b))),
+        Literal::Integer(Integer { raw: i, .. }) => Ok(singleton::integer(*i)),
+        Literal::Decimal(DecimalType { raw: d, .. }) => Ok(Rc::from(decimal::parse(d)?)),
+        Literal::Boolean(Boolean { raw: b, .. }) => Ok(singleton::boolean(*b)),
         Literal::String(StringType { raw: s, .. }) => Ok(Rc::from(Object::String(s.clone()))),
+        Literal::Char(CharType { raw: c, .. }) => Ok(Rc::from(Object::Char(*c))),
         Literal::Array(Array { elements, .. }) => {
             let list = eval_expressions(elements, env)?;
-            return Ok(Rc::from(Object::Array(list)));
+            return Ok(Rc::from(Object::Array(list.into())));
         }
         Literal::Hash(Hash { elements: map, .. }) => {
             let mut hash_map = HashMap::new();
+            let mut twice = None;
 
             for (k, v) in map {
                 let key = eval_expression(k, env)?;
@@ -257,8 +466,15 @@ fn eval_literal(literal: &Literal, env: &Env) -> Result<Rc<Object>, EvalError> {
                     return Err(format!("key {} is not hashable", key));
                 }
                 let value = eval_expression(v, env)?;
+                if hash_map.contains_key(&key) {
+                    twice.get_or_insert(Rc::clone(&key));
+                }
                 hash_map.insert(key, value);
             }
+            // once every key and value is, as the VM finds it
+            if let Some(key) = twice {
+                return Err(format!("key {} is in the hash twice", key));
+            }
 
             return Ok(Rc::new(Object::Hash(hash_map)));
         } // l => return Err(format!("unknown literal: {}", *l))
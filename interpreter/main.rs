@@ -1,6 +1,7 @@
 use interpreter::eval;
 use object::environment::Env;
-use parser::parse;
+use object::interrupt;
+use parser::{is_quiet, parse};
 use std::cell::RefCell;
 use std::io::stdin;
 use std::rc::Rc;
@@ -8,6 +9,12 @@ use std::rc::Rc;
 fn main() {
     println!("Welcome to monkey interpreter by gengjiawen");
     let env: Env = Rc::new(RefCell::new(Default::default()));
+    ctrlc::set_handler(|| {
+        if !interrupt::request() {
+            std::process::exit(130);
+        }
+    })
+    .expect("can't handle Ctrl-C");
     loop {
         let mut input = String::new();
         stdin().read_line(&mut input).unwrap();
@@ -17,9 +24,20 @@ fn main() {
             std::process::exit(0)
         }
 
+        // `:strict off` lets a function read globals bound after it, see `Environment::set_lenient`
+        if let Some(command) = input.trim().strip_prefix(':') {
+            match command.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["strict", "on"] => env.borrow_mut().set_lenient(false),
+                ["strict", "off"] => env.borrow_mut().set_lenient(true),
+                _ => println!("unknown command :{}, try :strict on or :strict off", command),
+            }
+            continue;
+        }
+
         match parse(&input) {
-            Ok(node) => match eval(node, &env) {
-                Ok(evaluated) => println!("{}", evaluated),
+            Ok(node) => match interrupt::run(|| eval(node, &env)) {
+                Ok(evaluated) if !is_quiet(&input) => println!("{}", evaluated),
+                Ok(_) => {}
                 Err(e) => eprintln!("{}", e),
             },
             Err(e) => eprintln!("parse error: {}", e[0]),
@@ -0,0 +1,155 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use object::builtins::BuiltIns;
+use object::environment::Env;
+use object::suggest;
+use object::EvalError;
+use parser::ast::{Assign, Expression, Literal, Statement, IDENTIFIER};
+
+// strict mode, see `Environment::set_lenient`: every name read must be bound where it's read, as
+// the compiler resolves them
+pub fn check(statements: &[Statement], env: &Env) -> Result<(), EvalError> {
+    let globals = env.borrow().names();
+    let mut scopes = Scopes { scopes: vec![globals] };
+    return scopes.statements(statements);
+}
+
+pub fn check_expression(expression: &Expression, env: &Env) -> Result<(), EvalError> {
+    let globals = env.borrow().names();
+    let mut scopes = Scopes { scopes: vec![globals] };
+    return scopes.expression(expression);
+}
+
+// the error for a name that isn't bound, in strict mode or when it's evaluated
+pub fn unknown_identifier(identifier: &IDENTIFIER, names: &[String]) -> EvalError {
+    let builtins = BuiltIns.iter().map(|b| b.0);
+    let candidates = names.iter().map(String::as_str).chain(builtins);
+    let suggestion = suggest::did_you_mean(&identifier.name, candidates);
+    return identifier.unbound(&suggestion);
+}
+
+// the names bound in each function around the code being checked, globals first
+struct Scopes {
+    scopes: Vec<Vec<String>>,
+}
+
+impl Scopes {
+    fn define(&mut self, name: &str) {
+        self.scopes.last_mut().unwrap().push(name.to_string());
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Result<(), EvalError> {
+        for statement in statements.iter() {
+            match statement {
+                Statement::Let(l) => {
+                    self.define(&l.identifier.kind.to_string());
+                    self.expression(&l.expr)?;
+                }
+                Statement::Return(r) => self.expression(&r.argument)?,
+                Statement::Expr(e) => self.expression(e)?,
+                Statement::Import(_) => {}
+                Statement::While(w) => {
+                    self.expression(&w.condition)?;
+                    self.statements(&w.body.body)?;
+                }
+                // that it has its loop was checked when it was parsed
+                Statement::Break(_) => {}
+            }
+        }
+        return Ok(());
+    }
+
+    fn assignable(&self, a: &Assign) -> Result<(), EvalError> {
+        let name = &a.name.name;
+        let (globals, enclosing) = self.scopes.split_first().unwrap();
+        let (current, enclosing) = enclosing.split_last().unwrap_or((globals, &[]));
+        if current.contains(name) {
+            return Ok(());
+        }
+        if enclosing.iter().any(|s| s.contains(name)) {
+            return Err(a.enclosing());
+        }
+        if globals.contains(name) {
+            return Ok(());
+        }
+        if BuiltIns.iter().any(|b| b.0 == *name) {
+            return Err(a.builtin());
+        }
+        return Err(a.unbound());
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<(), EvalError> {
+        match expression {
+            Expression::IDENTIFIER(identifier) => {
+                let bound = self.scopes.iter().any(|s| s.contains(&identifier.name));
+                if bound || BuiltIns.iter().any(|b| b.0 == identifier.name) {
+                    return Ok(());
+                }
+                let names: Vec<String> = self.scopes.concat();
+                return Err(unknown_identifier(identifier, &names));
+            }
+            Expression::LITERAL(Literal::Array(a)) => {
+                for e in a.elements.iter() {
+                    self.expression(e)?;
+                }
+            }
+            Expression::LITERAL(Literal::Hash(h)) => {
+                for (k, v) in h.elements.iter() {
+                    self.expression(k)?;
+                    self.expression(v)?;
+                }
+            }
+            Expression::LITERAL(_) => {}
+            Expression::PREFIX(p) => self.expression(&p.operand)?,
+            Expression::INFIX(i) => {
+                self.expression(&i.left)?;
+                self.expression(&i.right)?;
+            }
+            Expression::IF(i) => {
+                self.expression(&i.condition)?;
+                self.statements(&i.consequent.body)?;
+                if let Some(alternate) = &i.alternate {
+                    self.statements(&alternate.body)?;
+                }
+            }
+            Expression::FUNCTION(f) => {
+                let mut locals: Vec<String> = f.params.iter().map(|p| p.name.clone()).collect();
+                locals.push(f.name.clone());
+                self.scopes.push(locals);
+                let checked = self.statements(&f.body.body);
+                self.scopes.pop();
+                checked?;
+            }
+            Expression::FunctionCall(c) => {
+                self.expression(&c.callee)?;
+                for a in c.arguments.iter() {
+                    self.expression(a)?;
+                }
+            }
+            Expression::Index(i) => {
+                self.expression(&i.object)?;
+                self.expression(&i.index)?;
+            }
+            Expression::Match(m) => {
+                self.expression(&m.subject)?;
+                for arm in m.arms.iter() {
+                    for name in arm.pattern.names() {
+                        self.define(&name.name);
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard)?;
+                    }
+                    self.expression(&arm.body)?;
+                }
+            }
+            // the name first, as the compiler does, so `q += 1` is about assigning `q`
+            Expression::Assign(a) => {
+                self.assignable(a)?;
+                self.expression(&a.value)?;
+            }
+        }
+        return Ok(());
+    }
+}
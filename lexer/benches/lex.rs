@@ -0,0 +1,32 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use lexer::token::TokenKind;
+use lexer::Lexer;
+
+// a source of about 1MB, keywords and names as a long script would have them
+fn source() -> String {
+    let chunk = "
+let handler = fn(request, config) {
+    // a comment for the trivia to skip
+    let name = request[\"name\"];
+    if (name == \"index\") { return config[\"limit\"] + 10; } else { len(name) * 2 }
+    for (let i = 0; i < 10; i = i + 1) { if (i > 5) { break; } }
+    while (true) { match (name) { \"a\" => 1, _ => 2 }; break; }
+    for (item in items) { let total = total + item; }
+};
+";
+    return chunk.repeat((1 << 20) / chunk.len());
+}
+
+fn bench_lex(c: &mut Criterion) {
+    let source = source();
+    c.bench_function("lex 1MB", |b| {
+        b.iter(|| {
+            let mut lexer = Lexer::new(&source);
+            while lexer.next_token().kind != TokenKind::EOF {}
+        })
+    });
+}
+
+criterion_group!(benches, bench_lex);
+criterion_main!(benches);
@@ -3,6 +3,7 @@ mod tests {
     use crate::token::{Token, TokenKind};
     use crate::Lexer;
     use insta::*;
+    use std::rc::Rc;
 
     fn test_token_set(l: &mut Lexer) -> Vec<Token> {
         let mut token_vs: Vec<Token> = vec![];
@@ -30,6 +31,11 @@ mod tests {
         test_lexer_common("simple", "=+(){},:;");
     }
 
+    #[test]
+    fn test_lexer_compound_assignment() {
+        test_lexer_common("compound_assignment", "x += 1; x -= 1; x *= 2; x /= 2; x + = 1");
+    }
+
     #[test]
     fn test_lexer_let() {
         test_lexer_common("let", "let x=5");
@@ -40,6 +46,11 @@ mod tests {
         test_lexer_common("comments", "// I am comments");
     }
 
+    #[test]
+    fn test_comment_lines() {
+        test_lexer_common("comment_lines", "// one\n//\n  // three\nlet x = 1; // four\n1 / 2");
+    }
+
     #[test]
     fn test_lexer_let_with_space() {
         test_lexer_common("let_with_space", "let x = 5");
@@ -50,6 +61,217 @@ mod tests {
         test_lexer_common("string", r#""a""#);
     }
 
+    #[test]
+    fn test_lexer_string_escapes() {
+        test_lexer_common("string_escapes", r#""a\nb\t\"c\"\\" "\u{e9}" "\q" 1"#);
+    }
+
+    #[test]
+    fn test_lexer_unicode() {
+        test_lexer_common("unicode", "let s = \"h\u{e9}llo \u{1F600}\";\ns \u{20AC} 1");
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut l = Lexer::new("1 @ \"\\q\" 0x_1 # 2");
+        let tokens = test_token_set(&mut l);
+        assert_eq!(tokens.len(), 7);
+        let diagnostics: Vec<String> = l
+            .diagnostics()
+            .iter()
+            .filter_map(|t| t.diagnostic())
+            .collect();
+        let expected = [
+            "illegal token @ at line 1, column 3",
+            r#"unknown escape \q in string "\q" at line 1, column 5"#,
+            "leading `_` in number 0x_1 at line 1, column 10",
+            "illegal token # at line 1, column 15",
+        ];
+        assert_eq!(diagnostics, expected);
+        match &l.diagnostics()[1].kind {
+            TokenKind::Illegal { ch: 'q', span, .. } => assert_eq!((span.start, span.end), (6, 7)),
+            kind => panic!("unexpected token {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_symbols_shared() {
+        let mut l = Lexer::new("let x = \"a\"; x + \"a\"");
+        let tokens = test_token_set(&mut l);
+        match (&tokens[1].kind, &tokens[5].kind, &tokens[3].kind, &tokens[7].kind) {
+            (
+                TokenKind::IDENTIFIER { name: a },
+                TokenKind::IDENTIFIER { name: b },
+                TokenKind::STRING(c),
+                TokenKind::STRING(d),
+            ) => assert!(Rc::ptr_eq(a, b) && Rc::ptr_eq(c, d)),
+            kinds => panic!("unexpected tokens {:?}", kinds),
+        }
+    }
+
+    #[test]
+    fn test_shebang() {
+        let mut l = Lexer::new("#!/usr/bin/env -S monkey run\nlet x = 1;\n#!x");
+        let tokens = test_token_set(&mut l);
+        assert_eq!(tokens[0].kind, TokenKind::LET);
+        assert_eq!((tokens[0].position.line, tokens[0].position.column), (2, 1));
+        let diagnostics: Vec<String> = l
+            .diagnostics()
+            .iter()
+            .filter_map(|t| t.diagnostic())
+            .collect();
+        assert_eq!(diagnostics, ["illegal token # at line 3, column 1"]);
+        let mut l = Lexer::new(" #!x");
+        assert!(matches!(l.next_token().kind, TokenKind::Illegal { ch: '#', .. }));
+    }
+
+    #[test]
+    fn test_trivia() {
+        let input = "#!/bin/monkey\nlet x = 1; // one\n  // two\n\nx // end";
+        let mut l = Lexer::with_trivia(input);
+        let mut leading = vec![];
+        loop {
+            let token = l.next_token();
+            let trivia: Vec<String> = l
+                .trivia()
+                .iter()
+                .map(|t| format!("{:?} {:?}", t.kind, &input[t.span.start..t.span.end]))
+                .collect();
+            leading.push((token.kind.to_string(), trivia.join(", ")));
+            if token.kind == TokenKind::EOF {
+                break;
+            }
+        }
+        let expected = [
+            ("let", r##"Comment "#!/bin/monkey", Whitespace "\n""##),
+            ("x", r#"Whitespace " ""#),
+            ("=", r#"Whitespace " ""#),
+            ("1", r#"Whitespace " ""#),
+            (";", ""),
+            (
+                "x",
+                r#"Whitespace " ", Comment "// one", Whitespace "\n  ", Comment "// two", Whitespace "\n\n""#,
+            ),
+            ("EOF", r#"Whitespace " ", Comment "// end""#),
+        ];
+        let expected: Vec<(String, String)> = expected
+            .iter()
+            .map(|(k, t)| (k.to_string(), t.to_string()))
+            .collect();
+        assert_eq!(leading, expected);
+        let mut l = Lexer::new(input);
+        l.next_token();
+        assert!(l.trivia().is_empty());
+    }
+
+    #[test]
+    fn test_char_literals() {
+        let mut l = Lexer::new(r#"'a' '\n' '\'' '"' '\u{e9}' '' 'ab' '\q' 'x"#);
+        let chars: Vec<char> = test_token_set(&mut l)
+            .iter()
+            .filter_map(|t| match t.kind {
+                TokenKind::CHAR(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(chars, ['a', '\n', '\'', '"', '\u{e9}']);
+        let diagnostics: Vec<String> = l
+            .diagnostics()
+            .iter()
+            .filter_map(|t| t.diagnostic())
+            .collect();
+        let expected = [
+            "a char can't be empty at line 1, column 28",
+            "a char is one char, got 'ab' at line 1, column 31",
+            r"unknown escape \q in char '\q' at line 1, column 36",
+            "a char needs a `'` to end it, got 'x at line 1, column 41",
+        ];
+        assert_eq!(diagnostics, expected);
+        assert_eq!(TokenKind::CHAR('\'').to_string(), r"'\''");
+    }
+
+    #[test]
+    fn test_loop_keywords() {
+        let mut l = Lexer::new("while for break continue in whiles fore in_ inner _in continued");
+        let kinds: Vec<String> = test_token_set(&mut l)
+            .iter()
+            .filter(|t| t.kind != TokenKind::EOF)
+            .map(|t| match &t.kind {
+                TokenKind::IDENTIFIER { name } => format!("identifier {}", name),
+                kind => format!("keyword {}", kind),
+            })
+            .collect();
+        let expected = [
+            "keyword while",
+            "keyword for",
+            "keyword break",
+            "keyword continue",
+            "keyword in",
+            "identifier whiles",
+            "identifier fore",
+            "identifier in_",
+            "identifier inner",
+            "identifier _in",
+            "identifier continued",
+        ];
+        assert_eq!(kinds, expected);
+    }
+
+    #[test]
+    fn test_text_block() {
+        let input = "\"\"\"one\"two\" \\n ${x}\"\"\"
+            let s = \"\"\"
+                a
+                  b
+                \"\"\";
+            \"\"\"\n  c\n\"\"\" \"\"\"\"\"\" \"\"\"never ends";
+        let mut l = Lexer::new(input);
+        let strings: Vec<String> = test_token_set(&mut l)
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKind::STRING(s) => Some(s.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(strings, ["one\"two\" \\n ${x}", "a\n  b", "  c", ""]);
+        let diagnostics: Vec<String> = l
+            .diagnostics()
+            .iter()
+            .filter_map(|t| t.diagnostic())
+            .collect();
+        assert_eq!(diagnostics, [r#"a """ string needs a """ to end it at line 8, column 12"#]);
+    }
+
+    #[test]
+    fn test_lexer_interpolation() {
+        test_lexer_common("interpolation", r#""a ${b} ${ {"c": "${d}"}["c"] }!" "\${e}" }"#);
+    }
+
+    #[test]
+    fn test_lexer_decimal() {
+        test_lexer_common("decimal", "1.50d 2d 3.5");
+    }
+
+    #[test]
+    fn test_lexer_float() {
+        test_lexer_common("float", "3.14 0.5 1. 1.2.3 2.5d");
+    }
+
+    #[test]
+    fn test_lexer_exponent() {
+        test_lexer_common("exponent", "1e6 2.5e-3 1E+2 1e1_0 1e 1e+ 1e-) 1else 1_e5 1.e5 0x1e5");
+    }
+
+    #[test]
+    fn test_lexer_radix() {
+        test_lexer_common("radix", "0xFF 0x1f 0o77 0b1010 0 0x 0b102 0xG 0x8000000000000001");
+    }
+
+    #[test]
+    fn test_lexer_separators() {
+        test_lexer_common("separators", "1_000_000 0xFF_FF 1_000.5 1_0.0_5d 1_ 1__0 1_.5 0x_1 1_d");
+    }
+
     #[test]
     fn test_lexer_array() {
         test_lexer_common("array", "[3]");
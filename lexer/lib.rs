@@ -1,59 +1,158 @@
-use crate::token::{lookup_identifier, Span, Token, TokenKind};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::token::{keyword, Position, Span, Symbol, Token, TokenKind, Trivia, TriviaKind};
 
 mod lexer_test;
 pub mod token;
 
 pub struct Lexer<'a> {
     input: &'a str,
+    // byte offsets of `ch` and of the char after it
     position: usize,
     read_position: usize,
     ch: char,
+    // the line of `ch` and its column, counted in chars
+    line: usize,
+    column: usize,
+    // for each `${` of a string being read, how many `{` are open in it, innermost last
+    interpolations: Vec<usize>,
+    // the names and strings read so far, each token with the same text shares one, see `intern`
+    symbols: BTreeSet<Symbol>,
+    // the `Illegal` tokens read so far, see `diagnostics`
+    diagnostics: Vec<Token>,
+    // where the problem of an `Illegal` token just read is, when that's not where it starts
+    problem_at: Option<Position>,
+    // whether to keep the trivia in front of each token, see `with_trivia`, and where the trivia
+    // in front of the next one starts
+    keep_trivia: bool,
+    trivia_start: usize,
+    trivia: Vec<Trivia>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
-        let mut l = Lexer { input, position: 0, read_position: 0, ch: 0 as char };
+        let mut l = Lexer {
+            input,
+            position: 0,
+            read_position: 0,
+            ch: 0 as char,
+            line: 1,
+            column: 1,
+            interpolations: Vec::new(),
+            symbols: BTreeSet::new(),
+            diagnostics: Vec::new(),
+            problem_at: None,
+            keep_trivia: false,
+            trivia_start: 0,
+            trivia: Vec::new(),
+        };
 
         l.read_char();
+        // a `#!` line, for a script to run as a program on unix, is no monkey
+        if input.starts_with("#!") {
+            while l.ch != '\n' && l.ch != '\u{0}' {
+                l.read_char();
+            }
+        }
         return l;
     }
 
+    // a lexer keeping the whitespace and comments in front of each token, for a formatter
+    pub fn with_trivia(input: &'a str) -> Self {
+        let mut l = Lexer::new(input);
+        l.keep_trivia = true;
+        return l;
+    }
+
+    // the trivia in front of the token `next_token` returned last, in order, none unless the
+    // lexer is `with_trivia`; in front of `EOF` is what ends the source
+    pub fn trivia(&self) -> &[Trivia] {
+        return &self.trivia;
+    }
+
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = 0 as char
-        } else {
-            if let Some(ch) = self.input.chars().nth(self.read_position) {
-                self.ch = ch;
-            } else {
-                panic!("read out of range")
-            }
+        if self.ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if self.read_position > 0 {
+            self.column += 1;
         }
-
         self.position = self.read_position;
-        self.read_position += 1;
+        self.ch = self.char_at(self.read_position);
+        self.read_position += self.ch.len_utf8();
     }
 
     fn peek_char(&self) -> char {
-        if self.read_position >= self.input.len() {
-            0 as char
-        } else {
-            if let Some(ch) = self.input.chars().nth(self.read_position) {
-                ch
-            } else {
-                panic!("read out of range")
-            }
+        return self.char_at(self.read_position);
+    }
+
+    // the char starting at byte `offset`, `\0` past the end
+    fn char_at(&self, offset: usize) -> char {
+        let rest = self.input.get(offset..).unwrap_or("");
+        return rest.chars().next().unwrap_or('\u{0}');
+    }
+
+    // the one `Symbol` for `text`, so the parser cloning a token copies no text
+    fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(text) {
+            return Rc::clone(symbol);
         }
+        let symbol: Symbol = Rc::from(text);
+        self.symbols.insert(Rc::clone(&symbol));
+        return symbol;
     }
 
     pub fn next_token(&mut self) -> Token {
         // println!("self ch {}, position {} read_position {}", self.ch, self.position, self.read_position);
         self.skip_whitespace();
         self.skip_comments();
+        if self.keep_trivia {
+            self.trivia = split_trivia(self.input, self.trivia_start, self.position);
+        }
+        let position = Position { line: self.line, column: self.column };
+        let (span, kind) = self.read_token();
+        self.trivia_start = self.position;
+        let position = self.problem_at.take().unwrap_or(position);
+        let token = Token { kind, span, position };
+        if let TokenKind::Illegal { .. } = token.kind {
+            self.diagnostics.push(token.clone());
+        }
+        return token;
+    }
+
+    // every problem in the source read so far, as the `Illegal` tokens it made, see
+    // `Token::diagnostic`
+    pub fn diagnostics(&self) -> &[Token] {
+        return &self.diagnostics;
+    }
+
+    // an `Illegal` token for the problem `reason` at the char at byte `at`
+    fn illegal(&self, at: usize, reason: String) -> TokenKind {
+        let ch = self.char_at(at);
+        return TokenKind::Illegal {
+            ch,
+            span: Span { start: at, end: at + ch.len_utf8() },
+            reason,
+        };
+    }
+
+    fn read_token(&mut self) -> (Span, TokenKind) {
         let t = match self.ch {
             '=' => {
                 if self.peek_char() == '=' {
                     self.read_char();
                     TokenKind::EQ
+                } else if self.peek_char() == '>' {
+                    self.read_char();
+                    TokenKind::Arrow
                 } else {
                     TokenKind::ASSIGN
                 }
@@ -62,8 +161,8 @@ impl<'a> Lexer<'a> {
             '(' => TokenKind::LPAREN,
             ')' => TokenKind::RPAREN,
             ',' => TokenKind::COMMA,
-            '+' => TokenKind::PLUS,
-            '-' => TokenKind::MINUS,
+            '+' => self.or_assign(TokenKind::PLUS, TokenKind::PlusAssign),
+            '-' => self.or_assign(TokenKind::MINUS, TokenKind::MinusAssign),
             '!' => {
                 if self.peek_char() == '=' {
                     self.read_char();
@@ -72,41 +171,93 @@ impl<'a> Lexer<'a> {
                     TokenKind::BANG
                 }
             }
-            '*' => TokenKind::ASTERISK,
-            '/' => TokenKind::SLASH,
+            '*' => self.or_assign(TokenKind::ASTERISK, TokenKind::AsteriskAssign),
+            '/' => self.or_assign(TokenKind::SLASH, TokenKind::SlashAssign),
             '<' => TokenKind::LT,
-            '>' => TokenKind::GT,
-            '{' => TokenKind::LBRACE,
-            '}' => TokenKind::RBRACE,
+            '>' => {
+                if self.peek_char() == '>' {
+                    self.read_char();
+                    TokenKind::Compose
+                } else {
+                    TokenKind::GT
+                }
+            }
+            '{' => {
+                if let Some(open) = self.interpolations.last_mut() {
+                    *open += 1;
+                }
+                TokenKind::LBRACE
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    return self.read_string(false);
+                }
+                Some(open) => {
+                    *open -= 1;
+                    TokenKind::RBRACE
+                }
+                None => TokenKind::RBRACE,
+            },
             '[' => TokenKind::LBRACKET,
             ':' => TokenKind::COLON,
+            '?' => TokenKind::Question,
+            '.' if self.input[self.position..].starts_with("...") => {
+                self.read_char();
+                self.read_char();
+                TokenKind::Ellipsis
+            }
+            '.' => TokenKind::Dot,
             ']' => TokenKind::RBRACKET,
             '\u{0}' => TokenKind::EOF,
-            '"' => {
-                let (start, end, string) = self.read_string();
-                return Token { span: Span { start, end }, kind: TokenKind::STRING(string) };
+            '"' if self.input[self.position..].starts_with("\"\"\"") => {
+                return self.read_text_block()
             }
+            '"' => return self.read_string(true),
+            '\'' => return self.read_char_literal(),
             _ => {
                 if is_letter(self.ch) {
-                    let (start, end, identifier) = self.read_identifier();
-                    return Token {
-                        span: Span { start, end },
-                        kind: lookup_identifier(&identifier),
-                    };
+                    let (start, end) = self.read_identifier();
+                    let input = self.input;
+                    if let Some(keyword) = keyword(&input[start..end]) {
+                        return (Span { start, end }, keyword);
+                    }
+                    let name = self.intern(&input[start..end]);
+                    return (Span { start, end }, TokenKind::IDENTIFIER { name });
                 } else if is_digit(self.ch) {
-                    let (start, end, num) = self.read_number();
-                    return Token { span: Span { start, end }, kind: TokenKind::INT(num) };
+                    if let Some(token) = self.read_radix_number() {
+                        return token;
+                    }
+                    if let Some((start, end, d)) = self.read_decimal() {
+                        let kind = match strip_separators(&d) {
+                            Ok(d) => TokenKind::DECIMAL(d),
+                            Err((at, e)) => self.illegal(start + at, self.in_number(start, &e)),
+                        };
+                        return (Span { start, end }, kind);
+                    }
+                    if let Some(token) = self.read_float() {
+                        return token;
+                    }
+                    return self.read_number();
                 } else {
-                    TokenKind::ILLEGAL
+                    self.illegal(self.position, format!("illegal token {}", self.ch))
                 }
             }
         };
 
+        // the span of a two char operator is its last char
+        let last = self.position;
         self.read_char();
-        return Token {
-            span: Span { start: self.position - 1, end: self.read_position - 1 },
-            kind: t,
-        };
+        return (Span { start: last, end: self.position }, t);
+    }
+
+    // `compound` when the operator is followed by `=`
+    fn or_assign(&mut self, operator: TokenKind, compound: TokenKind) -> TokenKind {
+        if self.peek_char() != '=' {
+            return operator;
+        }
+        self.read_char();
+        return compound;
     }
 
     fn skip_whitespace(&mut self) {
@@ -115,60 +266,418 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    // `//` to the end of the line and the whitespace after it, for as many lines as there are
     fn skip_comments(&mut self) {
-        if self.ch == '/' && self.peek_char() == '/' {
-            self.read_char();
-            self.read_char();
-            loop {
+        while self.ch == '/' && self.peek_char() == '/' {
+            while self.ch != '\n' && self.ch != '\u{0}' {
                 self.read_char();
-                if self.ch == '\n' || self.ch == '\u{0}' {
-                    // consume the comments end
-                    if self.ch == '\n' {
-                        self.read_char();
-                    }
-                    break;
-                }
             }
+            self.skip_whitespace();
         }
     }
 
-    fn read_identifier(&mut self) -> (usize, usize, String) {
+    fn read_identifier(&mut self) -> (usize, usize) {
         let pos = self.position;
         while is_letter(self.ch) {
             self.read_char();
         }
 
-        let x = self.input[pos..self.position].to_string();
-        return (pos, self.position, x);
+        return (pos, self.position);
+    }
+
+    fn read_number(&mut self) -> (Span, TokenKind) {
+        let pos = self.position;
+        while is_digit(self.ch) || self.ch == '_' {
+            self.read_char();
+        }
+
+        let span = Span { start: pos, end: self.position };
+        let kind = match strip_separators(&self.input[pos..self.position]) {
+            Ok(digits) => match magnitude(&digits, 10) {
+                Some(i) => TokenKind::INT(i),
+                // the only digits that don't parse are too many of them
+                None => self.illegal(pos, self.too_large(pos)),
+            },
+            Err((at, e)) => self.illegal(pos + at, self.in_number(pos, &e)),
+        };
+        return (span, kind);
+    }
+
+    // `0xFF`, `0o77` or `0b1010`. Everything alphanumeric after the prefix is part of the
+    // literal, so `0x`, `0b102`, `0x_1` and literals past `i64` are a single `Illegal` token.
+    fn read_radix_number(&mut self) -> Option<(Span, TokenKind)> {
+        let radix = match (self.ch, self.peek_char()) {
+            ('0', 'x') => 16,
+            ('0', 'o') => 8,
+            ('0', 'b') => 2,
+            _ => return None,
+        };
+        let pos = self.position;
+        self.read_char();
+        self.read_char();
+        while self.ch.is_ascii_alphanumeric() || self.ch == '_' {
+            self.read_char();
+        }
+
+        let span = Span { start: pos, end: self.position };
+        let text = &self.input[pos..self.position];
+        let kind = match strip_separators(text).map(|d| magnitude(&d[2..], radix)) {
+            Ok(Some(i)) => TokenKind::INT(i),
+            Err((at, e)) => self.illegal(pos + at, self.in_number(pos, &e)),
+            // at the first digit not of the radix
+            Ok(None) => {
+                let digits = text.char_indices().skip(2);
+                let mut bad = digits.filter(|&(_, c)| c != '_' && c.to_digit(radix).is_none());
+                match bad.next() {
+                    Some((i, _)) => self.illegal(pos + i, format!("illegal token {}", text)),
+                    None if text.len() > 2 => self.illegal(pos, self.too_large(pos)),
+                    None => self.illegal(self.position - 1, format!("illegal token {}", text)),
+                }
+            }
+        };
+        return Some((span, kind));
+    }
+
+    // the reason of an `Illegal` number from `start` to the char read, when it's past `i64`
+    fn too_large(&self, start: usize) -> String {
+        let number = &self.input[start..self.position];
+        return format!("{} doesn't fit in an integer, the largest is {}", number, i64::MAX);
+    }
+
+    // the reason of an `Illegal` number from `start` to the char read, for the problem `e`
+    fn in_number(&self, start: usize, e: &str) -> String {
+        return format!("{} in number {}", e, &self.input[start..self.position]);
+    }
+
+    // all of the source, for what's around a token
+    pub fn source(&self) -> &'a str {
+        return self.input;
+    }
+
+    // the source of a token, `0x` for the `Illegal` one it makes
+    pub fn text(&self, span: &Span) -> &'a str {
+        return &self.input[span.start..span.end];
     }
 
-    fn read_number(&mut self) -> (usize, usize, i64) {
+    // `12d` or `12.50d`. Without the suffix the digits are left to `read_float` and
+    // `read_number`.
+    fn read_decimal(&mut self) -> Option<(usize, usize, String)> {
+        let rest = &self.input.as_bytes()[self.position..];
+        let mut len = count_digits(rest);
+        if rest.get(len) == Some(&b'.') && count_digits(&rest[len + 1..]) > 0 {
+            len += 1 + count_digits(&rest[len + 1..]);
+        }
+        let suffixed = rest.get(len) == Some(&b'd');
+        if !suffixed || rest.get(len + 1).is_some_and(|&c| is_letter(c as char)) {
+            return None;
+        }
+
         let pos = self.position;
-        while is_digit(self.ch) {
+        for _ in 0..=len {
             self.read_char();
         }
+        let digits = self.input[pos..pos + len].to_string();
+        return Some((pos, self.position, digits));
+    }
 
-        let x = self.input[pos..self.position].parse().unwrap();
+    // `3.14`, `1e6` and `2.5e-3`; `1.` is `1` and a `.`, an exponent without digits `Illegal`
+    fn read_float(&mut self) -> Option<(Span, TokenKind)> {
+        let rest = &self.input.as_bytes()[self.position..];
+        let mut len = count_digits(rest);
+        let fraction = match rest.get(len) {
+            Some(b'.') => count_digits(&rest[len + 1..]),
+            _ => 0,
+        };
+        if fraction > 0 {
+            len += 1 + fraction;
+        }
+        let exponent = exponent(&rest[len..]);
+        if fraction == 0 && exponent.is_none() {
+            return None;
+        }
+        let (exponent_len, complete) = exponent.unwrap_or((0, true));
 
-        return (pos, self.position, x);
+        let pos = self.position;
+        for _ in 0..len + exponent_len {
+            self.read_char();
+        }
+        let span = Span { start: pos, end: self.position };
+        let kind = match strip_separators(&self.input[pos..self.position]) {
+            Ok(x) if complete => TokenKind::FLOAT(x),
+            Ok(_) => {
+                let reason = format!("missing exponent digits in number {}", self.text(&span));
+                self.illegal(self.position - 1, reason)
+            }
+            Err((at, e)) => self.illegal(pos + at, self.in_number(pos, &e)),
+        };
+        return Some((span, kind));
     }
 
-    fn read_string(&mut self) -> (usize, usize, String) {
+    // a string literal, its escapes decoded, from its opening `"` or the `}` closing an
+    // interpolation, see `TemplateHead`
+    fn read_string(&mut self, opening: bool) -> (Span, TokenKind) {
+        let start = Position { line: self.line, column: self.column };
         let pos = self.position + 1;
         loop {
             self.read_char();
-            if self.ch == '"' || self.ch == '\u{0}' {
+            if self.ch == '\\' && self.peek_char() != '\u{0}' {
+                self.read_char();
+                continue;
+            }
+            if self.ch == '"' || self.ch == '\u{0}' || (self.ch == '$' && self.peek_char() == '{') {
                 break;
             }
         }
 
-        let x = self.input[pos..self.position].to_string();
+        let raw = &self.input[pos..self.position];
+        let interpolated = self.ch == '$';
 
-        // consume the end "
-        if self.ch == '"' {
+        // consume the end " or the ${
+        if interpolated {
+            self.read_char();
+            self.interpolations.push(0);
+        }
+        if self.ch == '"' || interpolated {
             self.read_char();
         }
-        return (pos - 1, self.position, x);
+        let span = Span { start: pos - 1, end: self.position };
+        if opening && !interpolated && !raw.contains('\\') {
+            return (span, TokenKind::STRING(self.intern(raw)));
+        }
+        let text = match unescape(raw) {
+            Ok(x) => x,
+            Err((at, e)) if opening => {
+                let reason = format!("{} in string {}", e, self.text(&span));
+                return (span.clone(), self.illegal(pos + at, reason));
+            }
+            // the text from the `}` isn't the string, the escape is told by where it is
+            Err((at, e)) => {
+                let escape = pos + at - 1;
+                self.problem_at = Some(position_after(start, &self.input[span.start..escape]));
+                return (span, self.illegal(pos + at, format!("{} in string", e)));
+            }
+        };
+        match (opening, interpolated) {
+            (true, false) => return (span, TokenKind::STRING(self.intern(&text))),
+            (true, true) => return (span, TokenKind::TemplateHead(text)),
+            (false, true) => return (span, TokenKind::TemplateMiddle(text)),
+            (false, false) => return (span, TokenKind::TemplateTail(text)),
+        }
+    }
+
+    // `'a'`, `'\n'` or `'\''`, exactly one char between the quotes, on the same line
+    fn read_char_literal(&mut self) -> (Span, TokenKind) {
+        let start = self.position;
+        loop {
+            self.read_char();
+            if self.ch == '\\' && !matches!(self.peek_char(), '\n' | '\u{0}') {
+                self.read_char();
+                continue;
+            }
+            if matches!(self.ch, '\'' | '\n' | '\u{0}') {
+                break;
+            }
+        }
+        if self.ch != '\'' {
+            let span = Span { start, end: self.position };
+            let reason = format!("a char needs a `'` to end it, got {}", self.text(&span));
+            return (span, self.illegal(start, reason));
+        }
+        self.read_char();
+        let span = Span { start, end: self.position };
+        let raw = &self.input[start + 1..self.position - 1];
+        let text = match unescape(raw) {
+            Ok(text) => text,
+            Err((at, e)) => {
+                let reason = format!("{} in char {}", e, self.text(&span));
+                return (span.clone(), self.illegal(start + 1 + at, reason));
+            }
+        };
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => return (span, TokenKind::CHAR(c)),
+            (None, _) => return (span, self.illegal(start, "a char can't be empty".to_string())),
+            (Some(_), Some(_)) => {
+                let reason = format!("a char is one char, got {}", self.text(&span));
+                return (span, self.illegal(start, reason));
+            }
+        }
+    }
+
+    // a `"""` string, taken as is, less a leading line break and the indentation of the closing
+    // quotes when they start a line
+    fn read_text_block(&mut self) -> (Span, TokenKind) {
+        let start = self.position;
+        let pos = start + 3;
+        let end = match self.input[pos..].find("\"\"\"") {
+            Some(end) => pos + end,
+            None => {
+                while self.ch != '\u{0}' {
+                    self.read_char();
+                }
+                let reason = "a \"\"\" string needs a \"\"\" to end it".to_string();
+                return (Span { start, end: self.position }, self.illegal(start, reason));
+            }
+        };
+        while self.position < end + 3 {
+            self.read_char();
+        }
+        let span = Span { start, end: self.position };
+        let text = dedent(&self.input[pos..end]);
+        return (span, TokenKind::STRING(self.intern(&text)));
+    }
+}
+
+// the text of a `"""` string, see `read_text_block`
+fn dedent(raw: &str) -> String {
+    let raw = raw
+        .strip_prefix("\r\n")
+        .or(raw.strip_prefix('\n'))
+        .unwrap_or(raw);
+    let (body, indent) = match raw.rfind('\n') {
+        Some(last) if raw[last + 1..].chars().all(|c| c == ' ' || c == '\t') => {
+            (raw[..last].strip_suffix('\r').unwrap_or(&raw[..last]), &raw[last + 1..])
+        }
+        _ => return raw.to_string(),
+    };
+    let lines: Vec<&str> = body
+        .split('\n')
+        .map(|line| line.strip_prefix(indent).unwrap_or(line))
+        .collect();
+    return lines.join("\n");
+}
+
+// the trivia in `input` from byte `start` to `end`, what the lexer skipped there
+fn split_trivia(input: &str, start: usize, end: usize) -> Vec<Trivia> {
+    let mut trivia = Vec::new();
+    let mut at = start;
+    while at < end {
+        let rest = &input[at..end];
+        let comment = rest.starts_with("//") || (at == 0 && rest.starts_with("#!"));
+        let (kind, len) = match comment {
+            true => (TriviaKind::Comment, rest.find('\n').unwrap_or(rest.len())),
+            false => {
+                let len = rest.find(|c: char| !c.is_ascii_whitespace());
+                (TriviaKind::Whitespace, len.unwrap_or(rest.len()))
+            }
+        };
+        trivia.push(Trivia { kind, span: Span { start: at, end: at + len } });
+        at += len;
+    }
+    return trivia;
+}
+
+// where the source goes on after `text`, which starts at `start`
+fn position_after(start: Position, text: &str) -> Position {
+    return text.chars().fold(start, |at, c| match c {
+        '\n' => Position { line: at.line + 1, column: 1 },
+        _ => Position { line: at.line, column: at.column + 1 },
+    });
+}
+
+// the value of the digits of an integer literal in `radix`, up to the magnitude of `i64::MIN`,
+// see `TokenKind::INT`
+fn magnitude(digits: &str, radix: u32) -> Option<i64> {
+    match u64::from_str_radix(digits, radix) {
+        Ok(n) if n <= i64::MIN.unsigned_abs() => return Some(n as i64),
+        _ => return None,
+    }
+}
+
+// what is wrong with the text of a literal, and the byte of it where it is
+pub type Problem = (usize, String);
+
+// Decodes `\n`, `\t`, `\"`, `\'`, `\\`, `\$` and `\u{1F600}` in the text between the quotes of
+// a string or a char. A problem is at the char after the `\` of the escape.
+pub fn unescape(raw: &str) -> Result<String, Problem> {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut chars = raw.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            decoded.push(c);
+            continue;
+        }
+        let at = i + 1;
+        match chars.next().map(|(_, c)| c) {
+            Some('n') => decoded.push('\n'),
+            Some('t') => decoded.push('\t'),
+            Some('"') => decoded.push('"'),
+            Some('\'') => decoded.push('\''),
+            Some('\\') => decoded.push('\\'),
+            Some('$') => decoded.push('$'),
+            Some('u') => {
+                let rest = &raw[at + 1..];
+                let code = match (rest.starts_with('{'), rest.find('}')) {
+                    (true, Some(end)) => &rest[1..end],
+                    _ => return Err((at, "expected \\u{...}".to_string())),
+                };
+                let hex = code.len() <= 6 && code.chars().all(|c| c.is_ascii_hexdigit());
+                let c = u32::from_str_radix(code, 16).ok().and_then(char::from_u32);
+                match c {
+                    Some(c) if hex => decoded.push(c),
+                    _ => return Err((at, format!("invalid unicode escape \\u{{{}}}", code))),
+                }
+                // past the hex digits and their braces
+                chars.nth(code.len() + 1);
+            }
+            Some(c) => return Err((at, format!("unknown escape \\{}", c))),
+            None => return Err((i, "a string can't end with a lone \\".to_string())),
+        }
+    }
+    return Ok(decoded);
+}
+
+// `1_000_000` without its `_`s; a `_` not between two digits is an error at the `_`
+pub fn strip_separators(literal: &str) -> Result<String, Problem> {
+    let (prefix, is_digit): (_, fn(&u8) -> bool) = match literal.get(..2) {
+        Some("0x" | "0o" | "0b") => (2, u8::is_ascii_alphanumeric),
+        _ => (0, u8::is_ascii_digit),
+    };
+    let digits = &literal[prefix..];
+    let digits = digits
+        .strip_suffix('d')
+        .filter(|_| prefix == 0)
+        .unwrap_or(digits);
+    let bytes = digits.as_bytes();
+    for (i, &c) in bytes.iter().enumerate() {
+        if c != b'_' {
+            continue;
+        }
+        match (i.checked_sub(1).map(|i| bytes[i]), bytes.get(i + 1)) {
+            (Some(b'_'), _) => continue,
+            (_, Some(b'_')) => return Err((prefix + i, "repeated `_`".to_string())),
+            (Some(b), Some(a)) if is_digit(&b) && is_digit(a) => {}
+            (Some(b), _) if is_digit(&b) => return Err((prefix + i, "trailing `_`".to_string())),
+            _ => return Err((prefix + i, "leading `_`".to_string())),
+        }
+    }
+    return Ok(literal.replace('_', ""));
+}
+
+// The source of a string literal with the value `s`, the inverse of `unescape`.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' if chars.peek() == Some(&'{') => escaped.push_str("\\$"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+// `c` as it's written between the `'`s of a char literal
+pub fn escape_char(c: char) -> String {
+    match c {
+        '\'' => return "\\'".to_string(),
+        '"' => return "\"".to_string(),
+        c => return escape(&c.to_string()),
     }
 }
 
@@ -177,5 +686,27 @@ fn is_letter(c: char) -> bool {
 }
 
 fn is_digit(c: char) -> bool {
-    c >= '0' && c <= '9'
+    c.is_ascii_digit()
+}
+
+// The length of the exponent `bytes` start with, `e6` or `E-3`, and whether it has its digits.
+// `1e` and `1e+` have none, but in `1else` the `e` starts a name.
+fn exponent(bytes: &[u8]) -> Option<(usize, bool)> {
+    if !matches!(bytes.first(), Some(b'e' | b'E')) {
+        return None;
+    }
+    let sign = matches!(bytes.get(1), Some(b'+' | b'-')) as usize;
+    let digits = count_digits(&bytes[1 + sign..]);
+    if sign == 0 && digits == 0 && bytes.get(1).is_some_and(|&c| is_letter(c as char)) {
+        return None;
+    }
+    return Some((1 + sign + digits, digits > 0));
+}
+
+// digits and the `_`s between them
+fn count_digits(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .take_while(|&&c| c.is_ascii_digit() || c == b'_')
+        .count()
 }
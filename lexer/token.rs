@@ -1,11 +1,18 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use core::fmt;
+use core::fmt::Formatter;
 use serde::{Deserialize, Serialize};
-use std::fmt;
-use std::fmt::Formatter;
+
+// the text of a name or a string, one for each text a lexer reads, see `Lexer::intern`
+pub type Symbol = Rc<str>;
 
 #[derive(Clone, Debug, Eq, Hash, Ord, Serialize, Deserialize, PartialOrd, PartialEq)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
+    pub position: Position,
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, Serialize, Deserialize, PartialOrd, PartialEq)]
@@ -14,22 +21,84 @@ pub struct Span {
     pub end: usize,
 }
 
+// Where a token starts, for people: lines and columns count from 1. `Span` has the offsets.
+#[derive(
+    Clone, Copy, Debug, Default, Eq, Hash, Ord, Serialize, Deserialize, PartialOrd, PartialEq,
+)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+// What the lexer skips between tokens, kept only by `Lexer::with_trivia`: a run of whitespace,
+// a `//` comment without its line break, or the `#!` line the source starts with.
+#[derive(Clone, Debug, Eq, Hash, Serialize, Deserialize, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Serialize, Deserialize, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+}
+
+impl Token {
+    // the error for an `Illegal` token
+    pub fn diagnostic(&self) -> Option<String> {
+        match &self.kind {
+            TokenKind::Illegal { reason, .. } => {
+                return Some(format!("{} at {}", reason, self.position))
+            }
+            _ => return None,
+        }
+    }
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "start: {}, end: {}, kind: {}", self.span.start, self.span.end, self.kind)
+        write!(f, "`{}` at {}", self.kind, self.position)
     }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, Serialize, Deserialize, PartialOrd, PartialEq)]
 #[serde(tag = "type", content = "value")]
 pub enum TokenKind {
-    ILLEGAL,
+    // a problem in the source, at the char it's about, and why; the lexer goes on after it
+    Illegal {
+        ch: char,
+        span: Span,
+        reason: String,
+    },
     EOF,
 
     // Identifiers + literals
-    IDENTIFIER { name: String },
+    IDENTIFIER {
+        name: Symbol,
+    },
+    // `12`, what's after a `-` in front not included. `9223372036854775808`, one past `i64::MAX`,
+    // is `i64::MIN`, for the parser to take only as `-9223372036854775808`.
     INT(i64),
-    STRING(String),
+    // `1.50d`, the digits without the suffix
+    DECIMAL(String),
+    // `3.14`, as written: text like `DECIMAL` rather than an `f64`, which would cost tokens `Eq`,
+    // `Hash` and `Ord`
+    FLOAT(String),
+    STRING(Symbol),
+    // `'a'`, one char, escapes as in strings and `\'`
+    CHAR(char),
+    // `"hello ${name}, ${greeting}!"` is `TemplateHead("hello ")`, the tokens of `name`,
+    // `TemplateMiddle(", ")`, those of `greeting` and `TemplateTail("!")`
+    TemplateHead(String),
+    TemplateMiddle(String),
+    TemplateTail(String),
 
     // Operators
     ASSIGN,   // =
@@ -39,9 +108,28 @@ pub enum TokenKind {
     ASTERISK, // *
     SLASH,    // /
 
+    // `x += 1` is `let x = x + 1`, see the parser
+    PlusAssign,     // +=
+    MinusAssign,    // -=
+    AsteriskAssign, // *=
+    SlashAssign,    // /=
+
     LT, // <
     GT, // >
 
+    // `f >> g`, the function calling `g` with what `f` returns
+    Compose, // >>
+
+    // `c ? a : b`, the `if` of an expression
+    Question, // ?
+
+    // `h.name`, the value at the key "name" of a hash
+    Dot, // .
+
+    // `[x] if x > 0 => x` and `[first, ...rest]`, in the arms of a `match`
+    Arrow,    // =>
+    Ellipsis, // ...
+
     EQ,    // ==
     NotEq, // !=
 
@@ -65,35 +153,69 @@ pub enum TokenKind {
     IF,
     ELSE,
     RETURN,
+    IMPORT,
+    WHILE,
+    FOR,
+    BREAK,
+    CONTINUE,
+    IN,
+    MATCH,
 }
 
-pub fn lookup_identifier(identifier: &str) -> TokenKind {
-    match identifier {
-        "fn" => TokenKind::FUNCTION,
-        "let" => TokenKind::LET,
-        "true" => TokenKind::TRUE,
-        "false" => TokenKind::FALSE,
-        "if" => TokenKind::IF,
-        "else" => TokenKind::ELSE,
-        "return" => TokenKind::RETURN,
-        _ => TokenKind::IDENTIFIER { name: identifier.to_string() },
-    }
+pub fn lookup_identifier(identifier: Symbol) -> TokenKind {
+    return keyword(&identifier).unwrap_or(TokenKind::IDENTIFIER { name: identifier });
+}
+
+// the keyword `text` is, if it's one, matched on bytes so a keyword is never interned
+pub fn keyword(text: &str) -> Option<TokenKind> {
+    let kind = match text.as_bytes() {
+        b"fn" => TokenKind::FUNCTION,
+        b"let" => TokenKind::LET,
+        b"true" => TokenKind::TRUE,
+        b"false" => TokenKind::FALSE,
+        b"if" => TokenKind::IF,
+        b"else" => TokenKind::ELSE,
+        b"return" => TokenKind::RETURN,
+        b"import" => TokenKind::IMPORT,
+        b"while" => TokenKind::WHILE,
+        b"for" => TokenKind::FOR,
+        b"break" => TokenKind::BREAK,
+        b"continue" => TokenKind::CONTINUE,
+        b"in" => TokenKind::IN,
+        b"match" => TokenKind::MATCH,
+        _ => return None,
+    };
+    return Some(kind);
 }
 
 impl fmt::Display for TokenKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             TokenKind::IDENTIFIER { name } => write!(f, "{}", name),
-            TokenKind::INT(i) => write!(f, "{}", i),
+            TokenKind::INT(i) => write!(f, "{}", *i as u64),
+            TokenKind::DECIMAL(d) => write!(f, "{}d", d),
+            TokenKind::FLOAT(x) => write!(f, "{}", x),
             TokenKind::STRING(s) => write!(f, "{}", s),
+            TokenKind::CHAR(c) => write!(f, "'{}'", crate::escape_char(*c)),
+            TokenKind::TemplateHead(s) => write!(f, "\"{}${{", s),
+            TokenKind::TemplateMiddle(s) => write!(f, "}}{}${{", s),
+            TokenKind::TemplateTail(s) => write!(f, "}}{}\"", s),
             TokenKind::ASSIGN => write!(f, "="),
             TokenKind::PLUS => write!(f, "+"),
             TokenKind::MINUS => write!(f, "-"),
             TokenKind::BANG => write!(f, "!"),
             TokenKind::ASTERISK => write!(f, "*"),
             TokenKind::SLASH => write!(f, "/"),
+            TokenKind::PlusAssign => write!(f, "+="),
+            TokenKind::MinusAssign => write!(f, "-="),
+            TokenKind::AsteriskAssign => write!(f, "*="),
+            TokenKind::SlashAssign => write!(f, "/="),
             TokenKind::LT => write!(f, "<"),
             TokenKind::GT => write!(f, ">"),
+            TokenKind::Compose => write!(f, ">>"),
+            TokenKind::Dot => write!(f, "."),
+            TokenKind::Arrow => write!(f, "=>"),
+            TokenKind::Ellipsis => write!(f, "..."),
             TokenKind::EQ => write!(f, "=="),
             TokenKind::NotEq => write!(f, "!="),
             TokenKind::COMMA => write!(f, ","),
@@ -111,9 +233,17 @@ impl fmt::Display for TokenKind {
             TokenKind::IF => write!(f, "if"),
             TokenKind::ELSE => write!(f, "else"),
             TokenKind::RETURN => write!(f, "return"),
-            TokenKind::ILLEGAL => write!(f, "ILLEGAL"),
+            TokenKind::IMPORT => write!(f, "import"),
+            TokenKind::WHILE => write!(f, "while"),
+            TokenKind::FOR => write!(f, "for"),
+            TokenKind::BREAK => write!(f, "break"),
+            TokenKind::CONTINUE => write!(f, "continue"),
+            TokenKind::IN => write!(f, "in"),
+            TokenKind::MATCH => write!(f, "match"),
+            TokenKind::Illegal { ch, .. } => write!(f, "{}", ch),
             TokenKind::EOF => write!(f, "EOF"),
             TokenKind::COLON => write!(f, ":"),
+            TokenKind::Question => write!(f, "?"),
         }
     }
 }
@@ -0,0 +1,36 @@
+use std::path::PathBuf;
+
+use proc_macro::TokenStream;
+use proc_macro2::Literal;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+// `include_monkey!("scripts/rules.mk")` compiles the script at build time into a `.mkc` bundle,
+// the path relative to the host crate's `Cargo.toml`
+#[proc_macro]
+pub fn include_monkey(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as LitStr);
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let path = PathBuf::from(root).join(lit.value());
+
+    let embedded = match cli::embed::compile_file(&path) {
+        Ok(e) => e,
+        Err(e) => return syn::Error::new(lit.span(), e).to_compile_error().into(),
+    };
+
+    // `include_bytes!` on every source makes cargo rebuild the host when a script changes
+    let sources = embedded
+        .sources
+        .iter()
+        .map(|s| s.to_string_lossy().to_string());
+    let bytes = Literal::byte_string(&embedded.bytes);
+    let expanded = quote! {
+        {
+            #(const _: &[u8] = include_bytes!(#sources);)*
+            let bundle: &'static [u8] = #bytes;
+            bundle
+        }
+    };
+
+    expanded.into()
+}
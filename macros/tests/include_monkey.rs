@@ -0,0 +1,13 @@
+use compiler::bundle::decode;
+use compiler::vm::VM;
+use monkey_macros::include_monkey;
+use object::Object;
+
+static RULES: &[u8] = include_monkey!("tests/scripts/rules.mk");
+
+#[test]
+fn test_include_monkey() {
+    let mut vm = VM::new(decode(RULES).unwrap());
+    vm.run().unwrap();
+    assert_eq!(*vm.last_popped_stack_elm().unwrap(), Object::Integer(42));
+}
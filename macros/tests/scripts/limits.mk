@@ -0,0 +1 @@
+let max = 21;
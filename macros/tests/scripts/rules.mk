@@ -0,0 +1,4 @@
+import "limits";
+
+let double = fn(x) { x * 2 };
+double(max)
@@ -0,0 +1,257 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[cfg(feature = "vm")]
+use compiler::compiler::{Bytecode, Compiler};
+#[cfg(feature = "vm")]
+use compiler::op_code::Instructions;
+#[cfg(feature = "vm")]
+use compiler::symbol_table::{SymbolScope, SymbolTable};
+#[cfg(feature = "vm")]
+use compiler::vm::VM;
+#[cfg(feature = "eval")]
+use object::environment::Env;
+use object::Object;
+
+// Which engine an `Engine` runs scripts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    #[cfg(feature = "eval")]
+    Evaluator,
+    #[cfg(feature = "vm")]
+    Vm,
+}
+
+// A function a script exports: `let exports = {"handler": fn(req) { ... }};` exports `handler`.
+// A hash rather than new syntax, so the same script runs unchanged on both engines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub name: String,
+    // unknown for builtins, they check their arguments themselves, and for functions taking any
+    // number of arguments past the first few, with `...rest`
+    pub arity: Option<usize>,
+}
+
+// a session on one engine, keeping globals between runs for the host to set and read back as
+// `Object`s, see `object::convert`
+pub struct Engine {
+    state: State,
+}
+
+enum State {
+    #[cfg(feature = "eval")]
+    Evaluator(Env),
+    // what the repl keeps between inputs
+    #[cfg(feature = "vm")]
+    Vm {
+        symbol_table: SymbolTable,
+        constants: Vec<Rc<Object>>,
+        globals: Vec<Rc<Object>>,
+    },
+}
+
+impl Engine {
+    pub fn new(backend: Backend) -> Engine {
+        let state = match backend {
+            #[cfg(feature = "eval")]
+            Backend::Evaluator => State::Evaluator(Default::default()),
+            #[cfg(feature = "vm")]
+            Backend::Vm => State::Vm {
+                symbol_table: Compiler::new().symbol_table,
+                constants: Vec::new(),
+                globals: Vec::new(),
+            },
+        };
+        return Engine { state };
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self.state {
+            #[cfg(feature = "eval")]
+            State::Evaluator(_) => return Backend::Evaluator,
+            #[cfg(feature = "vm")]
+            State::Vm { .. } => return Backend::Vm,
+        }
+    }
+
+    // binds `name` for the scripts run from now on, hiding a builtin of the same name
+    pub fn set_global(&mut self, name: &str, value: impl Into<Object>) {
+        let value = Rc::new(value.into());
+        match &mut self.state {
+            #[cfg(feature = "eval")]
+            State::Evaluator(env) => env.borrow_mut().set(name.to_string(), value),
+            #[cfg(feature = "vm")]
+            State::Vm { symbol_table, globals, .. } => {
+                let symbol = symbol_table.define(name.to_string());
+                if globals.len() <= symbol.index {
+                    globals.resize(symbol.index + 1, Rc::new(Object::Null));
+                }
+                globals[symbol.index] = value;
+            }
+        }
+    }
+
+    // a global bound by the host or by a script, builtins aren't globals
+    pub fn get_global(&self, name: &str) -> Option<Rc<Object>> {
+        match &self.state {
+            #[cfg(feature = "eval")]
+            State::Evaluator(env) => return env.borrow().get(name),
+            #[cfg(feature = "vm")]
+            State::Vm { symbol_table, globals, .. } => match symbol_table.lookup(name) {
+                Some(s) if s.scope == SymbolScope::Global => return globals.get(s.index).cloned(),
+                _ => return None,
+            },
+        }
+    }
+
+    // Runs `source` to the value of its last expression statement. The globals it binds stay,
+    // those bound before a runtime error included.
+    pub fn run(&mut self, source: &str) -> Result<Rc<Object>, String> {
+        let program = parser::parse(source).map_err(|errors| parser::error::render(&errors))?;
+        match &mut self.state {
+            #[cfg(feature = "eval")]
+            State::Evaluator(env) => return interpreter::eval(program, env),
+            #[cfg(feature = "vm")]
+            State::Vm { symbol_table, constants, globals } => {
+                // compiled against copies, so a source that doesn't compile binds nothing
+                let mut compiler =
+                    Compiler::new_with_state(symbol_table.clone(), constants.clone());
+                compiler.set_source(source);
+                let compiled = compiler.compile(&program)?;
+                *symbol_table = compiler.symbol_table;
+                *constants = compiler.constants;
+
+                let mut vm = VM::new_with_global_store(compiled, core::mem::take(globals));
+                let result = vm.run();
+                *globals = core::mem::take(&mut vm.globals);
+                result?;
+                match vm.last_popped_stack_elm() {
+                    Some(value) => return Ok(value),
+                    None => return Ok(Rc::new(Object::Null)),
+                }
+            }
+        }
+    }
+
+    // every global bound by the host or by a script, by name
+    pub fn globals(&self) -> Vec<(String, Rc<Object>)> {
+        match &self.state {
+            #[cfg(feature = "eval")]
+            State::Evaluator(env) => return env.borrow().bindings(),
+            #[cfg(feature = "vm")]
+            State::Vm { symbol_table, globals, .. } => {
+                let mut found = Vec::new();
+                for (symbol, _) in symbol_table.definitions() {
+                    match globals.get(symbol.index) {
+                        Some(value) if symbol.scope == SymbolScope::Global => {
+                            found.push((symbol.name.clone(), Rc::clone(value)))
+                        }
+                        _ => {}
+                    }
+                }
+                return found;
+            }
+        }
+    }
+
+    // Moves the session to `backend` with its globals. Each engine has its own kind of function,
+    // so functions stay behind, and so do arrays and hashes holding one; their names come back.
+    pub fn switch(&mut self, backend: Backend) -> Vec<String> {
+        if backend == self.backend() {
+            return Vec::new();
+        }
+        let mut next = Engine::new(backend);
+        let mut left_behind = Vec::new();
+        for (name, value) in self.globals() {
+            if is_portable(&value) {
+                next.set_global(&name, Object::clone(&value));
+            } else {
+                left_behind.push(name);
+            }
+        }
+        *self = next;
+        return left_behind;
+    }
+
+    // the functions of the `exports` hash, by name
+    pub fn exports(&self) -> Vec<Export> {
+        let exports = match self.get_global("exports") {
+            Some(e) => e,
+            None => return Vec::new(),
+        };
+        let mut found = Vec::new();
+        if let Object::Hash(map) = &*exports {
+            for (key, value) in map.iter() {
+                let arity = match &**value {
+                    Object::Function(params, _, _, false) => Some(params.len()),
+                    Object::ClosureObj(c) if !c.func.rest => Some(c.func.num_parameters),
+                    Object::Function(..) | Object::ClosureObj(_) => None,
+                    Object::Builtin(_) => None,
+                    _ => continue,
+                };
+                if let Object::String(name) = &**key {
+                    found.push(Export { name: name.clone(), arity });
+                }
+            }
+        }
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        return found;
+    }
+
+    // Calls the exported function `name`, in a script run before. The arguments come from Rust
+    // values with `into()` and the result goes back with `try_from`, see `object::convert`.
+    pub fn call(&mut self, name: &str, args: Vec<Object>) -> Result<Rc<Object>, String> {
+        let function = self
+            .get_global("exports")
+            .and_then(|e| match &*e {
+                Object::Hash(map) => map.get(&Object::String(name.to_string())).cloned(),
+                _ => None,
+            })
+            .ok_or_else(|| format!("no export named {}", name))?;
+        match &*function {
+            Object::Function(..) | Object::ClosureObj(_) | Object::Builtin(_) => {}
+            _ => return Err(format!("export {} is not a function: {}", name, function)),
+        }
+        let args: Vec<Rc<Object>> = args.into_iter().map(Rc::new).collect();
+        match &mut self.state {
+            #[cfg(feature = "eval")]
+            State::Evaluator(_) => return interpreter::apply_function(&function, &args),
+            #[cfg(feature = "vm")]
+            State::Vm { constants, globals, .. } => {
+                let bytecode = Bytecode {
+                    instructions: Instructions { data: Vec::new() },
+                    constants: constants.clone(),
+                    num_globals: 0,
+                };
+                let mut vm = VM::new_with_global_store(bytecode, core::mem::take(globals));
+                let result = vm.call(function, &args);
+                *globals = core::mem::take(&mut vm.globals);
+                return result;
+            }
+        }
+    }
+}
+
+// the host's handles a session holds are finalized when it's dropped, see `object::native`
+impl Drop for Engine {
+    fn drop(&mut self) {
+        match &self.state {
+            // a function keeps the environment it's bound in, and so itself, alive
+            #[cfg(feature = "eval")]
+            State::Evaluator(env) => env.borrow_mut().clear(),
+            #[cfg(feature = "vm")]
+            State::Vm { .. } => {}
+        }
+    }
+}
+
+fn is_portable(value: &Object) -> bool {
+    match value {
+        Object::Function(..) | Object::ClosureObj(_) | Object::CompiledFunction(_) => return false,
+        Object::Array(items) => return items.iter().all(|v| is_portable(v)),
+        Object::Hash(map) => return map.iter().all(|(k, v)| is_portable(k) && is_portable(v)),
+        _ => return true,
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::convert::TryFrom;
+    use std::rc::Rc;
+
+    use crate::prelude::*;
+
+    const BACKENDS: [Backend; 2] = [Backend::Evaluator, Backend::Vm];
+
+    #[test]
+    fn test_host_globals() {
+        for backend in BACKENDS {
+            let mut engine = Engine::new(backend);
+            engine.set_global("limit", 3);
+            engine.set_global("names", vec!["ann", "bob", "cy", "dee"]);
+            engine.set_global("config", vec![("verbose", true)]);
+            let source = "let picked = if (config[\"verbose\"]) { rest(names) } else { names }; \
+                let count = len(picked) - limit;";
+            engine.run(source).unwrap();
+
+            let count = engine.get_global("count").unwrap();
+            assert_eq!(i64::try_from(&*count), Ok(0), "{:?}", backend);
+            let picked = engine.get_global("picked").unwrap();
+            assert_eq!(picked.to_string(), "[bob, cy, dee]", "{:?}", backend);
+            assert_eq!(String::try_from(&*picked), Err("expected string, got array".to_string()));
+            assert_eq!(engine.get_global("nothing"), None);
+            assert_eq!(engine.get_global("len"), None);
+        }
+    }
+
+    #[test]
+    fn test_globals_across_runs() {
+        for backend in BACKENDS {
+            let mut engine = Engine::new(backend);
+            engine.set_global("x", 1);
+            engine.run("let double = fn(n) { n * 2 };").unwrap();
+            engine.set_global("x", 20);
+            assert_eq!(*engine.run("double(x) + 1").unwrap(), Object::Integer(41));
+
+            // a runtime error keeps what was bound before it
+            let error = engine.run("let y = x + 1; y / 0").unwrap_err();
+            assert_eq!(error.lines().next(), Some("division by zero"), "{:?}", backend);
+            assert_eq!(engine.get_global("y").map(|y| y.to_string()), Some("21".to_string()));
+            assert_eq!(engine.backend(), backend);
+        }
+    }
+
+    #[test]
+    fn test_exports() {
+        let source = "let greeting = \"hello\"; \
+            let exports = {\"greet\": fn(name) { greeting + \", \" + name }, \
+                \"add\": fn(a, b) { a + b }, \"size\": len, \"version\": 2};";
+        for backend in BACKENDS {
+            let mut engine = Engine::new(backend);
+            assert_eq!(engine.exports(), vec![]);
+            engine.run(source).unwrap();
+
+            let exports = engine.exports().into_iter();
+            let names: Vec<_> = exports.map(|e| (e.name, e.arity)).collect();
+            let expected = vec![
+                ("add".to_string(), Some(2)),
+                ("greet".to_string(), Some(1)),
+                ("size".to_string(), None),
+            ];
+            assert_eq!(names, expected, "{:?}", backend);
+
+            let greeting = engine.call("greet", vec!["ann".into()]).unwrap();
+            assert_eq!(String::try_from(&*greeting), Ok("hello, ann".to_string()));
+            let sum = engine.call("add", vec![2.into(), 3.into()]).unwrap();
+            assert_eq!(*sum, Object::Integer(5), "{:?}", backend);
+            let size = engine.call("size", vec![vec![1, 2, 3].into()]).unwrap();
+            assert_eq!(*size, Object::Integer(3), "{:?}", backend);
+
+            let error = engine.call("add", vec![1.into()]).unwrap_err();
+            let expected = "wrong number of arguments: want=2, got=1";
+            assert_eq!(error.lines().next(), Some(expected), "{:?}", backend);
+            let error = engine.call("version", vec![]).unwrap_err();
+            assert_eq!(error, "export version is not a function: 2", "{:?}", backend);
+            let error = engine.call("missing", vec![]).unwrap_err();
+            assert_eq!(error, "no export named missing", "{:?}", backend);
+
+            // the session carries on after a call
+            let sum = engine.run("let add = exports[\"add\"]; add(1, 1)").unwrap();
+            assert_eq!(*sum, Object::Integer(2), "{:?}", backend);
+        }
+    }
+
+    #[test]
+    fn test_session_scopes() {
+        for backend in BACKENDS {
+            let mut engine = Engine::new(backend);
+            let setup = "let n = 10; let make = fn(x) { fn(y) { x + y + n } };";
+            engine.run(setup).unwrap();
+            engine.run("let addTwo = make(2);").unwrap();
+            // a closure from one run, over a local of a call in another and a global
+            assert_eq!(*engine.run("addTwo(3)").unwrap(), Object::Integer(15), "{:?}", backend);
+
+            // locals stay in their call
+            let source = "let pair = fn() { let q = [n, n + 1]; q[1] }; pair()";
+            assert_eq!(*engine.run(source).unwrap(), Object::Integer(11), "{:?}", backend);
+            assert_eq!(engine.get_global("q"), None, "{:?}", backend);
+
+            // a source that doesn't compile or fails before its `let` binds nothing
+            assert!(engine.run("let broken = missing;").is_err());
+            assert_eq!(engine.get_global("broken"), None, "{:?}", backend);
+            assert_eq!(*engine.run("len([n])").unwrap(), Object::Integer(1), "{:?}", backend);
+        }
+    }
+
+    #[test]
+    fn test_switch_engines() {
+        for (from, to) in BACKENDS.iter().zip(BACKENDS.iter().rev()) {
+            let (from, to) = (*from, *to);
+            let mut engine = Engine::new(from);
+            engine.set_global("limit", 3);
+            let source = "let names = [\"ann\", {\"b\": 2}]; let size = len; \
+                let double = fn(x) { x * 2 }; let tools = [double]; let total = double(limit);";
+            engine.run(source).unwrap();
+
+            let left_behind = engine.switch(to);
+            assert_eq!(left_behind, vec!["double", "tools"], "{:?}", from);
+            assert_eq!(engine.backend(), to);
+            let names: Vec<String> = engine.globals().into_iter().map(|g| g.0).collect();
+            assert_eq!(names, vec!["limit", "names", "size", "total"], "{:?}", from);
+
+            let sum = "size(names) + names[1][\"b\"] + total + limit";
+            let got = engine.run(sum).unwrap();
+            assert_eq!(*got, Object::Integer(13), "{:?}", from);
+            // what stayed behind can be defined again
+            engine.run("let double = fn(x) { x * 2 };").unwrap();
+            assert_eq!(*engine.run("double(total)").unwrap(), Object::Integer(12));
+            assert_eq!(engine.switch(to), Vec::<String>::new());
+        }
+    }
+
+    #[test]
+    fn test_handles() {
+        for backend in BACKENDS {
+            let closed = Rc::new(Cell::new(0));
+            let handle = |fd: i64| {
+                let closed = Rc::clone(&closed);
+                return Handle::with_finalizer("file", fd, move |fd| closed.set(closed.get() + fd));
+            };
+            let mut engine = Engine::new(backend);
+            engine.set_global("file", handle(1));
+            let file = engine.get_global("file").unwrap();
+            match &*file {
+                Object::Native(h) => assert_eq!(h.with(|fd: &mut i64| *fd), Some(1)),
+                o => panic!("not a handle: {}", o),
+            }
+
+            // closed once, by the script
+            let source = "let first = close(file); [first, close(file), file == file, file]";
+            let got = engine.run(source).unwrap();
+            assert_eq!(got.to_string(), "[true, false, true, [closed handle file]]");
+            assert_eq!(closed.get(), 1, "{:?}", backend);
+
+            // or with the engine, a function holding the handle included
+            engine.set_global("file", handle(10));
+            engine.run("let read = fn() { file };").unwrap();
+            drop(file);
+            drop(engine);
+            assert_eq!(closed.get(), 11, "{:?}", backend);
+
+            let mut engine = Engine::new(backend);
+            let error = engine.run("let ok = close(1); ok").unwrap_err();
+            assert_eq!(error.lines().next(), Some("builtin close expected a handle, got integer"));
+        }
+    }
+}
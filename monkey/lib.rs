@@ -0,0 +1,72 @@
+// Monkey in one crate. The crate modules are re-exported whole, internals included, and change
+// with them; `prelude` and the functions below are the API kept stable across releases.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use lexer;
+pub use object;
+pub use parser;
+
+#[cfg(feature = "vm")]
+pub use compiler;
+#[cfg(feature = "eval")]
+pub use interpreter;
+#[cfg(feature = "wasm")]
+pub use monkey_wasm as wasm;
+
+#[cfg(any(feature = "vm", feature = "eval"))]
+pub mod engine;
+
+// the tests compare the two engines
+#[cfg(all(test, feature = "vm", feature = "eval"))]
+mod engine_test;
+#[cfg(all(test, feature = "vm", feature = "eval"))]
+mod prelude_test;
+
+#[cfg(any(feature = "vm", feature = "eval"))]
+use alloc::rc::Rc;
+#[cfg(any(feature = "vm", feature = "eval"))]
+use alloc::string::String;
+
+#[cfg(any(feature = "vm", feature = "eval"))]
+use object::Object;
+
+pub mod prelude {
+    pub use object::native::Handle;
+    pub use object::Object;
+    pub use parser::ast::Node;
+    pub use parser::parse;
+
+    #[cfg(any(feature = "vm", feature = "eval"))]
+    pub use crate::engine::{Backend, Engine};
+    #[cfg(feature = "eval")]
+    pub use crate::eval;
+    #[cfg(feature = "vm")]
+    pub use crate::run;
+    #[cfg(feature = "vm")]
+    pub use compiler::compiler::{Bytecode, CompileOptions};
+    #[cfg(feature = "vm")]
+    pub use compiler::vm::{VMError, VM};
+    #[cfg(feature = "vm")]
+    pub use compiler::{compile, compile_with_options};
+}
+
+// Evaluates `source` with the tree-walking evaluator. Parse errors come back one per line.
+#[cfg(feature = "eval")]
+pub fn eval(source: &str) -> Result<Rc<Object>, String> {
+    let program = parser::parse(source).map_err(|errors| parser::error::render(&errors))?;
+    return interpreter::eval(program, &Rc::new(core::cell::RefCell::new(Default::default())));
+}
+
+// Compiles `source` and runs it on the vm, to the value of its last expression statement.
+#[cfg(feature = "vm")]
+pub fn run(source: &str) -> Result<Rc<Object>, String> {
+    let mut vm = compiler::vm::VM::new(compiler::compile(source)?);
+    vm.run()?;
+    match vm.last_popped_stack_elm() {
+        Some(value) => return Ok(value),
+        None => return Ok(Rc::new(Object::Null)),
+    }
+}
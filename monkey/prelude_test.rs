@@ -0,0 +1,23 @@
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn test_engines_agree() {
+        let source =
+            "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(10)";
+        assert_eq!(*eval(source).unwrap(), Object::Integer(55));
+        assert_eq!(*run(source).unwrap(), Object::Integer(55));
+
+        assert_eq!(eval("let x = ;").unwrap_err(), run("let x = ;").unwrap_err());
+    }
+
+    #[test]
+    fn test_compile_and_run() {
+        let bytecode =
+            compile_with_options("1 + 2 * 3", CompileOptions::from_opt_level(1)).unwrap();
+        let mut vm = VM::new(bytecode);
+        vm.run().unwrap();
+        assert_eq!(*vm.last_popped_stack_elm().unwrap(), Object::Integer(7));
+    }
+}
@@ -0,0 +1,61 @@
+use std::io::{self, stdin, Write};
+
+use monkey::object::interrupt;
+use monkey::parser::is_quiet;
+use monkey::prelude::*;
+
+const HELP: &str = ":engine vm or :engine eval switches engines, keeping the globals";
+
+fn engine_name(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Vm => return "vm",
+        Backend::Evaluator => return "eval",
+    }
+}
+
+// `:engine eval` and back, to compare the engines on the same globals
+fn command(engine: &mut Engine, command: &str) {
+    let backend = match command.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["engine", "vm"] => Backend::Vm,
+        ["engine", "eval"] => Backend::Evaluator,
+        ["engine"] => return println!("engine {}", engine_name(engine.backend())),
+        _ => return println!("unknown command :{}, {}", command, HELP),
+    };
+    let left_behind = engine.switch(backend);
+    println!("engine {}", engine_name(backend));
+    if !left_behind.is_empty() {
+        println!("not moved, each engine has its own functions: {}", left_behind.join(", "));
+    }
+}
+
+fn main() {
+    println!("Welcome to monkey, {}", HELP);
+    let mut engine = Engine::new(Backend::Vm);
+    ctrlc::set_handler(|| {
+        if !interrupt::request() {
+            std::process::exit(130);
+        }
+    })
+    .expect("can't handle Ctrl-C");
+    loop {
+        print!("{}> ", engine_name(engine.backend()));
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+        stdin().read_line(&mut input).unwrap();
+
+        if input.trim_end().is_empty() {
+            println!("bye");
+            std::process::exit(0);
+        }
+        if let Some(c) = input.trim().strip_prefix(':') {
+            command(&mut engine, c);
+            continue;
+        }
+
+        match interrupt::run(|| engine.run(&input)) {
+            Ok(value) if !is_quiet(&input) => println!("{}", value),
+            Ok(_) => {}
+            Err(e) => println!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::{HashMap, Object};
+
+// `let b = a` shares `a`'s value, since nothing but `next` changes one in place; `clone(x)`
+// copies arrays and hashes all the way down
+
+// the `clone` builtin
+pub fn clone(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 1 {
+        return Rc::new(Object::Error(format!(
+            "builtin clone expected 1 argument, got {}",
+            args.len()
+        )));
+    }
+    return deep_copy(&args[0]);
+}
+
+fn deep_copy(value: &Rc<Object>) -> Rc<Object> {
+    match &**value {
+        Object::Array(elements) => {
+            return Rc::new(Object::Array(elements.iter().map(deep_copy).collect()));
+        }
+        Object::Hash(map) => {
+            let mut copy = HashMap::new();
+            for (k, v) in map.iter() {
+                copy.insert(deep_copy(k), deep_copy(v));
+            }
+            return Rc::new(Object::Hash(copy));
+        }
+        _ => return Rc::clone(value),
+    }
+}
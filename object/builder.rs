@@ -0,0 +1,39 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+use crate::{frozen, Object};
+
+// `append(s, piece)` is `s + piece`, adding to `s` in place when `let s = append(s, piece);`
+// hands it over, as `push` does an array
+
+// the `append` builtin
+pub fn append(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 2 {
+        let message = format!("builtin append expected 2 arguments, got {}", args.len());
+        return error(message);
+    }
+    let [mut s, piece]: [Rc<Object>; 2] = args.try_into().unwrap();
+    if !matches!(*s, Object::String(_)) {
+        return error(format!("builtin append not supported for type {}", s.type_name()));
+    }
+    if !matches!(*piece, Object::String(_) | Object::Char(_)) {
+        let message = "builtin append expected a string or a char to append, got";
+        return error(format!("{} {}", message, piece.type_name()));
+    }
+    if let Some(error) = frozen::check("append", &s) {
+        return error;
+    }
+    match (Rc::make_mut(&mut s), &*piece) {
+        (Object::String(s), Object::String(piece)) => s.push_str(piece),
+        (Object::String(s), Object::Char(c)) => s.push(*c),
+        _ => unreachable!("checked above"),
+    }
+    return s;
+}
+
+fn error(message: String) -> Rc<Object> {
+    return Rc::new(Object::Error(message));
+}
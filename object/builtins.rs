@@ -1,23 +1,141 @@
-use crate::{BuiltinFunc, Object};
-use std::rc::Rc;
+use crate::aliasing::clone;
+use crate::builder::append;
+use crate::cell::{cell, cell_add, cell_get, cell_set, cell_swap};
+use crate::channel::{chan, recv, send, spawn};
+use crate::chars::{chars, chars_calling, chr, chr_calling, ord, ord_calling};
+use crate::decimal::decimal;
+use crate::deque::{
+    pop, pop_calling, push, push_calling, shift, shift_calling, unshift, unshift_calling,
+};
+use crate::frozen::freeze;
+use crate::iterator::{
+    collect, collect_calling, each, each_calling, generate, ifilter, imap, iter, next,
+    next_calling, range, take,
+};
+use crate::memo::{cache_stats, memo};
+use crate::native::{close, close_calling};
+use crate::number::div;
+use crate::parallel::{pmap, pmap_calling};
+use crate::partial::{hole, hole_calling, partial, partial_calling};
+use crate::require::{require, require_calling};
+use crate::result::{err, is_ok, ok, unwrap_or};
+use crate::search::{bsearch, bsearch_calling, sorted_insert, sorted_insert_calling};
+use crate::template::string;
+use crate::update::{insert, insert_calling, remove, remove_calling, set, set_calling};
+use crate::{BuiltinFunc, CallingFunc, EvalError, Object};
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::ptr;
 
-lazy_static! {
-    pub static ref BuiltIns: Vec<(&'static str, BuiltinFunc)> = vec![
-        ("len", len),
-        ("puts", puts),
-        ("first", first),
-        ("last", last),
-        ("rest", rest),
-        ("push", push),
-        ("print", puts)
-    ];
+#[allow(non_upper_case_globals)]
+pub static BuiltIns: &[(&str, BuiltinFunc)] = &[
+    ("len", len),
+    ("puts", puts),
+    ("first", first),
+    ("last", last),
+    ("rest", rest),
+    ("push", push),
+    ("print", puts),
+    ("decimal", decimal),
+    ("div", div),
+    ("freeze", freeze),
+    ("clone", clone),
+    ("set", set),
+    ("insert", insert),
+    ("remove", remove),
+    ("pop", pop),
+    ("shift", shift),
+    ("unshift", unshift),
+    ("bsearch", bsearch),
+    ("sorted_insert", sorted_insert),
+    ("iter", iter),
+    ("range", range),
+    ("generate", generate),
+    ("next", next),
+    ("imap", imap),
+    ("ifilter", ifilter),
+    ("take", take),
+    ("collect", collect),
+    ("pmap", pmap),
+    ("spawn", spawn),
+    ("chan", chan),
+    ("send", send),
+    ("recv", recv),
+    ("cell", cell),
+    ("cell_get", cell_get),
+    ("cell_set", cell_set),
+    ("cell_add", cell_add),
+    ("cell_swap", cell_swap),
+    ("memo", memo),
+    ("cache_stats", cache_stats),
+    ("ok", ok),
+    ("err", err),
+    ("is_ok", is_ok),
+    ("unwrap_or", unwrap_or),
+    ("require", require),
+    ("partial", partial),
+    ("_", hole),
+    ("ord", ord),
+    ("chr", chr),
+    ("chars", chars),
+    ("append", append),
+    ("each", each),
+    ("string", string),
+    ("close", close),
+];
+
+// the builtins that call functions or stop the program, as the engines call them
+pub static CALLING: &[(BuiltinFunc, CallingFunc)] = &[
+    (bsearch, bsearch_calling),
+    (sorted_insert, sorted_insert_calling),
+    (next, next_calling),
+    (collect, collect_calling),
+    (pmap, pmap_calling),
+    (require, require_calling),
+    (each, each_calling),
+    (set, set_calling),
+    (insert, insert_calling),
+    (remove, remove_calling),
+    (push, push_calling),
+    (pop, pop_calling),
+    (unshift, unshift_calling),
+    (shift, shift_calling),
+    (partial, partial_calling),
+    (hole, hole_calling),
+    (ord, ord_calling),
+    (chr, chr_calling),
+    (chars, chars_calling),
+    (close, close_calling),
+];
+
+// the builtins whose results only depend on their arguments, for the compiler to run; not
+// `freeze`, which marks the value it's given
+pub static PURE: &[&str] = &[
+    "len", "first", "last", "rest", "push", "decimal", "div", "clone", "set", "insert", "remove",
+    "pop", "shift", "unshift", "append", "ord", "chr", "chars", "string",
+];
+
+pub fn calling(builtin: BuiltinFunc) -> Option<CallingFunc> {
+    let entry = CALLING.iter().find(|(b, _)| ptr::fn_addr_eq(*b, builtin));
+    return entry.map(|(_, calling)| *calling);
 }
 
-// a failed try
-// rust sucks: https://stackoverflow.com/a/27896014/1713757
-// pub static BUILTINS: HashMap<String, BuiltinFunc> = vec![(String::from("len"), len as BuiltinFunc) ]
-//     .into_iter()
-//     .collect();
+// how builtins in `CALLING` call functions where no engine runs them
+pub(crate) fn uncallable(
+    function: &Rc<Object>,
+    _: Vec<Rc<Object>>,
+) -> Result<Rc<Object>, EvalError> {
+    return Err(format!("can't call {} from here", function));
+}
+
+// the error a builtin returns as the one stopping the program, for its entry in `CALLING`
+pub(crate) fn stop(result: Rc<Object>) -> Result<Rc<Object>, EvalError> {
+    match &*result {
+        Object::Error(e) => return Err(e.clone()),
+        _ => return Ok(result),
+    }
+}
 
 pub fn len(args: Vec<Rc<Object>>) -> Rc<Object> {
     if args.len() != 1 {
@@ -33,14 +151,22 @@ pub fn len(args: Vec<Rc<Object>>) -> Rc<Object> {
     })
 }
 
+#[cfg(feature = "io-builtins")]
 pub fn puts(args: Vec<Rc<Object>>) -> Rc<Object> {
     args.iter().for_each(|obj| println!("{}", obj));
     Rc::from(Object::Null)
 }
 
+// without the io-builtins feature `puts` and `print` stay in the table, so builtin indexes in
+// compiled code don't depend on the feature, but fail when called
+#[cfg(not(feature = "io-builtins"))]
+pub fn puts(_args: Vec<Rc<Object>>) -> Rc<Object> {
+    Rc::from(Object::Error("puts is not available without the io-builtins feature".into()))
+}
+
 pub fn first(args: Vec<Rc<Object>>) -> Rc<Object> {
     match &*args[0] {
-        Object::Array(s) => match s.first() {
+        Object::Array(s) => match s.front() {
             Some(obj) => Rc::clone(obj),
             None => Rc::new(Object::Null),
         },
@@ -50,7 +176,7 @@ pub fn first(args: Vec<Rc<Object>>) -> Rc<Object> {
 
 pub fn last(args: Vec<Rc<Object>>) -> Rc<Object> {
     match &*args[0] {
-        Object::Array(s) => match s.last() {
+        Object::Array(s) => match s.back() {
             Some(obj) => Rc::clone(obj),
             None => Rc::new(Object::Null),
         },
@@ -61,9 +187,8 @@ pub fn last(args: Vec<Rc<Object>>) -> Rc<Object> {
 pub fn rest(args: Vec<Rc<Object>>) -> Rc<Object> {
     match &*args[0] {
         Object::Array(s) => {
-            let len = s.len();
-            if len > 0 {
-                let new_array = s[1..len].to_vec();
+            if !s.is_empty() {
+                let new_array = s.iter().skip(1).cloned().collect();
                 return Rc::new(Object::Array(new_array));
             }
             return Rc::new(Object::Null);
@@ -71,16 +196,3 @@ pub fn rest(args: Vec<Rc<Object>>) -> Rc<Object> {
         o => Rc::new(Object::Error(format!("builtin rest not supported for for type {}", o))),
     }
 }
-
-pub fn push(args: Vec<Rc<Object>>) -> Rc<Object> {
-    let array = args.first().unwrap();
-    let obj = Rc::clone(args.last().unwrap());
-    match &**array {
-        Object::Array(s) => {
-            let mut new_array = s.clone();
-            new_array.push(obj);
-            return Rc::new(Object::Array(new_array));
-        }
-        o => Rc::new(Object::Error(format!("builtin push not supported for for type {}", o))),
-    }
-}
@@ -0,0 +1,211 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::Object;
+
+// named integer or string cells the host defines and scripts on any thread read and change
+// atomically, see `define`
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shared {
+    Integer(i64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    // scripts read it, the host sets it
+    Read,
+    Write,
+}
+
+// the cells defined, by name
+type Cells = BTreeMap<String, (Shared, Access)>;
+
+#[cfg(feature = "std")]
+static DEFINED: std::sync::Mutex<Cells> = std::sync::Mutex::new(BTreeMap::new());
+
+// `f` run on the cells, with the lock on them held
+#[cfg(feature = "std")]
+fn with_cells<T>(f: impl FnOnce(&mut Cells) -> T) -> T {
+    // a panic holding the lock left no cell half changed
+    let mut cells = DEFINED.lock().unwrap_or_else(|e| e.into_inner());
+    return f(&mut cells);
+}
+
+// no cell is ever defined
+#[cfg(not(feature = "std"))]
+fn with_cells<T>(f: impl FnOnce(&mut Cells) -> T) -> T {
+    return f(&mut BTreeMap::new());
+}
+
+// Makes `name` a cell scripts can reach, holding `value`, or sets it and its access if it is
+// one already. The host can always `set` it.
+#[cfg(feature = "std")]
+pub fn define(name: &str, value: Shared, access: Access) {
+    with_cells(|cells| cells.insert(name.to_string(), (value, access)));
+}
+
+// `name` no longer a cell, a script's handle to it errors from then on
+#[cfg(feature = "std")]
+pub fn undefine(name: &str) {
+    with_cells(|cells| cells.remove(name));
+}
+
+// the value of the cell `name`, for the host
+#[cfg(feature = "std")]
+pub fn get(name: &str) -> Option<Shared> {
+    return with_cells(|cells| cells.get(name).map(|(value, _)| value.clone()));
+}
+
+// sets the cell `name`, `Read` or not, false if there's none
+#[cfg(feature = "std")]
+pub fn set(name: &str, value: Shared) -> bool {
+    return with_cells(|cells| match cells.get_mut(name) {
+        Some(cell) => {
+            cell.0 = value;
+            return true;
+        }
+        None => return false,
+    });
+}
+
+impl From<&Shared> for Object {
+    fn from(value: &Shared) -> Object {
+        match value {
+            Shared::Integer(i) => return Object::Integer(*i),
+            Shared::String(s) => return Object::String(s.clone()),
+        }
+    }
+}
+
+// the `cell` builtin
+pub fn cell(args: Vec<Rc<Object>>) -> Rc<Object> {
+    let name = match args.as_slice() {
+        [name] => match &**name {
+            Object::String(name) => name,
+            o => return error(format!("builtin cell expected a name, got {}", o.type_name())),
+        },
+        _ => return arity("cell", 1, &args),
+    };
+    if !with_cells(|cells| cells.contains_key(name)) {
+        return error(format!("no cell named {}, the host defines them", name));
+    }
+    return Rc::new(Object::Cell(name.clone()));
+}
+
+// the `cell_get` builtin
+pub fn cell_get(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 1 {
+        return arity("cell_get", 1, &args);
+    }
+    return update("cell_get", &args[0], false, |value| return Ok(Object::from(&*value)));
+}
+
+// the `cell_set` builtin
+pub fn cell_set(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 2 {
+        return arity("cell_set", 2, &args);
+    }
+    let new = match shared(&args[1]) {
+        Ok(new) => new,
+        Err(e) => return e,
+    };
+    return update("cell_set", &args[0], true, |value| {
+        *value = new;
+        return Ok(Object::Null);
+    });
+}
+
+// the `cell_add` builtin
+pub fn cell_add(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 2 {
+        return arity("cell_add", 2, &args);
+    }
+    let n = match &*args[1] {
+        Object::Integer(n) => *n,
+        o => return error(format!("builtin cell_add expected an integer, got {}", o.type_name())),
+    };
+    return update("cell_add", &args[0], true, |value| match value {
+        Shared::Integer(i) => match i.checked_add(n) {
+            Some(sum) => {
+                *i = sum;
+                return Ok(Object::Integer(sum));
+            }
+            None => return Err(format!("integer overflow: {} + {}", i, n)),
+        },
+        Shared::String(_) => {
+            return Err("builtin cell_add not supported for a cell holding string".to_string())
+        }
+    });
+}
+
+// the `cell_swap` builtin
+pub fn cell_swap(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 3 {
+        return arity("cell_swap", 3, &args);
+    }
+    let (old, new) = match (shared(&args[1]), shared(&args[2])) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(e), _) | (_, Err(e)) => return e,
+    };
+    return update("cell_swap", &args[0], true, |value| {
+        if *value != old {
+            return Ok(Object::Boolean(false));
+        }
+        *value = new;
+        return Ok(Object::Boolean(true));
+    });
+}
+
+// `f` run on the value of the cell `handle` while no other thread can change it, unless it
+// `writes` a `Read` cell
+fn update(
+    builtin: &str,
+    handle: &Object,
+    writes: bool,
+    f: impl FnOnce(&mut Shared) -> Result<Object, String>,
+) -> Rc<Object> {
+    let name = match handle {
+        Object::Cell(name) => name,
+        o => return error(format!("builtin {} not supported for type {}", builtin, o.type_name())),
+    };
+    let result = with_cells(|cells| match cells.get_mut(name) {
+        Some((_, Access::Read)) if writes => return Err(format!("cell {} is read-only", name)),
+        Some((value, _)) => return f(value),
+        None => return Err(format!("no cell named {}, the host defines them", name)),
+    });
+    match result {
+        Ok(value) => return Rc::new(value),
+        Err(e) => return error(e),
+    }
+}
+
+fn shared(value: &Object) -> Result<Shared, Rc<Object>> {
+    match value {
+        Object::Integer(i) => return Ok(Shared::Integer(*i)),
+        Object::String(s) => return Ok(Shared::String(s.clone())),
+        o => {
+            let message = format!("a cell holds an integer or a string, got {}", o.type_name());
+            return Err(error(message));
+        }
+    }
+}
+
+fn arity(name: &str, want: usize, args: &[Rc<Object>]) -> Rc<Object> {
+    let plural = if want == 1 { "" } else { "s" };
+    return error(format!(
+        "builtin {} expected {} argument{}, got {}",
+        name,
+        want,
+        plural,
+        args.len()
+    ));
+}
+
+fn error(message: String) -> Rc<Object> {
+    return Rc::new(Object::Error(message));
+}
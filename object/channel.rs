@@ -0,0 +1,34 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::Object;
+
+// `spawn`, `chan`, `send` and `recv`: threads of the vm, given copies of values, talking on
+// channels; an error without the `parallel` feature or in the evaluator
+
+// the builtins only the vm runs
+pub static VM_ONLY: &[&str] = &["spawn", "chan", "send", "recv"];
+
+// The four in `BuiltIns`, for where there are no threads: the vm with the `parallel` feature
+// calls its own, see `compiler::concurrent`.
+pub fn spawn(_: Vec<Rc<Object>>) -> Rc<Object> {
+    return unsupported("spawn");
+}
+
+pub fn chan(_: Vec<Rc<Object>>) -> Rc<Object> {
+    return unsupported("chan");
+}
+
+pub fn send(_: Vec<Rc<Object>>) -> Rc<Object> {
+    return unsupported("send");
+}
+
+pub fn recv(_: Vec<Rc<Object>>) -> Rc<Object> {
+    return unsupported("recv");
+}
+
+fn unsupported(name: &str) -> Rc<Object> {
+    let message = format!("builtin {} needs the vm, built with the `parallel` feature", name);
+    return Rc::new(Object::Error(message));
+}
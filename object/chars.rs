@@ -0,0 +1,85 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::builtins::stop;
+use crate::{Call, EvalError, Object};
+
+// chars, `'a'`, and `chars`, `ord` and `chr`; chars compare by code point and never equal a
+// string, and a failing call stops the program
+
+// `left + right` when one is a string and the other a char, `None` for any other values
+pub fn join(left: &Object, right: &Object) -> Option<String> {
+    match (left, right) {
+        (Object::String(s), Object::Char(c)) => return Some(format!("{}{}", s, c)),
+        (Object::Char(c), Object::String(s)) => return Some(format!("{}{}", c, s)),
+        _ => return None,
+    }
+}
+
+// the `ord` builtin
+pub fn ord(args: Vec<Rc<Object>>) -> Rc<Object> {
+    match args.as_slice() {
+        [c] => match **c {
+            Object::Char(c) => return Rc::new(Object::Integer(c as i64)),
+            ref o => return error(format!("builtin ord expected a char, got {}", o.type_name())),
+        },
+        _ => return arity("ord", &args),
+    }
+}
+
+// the `chr` builtin
+pub fn chr(args: Vec<Rc<Object>>) -> Rc<Object> {
+    let n = match args.as_slice() {
+        [n] => match **n {
+            Object::Integer(n) => n,
+            ref o => {
+                let message = format!("builtin chr expected a code point, got {}", o.type_name());
+                return error(message);
+            }
+        },
+        _ => return arity("chr", &args),
+    };
+    match u32::try_from(n).ok().and_then(char::from_u32) {
+        Some(c) => return Rc::new(Object::Char(c)),
+        None => return error(format!("builtin chr expected a code point, got {}", n)),
+    }
+}
+
+// the `chars` builtin
+pub fn chars(args: Vec<Rc<Object>>) -> Rc<Object> {
+    match args.as_slice() {
+        [s] => match &**s {
+            Object::String(s) => {
+                let chars = s.chars().map(|c| Rc::new(Object::Char(c)));
+                return Rc::new(Object::Array(chars.collect()));
+            }
+            o => return error(format!("builtin chars expected a string, got {}", o.type_name())),
+        },
+        _ => return arity("chars", &args),
+    }
+}
+
+// the three as the engines call them, an error stopping the program
+pub fn ord_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(ord(args));
+}
+
+pub fn chr_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(chr(args));
+}
+
+pub fn chars_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(chars(args));
+}
+
+fn arity(name: &str, args: &[Rc<Object>]) -> Rc<Object> {
+    let message = format!("builtin {} expected 1 argument, got {}", name, args.len());
+    return error(message);
+}
+
+fn error(message: String) -> Rc<Object> {
+    return Rc::new(Object::Error(message));
+}
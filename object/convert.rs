@@ -0,0 +1,125 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use rust_decimal::Decimal;
+
+use crate::native::Handle;
+use crate::{EvalError, HashMap, Object};
+
+// Rust values to and from `Object`s, for hosts passing data in and out of scripts. Going in never
+// fails; coming out fails with the kind of value found when it isn't the one asked for.
+
+impl From<i64> for Object {
+    fn from(value: i64) -> Object {
+        return Object::Integer(value);
+    }
+}
+
+impl From<bool> for Object {
+    fn from(value: bool) -> Object {
+        return Object::Boolean(value);
+    }
+}
+
+impl From<&str> for Object {
+    fn from(value: &str) -> Object {
+        return Object::String(value.to_string());
+    }
+}
+
+impl From<String> for Object {
+    fn from(value: String) -> Object {
+        return Object::String(value);
+    }
+}
+
+impl From<Decimal> for Object {
+    fn from(value: Decimal) -> Object {
+        return Object::Decimal(value);
+    }
+}
+
+impl From<Handle> for Object {
+    fn from(value: Handle) -> Object {
+        return Object::Native(Rc::new(value));
+    }
+}
+
+// `None` is null
+impl<T: Into<Object>> From<Option<T>> for Object {
+    fn from(value: Option<T>) -> Object {
+        match value {
+            Some(v) => return v.into(),
+            None => return Object::Null,
+        }
+    }
+}
+
+impl<T: Into<Object>> From<Vec<T>> for Object {
+    fn from(values: Vec<T>) -> Object {
+        return Object::Array(values.into_iter().map(|v| Rc::new(v.into())).collect());
+    }
+}
+
+impl<K: Into<Object>, V: Into<Object>> From<Vec<(K, V)>> for Object {
+    fn from(entries: Vec<(K, V)>) -> Object {
+        let mut map = HashMap::new();
+        for (k, v) in entries {
+            map.insert(Rc::new(k.into()), Rc::new(v.into()));
+        }
+        return Object::Hash(map);
+    }
+}
+
+fn expected(kind: &str, value: &Object) -> EvalError {
+    return format!("expected {}, got {}", kind, value.type_name());
+}
+
+impl TryFrom<&Object> for i64 {
+    type Error = EvalError;
+
+    fn try_from(value: &Object) -> Result<i64, EvalError> {
+        match value {
+            Object::Integer(i) => return Ok(*i),
+            _ => return Err(expected("integer", value)),
+        }
+    }
+}
+
+impl TryFrom<&Object> for bool {
+    type Error = EvalError;
+
+    fn try_from(value: &Object) -> Result<bool, EvalError> {
+        match value {
+            Object::Boolean(b) => return Ok(*b),
+            _ => return Err(expected("boolean", value)),
+        }
+    }
+}
+
+impl TryFrom<&Object> for String {
+    type Error = EvalError;
+
+    fn try_from(value: &Object) -> Result<String, EvalError> {
+        match value {
+            Object::String(s) => return Ok(s.clone()),
+            _ => return Err(expected("string", value)),
+        }
+    }
+}
+
+// integers too, they are decimals with no fraction
+impl TryFrom<&Object> for Decimal {
+    type Error = EvalError;
+
+    fn try_from(value: &Object) -> Result<Decimal, EvalError> {
+        match value {
+            Object::Decimal(d) => return Ok(*d),
+            Object::Integer(i) => return Ok(Decimal::from(*i)),
+            _ => return Err(expected("decimal", value)),
+        }
+    }
+}
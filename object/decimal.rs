@@ -0,0 +1,119 @@
+use alloc::format;
+use alloc::rc::Rc;
+#[cfg(feature = "bigint")]
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+
+#[cfg(feature = "bigint")]
+use num_bigint::Sign;
+use rust_decimal::Decimal;
+
+use crate::number::{self, Arithmetic};
+use crate::{EvalError, Object};
+
+// Exact base 10 arithmetic for `1.50d` literals and `decimal(...)`. An integer on either side of
+// a decimal is taken as a decimal, so `3 * 1.50d` is `4.50`, see `number`.
+
+// the digits of a literal, `1.50` of `1.50d`
+pub fn parse(digits: &str) -> Result<Object, EvalError> {
+    match Decimal::from_str_exact(digits) {
+        Ok(d) => return Ok(Object::Decimal(d)),
+        Err(e) => return Err(format!("invalid decimal {}: {}", digits, e)),
+    }
+}
+
+pub fn arithmetic(op: Arithmetic, left: &Object, right: &Object) -> Result<Object, EvalError> {
+    let (l, r) = match (to_decimal(left), to_decimal(right)) {
+        (Some(l), Some(r)) => (l, r),
+        _ => return Err(number::unsupported(op, left, right)),
+    };
+    let result = match op {
+        Arithmetic::Add => l.checked_add(r),
+        Arithmetic::Sub => l.checked_sub(r),
+        Arithmetic::Mul => l.checked_mul(r),
+        Arithmetic::Div if r.is_zero() => return Err("division by zero".into()),
+        Arithmetic::Div => l.checked_div(r),
+        Arithmetic::FloorDiv if r.is_zero() => return Err("division by zero".into()),
+        Arithmetic::FloorDiv => l.checked_div(r).map(|q| q.floor()),
+    };
+    match result {
+        Some(d) => return Ok(Object::Decimal(d)),
+        None => return Err(format!("decimal overflow: {} {} {}", left, op, right)),
+    }
+}
+
+pub fn negate(value: &Object) -> Result<Object, EvalError> {
+    match value {
+        Object::Decimal(d) => return Ok(Object::Decimal(-d)),
+        _ => return Err(format!("can't negate {}", value)),
+    }
+}
+
+// None unless both are decimals or integers
+pub fn compare(left: &Object, right: &Object) -> Option<Ordering> {
+    match (to_decimal(left), to_decimal(right)) {
+        (Some(l), Some(r)) => return Some(l.cmp(&r)),
+        (None, Some(_)) => return beyond_decimals(left),
+        (Some(_), None) => return beyond_decimals(right).map(Ordering::reverse),
+        (None, None) => return None,
+    }
+}
+
+// a big integer too large for a decimal is past every decimal, on the side of its sign
+fn beyond_decimals(value: &Object) -> Option<Ordering> {
+    match value {
+        #[cfg(feature = "bigint")]
+        Object::BigInteger(i) if i.sign() == Sign::Minus => return Some(Ordering::Less),
+        #[cfg(feature = "bigint")]
+        Object::BigInteger(_) => return Some(Ordering::Greater),
+        _ => return None,
+    }
+}
+
+// the integer a decimal with no fraction equals
+pub fn to_integer(value: &Decimal) -> Option<Object> {
+    if !value.fract().is_zero() {
+        return None;
+    }
+    if let Ok(i) = i64::try_from(*value) {
+        return Some(Object::Integer(i));
+    }
+    #[cfg(feature = "bigint")]
+    if let Ok(i) = value.trunc().to_string().parse() {
+        return Some(crate::integer::normalize(i));
+    }
+    return None;
+}
+
+fn to_decimal(value: &Object) -> Option<Decimal> {
+    match value {
+        Object::Decimal(d) => return Some(*d),
+        Object::Integer(i) => return Some(Decimal::from(*i)),
+        #[cfg(feature = "bigint")]
+        Object::BigInteger(i) => return Decimal::from_str_exact(&i.to_string()).ok(),
+        _ => return None,
+    }
+}
+
+// the `decimal` builtin: `decimal("1.50")`, `decimal(3)`
+pub fn decimal(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 1 {
+        return Rc::new(Object::Error(format!(
+            "builtin decimal expected 1 argument, got {}",
+            args.len()
+        )));
+    }
+    let converted = match &*args[0] {
+        Object::String(s) => parse(s.trim()),
+        o => match to_decimal(o) {
+            Some(d) => Ok(Object::Decimal(d)),
+            None => Err(format!("builtin decimal not supported for type {}", o)),
+        },
+    };
+    match converted {
+        Ok(d) => return Rc::new(d),
+        Err(e) => return Rc::new(Object::Error(e)),
+    }
+}
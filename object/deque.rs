@@ -0,0 +1,89 @@
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+use crate::builtins::stop;
+use crate::{frozen, Call, EvalError, Object};
+
+// `push`, `pop`, `unshift` and `shift`, changing the array in place when `let q = push(q, x);`
+// hands it over; a failing call stops the program
+
+pub fn push(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if let Some(error) = check("push", &args, 2) {
+        return error;
+    }
+    let [mut array, value]: [Rc<Object>; 2] = args.try_into().unwrap();
+    elements(&mut array).push_back(value);
+    return array;
+}
+
+pub fn pop(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if let Some(error) = check("pop", &args, 1) {
+        return error;
+    }
+    let [mut array]: [Rc<Object>; 1] = args.try_into().unwrap();
+    match elements(&mut array).pop_back() {
+        Some(_) => return array,
+        None => return Rc::new(Object::Error("builtin pop of an empty array".into())),
+    }
+}
+
+pub fn unshift(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if let Some(error) = check("unshift", &args, 2) {
+        return error;
+    }
+    let [mut array, value]: [Rc<Object>; 2] = args.try_into().unwrap();
+    elements(&mut array).push_front(value);
+    return array;
+}
+
+pub fn shift(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if let Some(error) = check("shift", &args, 1) {
+        return error;
+    }
+    let [mut array]: [Rc<Object>; 1] = args.try_into().unwrap();
+    match elements(&mut array).pop_front() {
+        Some(_) => return array,
+        None => return Rc::new(Object::Error("builtin shift of an empty array".into())),
+    }
+}
+
+// the four as the engines call them, an error stopping the program
+pub fn push_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(push(args));
+}
+
+pub fn pop_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(pop(args));
+}
+
+pub fn unshift_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(unshift(args));
+}
+
+pub fn shift_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(shift(args));
+}
+
+// the error to return unless `args` are `want` arguments, an array first
+fn check(name: &str, args: &[Rc<Object>], want: usize) -> Option<Rc<Object>> {
+    if args.len() != want {
+        let message = format!("builtin {} expected {} arguments, got {}", name, want, args.len());
+        return Some(Rc::new(Object::Error(message)));
+    }
+    if !matches!(*args[0], Object::Array(_)) {
+        let message = format!("builtin {} not supported for type {}", name, args[0].type_name());
+        return Some(Rc::new(Object::Error(message)));
+    }
+    return frozen::check(name, &args[0]);
+}
+
+// the elements of `array`, copied first if anything else holds them
+fn elements(array: &mut Rc<Object>) -> &mut VecDeque<Rc<Object>> {
+    match Rc::make_mut(array) {
+        Object::Array(elements) => return elements,
+        _ => unreachable!("checked by check"),
+    }
+}
@@ -1,27 +1,106 @@
-use crate::Object;
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::rc::Rc;
+use crate::{EvalError, HashMap, Object};
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
 
 pub type Env = Rc<RefCell<Environment>>;
 
+// what `Environment::assign` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assigned {
+    Set,
+    // the environment binding the name is frozen
+    ReadOnly,
+    // a function around the one assigning binds the name, not the one itself or the globals
+    Enclosing,
+    Unbound,
+}
+
+// how much an evaluation may do, each call and loop turn a step, calls nesting `max_depth` deep,
+// see `Environment::set_budget`
+#[derive(Debug, Default, Eq, Clone, PartialEq)]
+pub struct Budget {
+    steps: Cell<usize>,
+    depth: Cell<usize>,
+    max_depth: usize,
+}
+
+impl Budget {
+    pub fn new(steps: usize, max_depth: usize) -> Self {
+        return Budget { steps: Cell::new(steps), depth: Cell::new(0), max_depth };
+    }
+
+    pub fn spend(&self) -> Result<(), EvalError> {
+        if self.steps.get() == 0 {
+            return Err("out of budget".to_string());
+        }
+        self.steps.set(self.steps.get() - 1);
+        return Ok(());
+    }
+
+    // a call, `leave` when it returns
+    pub fn enter(&self) -> Result<(), EvalError> {
+        if self.depth.get() == self.max_depth {
+            return Err("out of budget".to_string());
+        }
+        self.depth.set(self.depth.get() + 1);
+        return Ok(());
+    }
+
+    pub fn leave(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+}
+
 #[derive(Debug, Default, Eq, Clone, PartialEq)]
 pub struct Environment {
     store: HashMap<String, Rc<Object>>,
     outer: Option<Env>,
+    // the names of `outer` this environment can see, all of them when None
+    capabilities: Option<Vec<String>>,
+    read_only: bool,
+    // names are only looked up when evaluated, see `set_lenient`
+    lenient: bool,
+    // shared with the environments enclosed in it, see `set_budget`
+    budget: Option<Rc<Budget>>,
 }
 
 impl Environment {
     pub fn new_enclosed_environment(outer: &Env) -> Self {
-        let mut env: Environment = Default::default();
-        env.outer = Some(Rc::clone(outer));
-        return env;
+        return Environment {
+            store: HashMap::new(),
+            outer: Some(Rc::clone(outer)),
+            capabilities: None,
+            read_only: false,
+            lenient: false,
+            budget: outer.borrow().budget.clone(),
+        };
+    }
+
+    // a plugin's globals over a shared prelude, seeing only the names in `capabilities` and binding
+    // in its own store
+    pub fn new_isolated_child(prelude: &Env, capabilities: &[&str]) -> Self {
+        let capabilities = capabilities.iter().map(|c| c.to_string()).collect();
+        return Environment {
+            store: HashMap::new(),
+            outer: Some(Rc::clone(prelude)),
+            capabilities: Some(capabilities),
+            read_only: false,
+            lenient: false,
+            budget: prelude.borrow().budget.clone(),
+        };
     }
 
     pub fn get(&self, name: &str) -> Option<Rc<Object>> {
         match self.store.get(name) {
             Some(obj) => Some(Rc::clone(obj)),
             None => {
+                if let Some(capabilities) = &self.capabilities {
+                    if !capabilities.iter().any(|c| c == name) {
+                        return None;
+                    }
+                }
                 if let Some(outer) = &self.outer {
                     return outer.borrow().get(name);
                 } else {
@@ -31,7 +110,108 @@ impl Environment {
         }
     }
 
+    // A `let` in a frozen environment is a runtime error, so a host can hand scripts shared
+    // configuration as it is. The host can still `set`.
+    pub fn freeze(&mut self) {
+        self.read_only = true;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        return self.read_only;
+    }
+
+    // a lenient environment only reports an unbound name when it's evaluated, for the repl
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        return self.lenient;
+    }
+
+    // what's evaluated in this environment stops with an error once it's spent `budget`
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = Some(Rc::new(budget));
+    }
+
+    pub fn budget(&self) -> Option<Rc<Budget>> {
+        return self.budget.clone();
+    }
+
+    // what this environment binds itself, not its outer ones, by name
+    pub fn bindings(&self) -> Vec<(String, Rc<Object>)> {
+        let mut bindings = Vec::new();
+        for (name, value) in self.store.iter() {
+            bindings.push((name.clone(), Rc::clone(value)));
+        }
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        return bindings;
+    }
+
+    // every name `get` finds, for suggestions when one isn't found
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.store.keys().cloned().collect();
+        if let Some(outer) = &self.outer {
+            for name in outer.borrow().names() {
+                let visible = match &self.capabilities {
+                    Some(capabilities) => capabilities.contains(&name),
+                    None => true,
+                };
+                if visible && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        return names;
+    }
+
+    // the value this environment itself binds `name` to, leaving null in its place, so whoever
+    // gets it may hold the only reference
+    pub fn take(&mut self, name: &str) -> Option<Rc<Object>> {
+        let value = self.store.get_mut(name)?;
+        return Some(core::mem::replace(value, Rc::new(Object::Null)));
+    }
+
+    // `name = val`, see `Assigned`, set where this environment binds it or else in its globals
+    pub fn assign(&mut self, name: &str, val: Rc<Object>) -> Assigned {
+        if self.store.contains_key(name) {
+            if self.read_only {
+                return Assigned::ReadOnly;
+            }
+            self.store.insert(name.to_string(), val);
+            return Assigned::Set;
+        }
+        let mut env = match &self.outer {
+            Some(outer) if !self.is_globals() => Rc::clone(outer),
+            _ => return Assigned::Unbound,
+        };
+        loop {
+            let outer = {
+                let e = env.borrow();
+                if e.is_globals() {
+                    break;
+                }
+                if e.store.contains_key(name) {
+                    return Assigned::Enclosing;
+                }
+                Rc::clone(e.outer.as_ref().unwrap())
+            };
+            env = outer;
+        }
+        return env.borrow_mut().assign(name, val);
+    }
+
+    fn is_globals(&self) -> bool {
+        return self.outer.is_none() || self.capabilities.is_some();
+    }
+
     pub fn set(&mut self, name: String, val: Rc<Object>) {
         self.store.insert(name, val);
     }
+
+    // unbinds every name, for what they hold to go even when a function bound here holds this
+    // environment in turn
+    pub fn clear(&mut self) {
+        self.store.clear();
+    }
 }
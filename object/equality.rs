@@ -0,0 +1,38 @@
+use alloc::rc::Rc;
+use core::cmp::Ordering;
+
+use crate::{number, Object};
+
+// what `==` means in both engines: numbers by value, arrays and hashes deeply, functions only
+// equal to themselves, different kinds never equal
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Object) -> bool {
+        if let Some(ordering) = number::compare(self, other) {
+            return ordering == Ordering::Equal;
+        }
+        match (self, other) {
+            (Object::Boolean(a), Object::Boolean(b)) => return a == b,
+            (Object::String(a), Object::String(b)) => return a == b,
+            (Object::Char(a), Object::Char(b)) => return a == b,
+            (Object::Array(a), Object::Array(b)) => return a == b,
+            (Object::Hash(a), Object::Hash(b)) => return a == b,
+            (Object::Null, Object::Null) => return true,
+            (Object::ReturnValue(a), Object::ReturnValue(b)) => return a == b,
+            // values live behind an `Rc`, the same function is the same allocation
+            (Object::Function(..), Object::Function(..)) => return core::ptr::eq(self, other),
+            (Object::ClosureObj(_), Object::ClosureObj(_)) => return core::ptr::eq(self, other),
+            (Object::Builtin(a), Object::Builtin(b)) => return core::ptr::fn_addr_eq(*a, *b),
+            (Object::Iterator(a), Object::Iterator(b)) => return Rc::ptr_eq(a, b),
+            (Object::Channel(a), Object::Channel(b)) => return a == b,
+            (Object::Cell(a), Object::Cell(b)) => return a == b,
+            (Object::Memo(a), Object::Memo(b)) => return Rc::ptr_eq(a, b),
+            (Object::Partial(a), Object::Partial(b)) => return Rc::ptr_eq(a, b),
+            (Object::Native(a), Object::Native(b)) => return Rc::ptr_eq(a, b),
+            (Object::Error(a), Object::Error(b)) => return a == b,
+            // a constant of the pool, not a value of the program
+            (Object::CompiledFunction(a), Object::CompiledFunction(b)) => return a == b,
+            _ => return false,
+        }
+    }
+}
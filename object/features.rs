@@ -0,0 +1,301 @@
+use crate::builtins::BuiltIns;
+use crate::channel::VM_ONLY;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+// what each engine runs, the source of `monkey features`; the engines' tests run every example
+pub struct Feature {
+    pub name: &'static str,
+    pub example: &'static str,
+    pub expected: &'static str,
+    // the tree-walking interpreter
+    pub evaluator: bool,
+    pub vm: bool,
+}
+
+pub static CONSTRUCTS: &[Feature] = &[
+    Feature {
+        name: "integer arithmetic",
+        example: "(1 + 2) * 3 - 8 / 4",
+        expected: "7",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "integer comparison",
+        example: "1 < 2 == (3 > 2)",
+        expected: "true",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "boolean equality",
+        example: "true != false",
+        expected: "true",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "prefix operators",
+        example: "!(-1 > 0)",
+        expected: "true",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "hex, octal and binary literals",
+        example: "0xFF + 0o10 + 0b11",
+        expected: "266",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "digit separators",
+        example: "1_000_000 + 0xFF_FF + 1_000.50d",
+        expected: "1066535.50",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "decimal arithmetic",
+        example: "3 * 1.10d + decimal(\"0.25\") - 0.5d",
+        expected: "3.05",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "floor division",
+        example: "div(-7, 2) == -7 / 2 - 1",
+        expected: "true",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "index assignment",
+        example: "let a = [1, 2]; a[0] = 3; let h = remove({\"k\": 1}, \"k\"); h[\"j\"] = a; h",
+        expected: "[j: [3, 2]]",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "compound assignment",
+        example: "let x = 2; x *= 5; let a = [x]; a[0] -= 3; a",
+        expected: "[7]",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "assignment",
+        example: "let n = 0; let bump = fn() { n = n + 1 }; bump(); bump()",
+        expected: "2",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "ternary conditional",
+        example: "let n = 0; n < 0 ? \"negative\" : n == 0 ? \"zero\" : \"positive\"",
+        expected: "zero",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "queues and stacks",
+        example: "let q = unshift(push([2], 3), 1); shift(pop(q))",
+        expected: "[2]",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "frozen values",
+        example: "let a = freeze([1]); let b = push(clone(a), 2); len(b) + len(a)",
+        expected: "3",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "while loops",
+        example: "let n = 1; while (n < 100) { let n = n * 2; } n",
+        expected: "128",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "for loops",
+        example: "let s = 0; for (let i = 1; i < 5; i = i + 1) { let s = s + i; } s",
+        expected: "10",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "for-in loops",
+        example: "let s = 0; for (x in [1, 2, 3]) { let s = s + x; } s",
+        expected: "6",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "string concatenation",
+        example: "\"mon\" + \"key\"",
+        expected: "monkey",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "string escapes",
+        example: "\"say \\\"hi\\\"\\u{21}\"",
+        expected: "say \"hi\"!",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "string interpolation",
+        example: "let name = \"monkey\"; \"hello ${name}, ${1 + 2}!\"",
+        expected: "hello monkey, 3!",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "string equality",
+        example: "\"monkey\" == \"mon\" + \"key\"",
+        expected: "true",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "array and hash equality",
+        example: "[1, {\"a\": [2]}] == [1, {\"a\": [2]}]",
+        expected: "true",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "string and array ordering",
+        example: "\"apple\" < \"banana\" == ([1, 2] < [1, 3])",
+        expected: "true",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "let bindings",
+        example: "let a = 1; let b = a + 1; b",
+        expected: "2",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "conditionals",
+        example: "if (1 > 2) { 10 } else { 20 }",
+        expected: "20",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "functions",
+        example: "let add = fn(a, b) { a + b }; add(1, 2)",
+        expected: "3",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "early return",
+        example: "fn() { return 1; 2 }()",
+        expected: "1",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "closures",
+        example: "let adder = fn(x) { fn(y) { x + y } }; let addTwo = adder(2); addTwo(3)",
+        expected: "5",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "recursion",
+        example: "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(10)",
+        expected: "55",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "function declarations",
+        example: "fn fib(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } } fib(10)",
+        expected: "55",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "arrays",
+        example: "[1, 2 * 2, 3][1]",
+        expected: "4",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "hashes",
+        example: "{\"one\": 1, true: 2}[true]",
+        expected: "2",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "dot access",
+        example: "let rec = {\"n\": 2, \"twice\": fn(x) { x * 2 }}; rec.twice(rec.n)",
+        expected: "4",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "pattern matching",
+        example: "match ([1, 2, 3]) { [] => 0, [x, ...others] if x > 0 => len(others) }",
+        expected: "2",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "function composition",
+        example: "let h = rest >> first; h([1, 2, 3])",
+        expected: "2",
+        evaluator: true,
+        vm: true,
+    },
+    Feature {
+        name: "threads and channels",
+        example: "let ch = chan(); spawn(fn() { send(ch, 1) }); recv(ch)",
+        expected: "1",
+        evaluator: false,
+        vm: cfg!(feature = "parallel"),
+    },
+];
+
+// Both engines resolve builtins against the one `BuiltIns` table, so every builtin is on both,
+// though the evaluator only returns an error from the ones in `channel::VM_ONLY`.
+pub fn builtins() -> Vec<&'static str> {
+    return BuiltIns.iter().map(|b| b.0).collect();
+}
+
+// the table printed by `monkey features`
+pub fn report() -> String {
+    let mut rows = vec![("construct", "evaluator", "vm")];
+    let mark = |supported: bool| if supported { "yes" } else { "no" };
+    for f in CONSTRUCTS.iter() {
+        rows.push((f.name, mark(f.evaluator), mark(f.vm)));
+    }
+    rows.push(("", "", ""));
+    rows.push(("builtin", "evaluator", "vm"));
+    for name in builtins() {
+        rows.push((name, mark(!VM_ONLY.contains(&name)), "yes"));
+    }
+
+    let width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (name, evaluator, vm) in rows {
+        if name.is_empty() {
+            out.push('\n');
+            continue;
+        }
+        out.push_str(&format!("{:width$}  {:9}  {}\n", name, evaluator, vm, width = width));
+    }
+    return out;
+}
@@ -0,0 +1,85 @@
+use alloc::format;
+use alloc::rc::Rc;
+#[cfg(feature = "std")]
+use alloc::rc::Weak;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::cell::RefCell;
+
+#[cfg(feature = "std")]
+use crate::HashMap;
+use crate::Object;
+
+// `freeze(x)` marks `x` and what's in it so updates of it are errors; without `std` there's
+// nowhere to keep the mark and `freeze(x)` is `x`
+
+// The frozen values of this thread, by address, which the `Weak` keeps from being reused. Those
+// nothing holds any more are forgotten each time there are twice as many as were left before.
+#[cfg(feature = "std")]
+struct Marked {
+    values: HashMap<usize, Weak<Object>>,
+    prune_at: usize,
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static MARKED: RefCell<Marked> = RefCell::new(Marked { values: HashMap::new(), prune_at: 64 });
+}
+
+// the `freeze` builtin
+pub fn freeze(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 1 {
+        return Rc::new(Object::Error(format!(
+            "builtin freeze expected 1 argument, got {}",
+            args.len()
+        )));
+    }
+    mark(&args[0]);
+    return Rc::clone(&args[0]);
+}
+
+fn mark(value: &Rc<Object>) {
+    match &**value {
+        Object::Array(elements) => elements.iter().for_each(mark),
+        Object::Hash(map) => map.values().for_each(mark),
+        Object::String(_) => {}
+        _ => return,
+    }
+    remember(value);
+}
+
+#[cfg(feature = "std")]
+fn remember(value: &Rc<Object>) {
+    MARKED.with(|marked| {
+        let marked = &mut *marked.borrow_mut();
+        if marked.values.len() >= marked.prune_at {
+            marked.values.retain(|_, weak| weak.strong_count() > 0);
+            marked.prune_at = 64.max(marked.values.len() * 2);
+        }
+        let address = Rc::as_ptr(value) as usize;
+        marked.values.insert(address, Rc::downgrade(value));
+    });
+}
+
+#[cfg(not(feature = "std"))]
+fn remember(_: &Rc<Object>) {}
+
+#[cfg(feature = "std")]
+pub fn is_frozen(value: &Rc<Object>) -> bool {
+    let address = Rc::as_ptr(value) as usize;
+    return MARKED.with(|marked| marked.borrow().values.contains_key(&address));
+}
+
+#[cfg(not(feature = "std"))]
+pub fn is_frozen(_: &Rc<Object>) -> bool {
+    return false;
+}
+
+// the error of the builtin `name` changing `value`, if it's frozen
+pub fn check(name: &str, value: &Rc<Object>) -> Option<Rc<Object>> {
+    if !is_frozen(value) {
+        return None;
+    }
+    let message = format!("builtin {} can't change a frozen {}", name, value.type_name());
+    return Some(Rc::new(Object::Error(message)));
+}
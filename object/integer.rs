@@ -0,0 +1,118 @@
+use alloc::format;
+use core::cmp::Ordering;
+
+#[cfg(feature = "bigint")]
+use core::convert::TryFrom;
+#[cfg(feature = "bigint")]
+use num_bigint::{BigInt, Sign};
+
+use crate::number::{self, Arithmetic};
+use crate::{EvalError, Object};
+
+// integer arithmetic, see `number`: past `i64` a `BigInteger` with `bigint`, an error without
+
+pub fn is_integer(value: &Object) -> bool {
+    match value {
+        Object::Integer(_) => return true,
+        #[cfg(feature = "bigint")]
+        Object::BigInteger(_) => return true,
+        _ => return false,
+    }
+}
+
+pub fn arithmetic(op: Arithmetic, left: &Object, right: &Object) -> Result<Object, EvalError> {
+    if let (Object::Integer(l), Object::Integer(r)) = (left, right) {
+        let result = match op {
+            Arithmetic::Add => l.checked_add(*r),
+            Arithmetic::Sub => l.checked_sub(*r),
+            Arithmetic::Mul => l.checked_mul(*r),
+            Arithmetic::Div if *r == 0 => return Err("division by zero".into()),
+            Arithmetic::Div => l.checked_div(*r),
+            Arithmetic::FloorDiv if *r == 0 => return Err("division by zero".into()),
+            Arithmetic::FloorDiv => l.checked_div(*r).map(|q| {
+                // truncated toward zero, one too high when there's a remainder of opposite sign
+                if l % r != 0 && (*l < 0) != (*r < 0) {
+                    return q - 1;
+                }
+                return q;
+            }),
+        };
+        if let Some(i) = result {
+            return Ok(Object::Integer(i));
+        }
+    }
+    return big_arithmetic(op, left, right);
+}
+
+pub fn negate(value: &Object) -> Result<Object, EvalError> {
+    match value {
+        Object::Integer(i) => match i.checked_neg() {
+            Some(i) => return Ok(Object::Integer(i)),
+            None => return big_arithmetic(Arithmetic::Sub, &Object::Integer(0), value),
+        },
+        #[cfg(feature = "bigint")]
+        Object::BigInteger(b) => return Ok(normalize(-b)),
+        _ => return Err(format!("can't negate {}", value)),
+    }
+}
+
+// None unless both are integers
+pub fn compare(left: &Object, right: &Object) -> Option<Ordering> {
+    match (left, right) {
+        (Object::Integer(l), Object::Integer(r)) => return Some(l.cmp(r)),
+        #[cfg(feature = "bigint")]
+        _ => return Some(to_big(left)?.cmp(&to_big(right)?)),
+        #[cfg(not(feature = "bigint"))]
+        _ => return None,
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+fn big_arithmetic(op: Arithmetic, left: &Object, right: &Object) -> Result<Object, EvalError> {
+    if !is_integer(left) || !is_integer(right) {
+        return Err(number::unsupported(op, left, right));
+    }
+    return Err(format!("integer overflow: {} {} {}", left, op, right));
+}
+
+#[cfg(feature = "bigint")]
+fn big_arithmetic(op: Arithmetic, left: &Object, right: &Object) -> Result<Object, EvalError> {
+    let (l, r) = match (to_big(left), to_big(right)) {
+        (Some(l), Some(r)) => (l, r),
+        _ => return Err(number::unsupported(op, left, right)),
+    };
+    let result = match op {
+        Arithmetic::Add => l + r,
+        Arithmetic::Sub => l - r,
+        Arithmetic::Mul => l * r,
+        Arithmetic::Div if r == BigInt::ZERO => return Err("division by zero".into()),
+        Arithmetic::Div => l / r,
+        Arithmetic::FloorDiv if r == BigInt::ZERO => return Err("division by zero".into()),
+        Arithmetic::FloorDiv => {
+            let q = &l / &r;
+            if &q * &r != l && (l.sign() == Sign::Minus) != (r.sign() == Sign::Minus) {
+                q - 1
+            } else {
+                q
+            }
+        }
+    };
+    return Ok(normalize(result));
+}
+
+#[cfg(feature = "bigint")]
+fn to_big(value: &Object) -> Option<BigInt> {
+    match value {
+        Object::Integer(i) => return Some(BigInt::from(*i)),
+        Object::BigInteger(b) => return Some(b.clone()),
+        _ => return None,
+    }
+}
+
+#[cfg(feature = "bigint")]
+pub fn normalize(value: BigInt) -> Object {
+    match i64::try_from(&value) {
+        Ok(i) => return Object::Integer(i),
+        Err(_) => return Object::BigInteger(value),
+    }
+}
@@ -0,0 +1,33 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::EvalError;
+
+// Ctrl-C in the repls: `request` sets a flag the engines check at every call and loop turn
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+// runs `f` as the one `request` cancels
+pub fn run<T>(f: impl FnOnce() -> T) -> T {
+    REQUESTED.store(false, Ordering::SeqCst);
+    RUNNING.store(true, Ordering::SeqCst);
+    let result = f();
+    RUNNING.store(false, Ordering::SeqCst);
+    return result;
+}
+
+// false when nothing is running, for the repl to quit as it did before it handled Ctrl-C
+pub fn request() -> bool {
+    if !RUNNING.load(Ordering::SeqCst) {
+        return false;
+    }
+    REQUESTED.store(true, Ordering::SeqCst);
+    return true;
+}
+
+pub fn check() -> Result<(), EvalError> {
+    if REQUESTED.load(Ordering::Relaxed) {
+        REQUESTED.store(false, Ordering::Relaxed);
+        return Err("interrupted".into());
+    }
+    return Ok(());
+}
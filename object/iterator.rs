@@ -0,0 +1,319 @@
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::mem;
+
+use crate::builtins::uncallable;
+use crate::{interrupt, Call, EvalError, HashMap, Object};
+
+// iterators for `next`, `iter`, `range`, `generate`, the lazy `imap`, `ifilter` and `take`, and
+// `collect`; `next(it)` is `{"value": v, "done": d}`, and an iterator is only equal to itself
+
+#[derive(Debug, Clone)]
+pub enum Sequence {
+    Range { next: i64, end: i64, step: i64 },
+    // the elements of an array, from `at`
+    Elements { of: Rc<Object>, at: usize },
+    Values(VecDeque<Rc<Object>>),
+    Generate { state: Rc<Object>, with: Rc<Object> },
+    Map(Rc<RefCell<Sequence>>, Rc<Object>),
+    Filter(Rc<RefCell<Sequence>>, Rc<Object>),
+    Take(Rc<RefCell<Sequence>>, usize),
+    Done,
+}
+
+// `Object` is `Eq`, but iterators are compared as the one they are, see `equality`
+impl PartialEq for Sequence {
+    fn eq(&self, other: &Sequence) -> bool {
+        return core::ptr::eq(self, other);
+    }
+}
+
+impl Eq for Sequence {}
+
+// the next value of `sequence`, calling functions by `call`
+pub fn advance(
+    sequence: &Rc<RefCell<Sequence>>,
+    call: &mut Call,
+) -> Result<Option<Rc<Object>>, EvalError> {
+    let mut current = sequence.borrow_mut();
+    match &mut *current {
+        Sequence::Range { next, end, step } => {
+            if (*step > 0 && *next >= *end) || (*step < 0 && *next <= *end) {
+                return Ok(None);
+            }
+            let value = *next;
+            *next = next.checked_add(*step).unwrap_or(*end);
+            return Ok(Some(Rc::new(Object::Integer(value))));
+        }
+        Sequence::Elements { of, at } => match &**of {
+            Object::Array(elements) => {
+                let value = elements.get(*at).cloned();
+                *at += 1;
+                return Ok(value);
+            }
+            _ => unreachable!("made of arrays only"),
+        },
+        Sequence::Values(values) => return Ok(values.pop_front()),
+        Sequence::Done => return Ok(None),
+        _ => {}
+    }
+    // the rest call functions, which may reach this iterator, so it isn't borrowed while they run
+    let taken = mem::replace(&mut *current, Sequence::Done);
+    drop(current);
+    let restore = |current| *sequence.borrow_mut() = current;
+    match taken {
+        Sequence::Map(inner, f) => {
+            restore(Sequence::Map(Rc::clone(&inner), Rc::clone(&f)));
+            match advance(&inner, call)? {
+                Some(value) => return Ok(Some(call(&f, vec![value])?)),
+                None => return Ok(None),
+            }
+        }
+        Sequence::Filter(inner, f) => {
+            restore(Sequence::Filter(Rc::clone(&inner), Rc::clone(&f)));
+            while let Some(value) = advance(&inner, call)? {
+                if call(&f, vec![Rc::clone(&value)])?.is_truthy() {
+                    return Ok(Some(value));
+                }
+            }
+            return Ok(None);
+        }
+        Sequence::Take(_, 0) => return Ok(None),
+        Sequence::Take(inner, n) => {
+            restore(Sequence::Take(Rc::clone(&inner), n - 1));
+            return advance(&inner, call);
+        }
+        Sequence::Generate { state, with } => match &*call(&with, vec![state])? {
+            Object::Null => return Ok(None),
+            Object::Array(pair) if pair.len() == 2 => {
+                let (value, state) = (Rc::clone(&pair[0]), Rc::clone(&pair[1]));
+                restore(Sequence::Generate { state, with });
+                return Ok(Some(value));
+            }
+            o => {
+                let message = format!(
+                    "a generator must return [value, state] or null, got {}",
+                    o.type_name()
+                );
+                return Err(message);
+            }
+        },
+        _ => unreachable!("handled above"),
+    }
+}
+
+// the `iter` builtin
+pub fn iter(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if let Some(error) = arity("iter", &args, 1) {
+        return error;
+    }
+    match sequence("iter", &args[0]) {
+        Ok(sequence) => return Rc::new(Object::Iterator(sequence)),
+        Err(error) => return error,
+    }
+}
+
+// the `each` builtin, what `for (x in xs)` goes through: as `iter`, but the keys of a hash. It
+// calls nothing, but a value it can't go through stops the program rather than loop over an error.
+pub fn each_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    let value = match args.as_slice() {
+        [value] => value,
+        _ => return Err(format!("builtin each expected 1 argument, got {}", args.len())),
+    };
+    match &**value {
+        Object::Hash(map) => return Ok(iterator(Sequence::Values(map.keys().cloned().collect()))),
+        Object::Iterator(_) | Object::Array(_) | Object::String(_) => return Ok(iter(args)),
+        o => return Err(format!("builtin each not supported for type {}", o.type_name())),
+    }
+}
+
+pub fn each(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return each_calling(args, &mut uncallable).unwrap_or_else(error);
+}
+
+// the `range` builtin
+pub fn range(args: Vec<Rc<Object>>) -> Rc<Object> {
+    let mut bounds = Vec::new();
+    for arg in &args {
+        match **arg {
+            Object::Integer(i) => bounds.push(i),
+            ref o => return unsupported("range", o),
+        }
+    }
+    let (next, end, step) = match bounds[..] {
+        [end] => (0, end, 1),
+        [next, end] => (next, end, 1),
+        [_, _, 0] => return error("builtin range step can't be 0".to_string()),
+        [next, end, step] => (next, end, step),
+        _ => return error(format!("builtin range expected 1 to 3 arguments, got {}", args.len())),
+    };
+    return iterator(Sequence::Range { next, end, step });
+}
+
+// the `generate` builtin
+pub fn generate(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if let Some(error) = arity("generate", &args, 2) {
+        return error;
+    }
+    if let Some(error) = function("generate", &args[1]) {
+        return error;
+    }
+    let (state, with) = (Rc::clone(&args[0]), Rc::clone(&args[1]));
+    return iterator(Sequence::Generate { state, with });
+}
+
+// the `imap` builtin
+pub fn imap(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return over("imap", &args, |inner, f| Sequence::Map(inner, Rc::clone(f)));
+}
+
+// the `ifilter` builtin
+pub fn ifilter(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return over("ifilter", &args, |inner, f| Sequence::Filter(inner, Rc::clone(f)));
+}
+
+// the `take` builtin
+pub fn take(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if let Some(error) = arity("take", &args, 2) {
+        return error;
+    }
+    let n = match *args[1] {
+        Object::Integer(n) if n >= 0 => n as usize,
+        Object::Integer(n) => {
+            return error(format!("builtin take expected a count of 0 or more, got {}", n))
+        }
+        ref o => return unsupported("take", o),
+    };
+    match sequence("take", &args[0]) {
+        Ok(inner) => return iterator(Sequence::Take(inner, n)),
+        Err(error) => return error,
+    }
+}
+
+// the `next` builtin, calling functions by `call`
+pub fn next_calling(args: Vec<Rc<Object>>, call: &mut Call) -> Result<Rc<Object>, EvalError> {
+    if let Some(error) = arity("next", &args, 1) {
+        return Ok(error);
+    }
+    let sequence = match &*args[0] {
+        Object::Iterator(sequence) => sequence,
+        o => return Ok(unsupported("next", o)),
+    };
+    let (value, done) = match advance(sequence, call)? {
+        Some(value) => (value, false),
+        None => (Rc::new(Object::Null), true),
+    };
+    let mut result = HashMap::new();
+    result.insert(Rc::new(Object::String("value".to_string())), value);
+    result.insert(Rc::new(Object::String("done".to_string())), Rc::new(Object::Boolean(done)));
+    return Ok(Rc::new(Object::Hash(result)));
+}
+
+// the `collect` builtin, calling functions by `call`
+pub fn collect_calling(args: Vec<Rc<Object>>, call: &mut Call) -> Result<Rc<Object>, EvalError> {
+    if let Some(error) = arity("collect", &args, 1) {
+        return Ok(error);
+    }
+    let sequence = match sequence("collect", &args[0]) {
+        Ok(sequence) => sequence,
+        Err(error) => return Ok(error),
+    };
+    let mut values = VecDeque::new();
+    while let Some(value) = advance(&sequence, call)? {
+        // an endless sequence is collected until interrupted
+        interrupt::check()?;
+        values.push_back(value);
+    }
+    return Ok(Rc::new(Object::Array(values)));
+}
+
+// the two calling ones as builtins that can't call, for whoever calls them without an engine
+pub fn next(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return next_calling(args, &mut uncallable).unwrap_or_else(error);
+}
+
+pub fn collect(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return collect_calling(args, &mut uncallable).unwrap_or_else(error);
+}
+
+// what iterating `value` yields, or the error for builtin `name` to return
+fn sequence(name: &str, value: &Rc<Object>) -> Result<Rc<RefCell<Sequence>>, Rc<Object>> {
+    let sequence = match &**value {
+        Object::Iterator(sequence) => return Ok(Rc::clone(sequence)),
+        Object::Array(_) => Sequence::Elements { of: Rc::clone(value), at: 0 },
+        Object::String(s) => {
+            let characters = s.chars().map(|c| Rc::new(Object::String(c.to_string())));
+            Sequence::Values(characters.collect())
+        }
+        Object::Hash(map) => {
+            let pairs = map.iter().map(|(k, v)| {
+                Rc::new(Object::Array(VecDeque::from(vec![Rc::clone(k), Rc::clone(v)])))
+            });
+            Sequence::Values(pairs.collect())
+        }
+        o => return Err(unsupported(name, o)),
+    };
+    return Ok(Rc::new(RefCell::new(sequence)));
+}
+
+// builtin `name` making an iterator of the iterator and function in `args`
+fn over(
+    name: &str,
+    args: &[Rc<Object>],
+    make: impl FnOnce(Rc<RefCell<Sequence>>, &Rc<Object>) -> Sequence,
+) -> Rc<Object> {
+    if let Some(error) = arity(name, args, 2) {
+        return error;
+    }
+    if let Some(error) = function(name, &args[1]) {
+        return error;
+    }
+    match sequence(name, &args[0]) {
+        Ok(inner) => return iterator(make(inner, &args[1])),
+        Err(error) => return error,
+    }
+}
+
+fn iterator(sequence: Sequence) -> Rc<Object> {
+    return Rc::new(Object::Iterator(Rc::new(RefCell::new(sequence))));
+}
+
+fn function(name: &str, value: &Object) -> Option<Rc<Object>> {
+    match value {
+        o if o.is_callable() => return None,
+        o => {
+            return Some(error(format!(
+                "builtin {} expected a function, got {}",
+                name,
+                o.type_name()
+            )))
+        }
+    }
+}
+
+fn arity(name: &str, args: &[Rc<Object>], want: usize) -> Option<Rc<Object>> {
+    if args.len() == want {
+        return None;
+    }
+    let plural = if want == 1 { "" } else { "s" };
+    return Some(error(format!(
+        "builtin {} expected {} argument{}, got {}",
+        name,
+        want,
+        plural,
+        args.len()
+    )));
+}
+
+fn unsupported(name: &str, value: &Object) -> Rc<Object> {
+    return error(format!("builtin {} not supported for type {}", name, value.type_name()));
+}
+
+fn error(message: EvalError) -> Rc<Object> {
+    return Rc::new(Object::Error(message));
+}
@@ -0,0 +1,32 @@
+use alloc::rc::Rc;
+
+use crate::Object;
+
+// how `match` picks an arm, in both engines: the first whose pattern fits and whose guard holds,
+// binding its names in the scope around the `match`
+
+// whether `value` is an array of `len` elements, or of `len` and more with a rest
+pub fn array_fits(value: &Object, len: usize, rest: bool) -> bool {
+    match value {
+        Object::Array(elements) => return elements.len() == len || (rest && elements.len() > len),
+        _ => return false,
+    }
+}
+
+// whether `value` is a hash with the key `key`
+pub fn hash_has(value: &Object, key: &Object) -> bool {
+    match value {
+        Object::Hash(pairs) => return key.is_hashable() && pairs.contains_key(key),
+        _ => return false,
+    }
+}
+
+// the elements of the array `value` from `from` on, what `...rest` binds
+pub fn array_rest(value: &Object, from: usize) -> Rc<Object> {
+    match value {
+        Object::Array(elements) => {
+            return Rc::new(Object::Array(elements.iter().skip(from).cloned().collect()));
+        }
+        _ => return Rc::new(Object::Null),
+    }
+}
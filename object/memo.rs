@@ -0,0 +1,130 @@
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+
+use crate::{HashMap, Object};
+
+// `memo(f)` remembers the results of `f` by hashable arguments, up to 1024 or `memo(f, n)`, and
+// `cache_stats(m)` reports its hits, misses, size and limit
+
+pub const DEFAULT_LIMIT: usize = 1024;
+
+#[derive(Debug)]
+pub struct Memo {
+    pub function: Rc<Object>,
+    pub limit: usize,
+    results: RefCell<HashMap<Vec<Rc<Object>>, Rc<Object>>>,
+    // the arguments remembered, first first, to forget in that order
+    order: RefCell<VecDeque<Vec<Rc<Object>>>>,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+}
+
+// `Object` is `Eq`, but memoized functions are compared as the one they are, see `equality`
+impl PartialEq for Memo {
+    fn eq(&self, other: &Memo) -> bool {
+        return core::ptr::eq(self, other);
+    }
+}
+
+impl Eq for Memo {}
+
+impl Memo {
+    pub fn new(function: Rc<Object>, limit: usize) -> Memo {
+        return Memo {
+            function,
+            limit,
+            results: RefCell::new(HashMap::new()),
+            order: RefCell::new(VecDeque::new()),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        };
+    }
+
+    // what `function` returned for `args` before, or `None` for the engine to call it and
+    // `remember` the result
+    pub fn recall(&self, args: &[Rc<Object>]) -> Option<Rc<Object>> {
+        if args.iter().all(|arg| arg.is_hashable()) {
+            if let Some(result) = self.results.borrow().get(args) {
+                self.hits.set(self.hits.get() + 1);
+                return Some(Rc::clone(result));
+            }
+        }
+        self.misses.set(self.misses.get() + 1);
+        return None;
+    }
+
+    pub fn remember(&self, args: Vec<Rc<Object>>, result: &Rc<Object>) {
+        if matches!(**result, Object::Error(_)) || !args.iter().all(|arg| arg.is_hashable()) {
+            return;
+        }
+        let mut results = self.results.borrow_mut();
+        let mut order = self.order.borrow_mut();
+        // a recursive call remembered it in the meantime
+        if results.contains_key(&args) {
+            return;
+        }
+        if results.len() == self.limit {
+            if let Some(first) = order.pop_front() {
+                results.remove(&first);
+            }
+        }
+        order.push_back(args.clone());
+        results.insert(args, Rc::clone(result));
+    }
+}
+
+// the `memo` builtin
+pub fn memo(args: Vec<Rc<Object>>) -> Rc<Object> {
+    let (function, limit) = match args.as_slice() {
+        [function] => (function, DEFAULT_LIMIT),
+        [function, limit] => match **limit {
+            Object::Integer(n) if n >= 1 => (function, n as usize),
+            Object::Integer(n) => {
+                return error(format!("builtin memo expected a limit of 1 or more, got {}", n))
+            }
+            ref o => return error(format!("builtin memo expected a limit, got {}", o.type_name())),
+        },
+        _ => return error(format!("builtin memo expected 1 or 2 arguments, got {}", args.len())),
+    };
+    if !function.is_callable() {
+        let message = format!("builtin memo expected a function, got {}", function.type_name());
+        return error(message);
+    }
+    return Rc::new(Object::Memo(Rc::new(Memo::new(Rc::clone(function), limit))));
+}
+
+// the `cache_stats` builtin
+pub fn cache_stats(args: Vec<Rc<Object>>) -> Rc<Object> {
+    let memo = match args.as_slice() {
+        [memo] => match &**memo {
+            Object::Memo(memo) => memo,
+            o => {
+                let message = "builtin cache_stats expected a memoized function, got";
+                return error(format!("{} {}", message, o.type_name()));
+            }
+        },
+        _ => {
+            let message = "builtin cache_stats expected 1 argument, got";
+            return error(format!("{} {}", message, args.len()));
+        }
+    };
+    let stats = [
+        ("hits", memo.hits.get() as i64),
+        ("misses", memo.misses.get() as i64),
+        ("size", memo.results.borrow().len() as i64),
+        ("limit", memo.limit as i64),
+    ];
+    let mut hash = HashMap::new();
+    for (name, value) in stats {
+        hash.insert(Rc::new(Object::String(name.to_string())), Rc::new(Object::Integer(value)));
+    }
+    return Rc::new(Object::Hash(hash));
+}
+
+fn error(message: String) -> Rc<Object> {
+    return Rc::new(Object::Error(message));
+}
@@ -0,0 +1,123 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt;
+
+use crate::builtins::stop;
+use crate::{Call, EvalError, Object};
+
+// a host resource handed to scripts, finalized once: at `close(h)` or when the last reference goes
+pub struct Handle {
+    // what it holds, for printing
+    kind: String,
+    resource: RefCell<Option<Box<dyn Any>>>,
+    finalizer: RefCell<Option<Finalizer>>,
+}
+
+type Finalizer = Box<dyn FnOnce(Box<dyn Any>)>;
+
+impl Handle {
+    // a handle on `resource` finalized by its own `Drop`
+    pub fn new<T: Any>(kind: &str, resource: T) -> Handle {
+        return Handle {
+            kind: kind.to_string(),
+            resource: RefCell::new(Some(Box::new(resource))),
+            finalizer: RefCell::new(None),
+        };
+    }
+
+    pub fn with_finalizer<T: Any>(
+        kind: &str,
+        resource: T,
+        finalizer: impl FnOnce(T) + 'static,
+    ) -> Handle {
+        let handle = Handle::new(kind, resource);
+        let finalizer = move |resource: Box<dyn Any>| {
+            if let Ok(resource) = resource.downcast::<T>() {
+                finalizer(*resource);
+            }
+        };
+        *handle.finalizer.borrow_mut() = Some(Box::new(finalizer));
+        return handle;
+    }
+
+    // `f` on the resource, for the host; `None` once it's closed or if it isn't a `T`
+    pub fn with<T: Any, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let mut resource = self.resource.borrow_mut();
+        return resource.as_mut()?.downcast_mut::<T>().map(f);
+    }
+
+    pub fn is_open(&self) -> bool {
+        return self.resource.borrow().is_some();
+    }
+
+    // finalizes the resource, `false` if it was closed already
+    pub fn close(&self) -> bool {
+        let resource = self.resource.borrow_mut().take();
+        let resource = match resource {
+            Some(resource) => resource,
+            None => return false,
+        };
+        let finalizer = self.finalizer.borrow_mut().take();
+        if let Some(finalizer) = finalizer {
+            finalizer(resource);
+        }
+        return true;
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl fmt::Display for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.is_open() {
+            true => write!(f, "[handle {}]", self.kind),
+            false => write!(f, "[closed handle {}]", self.kind),
+        }
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let open = self.is_open();
+        return f
+            .debug_struct("Handle")
+            .field("kind", &self.kind)
+            .field("open", &open)
+            .finish();
+    }
+}
+
+// `Object` is `Eq`, but handles are compared as the one they are, see `equality`
+impl PartialEq for Handle {
+    fn eq(&self, other: &Handle) -> bool {
+        return core::ptr::eq(self, other);
+    }
+}
+
+impl Eq for Handle {}
+
+// the `close` builtin, `true` if it closed the handle and `false` if it was closed already
+pub fn close(args: Vec<Rc<Object>>) -> Rc<Object> {
+    let message = match args.as_slice() {
+        [handle] => match &**handle {
+            Object::Native(handle) => return Rc::new(Object::Boolean(handle.close())),
+            o => format!("builtin close expected a handle, got {}", o.type_name()),
+        },
+        _ => format!("builtin close expected 1 argument, got {}", args.len()),
+    };
+    return Rc::new(Object::Error(message));
+}
+
+// `close` as the engines call it, an error stopping the program
+pub fn close_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(close(args));
+}
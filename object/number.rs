@@ -0,0 +1,107 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use core::fmt::Formatter;
+use core::hash::{Hash, Hasher};
+
+use crate::{decimal, integer, EvalError, Object};
+
+// the numeric tower: integers, big integers with `bigint`, and decimals, mixed on decimals and
+// compared by value, so `2`, `2d` and `2.00d` are the same hash key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arithmetic {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    // rounds toward negative infinity, the `div` builtin
+    FloorDiv,
+}
+
+impl fmt::Display for Arithmetic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Arithmetic::Add => write!(f, "+"),
+            Arithmetic::Sub => write!(f, "-"),
+            Arithmetic::Mul => write!(f, "*"),
+            Arithmetic::Div => write!(f, "/"),
+            Arithmetic::FloorDiv => write!(f, "div"),
+        }
+    }
+}
+
+pub fn is_number(value: &Object) -> bool {
+    return integer::is_integer(value) || matches!(value, Object::Decimal(_));
+}
+
+fn is_decimal_operation(left: &Object, right: &Object) -> bool {
+    return matches!(left, Object::Decimal(_)) || matches!(right, Object::Decimal(_));
+}
+
+pub fn arithmetic(op: Arithmetic, left: &Object, right: &Object) -> Result<Object, EvalError> {
+    if is_decimal_operation(left, right) {
+        return decimal::arithmetic(op, left, right);
+    }
+    return integer::arithmetic(op, left, right);
+}
+
+// the error of an operator applied to operands it doesn't take, the same in both engines
+pub fn unsupported(op: Arithmetic, left: &Object, right: &Object) -> EvalError {
+    return format!("can't apply {} to {} and {}", op, left, right);
+}
+
+pub fn unsupported_negation(value: &Object) -> EvalError {
+    return format!("can't apply - to {}", value);
+}
+
+pub fn negate(value: &Object) -> Result<Object, EvalError> {
+    if let Object::Decimal(_) = value {
+        return decimal::negate(value);
+    }
+    return integer::negate(value);
+}
+
+// None unless both are numbers
+pub fn compare(left: &Object, right: &Object) -> Option<Ordering> {
+    if !is_number(left) || !is_number(right) {
+        return None;
+    }
+    if is_decimal_operation(left, right) {
+        return decimal::compare(left, right);
+    }
+    return integer::compare(left, right);
+}
+
+// Equal numbers hash the same: a decimal with no fraction hashes as the integer it equals.
+pub(crate) fn hash<H: Hasher>(value: &Object, state: &mut H) {
+    match value {
+        Object::Decimal(d) => match decimal::to_integer(d) {
+            Some(i) => hash(&i, state),
+            None => d.normalize().hash(state),
+        },
+        Object::Integer(i) => i.hash(state),
+        #[cfg(feature = "bigint")]
+        Object::BigInteger(i) => i.hash(state),
+        _ => {}
+    }
+}
+
+// the `div` builtin: `div(-7, 2)` is `-4` where `-7 / 2` is `-3`
+pub fn div(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 2 {
+        return Rc::new(Object::Error(format!(
+            "builtin div expected 2 arguments, got {}",
+            args.len()
+        )));
+    }
+    let (left, right) = (&*args[0], &*args[1]);
+    if !is_number(left) || !is_number(right) {
+        return Rc::new(Object::Error(unsupported(Arithmetic::FloorDiv, left, right)));
+    }
+    match arithmetic(Arithmetic::FloorDiv, left, right) {
+        Ok(quotient) => return Rc::new(quotient),
+        Err(e) => return Rc::new(Object::Error(e)),
+    }
+}
@@ -1,52 +1,124 @@
-use std::collections::HashMap;
-use std::fmt;
-use std::fmt::{Formatter, write};
-use std::hash::{Hash, Hasher};
-use std::rc::Rc;
+#![cfg_attr(not(feature = "std"), no_std)]
 
-use parser::ast::{BlockStatement, IDENTIFIER};
+extern crate alloc;
 
-#[macro_use]
-extern crate lazy_static;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+use core::fmt::Formatter;
+use core::hash::{Hash, Hasher};
+
+#[cfg(feature = "bigint")]
+pub use num_bigint::BigInt;
+use parser::ast::{BlockStatement, Break, IDENTIFIER};
+pub use rust_decimal::Decimal;
+
+// std's map, or the same map from hashbrown when built without `std`
+#[cfg(not(feature = "std"))]
+pub use hashbrown::HashMap;
+#[cfg(feature = "std")]
+pub use std::collections::HashMap;
 
 use crate::environment::Env;
 
+pub mod aliasing;
+pub mod builder;
 pub mod builtins;
+pub mod cell;
+pub mod channel;
+pub mod chars;
+pub mod convert;
+pub mod decimal;
+pub mod deque;
 pub mod environment;
+pub mod equality;
+pub mod features;
+pub mod frozen;
+pub mod integer;
+pub mod interrupt;
+pub mod iterator;
+pub mod matching;
+pub mod memo;
+pub mod native;
+pub mod number;
+pub mod order;
+pub mod parallel;
+pub mod partial;
+pub mod require;
+pub mod result;
+pub mod search;
+pub mod singleton;
+pub mod suggest;
+pub mod template;
+pub mod truthiness;
+pub mod update;
 
 pub type EvalError = String;
 pub type BuiltinFunc = fn(Vec<Rc<Object>>) -> Rc<Object>;
+// how a builtin calls a function it's passed, given by the engine, see `builtins::CALLING`
+pub type Call<'a> = dyn FnMut(&Rc<Object>, Vec<Rc<Object>>) -> Result<Rc<Object>, EvalError> + 'a;
+pub type CallingFunc = fn(Vec<Rc<Object>>, &mut Call) -> Result<Rc<Object>, EvalError>;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq)]
 pub enum Object {
     Integer(i64),
+    // only values out of `i64` range, see `integer`
+    #[cfg(feature = "bigint")]
+    BigInteger(BigInt),
+    Decimal(Decimal),
     Boolean(bool),
     String(String),
-    Array(Vec<Rc<Object>>),
+    Char(char),
+    Array(VecDeque<Rc<Object>>),
     Hash(HashMap<Rc<Object>, Rc<Object>>),
     Null,
     ReturnValue(Rc<Object>),
-    Function(Vec<IDENTIFIER>, BlockStatement, Env),
+    // a `break` on its way out to its loop, as `ReturnValue` to its function
+    Break(Break),
+    // with whether the last parameter is `...rest`, see `arity_error`
+    Function(Vec<IDENTIFIER>, BlockStatement, Env, bool),
     Builtin(BuiltinFunc),
     Error(String),
     CompiledFunction(Rc<CompiledFunction>),
-    ClosureObj(Closure)
+    ClosureObj(Closure),
+    // the one value that changes, see `iterator`
+    Iterator(Rc<RefCell<iterator::Sequence>>),
+    // the same channel on every thread, by its number, see `channel`
+    Channel(u64),
+    // a handle on the cell of that name, see `cell`
+    Cell(String),
+    Memo(Rc<memo::Memo>),
+    Partial(Rc<partial::Partial>),
+    // a resource of the host, see `native`
+    Native(Rc<native::Handle>),
 }
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Object::Integer(i) => write!(f, "{}", i),
+            #[cfg(feature = "bigint")]
+            Object::BigInteger(i) => write!(f, "{}", i),
+            Object::Decimal(d) => write!(f, "{}", d),
             Object::Boolean(b) => write!(f, "{}", b),
             Object::String(s) => write!(f, "{}", s),
+            Object::Char(c) => write!(f, "{}", c),
             Object::Null => write!(f, "null"),
             Object::ReturnValue(expr) => write!(f, "{}", expr),
-            Object::Function(params, body, _env) => {
-                let func_params = params
+            Object::Break(_) => write!(f, "break"),
+            Object::Function(params, body, _env, rest) => {
+                let mut func_params = params
                     .iter()
                     .map(|stmt| stmt.to_string())
-                    .collect::<Vec<String>>()
-                    .join(", ");
+                    .collect::<Vec<String>>();
+                if let (true, Some(last)) = (*rest, func_params.last_mut()) {
+                    last.insert_str(0, "...");
+                }
+                let func_params = func_params.join(", ");
                 write!(f, "fn({}) {{ {} }}", func_params, body)
             }
             Object::Builtin(_) => write!(f, "[builtin function]"),
@@ -73,6 +145,12 @@ impl fmt::Display for Object {
             Object::ClosureObj(_) => {
                 write!(f, "[closure function]")
             }
+            Object::Iterator(_) => write!(f, "[iterator]"),
+            Object::Channel(_) => write!(f, "[channel]"),
+            Object::Cell(name) => write!(f, "[cell {}]", name),
+            Object::Memo(_) => write!(f, "[memoized function]"),
+            Object::Partial(_) => write!(f, "[partial function]"),
+            Object::Native(handle) => write!(f, "{}", handle),
         }
     }
 }
@@ -80,18 +158,59 @@ impl fmt::Display for Object {
 impl Object {
     pub fn is_hashable(&self) -> bool {
         match self {
-            Object::Integer(_) | Object::Boolean(_) | Object::String(_) => return true,
+            Object::Integer(_) | Object::Boolean(_) | Object::String(_) | Object::Char(_) => {
+                return true
+            }
+            #[cfg(feature = "bigint")]
+            Object::BigInteger(_) => return true,
+            Object::Decimal(_) => return true,
             _ => return false,
         }
     }
+
+    // whether the engines can call it
+    pub fn is_callable(&self) -> bool {
+        return matches!(
+            self,
+            Object::Function(..)
+                | Object::ClosureObj(_)
+                | Object::Builtin(_)
+                | Object::Memo(_)
+                | Object::Partial(_)
+        );
+    }
+
+    // for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => return "integer",
+            #[cfg(feature = "bigint")]
+            Object::BigInteger(_) => return "integer",
+            Object::Decimal(_) => return "decimal",
+            Object::Boolean(_) => return "boolean",
+            Object::String(_) => return "string",
+            Object::Char(_) => return "char",
+            Object::Null => return "null",
+            Object::Array(_) => return "array",
+            Object::Hash(_) => return "hash",
+            Object::Error(_) => return "error",
+            Object::Iterator(_) => return "iterator",
+            Object::Channel(_) => return "channel",
+            Object::Cell(_) => return "cell",
+            Object::Native(_) => return "handle",
+            Object::ReturnValue(v) => return v.type_name(),
+            _ => return "function",
+        }
+    }
 }
 
 impl Hash for Object {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
-            Object::Integer(i) => i.hash(state),
+            o if number::is_number(o) => number::hash(o, state),
             Object::Boolean(b) => b.hash(state),
             Object::String(s) => s.hash(state),
+            Object::Char(c) => c.hash(state),
             t => panic!("can't hashable for {}", t),
         }
     }
@@ -102,10 +221,31 @@ pub struct CompiledFunction {
     pub instructions: Vec<u8>,
     pub num_locals: usize,
     pub num_parameters: usize,
+    // the last parameter is `...rest`, see `arity_error`
+    pub rest: bool,
+    // the let binding it was defined by, or `fn@<line>`, for error messages
+    pub name: String,
+}
+
+// what's wrong with calling a function of `params` parameters with `got` arguments, if anything;
+// a `...rest` last takes the arguments past the others
+pub fn arity_error(params: usize, rest: bool, got: usize) -> Option<EvalError> {
+    match rest {
+        true if got + 1 < params => {
+            return Some(format!("wrong number of arguments: want>={}, got={}", params - 1, got))
+        }
+        false if got != params => {
+            return Some(format!("wrong number of arguments: want={}, got={}", params, got))
+        }
+        _ => return None,
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Closure {
     pub func: Rc<CompiledFunction>,
     pub free: Vec<Rc<Object>>,
+    // base pointer of the frame that created a closure known not to escape it: such a closure
+    // reads that frame's locals in place instead of owning copies in `free`
+    pub frame: Option<usize>,
 }
@@ -0,0 +1,30 @@
+use alloc::format;
+use core::cmp::Ordering;
+
+use crate::{number, EvalError, Object};
+
+// what `<` and `>` order: numbers by value, strings by bytes, chars by code point and arrays
+// element by element; anything else is an error
+
+pub fn compare(left: &Object, right: &Object) -> Result<Ordering, EvalError> {
+    if let Some(ordering) = number::compare(left, right) {
+        return Ok(ordering);
+    }
+    match (left, right) {
+        (Object::String(l), Object::String(r)) => return Ok(l.cmp(r)),
+        (Object::Char(l), Object::Char(r)) => return Ok(l.cmp(r)),
+        (Object::Array(l), Object::Array(r)) => {
+            for (a, b) in l.iter().zip(r.iter()) {
+                let ordering = compare(a, b)?;
+                if ordering != Ordering::Equal {
+                    return Ok(ordering);
+                }
+            }
+            return Ok(l.len().cmp(&r.len()));
+        }
+        _ => {
+            let (l, r) = (left.type_name(), right.type_name());
+            return Err(format!("can't compare {} and {}", l, r));
+        }
+    }
+}
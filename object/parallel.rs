@@ -0,0 +1,44 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::builtins::uncallable;
+use crate::{Call, EvalError, Object};
+
+// `pmap(a, f)`, mapped on worker threads by the vm when `f` can be copied and only reads what
+// it's given, see `compiler::parallel`, in order otherwise
+
+// the `pmap` builtin one element after the other, calling `f` by `call`
+pub fn pmap_calling(args: Vec<Rc<Object>>, call: &mut Call) -> Result<Rc<Object>, EvalError> {
+    if let Some(error) = check(&args) {
+        return Ok(error);
+    }
+    let elements = match &*args[0] {
+        Object::Array(elements) => elements,
+        _ => unreachable!("checked by check"),
+    };
+    let mut results = Vec::with_capacity(elements.len());
+    for element in elements.iter() {
+        results.push(call(&args[1], vec![Rc::clone(element)])?);
+    }
+    return Ok(Rc::new(Object::Array(results.into())));
+}
+
+// as a builtin that can't call, for whoever calls it without an engine
+pub fn pmap(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return pmap_calling(args, &mut uncallable).unwrap_or_else(|e| Rc::new(Object::Error(e)));
+}
+
+// the error to return unless `args` are an array and a function
+fn check(args: &[Rc<Object>]) -> Option<Rc<Object>> {
+    let message = match args {
+        [array, _] if !matches!(**array, Object::Array(_)) => {
+            format!("builtin pmap not supported for type {}", array.type_name())
+        }
+        [_, f] if f.is_callable() => return None,
+        [_, f] => format!("builtin pmap expected a function, got {}", f.type_name()),
+        _ => format!("builtin pmap expected 2 arguments, got {}", args.len()),
+    };
+    return Some(Rc::new(Object::Error(message)));
+}
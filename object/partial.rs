@@ -0,0 +1,87 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::builtins::stop;
+use crate::{BuiltinFunc, Call, EvalError, Object};
+
+// `partial(f, a, b)` binds the first arguments of `f`, with `_` leaving a hole for an argument of
+// the call; a failing `partial` stops the program
+
+#[derive(Debug)]
+pub struct Partial {
+    pub function: Rc<Object>,
+    // the arguments bound, holes as the builtin `_`
+    pub arguments: Vec<Rc<Object>>,
+}
+
+// `Object` is `Eq`, but partial functions are compared as the one they are, see `equality`
+impl PartialEq for Partial {
+    fn eq(&self, other: &Partial) -> bool {
+        return core::ptr::eq(self, other);
+    }
+}
+
+impl Eq for Partial {}
+
+impl Partial {
+    // the arguments to call `function` with, for `args` given to the partial function
+    pub fn arguments(&self, args: &[Rc<Object>]) -> Result<Vec<Rc<Object>>, EvalError> {
+        let holes = self.arguments.iter().filter(|a| is_hole(a)).count();
+        if args.len() < holes {
+            let (plural, got) = (if holes == 1 { "" } else { "s" }, args.len());
+            let message = "partial function expected at least";
+            return Err(format!("{} {} argument{}, got {}", message, holes, plural, got));
+        }
+        let mut args = args.iter();
+        let mut all = Vec::with_capacity(self.arguments.len() + args.len());
+        for bound in self.arguments.iter() {
+            match is_hole(bound) {
+                true => all.push(Rc::clone(args.next().unwrap())),
+                false => all.push(Rc::clone(bound)),
+            }
+        }
+        all.extend(args.cloned());
+        return Ok(all);
+    }
+}
+
+// the `partial` builtin
+pub fn partial(args: Vec<Rc<Object>>) -> Rc<Object> {
+    let mut args = args;
+    if args.is_empty() {
+        return error("builtin partial expected a function and arguments, got none".to_string());
+    }
+    let function = args.remove(0);
+    if is_hole(&function) {
+        return error("builtin partial expected a function, got `_`".to_string());
+    }
+    if !function.is_callable() {
+        let message = format!("builtin partial expected a function, got {}", function.type_name());
+        return error(message);
+    }
+    return Rc::new(Object::Partial(Rc::new(Partial { function, arguments: args })));
+}
+
+// `partial` and `_` as the engines call them, an error stopping the program
+pub fn partial_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(partial(args));
+}
+
+pub fn hole_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(hole(args));
+}
+
+// the `_` builtin, a hole in the arguments `partial` binds
+pub fn hole(_: Vec<Rc<Object>>) -> Rc<Object> {
+    return error("`_` is a hole for `partial`, not a function".to_string());
+}
+
+fn is_hole(value: &Object) -> bool {
+    return matches!(value, Object::Builtin(b) if core::ptr::fn_addr_eq(*b, hole as BuiltinFunc));
+}
+
+fn error(message: String) -> Rc<Object> {
+    return Rc::new(Object::Error(message));
+}
@@ -0,0 +1,37 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::builtins::uncallable;
+use crate::{Call, EvalError, Object};
+
+// `require(cond, message)` stops the program with "requirement failed: message" when `cond` is
+// falsy; `message` can be a function, called only then
+
+// the `require` builtin, calling a `message` function by `call`
+pub fn require_calling(args: Vec<Rc<Object>>, call: &mut Call) -> Result<Rc<Object>, EvalError> {
+    let (cond, message) = match args.as_slice() {
+        [cond] => (cond, None),
+        [cond, message] => (cond, Some(message)),
+        _ => {
+            let message = "builtin require expected 1 or 2 arguments, got";
+            return Ok(Rc::new(Object::Error(format!("{} {}", message, args.len()))));
+        }
+    };
+    if cond.is_truthy() {
+        return Ok(Rc::new(Object::Null));
+    }
+    let message = match message {
+        None => return Err("requirement failed".to_string()),
+        Some(f) if f.is_callable() => call(f, vec![])?,
+        Some(message) => Rc::clone(message),
+    };
+    return Err(format!("requirement failed: {}", message));
+}
+
+// the `require` builtin
+pub fn require(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return require_calling(args, &mut uncallable).unwrap_or_else(|e| Rc::new(Object::Error(e)));
+}
@@ -0,0 +1,81 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{HashMap, Object};
+
+// `ok(v)` and `err(e)`, a hash with the one key "ok" or "err", read with `is_ok` and
+// `unwrap_or`, for failures a caller can recover from
+
+// the `ok` builtin
+pub fn ok(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return tagged("ok", args);
+}
+
+// the `err` builtin
+pub fn err(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return tagged("err", args);
+}
+
+// the `is_ok` builtin
+pub fn is_ok(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 1 {
+        return arity("is_ok", 1, &args);
+    }
+    match result("is_ok", &args[0]) {
+        Ok((tag, _)) => return Rc::new(Object::Boolean(tag == "ok")),
+        Err(e) => return e,
+    }
+}
+
+// the `unwrap_or` builtin
+pub fn unwrap_or(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 2 {
+        return arity("unwrap_or", 2, &args);
+    }
+    match result("unwrap_or", &args[0]) {
+        Ok(("ok", value)) => return value,
+        Ok(_) => return Rc::clone(&args[1]),
+        Err(e) => return e,
+    }
+}
+
+fn tagged(tag: &str, args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 1 {
+        return arity(tag, 1, &args);
+    }
+    let mut hash = HashMap::new();
+    hash.insert(Rc::new(Object::String(tag.to_string())), Rc::clone(&args[0]));
+    return Rc::new(Object::Hash(hash));
+}
+
+// the tag of the result `value` and what it holds
+fn result(builtin: &str, value: &Object) -> Result<(&'static str, Rc<Object>), Rc<Object>> {
+    if let Object::Hash(hash) = value {
+        if hash.len() == 1 {
+            for tag in ["ok", "err"] {
+                if let Some(held) = hash.get(&Object::String(tag.to_string())) {
+                    return Ok((tag, Rc::clone(held)));
+                }
+            }
+        }
+    }
+    let message = format!("builtin {} expected a result, got {}", builtin, value.type_name());
+    return Err(error(message));
+}
+
+fn arity(name: &str, want: usize, args: &[Rc<Object>]) -> Rc<Object> {
+    let plural = if want == 1 { "" } else { "s" };
+    return error(format!(
+        "builtin {} expected {} argument{}, got {}",
+        name,
+        want,
+        plural,
+        args.len()
+    ));
+}
+
+fn error(message: String) -> Rc<Object> {
+    return Rc::new(Object::Error(message));
+}
@@ -0,0 +1,125 @@
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::builtins::uncallable;
+use crate::{frozen, order, Call, EvalError, Object};
+
+// `bsearch(a, x)` and `sorted_insert(a, x)` on an array sorted by `<` or a comparator passed
+// third, in O(log n) comparisons
+
+// the `bsearch` builtin, calling the comparator by `call`
+pub fn bsearch_calling(args: Vec<Rc<Object>>, call: &mut Call) -> Result<Rc<Object>, EvalError> {
+    if let Some(error) = check("bsearch", &args) {
+        return Ok(error);
+    }
+    let elements = match &*args[0] {
+        Object::Array(elements) => elements,
+        _ => unreachable!("checked by check"),
+    };
+    let (x, comparator) = (&args[1], args.get(2));
+    let i = position(elements, x, false, comparator, call)?;
+    if i < elements.len() && compare(&elements[i], x, comparator, call)? == Ordering::Equal {
+        return Ok(Rc::new(Object::Integer(i as i64)));
+    }
+    return Ok(Rc::new(Object::Null));
+}
+
+// the `sorted_insert` builtin, calling the comparator by `call`
+pub fn sorted_insert_calling(
+    args: Vec<Rc<Object>>,
+    call: &mut Call,
+) -> Result<Rc<Object>, EvalError> {
+    if let Some(error) = check("sorted_insert", &args) {
+        return Ok(error);
+    }
+    if let Some(error) = frozen::check("sorted_insert", &args[0]) {
+        return Ok(error);
+    }
+    let elements = match &*args[0] {
+        Object::Array(elements) => elements,
+        _ => unreachable!("checked by check"),
+    };
+    let i = position(elements, &args[1], true, args.get(2), call)?;
+    let mut args = args;
+    let value = Rc::clone(&args[1]);
+    match Rc::make_mut(&mut args[0]) {
+        Object::Array(elements) => elements.insert(i, value),
+        _ => unreachable!("checked by check"),
+    }
+    return Ok(args.swap_remove(0));
+}
+
+// the two as builtins that can't call a comparator, for whoever calls them without an engine
+pub fn bsearch(args: Vec<Rc<Object>>) -> Rc<Object> {
+    return bsearch_calling(args, &mut uncallable).unwrap_or_else(|e| Rc::new(Object::Error(e)));
+}
+
+pub fn sorted_insert(args: Vec<Rc<Object>>) -> Rc<Object> {
+    let result = sorted_insert_calling(args, &mut uncallable);
+    return result.unwrap_or_else(|e| Rc::new(Object::Error(e)));
+}
+
+// the first index `x` sorts before, or with if not `after`
+fn position(
+    elements: &VecDeque<Rc<Object>>,
+    x: &Rc<Object>,
+    after: bool,
+    comparator: Option<&Rc<Object>>,
+    call: &mut Call,
+) -> Result<usize, EvalError> {
+    let (mut low, mut high) = (0, elements.len());
+    while low < high {
+        let middle = low + (high - low) / 2;
+        match compare(&elements[middle], x, comparator, call)? {
+            Ordering::Less => low = middle + 1,
+            Ordering::Equal if after => low = middle + 1,
+            _ => high = middle,
+        }
+    }
+    return Ok(low);
+}
+
+// the order of `a` and `b` by `comparator`, or by `<` without one
+fn compare(
+    a: &Rc<Object>,
+    b: &Rc<Object>,
+    comparator: Option<&Rc<Object>>,
+    call: &mut Call,
+) -> Result<Ordering, EvalError> {
+    let comparator = match comparator {
+        Some(comparator) => comparator,
+        None => return order::compare(a, b),
+    };
+    match &*call(comparator, vec![Rc::clone(a), Rc::clone(b)])? {
+        Object::Integer(i) => return Ok(i.cmp(&0)),
+        o => return Err(format!("a comparator must return an integer, got {}", o.type_name())),
+    }
+}
+
+// the error to return unless `args` are an array, a value and maybe a function
+fn check(name: &str, args: &[Rc<Object>]) -> Option<Rc<Object>> {
+    if args.len() != 2 && args.len() != 3 {
+        let message = format!("builtin {} expected 2 or 3 arguments, got {}", name, args.len());
+        return Some(Rc::new(Object::Error(message)));
+    }
+    if !matches!(*args[0], Object::Array(_)) {
+        let message = format!("builtin {} not supported for type {}", name, args[0].type_name());
+        return Some(Rc::new(Object::Error(message)));
+    }
+    match args.get(2).map(|f| &**f) {
+        None => return None,
+        Some(f) if f.is_callable() => return None,
+        Some(o) => {
+            let message = format!(
+                "builtin {} expected a function to compare with, got {}",
+                name,
+                o.type_name()
+            );
+            return Some(Rc::new(Object::Error(message)));
+        }
+    }
+}
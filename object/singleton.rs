@@ -0,0 +1,54 @@
+use alloc::rc::Rc;
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+
+use crate::Object;
+
+// The integers shared rather than allocated each time, the loop counters, indexes and lengths
+// most programs make most of
+#[cfg(feature = "std")]
+const SMALL: core::ops::RangeInclusive<i64> = -128..=256;
+
+// `null`, the booleans and the small integers, made once per thread and shared; without `std`
+// each one is allocated
+#[cfg(feature = "std")]
+std::thread_local! {
+    static SHARED: Vec<Rc<Object>> = {
+        let constants = alloc::vec![Object::Null, Object::Boolean(false), Object::Boolean(true)];
+        let integers = SMALL.map(Object::Integer);
+        constants.into_iter().chain(integers).map(Rc::new).collect()
+    };
+}
+
+// `o` in an `Rc`, the shared one if there is one
+pub fn share(o: Object) -> Rc<Object> {
+    return shared(&o).unwrap_or_else(|| Rc::new(o));
+}
+
+pub fn null() -> Rc<Object> {
+    return share(Object::Null);
+}
+
+pub fn boolean(b: bool) -> Rc<Object> {
+    return share(Object::Boolean(b));
+}
+
+pub fn integer(i: i64) -> Rc<Object> {
+    return share(Object::Integer(i));
+}
+
+#[cfg(feature = "std")]
+fn shared(o: &Object) -> Option<Rc<Object>> {
+    let index = match *o {
+        Object::Null => 0,
+        Object::Boolean(b) => 1 + b as usize,
+        Object::Integer(i) if SMALL.contains(&i) => 3 + (i - SMALL.start()) as usize,
+        _ => return None,
+    };
+    return Some(SHARED.with(|shared| Rc::clone(&shared[index])));
+}
+
+#[cfg(not(feature = "std"))]
+fn shared(_: &Object) -> Option<Rc<Object>> {
+    return None;
+}
@@ -0,0 +1,53 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// "did you mean" for names that aren't defined: the closest candidate by edit distance, if it's
+// close enough to be a typo
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let length = name.chars().count();
+    let limit = core::cmp::max(1, length / 3);
+    let mut best: Option<(usize, &str)> = None;
+    for candidate in candidates {
+        if candidate == name {
+            continue;
+        }
+        let distance = levenshtein(name, candidate);
+        if distance > limit || distance >= length {
+            continue;
+        }
+        // ties go to the name first in order, so suggestions don't depend on hash order
+        match best {
+            Some((d, b)) if d < distance || (d == distance && b <= candidate) => {}
+            _ => best = Some((distance, candidate)),
+        }
+    }
+    return best.map(|(_, candidate)| candidate);
+}
+
+// `, did you mean 'length'?` to append to an error about `name`, or nothing
+pub fn did_you_mean<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    match closest(name, candidates) {
+        Some(candidate) => return format!(", did you mean '{}'?", candidate),
+        None => return String::new(),
+    }
+}
+
+// the number of characters to insert, delete or replace to turn `a` into `b`
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = Vec::with_capacity(b.len() + 1);
+    for (i, ca) in a.chars().enumerate() {
+        current.clear();
+        current.push(i + 1);
+        for (j, cb) in b.iter().enumerate() {
+            let replace = previous[j] + if ca == *cb { 0 } else { 1 };
+            let insert = current[j] + 1;
+            let delete = previous[j + 1] + 1;
+            current.push(replace.min(insert).min(delete));
+        }
+        core::mem::swap(&mut previous, &mut current);
+    }
+    return previous[b.len()];
+}
@@ -0,0 +1,19 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::Object;
+
+// `"n=${e}"` is `"n=" + string(e)`, any value written as `puts` writes it
+
+// the `string` builtin
+pub fn string(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 1 {
+        let message = format!("builtin string expected 1 argument, got {}", args.len());
+        return Rc::new(Object::Error(message));
+    }
+    if let Object::String(_) = &*args[0] {
+        return Rc::clone(&args[0]);
+    }
+    return Rc::new(Object::String(format!("{}", args[0])));
+}
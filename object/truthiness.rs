@@ -0,0 +1,13 @@
+use crate::Object;
+
+// what `if` and `!` take for true, in both engines: only `false` and null are falsy
+
+impl Object {
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Object::Boolean(b) => return *b,
+            Object::Null => return false,
+            _ => return true,
+        }
+    }
+}
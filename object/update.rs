@@ -0,0 +1,128 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::builtins::stop;
+use crate::{frozen, Call, EvalError, Object};
+
+// `set`, `insert` and `remove`, returning a new array or hash; `a[i] = v;` is `a = set(a, i, v);`
+// and an update that fails stops the program
+
+// the `set` builtin
+pub fn set(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 3 {
+        return arity("set", 3, &args);
+    }
+    if let Some(error) = frozen::check("set", &args[0]) {
+        return error;
+    }
+    match &*args[0] {
+        Object::Array(elements) => match index(&args[1], elements.len(), false) {
+            Ok(i) => {
+                let mut elements = elements.clone();
+                elements[i] = Rc::clone(&args[2]);
+                return Rc::new(Object::Array(elements));
+            }
+            Err(e) => return e,
+        },
+        Object::Hash(map) if args[1].is_hashable() => {
+            let mut map = map.clone();
+            map.insert(Rc::clone(&args[1]), Rc::clone(&args[2]));
+            return Rc::new(Object::Hash(map));
+        }
+        Object::Hash(_) => {
+            return Rc::new(Object::Error(format!("key {} is not hashable", args[1])))
+        }
+        o => return unsupported("set", o),
+    }
+}
+
+// the `insert` builtin, arrays only: a hash has no order to insert into, `set` adds a key
+pub fn insert(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 3 {
+        return arity("insert", 3, &args);
+    }
+    if let Some(error) = frozen::check("insert", &args[0]) {
+        return error;
+    }
+    match &*args[0] {
+        Object::Array(elements) => match index(&args[1], elements.len(), true) {
+            Ok(i) => {
+                let mut elements = elements.clone();
+                elements.insert(i, Rc::clone(&args[2]));
+                return Rc::new(Object::Array(elements));
+            }
+            Err(e) => return e,
+        },
+        o => return unsupported("insert", o),
+    }
+}
+
+// the `remove` builtin
+pub fn remove(args: Vec<Rc<Object>>) -> Rc<Object> {
+    if args.len() != 2 {
+        return arity("remove", 2, &args);
+    }
+    if let Some(error) = frozen::check("remove", &args[0]) {
+        return error;
+    }
+    match &*args[0] {
+        Object::Array(elements) => match index(&args[1], elements.len(), false) {
+            Ok(i) => {
+                let mut elements = elements.clone();
+                elements.remove(i);
+                return Rc::new(Object::Array(elements));
+            }
+            Err(e) => return e,
+        },
+        Object::Hash(map) if args[1].is_hashable() => {
+            let mut map = map.clone();
+            map.remove(&args[1]);
+            return Rc::new(Object::Hash(map));
+        }
+        Object::Hash(_) => {
+            return Rc::new(Object::Error(format!("key {} is not hashable", args[1])))
+        }
+        o => return unsupported("remove", o),
+    }
+}
+
+// `set`, `insert` and `remove` as the engines call them, an error stopping the program
+pub fn set_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(set(args));
+}
+
+pub fn insert_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(insert(args));
+}
+
+pub fn remove_calling(args: Vec<Rc<Object>>, _: &mut Call) -> Result<Rc<Object>, EvalError> {
+    return stop(remove(args));
+}
+
+// `index` as a position in an array of `len` elements, or right after them if `past_end`, or
+// the error to return
+fn index(index: &Object, len: usize, past_end: bool) -> Result<usize, Rc<Object>> {
+    let end = if past_end { len + 1 } else { len };
+    match index {
+        Object::Integer(i) if *i >= 0 && (*i as usize) < end => return Ok(*i as usize),
+        Object::Integer(i) => {
+            let message = format!("index {} out of range for an array of length {}", i, len);
+            return Err(Rc::new(Object::Error(message)));
+        }
+        o => {
+            let message = format!("an array index must be an integer, got {}", o.type_name());
+            return Err(Rc::new(Object::Error(message)));
+        }
+    }
+}
+
+fn arity(name: &str, want: usize, args: &[Rc<Object>]) -> Rc<Object> {
+    let message = format!("builtin {} expected {} arguments, got {}", name, want, args.len());
+    return Rc::new(Object::Error(message));
+}
+
+fn unsupported(name: &str, value: &Object) -> Rc<Object> {
+    let message = format!("builtin {} not supported for type {}", name, value.type_name());
+    return Rc::new(Object::Error(message));
+}
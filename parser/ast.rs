@@ -1,8 +1,12 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
 use core::fmt;
+use core::fmt::Formatter;
 use core::fmt::Result;
-use lexer::token::{Span, Token, TokenKind};
+use lexer::token::{Position, Span, Token, TokenKind};
 use serde::{Deserialize, Serialize};
-use std::fmt::Formatter;
 
 // still wait for https://github.com/serde-rs/serde/issues/1402
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
@@ -33,6 +37,74 @@ impl Program {
     pub fn new() -> Self {
         Program { body: vec![], span: Span { start: 0, end: 0 } }
     }
+
+    // every name a `name = value` anywhere in the program sets, nested functions included, so a
+    // compiler knows which bindings may change after they're bound
+    pub fn assigned_names(&self) -> Vec<&str> {
+        let mut names = vec![];
+        assigned_in_block(&self.body, &mut names);
+        return names;
+    }
+}
+
+fn assigned_in_block<'a>(body: &'a [Statement], names: &mut Vec<&'a str>) {
+    for stmt in body.iter() {
+        match stmt {
+            Statement::Let(l) => assigned_in(&l.expr, names),
+            Statement::Return(r) => assigned_in(&r.argument, names),
+            Statement::Expr(e) => assigned_in(e, names),
+            Statement::Import(_) | Statement::Break(_) => {}
+            Statement::While(w) => {
+                assigned_in(&w.condition, names);
+                assigned_in_block(&w.body.body, names);
+            }
+        }
+    }
+}
+
+fn assigned_in<'a>(e: &'a Expression, names: &mut Vec<&'a str>) {
+    match e {
+        Expression::Assign(a) => {
+            names.push(&a.name.name);
+            assigned_in(&a.value, names);
+        }
+        Expression::LITERAL(Literal::Array(a)) => {
+            a.elements.iter().for_each(|e| assigned_in(e, names))
+        }
+        Expression::LITERAL(Literal::Hash(h)) => h.elements.iter().for_each(|(k, v)| {
+            assigned_in(k, names);
+            assigned_in(v, names);
+        }),
+        Expression::IDENTIFIER(_) | Expression::LITERAL(_) => {}
+        Expression::PREFIX(p) => assigned_in(&p.operand, names),
+        Expression::INFIX(i) => {
+            assigned_in(&i.left, names);
+            assigned_in(&i.right, names);
+        }
+        Expression::IF(i) => {
+            assigned_in(&i.condition, names);
+            assigned_in_block(&i.consequent.body, names);
+            if let Some(alternate) = &i.alternate {
+                assigned_in_block(&alternate.body, names);
+            }
+        }
+        Expression::FUNCTION(f) => assigned_in_block(&f.body.body, names),
+        Expression::FunctionCall(c) => {
+            assigned_in(&c.callee, names);
+            c.arguments.iter().for_each(|a| assigned_in(a, names));
+        }
+        Expression::Index(i) => {
+            assigned_in(&i.object, names);
+            assigned_in(&i.index, names);
+        }
+        Expression::Match(m) => {
+            assigned_in(&m.subject, names);
+            for arm in m.arms.iter() {
+                arm.guard.iter().for_each(|g| assigned_in(g, names));
+                assigned_in(&arm.body, names);
+            }
+        }
+    }
 }
 
 impl fmt::Display for Program {
@@ -47,6 +119,9 @@ pub enum Statement {
     Let(Let),
     Return(ReturnStatement),
     Expr(Expression),
+    Import(Import),
+    While(While),
+    Break(Break),
 }
 
 #[derive(Clone, Debug, Eq, Serialize, Deserialize, Hash, PartialEq)]
@@ -64,6 +139,125 @@ pub struct ReturnStatement {
     pub span: Span,
 }
 
+#[derive(Clone, Debug, Eq, Serialize, Deserialize, Hash, PartialEq)]
+#[serde(tag = "type")]
+pub struct Import {
+    pub path: String,
+    pub span: Span,
+}
+
+// `for (let i = 0; i < n; i = i + 1) { ... }` is parsed as `let i = 0;` followed by a loop with
+// `let i = i + 1;` at the end of its body
+#[derive(Clone, Debug, Eq, Serialize, Deserialize, Hash, PartialEq)]
+#[serde(tag = "type")]
+pub struct While {
+    // `outer` of `outer: while (...) { ... }`, for a `break` in a nested loop to name it
+    pub label: Option<IDENTIFIER>,
+    pub condition: Expression,
+    pub body: BlockStatement,
+    pub span: Span,
+}
+
+// `break;` leaves the innermost loop around it, `break outer;` the one labeled `outer`
+#[derive(Clone, Debug, Eq, Serialize, Deserialize, Hash, PartialEq)]
+#[serde(tag = "type")]
+pub struct Break {
+    pub label: Option<IDENTIFIER>,
+    pub position: Position,
+    pub span: Span,
+}
+
+impl Statement {
+    // where the statement is in the source; a `for` loop is two sharing the loop's, see `While`
+    pub fn span(&self) -> &Span {
+        match self {
+            Statement::Let(l) => return &l.span,
+            Statement::Return(r) => return &r.span,
+            Statement::Expr(e) => return e.span(),
+            Statement::Import(i) => return &i.span,
+            Statement::While(w) => return &w.span,
+            Statement::Break(b) => return &b.span,
+        }
+    }
+}
+
+impl Let {
+    // `let _ = v;`, see `is_throwaway`
+    pub fn is_throwaway(&self) -> bool {
+        return matches!(&self.identifier.kind, TokenKind::IDENTIFIER { name } if is_throwaway(name));
+    }
+
+    // `let q = f(q, ...)`, where `f` can have the only reference to the value of `q`, see
+    // `object::deque`: the other arguments don't read `q`, and make no calls that could
+    pub fn handover(&self) -> Option<&FunctionCall> {
+        let name = match &self.identifier.kind {
+            TokenKind::IDENTIFIER { name } => name,
+            _ => return None,
+        };
+        let call = match &self.expr {
+            Expression::FunctionCall(call) => call,
+            _ => return None,
+        };
+        match (&*call.callee, call.arguments.split_first()) {
+            (Expression::IDENTIFIER(callee), Some((Expression::IDENTIFIER(first), rest)))
+                if *callee.name != **name
+                    && *first.name == **name
+                    && rest.iter().all(|a| plain(a, name)) =>
+            {
+                return Some(call);
+            }
+            _ => return None,
+        }
+    }
+}
+
+// no calls and no `name`
+fn plain(e: &Expression, name: &str) -> bool {
+    match e {
+        Expression::IDENTIFIER(id) => return id.name != name,
+        Expression::LITERAL(Literal::Array(a)) => return a.elements.iter().all(|e| plain(e, name)),
+        Expression::LITERAL(Literal::Hash(h)) => {
+            return h
+                .elements
+                .iter()
+                .all(|(k, v)| plain(k, name) && plain(v, name));
+        }
+        Expression::LITERAL(_) => return true,
+        Expression::PREFIX(p) => return plain(&p.operand, name),
+        Expression::INFIX(i) => return plain(&i.left, name) && plain(&i.right, name),
+        Expression::Index(i) => return plain(&i.object, name) && plain(&i.index, name),
+        _ => return false,
+    }
+}
+
+impl Break {
+    // the error for a `break` with no such loop to leave, in both engines
+    pub fn misplaced(&self) -> String {
+        match &self.label {
+            Some(label) => {
+                format!("no loop labeled `{}` around the `break` at {}", label, self.position)
+            }
+            None => format!("`break` outside a loop at {}", self.position),
+        }
+    }
+}
+
+impl Assign {
+    // the errors for assigning to a name that can't be, in both engines
+    pub fn unbound(&self) -> String {
+        return format!("assignment to undefined variable {} at {}", self.name, self.name.position);
+    }
+
+    pub fn builtin(&self) -> String {
+        return format!("can't assign to the builtin {} at {}", self.name, self.name.position);
+    }
+
+    pub fn enclosing(&self) -> String {
+        let (name, at) = (&self.name, self.name.position);
+        return format!("can't assign to {} at {}, a function around this one binds it", name, at);
+    }
+}
+
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
@@ -77,6 +271,15 @@ impl fmt::Display for Statement {
                 write!(f, "return {};", argument)
             }
             Statement::Expr(expr) => write!(f, "{}", expr),
+            Statement::Import(Import { path, .. }) => write!(f, "import \"{}\";", path),
+            Statement::While(While { label: Some(label), condition, body, .. }) => {
+                write!(f, "{}: while {} {{ {} }}", label, condition, body)
+            }
+            Statement::While(While { condition, body, .. }) => {
+                write!(f, "while {} {{ {} }}", condition, body)
+            }
+            Statement::Break(Break { label: Some(label), .. }) => write!(f, "break {};", label),
+            Statement::Break(_) => write!(f, "break;"),
         }
     }
 }
@@ -105,6 +308,8 @@ pub enum Expression {
     FUNCTION(FunctionDeclaration),
     FunctionCall(FunctionCall),
     Index(Index),
+    Match(Match),
+    Assign(Assign),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
@@ -112,6 +317,31 @@ pub enum Expression {
 pub struct IDENTIFIER {
     pub name: String,
     pub span: Span,
+    // for errors about the name, see `Position`
+    pub position: Position,
+}
+
+// `_` binds nothing: `let _ = v;` keeps no slot, a `_` parameter takes any argument and the
+// pattern `_` fits anything
+pub fn is_throwaway(name: &str) -> bool {
+    return name == "_";
+}
+
+// A name starting with `_`, as `_unused`, is bound as any other but says it's meant to go
+// unused, so lints about names leave it alone, `_` among them.
+pub fn is_meant_unused(name: &str) -> bool {
+    return name.starts_with('_');
+}
+
+impl IDENTIFIER {
+    pub fn is_throwaway(&self) -> bool {
+        return is_throwaway(&self.name);
+    }
+
+    // the error of reading the name where it isn't bound, the same in both engines
+    pub fn unbound(&self, suggestion: &str) -> String {
+        return format!("unknown identifier {} at {}{}", self.name, self.position, suggestion);
+    }
 }
 
 impl fmt::Display for IDENTIFIER {
@@ -153,6 +383,8 @@ pub struct FunctionDeclaration {
     pub body: BlockStatement,
     pub span: Span,
     pub name: String,
+    // the last of `params` is `...rest`, bound to an array of the arguments past the others
+    pub rest: bool,
 }
 
 // function can be Identifier or FunctionLiteral (think iife)
@@ -172,6 +404,122 @@ pub struct Index {
     pub span: Span,
 }
 
+// `name = value`, setting a name already bound in the function it's in, or a global, to the
+// value, which is also what it evaluates to
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
+#[serde(tag = "type")]
+pub struct Assign {
+    pub name: IDENTIFIER,
+    pub value: Box<Expression>,
+    pub span: Span,
+}
+
+// `match (subject) { pattern if guard => value, ... }`, the value of the first arm whose pattern
+// fits the subject and whose guard, if it has one, is truthy, or null, see `object::matching`
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
+#[serde(tag = "type")]
+pub struct Match {
+    pub subject: Box<Expression>,
+    pub arms: Vec<Arm>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
+#[serde(tag = "type")]
+pub struct Arm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Expression,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
+#[serde(tag = "type", content = "value")]
+pub enum Pattern {
+    // any value, bound to the name unless it's `_`
+    Binding(IDENTIFIER),
+    // a value `==` to an integer, decimal, string or boolean
+    Literal(Literal),
+    // an array of as many elements, or at least as many with `...rest` last, bound to the rest
+    Array {
+        elements: Vec<Pattern>,
+        rest: Option<IDENTIFIER>,
+        span: Span,
+    },
+    // a hash with these keys, and maybe others, `{name: n}` being `{"name": n}`
+    Hash {
+        entries: Vec<(Literal, Pattern)>,
+        span: Span,
+    },
+}
+
+impl Pattern {
+    // the names the pattern binds, in order
+    pub fn names(&self) -> Vec<&IDENTIFIER> {
+        match self {
+            Pattern::Binding(id) if id.is_throwaway() => return vec![],
+            Pattern::Binding(id) => return vec![id],
+            Pattern::Literal(_) => return vec![],
+            Pattern::Array { elements, rest, .. } => {
+                let mut names: Vec<&IDENTIFIER> = elements.iter().flat_map(|e| e.names()).collect();
+                names.extend(rest.iter().filter(|r| !r.is_throwaway()));
+                return names;
+            }
+            Pattern::Hash { entries, .. } => {
+                return entries.iter().flat_map(|(_, p)| p.names()).collect();
+            }
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Pattern::Binding(id) => write!(f, "{}", id),
+            Pattern::Literal(l) => write!(f, "{}", l),
+            Pattern::Array { elements, rest, .. } => {
+                let mut parts: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                parts.extend(rest.iter().map(|r| format!("...{}", r)));
+                write!(f, "[{}]", parts.join(", "))
+            }
+            Pattern::Hash { entries, .. } => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(k, p)| format!("{}: {}", k, p))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Arm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match &self.guard {
+            Some(guard) => write!(f, "{} if {} => {}", self.pattern, guard, self.body),
+            None => write!(f, "{} => {}", self.pattern, self.body),
+        }
+    }
+}
+
+impl Expression {
+    // where the expression is in the source, the parentheses around it not included
+    pub fn span(&self) -> &Span {
+        match self {
+            Expression::IDENTIFIER(id) => return &id.span,
+            Expression::LITERAL(l) => return l.span(),
+            Expression::PREFIX(p) => return &p.span,
+            Expression::INFIX(i) => return &i.span,
+            Expression::IF(i) => return &i.span,
+            Expression::FUNCTION(f) => return &f.span,
+            Expression::FunctionCall(c) => return &c.span,
+            Expression::Index(i) => return &i.span,
+            Expression::Match(m) => return &m.span,
+            Expression::Assign(a) => return &a.span,
+        }
+    }
+}
+
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -190,12 +538,15 @@ impl fmt::Display for Expression {
                     write!(f, "if {} {{ {} }}", condition, consequent,)
                 }
             }
-            Expression::FUNCTION(FunctionDeclaration { name, params, body, .. }) => {
-                let func_params = params
+            Expression::FUNCTION(FunctionDeclaration { name, params, body, rest, .. }) => {
+                let mut func_params = params
                     .iter()
                     .map(|stmt| stmt.to_string())
-                    .collect::<Vec<String>>()
-                    .join(", ");
+                    .collect::<Vec<String>>();
+                if let (true, Some(last)) = (*rest, func_params.last_mut()) {
+                    last.insert_str(0, "...");
+                }
+                let func_params = func_params.join(", ");
                 write!(f, "fn {}({}) {{ {} }}", name, func_params, body)
             }
             Expression::FunctionCall(FunctionCall { callee, arguments, .. }) => {
@@ -204,6 +555,11 @@ impl fmt::Display for Expression {
             Expression::Index(Index { object, index, .. }) => {
                 write!(f, "({}[{}])", object, index)
             }
+            Expression::Match(Match { subject, arms, .. }) => {
+                let arms: Vec<String> = arms.iter().map(|a| a.to_string()).collect();
+                write!(f, "match {} {{ {} }}", subject, arms.join(", "))
+            }
+            Expression::Assign(Assign { name, value, .. }) => write!(f, "({} = {})", name, value),
         }
     }
 }
@@ -212,8 +568,10 @@ impl fmt::Display for Expression {
 #[serde(tag = "type")]
 pub enum Literal {
     Integer(Integer),
+    Decimal(DecimalType),
     Boolean(Boolean),
     String(StringType),
+    Char(CharType),
     Array(Array),
     Hash(Hash),
 }
@@ -224,6 +582,13 @@ pub struct Integer {
     pub span: Span,
 }
 
+// the digits of `1.50d`, converted to a number by whoever evaluates it
+#[derive(Clone, Debug, Eq, Serialize, Deserialize, Hash, PartialEq)]
+pub struct DecimalType {
+    pub raw: String,
+    pub span: Span,
+}
+
 #[derive(Clone, Debug, Eq, Serialize, Deserialize, Hash, PartialEq)]
 pub struct Boolean {
     pub raw: bool,
@@ -236,6 +601,12 @@ pub struct StringType {
     pub span: Span,
 }
 
+#[derive(Clone, Debug, Eq, Serialize, Deserialize, Hash, PartialEq)]
+pub struct CharType {
+    pub raw: char,
+    pub span: Span,
+}
+
 #[derive(Clone, Debug, Eq, Serialize, Deserialize, Hash, PartialEq)]
 pub struct Array {
     pub elements: Vec<Expression>,
@@ -248,12 +619,28 @@ pub struct Hash {
     pub span: Span,
 }
 
+impl Literal {
+    pub fn span(&self) -> &Span {
+        match self {
+            Literal::Integer(i) => return &i.span,
+            Literal::Decimal(d) => return &d.span,
+            Literal::Boolean(b) => return &b.span,
+            Literal::String(s) => return &s.span,
+            Literal::Char(c) => return &c.span,
+            Literal::Array(a) => return &a.span,
+            Literal::Hash(h) => return &h.span,
+        }
+    }
+}
+
 impl fmt::Display for Literal {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Literal::Integer(Integer { raw: i, .. }) => write!(f, "{}", i),
+            Literal::Decimal(DecimalType { raw: d, .. }) => write!(f, "{}d", d),
             Literal::Boolean(Boolean { raw: b, .. }) => write!(f, "{}", b),
-            Literal::String(StringType { raw: s, .. }) => write!(f, "\"{}\"", s),
+            Literal::String(StringType { raw: s, .. }) => write!(f, "\"{}\"", lexer::escape(s)),
+            Literal::Char(CharType { raw: c, .. }) => write!(f, "'{}'", lexer::escape_char(*c)),
             Literal::Array(Array { elements: e, .. }) => write!(f, "[{}]", format_expressions(e)),
             Literal::Hash(Hash { elements: map, .. }) => {
                 let to_string = map
@@ -268,12 +655,17 @@ impl fmt::Display for Literal {
     }
 }
 
+// each ended as the parser would need it to be on one line: an expression with a `;` when
+// another statement follows it
 fn format_statements(statements: &Vec<Statement>) -> String {
-    return statements
-        .iter()
-        .map(|stmt| stmt.to_string())
-        .collect::<Vec<String>>()
-        .join("");
+    let mut out = String::new();
+    for (i, stmt) in statements.iter().enumerate() {
+        out.push_str(&stmt.to_string());
+        if matches!(stmt, Statement::Expr(_)) && i + 1 < statements.len() {
+            out.push(';');
+        }
+    }
+    return out;
 }
 
 fn format_expressions(exprs: &Vec<Expression>) -> String {
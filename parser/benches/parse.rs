@@ -0,0 +1,53 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use lexer::token::Span;
+use parser::ast::Node;
+use parser::incremental::{reparse, Edit};
+use parser::parse;
+
+// identifiers are letters, `handler_ba` for 26
+fn name(mut i: usize) -> String {
+    let mut name = String::from("handler_");
+    loop {
+        name.push((b'a' + (i % 26) as u8) as char);
+        i /= 26;
+        if i == 0 {
+            return name;
+        }
+    }
+}
+
+// a large program, many names and strings, as a long script would have
+fn program() -> String {
+    let mut source = String::new();
+    for i in 0..500 {
+        let handler = name(i);
+        source.push_str(&format!(
+            "let {handler} = fn(request, config) {{
+                 let name = request[\"name\"];
+                 if (name == \"{handler}\") {{ config[\"limit\"] + {i} }} else {{ len(name) * {i} }}
+             }};
+             let result = {handler}({{\"name\": \"{handler}\"}}, {{\"limit\": 10}});
+             puts(\"done \", result);\n"
+        ));
+    }
+    return source;
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let source = program();
+    c.bench_function("parse 500 functions", |b| b.iter(|| parse(&source).unwrap()));
+    // a digit typed into the middle one of them
+    let program = match parse(&source).unwrap() {
+        Node::Program(program) => program,
+        _ => unreachable!(),
+    };
+    let at = source.len() / 2 + source[source.len() / 2..].find("+ ").unwrap() + 2;
+    let edit = Edit { range: Span { start: at, end: at }, text: String::from("1") };
+    c.bench_function("reparse one edit of 500 functions", |b| {
+        b.iter(|| reparse(&source, program.clone(), &edit).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
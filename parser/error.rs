@@ -0,0 +1,74 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use lexer::token::{Position, Span};
+
+use crate::incremental::position_at;
+
+// a mistake in a source, with its span and the line holding it, shown with a `^` under the token:
+// ```text
+// expected `=` at line 2, column 7, got `5`
+//   2 | let x 5;
+//     |       ^
+// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+    pub position: Position,
+    pub line: String,
+}
+
+impl ParseError {
+    // `message` about `span` of `source`
+    pub fn new(source: &str, span: Span, message: String) -> Self {
+        let start = floor_char_boundary(source, span.start.min(source.len()));
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line = source[line_start..line_end]
+            .trim_end_matches('\r')
+            .to_string();
+        ParseError { message, span, position: position_at(source, start), line }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let number = self.position.line.to_string();
+        let gutter = " ".repeat(number.len());
+        // a tab before the token is a tab under it too, so the `^` lines up whatever its width
+        let before = self.line.chars().take(self.position.column - 1);
+        let indent: String = before.map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+        // as many as the token has chars on the line, one for the end of the source
+        let (mut carets, mut bytes) = (0, 0);
+        for c in self.line.chars().skip(self.position.column - 1) {
+            if bytes >= self.span.end.saturating_sub(self.span.start) {
+                break;
+            }
+            bytes += c.len_utf8();
+            carets += 1;
+        }
+        let carets = "^".repeat(carets.max(1));
+        write!(
+            f,
+            "{}\n  {} | {}\n  {} | {}{}",
+            self.message, number, self.line, gutter, indent, carets
+        )
+    }
+}
+
+// each error with its line of source, one after the other, as a REPL or the CLI shows them
+pub fn render(errors: &[ParseError]) -> String {
+    let rendered: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    return rendered.join("\n");
+}
+
+fn floor_char_boundary(source: &str, mut at: usize) -> usize {
+    while !source.is_char_boundary(at) {
+        at -= 1;
+    }
+    return at;
+}
@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use crate::error::render;
+    use crate::parse;
+
+    fn rendered(input: &str) -> String {
+        return render(&parse(input).unwrap_err());
+    }
+
+    #[test]
+    fn test_snippets() {
+        let test_case = [
+            (
+                "let x = 1;\nlet y 5;",
+                "expected `=` at line 2, column 7, got `5`\n  2 | let y 5;\n    |       ^",
+            ),
+            (
+                "let total = price * * 2;",
+                "no prefix function for token: `*` at line 1, column 21\n  1 | let total = price * * 2;\n    |                     ^",
+            ),
+            // as wide as the token, and a tab under a tab
+            (
+                "\tlet 12345 = x;",
+                "`12345` at line 1, column 6 not an identifier\n  1 | \tlet 12345 = x;\n    | \t    ^^^^^",
+            ),
+            // at the end of what's missing its `;`, and at the end of the source
+            (
+                "let a = 1 let b = 2",
+                "expected `;` at line 1, column 10, got `let`\n  1 | let a = 1 let b = 2\n    |          ^",
+            ),
+            ("1 +", "no prefix function for token: `EOF` at line 1, column 4\n  1 | 1 +\n    |    ^"),
+        ];
+        for (input, expected) in test_case {
+            assert_eq!(rendered(input), expected, "{}", input);
+        }
+
+        // one after the other, the line numbers lined up
+        let input = "let = 1;\n".repeat(10);
+        let errors = parse(&input).unwrap_err();
+        assert_eq!(errors[9].position.line, 10);
+        assert!(
+            render(&errors).ends_with("\n  10 | let = 1;\n     |     ^"),
+            "{}",
+            render(&errors)
+        );
+    }
+
+    #[test]
+    fn test_error_fields() {
+        let input = "let a = 1;\r\nlet b = \u{20AC};";
+        let error = &parse(input).unwrap_err()[0];
+        assert_eq!(error.message, "illegal token \u{20AC} at line 2, column 9");
+        assert_eq!(error.line, "let b = \u{20AC};");
+        assert_eq!((error.position.line, error.position.column), (2, 9));
+        assert_eq!(&input[error.span.start..error.span.end], "\u{20AC}");
+        assert!(error.to_string().ends_with("\n    |         ^"), "{}", error);
+    }
+}
@@ -0,0 +1,300 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use core::ops::Range;
+
+use crate::ast::{
+    Arm, BlockStatement, Expression, Literal, Pattern, Program, Statement, IDENTIFIER,
+};
+use crate::{ParseError, ParseErrors, Parser};
+use lexer::token::{Position, Span, Token, TokenKind};
+use lexer::Lexer;
+
+// parsing again after an edit: only the top-level statements between the `;` around the edit are
+// parsed, the ones after moved, and the whole source when that region doesn't parse on its own
+
+// `range`, byte offsets into the source, replaced by `text`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edit {
+    pub range: Span,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reparse {
+    // the source with the edit made
+    pub source: String,
+    pub program: Program,
+    // the statements of `program` that were parsed again, the others were kept as they were
+    pub reparsed: Range<usize>,
+}
+
+// `program`, parsed from `source`, after `edit`
+pub fn reparse(source: &str, mut program: Program, edit: &Edit) -> Result<Reparse, ParseErrors> {
+    let Span { start, end } = edit.range;
+    if start > end || !source.is_char_boundary(start) || !source.is_char_boundary(end) {
+        let len = source.len();
+        let e = format!("can't edit {}..{} of a source of {} bytes", start, end, len);
+        return Err(vec![ParseError::new(source, edit.range.clone(), e)]);
+    }
+    let edited = [&source[..start], edit.text.as_str(), &source[end..]].concat();
+
+    // the statements `first..last`, from `from` to `to` in `source`
+    let (mut first, mut from) = (0, 0);
+    let (mut last, mut to) = (program.body.len(), source.len());
+    for k in 0..program.body.len() {
+        match terminated(source, &program.body, k) {
+            Some(ends) if ends <= start => (first, from) = (k + 1, ends),
+            Some(ends) if ends > end => {
+                (last, to) = (k + 1, ends);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let region = &edited[from..to - end + start + edit.text.len()];
+    // a `#!` line is only one at the very start
+    if from > 0 && region.starts_with("#!") {
+        return parse_all(edited);
+    }
+    let mut parsed = match Parser::new(Lexer::new(region)).parse_program() {
+        Ok(parsed) => parsed.body,
+        Err(_) => return parse_all(edited),
+    };
+    // nor is it when its `;` ended up in a string or a comment the edit opened, the rest of the
+    // source isn't then what it was
+    if to < source.len() && !ends_with_semicolon(region) {
+        return parse_all(edited);
+    }
+
+    let at = position_at(source, from);
+    let moved = Shift { offset: from, line: 1, lines: at.line - 1, columns: at.column - 1 };
+    parsed.iter_mut().for_each(|s| moved.statement(s));
+    let (old, new) = (position_at(source, end), position_at(&edited, start + edit.text.len()));
+    let shift = Shift {
+        offset: (start + edit.text.len()).wrapping_sub(end),
+        line: old.line,
+        lines: new.line.wrapping_sub(old.line),
+        columns: new.column.wrapping_sub(old.column),
+    };
+    let mut rest = program.body.split_off(last);
+    rest.iter_mut().for_each(|s| shift.statement(s));
+
+    program.body.truncate(first);
+    let reparsed = first..first + parsed.len();
+    program.body.extend(parsed);
+    program.body.extend(rest);
+    program.span.end = program.span.end.wrapping_add(shift.offset);
+    return Ok(Reparse { source: edited, program, reparsed });
+}
+
+fn parse_all(source: String) -> Result<Reparse, ParseErrors> {
+    let program = Parser::new(Lexer::new(&source)).parse_program()?;
+    let reparsed = 0..program.body.len();
+    return Ok(Reparse { source, program, reparsed });
+}
+
+// just past the `;` ending statement `k`, if it ends with one, checking only the source between
+// it and the next statement
+fn terminated(source: &str, body: &[Statement], k: usize) -> Option<usize> {
+    let end = body[k].span().end;
+    let next = body.get(k + 1).map_or(source.len(), |s| s.span().start);
+    // the two statements of a `for` loop
+    if next < end {
+        return None;
+    }
+    if source[..end].ends_with(';') {
+        return Some(end);
+    }
+    let mut rest = &source[end..next];
+    loop {
+        rest = rest.trim_start();
+        match rest.strip_prefix("//") {
+            Some(comment) => rest = comment.find('\n').map_or("", |i| &comment[i..]),
+            None => break,
+        }
+    }
+    return rest.strip_prefix(';').map(|_| next - rest.len() + 1);
+}
+
+// whether the last token of `source` is a `;` at its very end
+fn ends_with_semicolon(source: &str) -> bool {
+    let mut lexer = Lexer::new(source);
+    let mut last = lexer.next_token();
+    loop {
+        let token = lexer.next_token();
+        if token.kind == TokenKind::EOF {
+            return last.kind == TokenKind::SEMICOLON && last.span.end == source.len();
+        }
+        last = token;
+    }
+}
+
+// the line and column of the byte `offset`, as the lexer counts them
+pub(crate) fn position_at(source: &str, offset: usize) -> Position {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    return Position { line, column: before[line_start..].chars().count() + 1 };
+}
+
+// Moves a statement: `offset` is added to its spans, and `lines` to its lines, with `columns`
+// added to the columns on the line `line` first. Each is wrapping, to move back.
+struct Shift {
+    offset: usize,
+    line: usize,
+    lines: usize,
+    columns: usize,
+}
+
+impl Shift {
+    fn span(&self, span: &mut Span) {
+        span.start = span.start.wrapping_add(self.offset);
+        span.end = span.end.wrapping_add(self.offset);
+    }
+
+    fn position(&self, position: &mut Position) {
+        if position.line == self.line {
+            position.column = position.column.wrapping_add(self.columns);
+        }
+        position.line = position.line.wrapping_add(self.lines);
+    }
+
+    fn token(&self, token: &mut Token) {
+        self.span(&mut token.span);
+        self.position(&mut token.position);
+    }
+
+    fn identifier(&self, identifier: &mut IDENTIFIER) {
+        self.span(&mut identifier.span);
+        self.position(&mut identifier.position);
+    }
+
+    fn block(&self, block: &mut BlockStatement) {
+        self.span(&mut block.span);
+        block.body.iter_mut().for_each(|s| self.statement(s));
+    }
+
+    fn statement(&self, statement: &mut Statement) {
+        match statement {
+            Statement::Let(l) => {
+                self.token(&mut l.identifier);
+                self.expression(&mut l.expr);
+                self.span(&mut l.span);
+            }
+            Statement::Return(r) => {
+                self.expression(&mut r.argument);
+                self.span(&mut r.span);
+            }
+            Statement::Expr(e) => self.expression(e),
+            Statement::Import(i) => self.span(&mut i.span),
+            Statement::While(w) => {
+                w.label.iter_mut().for_each(|l| self.identifier(l));
+                self.expression(&mut w.condition);
+                self.block(&mut w.body);
+                self.span(&mut w.span);
+            }
+            Statement::Break(b) => {
+                b.label.iter_mut().for_each(|l| self.identifier(l));
+                self.position(&mut b.position);
+                self.span(&mut b.span);
+            }
+        }
+    }
+
+    fn expression(&self, expression: &mut Expression) {
+        match expression {
+            Expression::IDENTIFIER(id) => self.identifier(id),
+            Expression::LITERAL(l) => self.literal(l),
+            Expression::PREFIX(p) => {
+                self.token(&mut p.op);
+                self.expression(&mut p.operand);
+                self.span(&mut p.span);
+            }
+            Expression::INFIX(i) => {
+                self.token(&mut i.op);
+                self.expression(&mut i.left);
+                self.expression(&mut i.right);
+                self.span(&mut i.span);
+            }
+            Expression::IF(i) => {
+                self.expression(&mut i.condition);
+                self.block(&mut i.consequent);
+                i.alternate.iter_mut().for_each(|a| self.block(a));
+                self.span(&mut i.span);
+            }
+            Expression::FUNCTION(f) => {
+                f.params.iter_mut().for_each(|p| self.identifier(p));
+                self.block(&mut f.body);
+                self.span(&mut f.span);
+            }
+            Expression::FunctionCall(c) => {
+                self.expression(&mut c.callee);
+                c.arguments.iter_mut().for_each(|a| self.expression(a));
+                self.span(&mut c.span);
+            }
+            Expression::Index(i) => {
+                self.expression(&mut i.object);
+                self.expression(&mut i.index);
+                self.span(&mut i.span);
+            }
+            Expression::Match(m) => {
+                self.expression(&mut m.subject);
+                m.arms.iter_mut().for_each(|a| self.arm(a));
+                self.span(&mut m.span);
+            }
+            Expression::Assign(a) => {
+                self.identifier(&mut a.name);
+                self.expression(&mut a.value);
+                self.span(&mut a.span);
+            }
+        }
+    }
+
+    fn literal(&self, literal: &mut Literal) {
+        match literal {
+            Literal::Integer(i) => self.span(&mut i.span),
+            Literal::Decimal(d) => self.span(&mut d.span),
+            Literal::Boolean(b) => self.span(&mut b.span),
+            Literal::String(s) => self.span(&mut s.span),
+            Literal::Char(c) => self.span(&mut c.span),
+            Literal::Array(a) => {
+                a.elements.iter_mut().for_each(|e| self.expression(e));
+                self.span(&mut a.span);
+            }
+            Literal::Hash(h) => {
+                for (k, v) in h.elements.iter_mut() {
+                    self.expression(k);
+                    self.expression(v);
+                }
+                self.span(&mut h.span);
+            }
+        }
+    }
+
+    fn arm(&self, arm: &mut Arm) {
+        self.pattern(&mut arm.pattern);
+        arm.guard.iter_mut().for_each(|g| self.expression(g));
+        self.expression(&mut arm.body);
+        self.span(&mut arm.span);
+    }
+
+    fn pattern(&self, pattern: &mut Pattern) {
+        match pattern {
+            Pattern::Binding(id) => self.identifier(id),
+            Pattern::Literal(l) => self.literal(l),
+            Pattern::Array { elements, rest, span } => {
+                elements.iter_mut().for_each(|p| self.pattern(p));
+                rest.iter_mut().for_each(|r| self.identifier(r));
+                self.span(span);
+            }
+            Pattern::Hash { entries, span } => {
+                for (key, pattern) in entries.iter_mut() {
+                    self.literal(key);
+                    self.pattern(pattern);
+                }
+                self.span(span);
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use crate::ast::{Node, Program, Statement};
+    use crate::incremental::{reparse, Edit};
+    use crate::parse;
+    use lexer::token::Span;
+
+    fn program(source: &str) -> Program {
+        match parse(source).unwrap() {
+            Node::Program(p) => p,
+            node => panic!("not a program: {}", node),
+        }
+    }
+
+    fn edit(start: usize, end: usize, text: &str) -> Edit {
+        return Edit { range: Span { start, end }, text: text.to_string() };
+    }
+
+    const SOURCE: &str = "#!/usr/bin/env monkey
+let add = fn(a, b) { a + b }; // sum
+let xs = [1, 2, \"three;\"];
+for (x in xs) { puts(x); }
+let total = add(1, 2);
+match (total) { [h, ...t] => h, {\"k\": v} if v > 1 => v, _ => 'c' };
+n = total;
+outer: while (true) { break outer; }
+total;";
+
+    #[test]
+    fn test_reparse_is_parse() {
+        // every edit of a few kinds at every offset gives what parsing the edited source does
+        let texts = ["", "1", ";", "\n", "\"", "// c\n", "let y = 2;", "}", "é"];
+        let offsets: Vec<usize> = (0..=SOURCE.len())
+            .filter(|&i| SOURCE.is_char_boundary(i))
+            .collect();
+        for (i, &start) in offsets.iter().enumerate() {
+            let ends = [start, offsets.get(i + 1).copied().unwrap_or(start)];
+            for (end, text) in ends.iter().flat_map(|&e| texts.iter().map(move |t| (e, t))) {
+                let edit = edit(start, end, text);
+                let edited = [&SOURCE[..start], text, &SOURCE[end..]].concat();
+                let expected = parse(&edited);
+                match reparse(SOURCE, program(SOURCE), &edit) {
+                    Ok(reparsed) => {
+                        assert_eq!(reparsed.source, edited);
+                        let expected = expected.unwrap_or_else(|e| panic!("{:?}: {:?}", edit, e));
+                        assert_eq!(Node::Program(reparsed.program), expected, "{:?}", edit);
+                    }
+                    Err(e) => assert_eq!(Err(e), expected, "{:?}", edit),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reparse_around_edit() {
+        let source = "let a = 1;\nlet b = 2; let c = 3;\nlet d = 4;\nd";
+        let at = source.find('2').unwrap();
+        let reparsed = reparse(source, program(source), &edit(at, at + 1, "20\n")).unwrap();
+        assert_eq!(reparsed.source, "let a = 1;\nlet b = 20\n; let c = 3;\nlet d = 4;\nd");
+        assert_eq!(reparsed.reparsed, 1..2);
+        // the statements after it moved down a line, `c` along it as well
+        let positions: Vec<String> = reparsed
+            .program
+            .body
+            .iter()
+            .map(|s| match s {
+                Statement::Let(l) => format!("{} {:?}", l.identifier.position, l.span),
+                s => format!("{:?}", s.span()),
+            })
+            .collect();
+        let expected = [
+            "line 1, column 5 Span { start: 0, end: 10 }",
+            "line 2, column 5 Span { start: 11, end: 23 }",
+            "line 3, column 7 Span { start: 24, end: 34 }",
+            "line 4, column 5 Span { start: 35, end: 45 }",
+            "Span { start: 46, end: 47 }",
+        ];
+        assert_eq!(positions, expected);
+
+        // the whole of a `for` loop, its two statements sharing their `;`
+        let source = "let a = 1; for (let i = 0; i < a; i = i + 1) { a } let b = 2; a";
+        let at = source.find("i + 1").unwrap();
+        let reparsed = reparse(source, program(source), &edit(at, at + 1, "a")).unwrap();
+        assert_eq!(reparsed.reparsed, 1..4);
+        // and everything when the edit opens a string running over the `;`s after it
+        let reparsed = reparse(source, program(source), &edit(11, 11, "\"")).unwrap();
+        assert_eq!(reparsed.reparsed, 0..2);
+        let edited = parse(&reparsed.source).unwrap();
+        assert_eq!(Node::Program(reparsed.program), edited);
+    }
+
+    #[test]
+    fn test_edit_outside_source() {
+        let error = "can't edit 3..9 of a source of 5 bytes";
+        assert_eq!(
+            reparse("let a", program("let a = 1"), &edit(3, 9, "")).unwrap_err()[0].message,
+            error
+        );
+        let error = "can't edit 1..2 of a source of 2 bytes";
+        assert_eq!(reparse("é", program("1"), &edit(1, 2, "")).unwrap_err()[0].message, error);
+    }
+}
@@ -1,53 +1,96 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod ast;
 mod ast_tree_test;
+pub mod error;
+mod error_test;
+pub mod incremental;
+mod incremental_test;
 mod parser_test;
 mod precedences;
+mod validate;
 
 pub extern crate lexer;
 
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
 use crate::ast::{
-    Array, BinaryExpression, BlockStatement, Boolean, Expression, FunctionCall,
-    FunctionDeclaration, Hash, Index, Integer, Let, Literal, Node, Program, ReturnStatement,
-    Statement, StringType, UnaryExpression, IDENTIFIER, IF,
+    Arm, Array, Assign, BinaryExpression, BlockStatement, Boolean, Break, CharType, DecimalType,
+    Expression, FunctionCall, FunctionDeclaration, Hash, Import, Index, Integer, Let, Literal,
+    Match, Node, Pattern, Program, ReturnStatement, Statement, StringType, UnaryExpression, While,
+    IDENTIFIER, IF,
 };
+pub use crate::error::ParseError;
 use crate::precedences::{get_token_precedence, Precedence};
-use lexer::token::{Span, Token, TokenKind};
+use lexer::token::{Position, Span, Token, TokenKind};
 use lexer::Lexer;
 
-type ParseError = String;
-type ParseErrors = Vec<ParseError>;
+// what's wrong, until the parse is over and it's given where, see `ParseError`
+type Message = String;
+pub type ParseErrors = Vec<ParseError>;
+
+// more errors than this is one mistake cascading, or not a monkey source at all
+const MAX_ERRORS: usize = 20;
 
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     current_token: Token,
     peek_token: Token,
-    errors: ParseErrors,
+    // each with where the parser was when it gave up, to sort them by
+    errors: Vec<(Span, Message)>,
+    // the `for (x in xs)` loops around the code being parsed, see `parse_for_in_statement`
+    for_in_depth: usize,
+    // the `{` before the current token not yet closed, see `synchronize`
+    braces: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(mut lexer: Lexer<'a>) -> Parser<'a> {
         let cur = lexer.next_token();
         let next = lexer.next_token();
-        let errors = Vec::new();
+        let errors = vec![];
         // in strict sense, rust can be as classic go pattern, but it requires more work
         // so let's just use pattern matching
         // ```rust
-        // type PrefixParseFn = fn() -> Result<Expression, ParseError>;
-        // type InfixParseFn = fn(Expression) -> Result<Expression, ParseError>;
+        // type PrefixParseFn = fn() -> Result<Expression, Message>;
+        // type InfixParseFn = fn(Expression) -> Result<Expression, Message>;
         // let prefix_parse_fns = HashMap::new();
         // let infix_parse_fns = HashMap::new();
         // ```
 
-        let p = Parser { lexer, current_token: cur, peek_token: next, errors };
+        let p = Parser {
+            lexer,
+            current_token: cur,
+            peek_token: next,
+            errors,
+            for_in_depth: 0,
+            braces: 0,
+        };
 
         return p;
     }
 
     fn next_token(&mut self) {
+        match self.current_token.kind {
+            TokenKind::LBRACE => self.braces += 1,
+            TokenKind::RBRACE => self.braces = self.braces.saturating_sub(1),
+            _ => {}
+        }
         self.current_token = self.peek_token.clone();
         self.peek_token = self.lexer.next_token();
     }
 
+    fn current_identifier(&self, name: &str) -> IDENTIFIER {
+        let (span, position) = (self.current_token.span.clone(), self.current_token.position);
+        return IDENTIFIER { name: name.to_string(), span, position };
+    }
+
     fn current_token_is(&mut self, token: &TokenKind) -> bool {
         self.current_token.kind == *token
     }
@@ -56,12 +99,13 @@ impl<'a> Parser<'a> {
         self.peek_token.kind == *token
     }
 
-    fn expect_peek(&mut self, token: &TokenKind) -> Result<(), ParseError> {
+    fn expect_peek(&mut self, token: &TokenKind) -> Result<(), Message> {
         self.next_token();
         if self.current_token.kind == *token {
             Ok(())
         } else {
-            let e = format!("expected token: {} got: {}", token, self.current_token);
+            let (found, at) = (&self.current_token.kind, self.current_token.position);
+            let e = format!("expected `{}` at {}, got `{}`", token, at, found);
             Err(e)
         }
     }
@@ -69,39 +113,152 @@ impl<'a> Parser<'a> {
     pub fn parse_program(&mut self) -> Result<Program, ParseErrors> {
         let mut program = Program::new();
         while !self.current_token_is(&TokenKind::EOF) {
-            match self.parse_statement() {
-                Ok(stmt) => program.body.push(stmt),
-                Err(e) => self.errors.push(e),
+            let depth = self.braces;
+            match self.parse_statements() {
+                Ok(statements) => {
+                    program.body.extend(statements);
+                    self.end_statement();
+                }
+                Err(e) => {
+                    self.errors.push((self.current_token.span.clone(), e));
+                    self.synchronize(depth);
+                }
+            }
+            if self.errors.len() > MAX_ERRORS {
+                break;
             }
             self.next_token();
         }
         program.span.end = self.current_token.span.end;
+        self.errors.extend(validate::validate(&program.body));
+        // the problems in the source the errors above skipped, as in a block that failed
+        for token in self.lexer.diagnostics() {
+            let e = token.diagnostic().unwrap_or_default();
+            self.errors.push((token.span.clone(), e));
+        }
 
         if self.errors.is_empty() {
             return Ok(program);
         } else {
-            return Err(self.errors.clone());
+            return Err(self.sorted_errors());
+        }
+    }
+
+    // The errors in the order of the source, each once, whatever order recovering from one
+    // found the next in, and no more than `MAX_ERRORS` of them, the last saying where it stopped.
+    fn sorted_errors(&mut self) -> ParseErrors {
+        let mut errors = core::mem::take(&mut self.errors);
+        errors.sort_by_key(|(span, _)| span.start);
+        let mut sorted: Vec<(Span, Message)> = vec![];
+        for (span, e) in errors {
+            if !sorted.iter().any(|(_, seen)| *seen == e) {
+                sorted.push((span, e));
+            }
+        }
+        if sorted.len() > MAX_ERRORS {
+            sorted.truncate(MAX_ERRORS + 1);
+            sorted[MAX_ERRORS].1 = "too many errors, stopping".to_string();
+        }
+        let source = self.lexer.source();
+        return sorted
+            .into_iter()
+            .map(|(span, e)| ParseError::new(source, span, e))
+            .collect();
+    }
+
+    // a statement ends at a `;`, a line break, a `}` or the end, or right after a `}` of its own
+    fn end_statement(&mut self) {
+        let ended = matches!(self.current_token.kind, TokenKind::SEMICOLON | TokenKind::RBRACE)
+            || matches!(self.peek_token.kind, TokenKind::RBRACE | TokenKind::EOF);
+        if ended {
+            return;
+        }
+        let mut end = self.current_token.position;
+        for ch in self.lexer.text(&self.current_token.span).chars() {
+            if ch == '\n' {
+                end.line += 1;
+                end.column = 1;
+            } else {
+                end.column += 1;
+            }
+        }
+        if self.peek_token.position.line > end.line {
+            return;
+        }
+        let e = format!("expected `;` at {}, got `{}`", end, self.peek_token.kind);
+        let at = self.current_token.span.end;
+        self.errors.push((Span { start: at, end: at }, e));
+    }
+
+    // after a statement that failed to parse, skips to its last token, so one mistake is one error
+    fn synchronize(&mut self, depth: usize) {
+        loop {
+            let ended = match self.current_token.kind {
+                TokenKind::EOF => true,
+                TokenKind::SEMICOLON | TokenKind::RBRACE => self.braces <= depth,
+                _ => self.braces < depth,
+            };
+            if ended {
+                return;
+            }
+            let next = match self.current_token.kind {
+                TokenKind::LBRACE => self.braces + 1,
+                TokenKind::RBRACE => self.braces - 1,
+                _ => self.braces,
+            };
+            let starts = match self.peek_token.kind {
+                TokenKind::LET
+                | TokenKind::RETURN
+                | TokenKind::IMPORT
+                | TokenKind::WHILE
+                | TokenKind::FOR
+                | TokenKind::BREAK
+                | TokenKind::RBRACE => next <= depth,
+                TokenKind::EOF => true,
+                _ => false,
+            };
+            if starts {
+                return;
+            }
+            self.next_token();
+        }
+    }
+
+    // a `for` loop is two statements, see `While`
+    fn parse_statements(&mut self) -> Result<Vec<Statement>, Message> {
+        let labeled = self.peek_token_is(&TokenKind::COLON);
+        match self.current_token.kind {
+            TokenKind::FOR => self.parse_for_statement(),
+            TokenKind::IDENTIFIER { .. } if labeled => self.parse_labeled_statement(),
+            _ => Ok(vec![self.parse_statement()?]),
         }
     }
 
-    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+    fn parse_statement(&mut self) -> Result<Statement, Message> {
         match self.current_token.kind {
             TokenKind::LET => self.parse_let_statement(),
             TokenKind::RETURN => self.parse_return_statement(),
+            TokenKind::IMPORT => self.parse_import_statement(),
+            TokenKind::WHILE => self.parse_while_statement(),
+            TokenKind::BREAK => self.parse_break_statement(),
+            TokenKind::FUNCTION if matches!(self.peek_token.kind, TokenKind::IDENTIFIER { .. }) => {
+                self.parse_fn_statement()
+            }
             _ => self.parse_expression_statement(),
         }
     }
 
-    fn parse_let_statement(&mut self) -> Result<Statement, ParseError> {
+    fn parse_let_statement(&mut self) -> Result<Statement, Message> {
         let start = self.current_token.span.start;
         self.next_token();
+        return self.parse_binding(start);
+    }
 
+    // `x = 1`, the part of a `let` after the keyword
+    fn parse_binding(&mut self, start: usize) -> Result<Statement, Message> {
         let name = self.current_token.clone();
-        let mut identifier_name = "".to_string();
-        match &self.current_token.kind {
-            TokenKind::IDENTIFIER { name } => {
-                identifier_name = name.to_string();
-            }
+        let identifier_name = match &self.current_token.kind {
+            TokenKind::IDENTIFIER { name } => name.to_string(),
             _ => return Err(format!("{} not an identifier", self.current_token)),
         };
 
@@ -109,11 +266,8 @@ impl<'a> Parser<'a> {
         self.next_token();
 
         let mut value = self.parse_expression(Precedence::LOWEST)?.0;
-        match value {
-            Expression::FUNCTION(ref mut f) => {
-                f.name = identifier_name;
-            }
-            _ => {}
+        if let Expression::FUNCTION(ref mut f) = value {
+            f.name = identifier_name;
         }
 
         if self.peek_token_is(&TokenKind::SEMICOLON) {
@@ -129,7 +283,31 @@ impl<'a> Parser<'a> {
         }));
     }
 
-    fn parse_return_statement(&mut self) -> Result<Statement, ParseError> {
+    // `fn add(a, b) { a + b }` is `let add = fn(a, b) { a + b };`, the name bound before the
+    // function is made so it can call itself by it
+    fn parse_fn_statement(&mut self) -> Result<Statement, Message> {
+        let start = self.current_token.span.start;
+        self.next_token();
+        let name = self.current_token.clone();
+        let mut value = self.parse_fn_expression()?;
+        if let Expression::FUNCTION(ref mut f) = value {
+            f.name = name.kind.to_string();
+            f.span.start = start;
+        }
+
+        if self.peek_token_is(&TokenKind::SEMICOLON) {
+            self.next_token();
+        }
+        let end = self.current_token.span.end;
+
+        return Ok(Statement::Let(Let {
+            identifier: name,
+            expr: value,
+            span: Span { start, end },
+        }));
+    }
+
+    fn parse_return_statement(&mut self) -> Result<Statement, Message> {
         let start = self.current_token.span.start;
         self.next_token();
 
@@ -146,19 +324,307 @@ impl<'a> Parser<'a> {
         }));
     }
 
-    fn parse_expression_statement(&mut self) -> Result<Statement, ParseError> {
-        let expr = self.parse_expression(Precedence::LOWEST)?.0;
+    fn parse_while_statement(&mut self) -> Result<Statement, Message> {
+        let start = self.current_token.span.start;
+        self.expect_peek(&TokenKind::LPAREN)?;
+        self.next_token();
+
+        let condition = self.parse_expression(Precedence::LOWEST)?.0;
+        self.expect_peek(&TokenKind::RPAREN)?;
+        self.expect_peek(&TokenKind::LBRACE)?;
+
+        let body = self.parse_block_statement()?;
+        let end = self.current_token.span.end;
+
+        let span = Span { start, end };
+        return Ok(Statement::While(While { label: None, condition, body, span }));
+    }
+
+    // `outer: while (...) { ... }`, only loops take a label
+    fn parse_labeled_statement(&mut self) -> Result<Vec<Statement>, Message> {
+        let start = self.current_token.span.start;
+        let label = match &self.current_token.kind {
+            TokenKind::IDENTIFIER { name } => self.current_identifier(name),
+            _ => return Err(format!("{} not an identifier", self.current_token)),
+        };
+        self.next_token();
+        self.next_token();
+
+        let mut statements = match self.current_token.kind {
+            TokenKind::WHILE => vec![self.parse_while_statement()?],
+            TokenKind::FOR => self.parse_for_statement()?,
+            _ => {
+                let (found, at) = (&self.current_token.kind, self.current_token.position);
+                return Err(format!(
+                    "expected a loop after `{}:` at {}, got `{}`",
+                    label, at, found
+                ));
+            }
+        };
+        if let Some(Statement::While(w)) = statements.last_mut() {
+            w.label = Some(label);
+            w.span.start = start;
+        }
+        return Ok(statements);
+    }
+
+    // a label on the next line is a statement of its own
+    fn parse_break_statement(&mut self) -> Result<Statement, Message> {
+        let (start, position) = (self.current_token.span.start, self.current_token.position);
+        let mut label = None;
+        if let TokenKind::IDENTIFIER { name } = &self.peek_token.kind {
+            if self.peek_token.position.line == position.line {
+                let name = name.clone();
+                self.next_token();
+                label = Some(self.current_identifier(&name));
+            }
+        }
         if self.peek_token_is(&TokenKind::SEMICOLON) {
             self.next_token();
         }
+        let end = self.current_token.span.end;
 
-        Ok(Statement::Expr(expr))
+        return Ok(Statement::Break(Break { label, position, span: Span { start, end } }));
     }
 
-    fn parse_expression(
-        &mut self,
-        precedence: Precedence,
-    ) -> Result<(Expression, Span), ParseError> {
+    // `for (init; condition; update) { body }` is `init; while (condition) { body update }`, any
+    // of the three can be left out. `i = i + 1` in the init or the update is `let i = i + 1`.
+    fn parse_for_statement(&mut self) -> Result<Vec<Statement>, Message> {
+        let start = self.current_token.span.start;
+        self.expect_peek(&TokenKind::LPAREN)?;
+        self.next_token();
+        let variable = matches!(self.current_token.kind, TokenKind::IDENTIFIER { .. });
+        if variable && self.peek_token_is(&TokenKind::IN) {
+            return self.parse_for_in_statement(start);
+        }
+
+        let mut statements = vec![];
+        if !self.current_token_is(&TokenKind::SEMICOLON) {
+            statements.push(self.parse_for_clause()?);
+            if !self.current_token_is(&TokenKind::SEMICOLON) {
+                self.expect_peek(&TokenKind::SEMICOLON)?;
+            }
+        }
+        self.next_token();
+
+        let condition = if self.current_token_is(&TokenKind::SEMICOLON) {
+            let span = self.current_token.span.clone();
+            Expression::LITERAL(Literal::Boolean(Boolean { raw: true, span }))
+        } else {
+            let condition = self.parse_expression(Precedence::LOWEST)?.0;
+            self.expect_peek(&TokenKind::SEMICOLON)?;
+            condition
+        };
+        self.next_token();
+
+        let update = if self.current_token_is(&TokenKind::RPAREN) {
+            None
+        } else {
+            let update = self.parse_for_clause()?;
+            self.expect_peek(&TokenKind::RPAREN)?;
+            Some(update)
+        };
+        self.expect_peek(&TokenKind::LBRACE)?;
+
+        let mut body = self.parse_block_statement()?;
+        body.body.extend(update);
+        let end = self.current_token.span.end;
+
+        let span = Span { start, end };
+        statements.push(Statement::While(While { label: None, condition, body, span }));
+        return Ok(statements);
+    }
+
+    // `for (x in xs) { body }` is a `while (true)` calling `next` on `each(xs)`, with hidden names
+    // numbered by nesting so it parses the same wherever it moves
+    fn parse_for_in_statement(&mut self, start: usize) -> Result<Vec<Statement>, Message> {
+        let variable = self.current_token.clone();
+        let name = variable.kind.to_string();
+        self.next_token();
+        self.next_token();
+        let collection = self.parse_expression(Precedence::LOWEST)?.0;
+        self.expect_peek(&TokenKind::RPAREN)?;
+        self.expect_peek(&TokenKind::LBRACE)?;
+        let depth = self.for_in_depth;
+        self.for_in_depth += 1;
+        let block = self.parse_block_statement();
+        self.for_in_depth = depth;
+        let block = block?;
+        let span = Span { start, end: self.current_token.span.end };
+
+        let (at, position) = (variable.span.clone(), variable.position);
+        let identifier = |name: &str| {
+            let name = IDENTIFIER { name: name.to_string(), span: at.clone(), position };
+            return Expression::IDENTIFIER(name);
+        };
+        let bind = |name: &str, expr: Expression| {
+            let kind = TokenKind::IDENTIFIER { name: name.into() };
+            let identifier = Token { kind, span: at.clone(), position };
+            return Statement::Let(Let { identifier, expr, span: span.clone() });
+        };
+        let call = |callee: &str, argument: Expression| {
+            return Expression::FunctionCall(FunctionCall {
+                callee: Box::new(identifier(callee)),
+                arguments: vec![argument],
+                span: span.clone(),
+            });
+        };
+        let (iterator, step) = (format!("in#{}", depth), format!("step#{}", depth));
+        let field = |field: &str| {
+            let raw = field.to_string();
+            return Expression::Index(Index {
+                object: Box::new(identifier(&step)),
+                index: Box::new(Expression::LITERAL(Literal::String(StringType {
+                    raw,
+                    span: at.clone(),
+                }))),
+                span: span.clone(),
+            });
+        };
+
+        let leave = Statement::Break(Break { label: None, position, span: span.clone() });
+        let done = Expression::IF(IF {
+            condition: Box::new(field("done")),
+            consequent: BlockStatement { body: vec![leave], span: span.clone() },
+            alternate: None,
+            span: span.clone(),
+        });
+        let mut body = vec![
+            bind(&step, call("next", identifier(&iterator))),
+            Statement::Expr(done),
+            bind(&name, field("value")),
+        ];
+        body.extend(block.body);
+        let body = BlockStatement { body, span: block.span };
+        let condition =
+            Expression::LITERAL(Literal::Boolean(Boolean { raw: true, span: at.clone() }));
+        return Ok(vec![
+            bind(&iterator, call("each", collection)),
+            Statement::While(While { label: None, condition, body, span }),
+        ]);
+    }
+
+    fn parse_for_clause(&mut self) -> Result<Statement, Message> {
+        let rebinding = self.peek_token_is(&TokenKind::ASSIGN);
+        match self.current_token.kind {
+            TokenKind::LET => return self.parse_let_statement(),
+            TokenKind::IDENTIFIER { .. } if rebinding => {
+                let start = self.current_token.span.start;
+                return self.parse_binding(start);
+            }
+            _ => return self.parse_expression_or_assignment(),
+        }
+    }
+
+    fn parse_import_statement(&mut self) -> Result<Statement, Message> {
+        let start = self.current_token.span.start;
+        self.next_token();
+
+        let path = match &self.current_token.kind {
+            TokenKind::STRING(s) => s.to_string(),
+            _ => {
+                return Err(format!(
+                    "expected import path to be a string, got {}",
+                    self.current_token
+                ))
+            }
+        };
+
+        if self.peek_token_is(&TokenKind::SEMICOLON) {
+            self.next_token();
+        }
+        let end = self.current_token.span.end;
+
+        return Ok(Statement::Import(Import { path, span: Span { start, end } }));
+    }
+
+    fn parse_expression_statement(&mut self) -> Result<Statement, Message> {
+        let statement = self.parse_expression_or_assignment()?;
+        if self.peek_token_is(&TokenKind::SEMICOLON) {
+            self.next_token();
+        }
+
+        Ok(statement)
+    }
+
+    // an expression, or `a[i] = v`, `a = set(a, i, v)`, or a compound assignment, `x += v` being
+    // `x = x + v`
+    fn parse_expression_or_assignment(&mut self) -> Result<Statement, Message> {
+        let start = self.current_token.span.start;
+        let target = self.parse_expression(Precedence::ASSIGN)?.0;
+        let operator = compound_operator(&self.peek_token.kind);
+        let assigns = match target {
+            Expression::Index(_) => self.peek_token_is(&TokenKind::ASSIGN) || operator.is_some(),
+            Expression::IDENTIFIER(_) => operator.is_some(),
+            _ => false,
+        };
+        if !assigns && self.peek_token_is(&TokenKind::ASSIGN) {
+            self.next_token();
+            return Ok(Statement::Expr(self.parse_assign_expression(target, start)?));
+        }
+        if !assigns {
+            if operator.is_some() {
+                let at = self.peek_token.position;
+                return Err(format!(
+                    "can't assign to `{}` at {}, only to a name or an index",
+                    target, at
+                ));
+            }
+            return Ok(Statement::Expr(target));
+        }
+        self.next_token();
+        let assign = self.current_token.clone();
+        self.next_token();
+        let mut value = self.parse_expression(Precedence::LOWEST)?.0;
+        if let Some(kind) = operator {
+            let end = self.current_token.span.end;
+            value = Expression::INFIX(BinaryExpression {
+                op: Token { kind, span: assign.span.clone(), position: assign.position },
+                left: Box::new(target.clone()),
+                right: Box::new(value),
+                span: Span { start, end },
+            });
+        }
+        let (name, value) = self.lower_index_assignment(target, value, &assign)?;
+        let span = Span { start, end: self.current_token.span.end };
+        let assignment = Assign { name, value: Box::new(value), span };
+        return Ok(Statement::Expr(Expression::Assign(assignment)));
+    }
+
+    // the name `target` indexes into and what to bind it to, `a[i][j] = v` being
+    // `a = set(a, i, set(a[i], j, v))`
+    fn lower_index_assignment(
+        &self,
+        target: Expression,
+        value: Expression,
+        assign: &Token,
+    ) -> Result<(IDENTIFIER, Expression), Message> {
+        match target {
+            Expression::IDENTIFIER(name) => return Ok((name, value)),
+            Expression::Index(Index { object, index, span }) => {
+                let set = IDENTIFIER {
+                    name: "set".to_string(),
+                    span: assign.span.clone(),
+                    position: assign.position,
+                };
+                let value = Expression::FunctionCall(FunctionCall {
+                    callee: Box::new(Expression::IDENTIFIER(set)),
+                    arguments: vec![(*object).clone(), *index, value],
+                    span,
+                });
+                return self.lower_index_assignment(*object, value, assign);
+            }
+            target => {
+                let at = assign.position;
+                return Err(format!(
+                    "can't assign to an index of `{}` at {}, only of a name",
+                    target, at
+                ));
+            }
+        }
+    }
+
+    fn parse_expression(&mut self, precedence: Precedence) -> Result<(Expression, Span), Message> {
         let mut left_start = self.current_token.span.start;
         let mut left = self.parse_prefix_expression()?;
         while self.peek_token.kind != TokenKind::SEMICOLON
@@ -182,33 +648,69 @@ impl<'a> Parser<'a> {
         Ok((left, Span { start: left_start, end }))
     }
 
-    fn parse_prefix_expression(&mut self) -> Result<Expression, ParseError> {
+    // the error of `9223372036854775808` without a `-` in front, see `TokenKind::INT`
+    fn too_large(&self) -> Message {
+        let number = self.lexer.text(&self.current_token.span);
+        let reason = format!("{} doesn't fit in an integer, the largest is {}", number, i64::MAX);
+        return format!("{} at {}", reason, self.current_token.position);
+    }
+
+    fn parse_prefix_expression(&mut self) -> Result<Expression, Message> {
         // this is prefix fn map :)
         match &self.current_token.kind {
             TokenKind::IDENTIFIER { name } => {
-                return Ok(Expression::IDENTIFIER(IDENTIFIER {
-                    name: name.clone(),
-                    span: self.current_token.clone().span,
-                }))
+                return Ok(Expression::IDENTIFIER(self.current_identifier(name)))
             }
+            TokenKind::INT(i) if *i == i64::MIN => return Err(self.too_large()),
             TokenKind::INT(i) => {
                 return Ok(Expression::LITERAL(Literal::Integer(Integer {
                     raw: *i,
                     span: self.current_token.clone().span,
                 })))
             }
+            TokenKind::DECIMAL(d) => {
+                return Ok(Expression::LITERAL(Literal::Decimal(DecimalType {
+                    raw: d.to_string(),
+                    span: self.current_token.clone().span,
+                })))
+            }
+            TokenKind::Illegal { .. } => {
+                return Err(self.current_token.diagnostic().unwrap_or_default());
+            }
+            TokenKind::FLOAT(x) => {
+                let at = self.current_token.position;
+                let decimal = match without_exponent(x) {
+                    Some(d) => format!("{}d is a decimal", d),
+                    None => "use a decimal".to_string(),
+                };
+                return Err(format!("float literals aren't supported yet, {}, at {}", decimal, at));
+            }
             TokenKind::STRING(s) => {
                 return Ok(Expression::LITERAL(Literal::String(StringType {
                     raw: s.to_string(),
                     span: self.current_token.clone().span,
                 })))
             }
+            TokenKind::CHAR(c) => {
+                return Ok(Expression::LITERAL(Literal::Char(CharType {
+                    raw: *c,
+                    span: self.current_token.clone().span,
+                })))
+            }
+            TokenKind::TemplateHead(_) => self.parse_template_expression(),
             b @ TokenKind::TRUE | b @ TokenKind::FALSE => {
                 return Ok(Expression::LITERAL(Literal::Boolean(Boolean {
                     raw: *b == TokenKind::TRUE,
                     span: self.current_token.clone().span,
                 })))
             }
+            // the one integer that's only written negative
+            TokenKind::MINUS if self.peek_token.kind == TokenKind::INT(i64::MIN) => {
+                let start = self.current_token.span.start;
+                self.next_token();
+                let span = Span { start, end: self.current_token.span.end };
+                return Ok(Expression::LITERAL(Literal::Integer(Integer { raw: i64::MIN, span })));
+            }
             TokenKind::BANG | TokenKind::MINUS => {
                 let start = self.current_token.span.start;
                 let prefix_op = self.current_token.clone();
@@ -221,12 +723,17 @@ impl<'a> Parser<'a> {
                 }));
             }
             TokenKind::LPAREN => {
+                let start = self.current_token.span.start;
                 self.next_token();
+                if self.peek_token_is(&TokenKind::RPAREN) && is_section(&self.current_token.kind) {
+                    return Ok(self.parse_section(start));
+                }
                 let expr = self.parse_expression(Precedence::LOWEST)?.0;
                 self.expect_peek(&TokenKind::RPAREN)?;
                 return Ok(expr);
             }
             TokenKind::IF => self.parse_if_expression(),
+            TokenKind::MATCH => self.parse_match_expression(),
             TokenKind::FUNCTION => self.parse_fn_expression(),
             TokenKind::LBRACKET => {
                 let (elements, span) = self.parse_expression_list(&TokenKind::RBRACKET)?;
@@ -241,7 +748,7 @@ impl<'a> Parser<'a> {
         &mut self,
         left: &Expression,
         left_start: usize,
-    ) -> Option<Result<Expression, ParseError>> {
+    ) -> Option<Result<Expression, Message>> {
         match self.peek_token.kind {
             TokenKind::PLUS
             | TokenKind::MINUS
@@ -255,7 +762,10 @@ impl<'a> Parser<'a> {
                 let infix_op = self.current_token.clone();
                 let precedence_value = get_token_precedence(&self.current_token.kind);
                 self.next_token();
-                let (right, span) = self.parse_expression(precedence_value).unwrap();
+                let (right, span) = match self.parse_expression(precedence_value) {
+                    Ok(parsed) => parsed,
+                    Err(e) => return Some(Err(e)),
+                };
                 return Some(Ok(Expression::INFIX(BinaryExpression {
                     op: infix_op,
                     left: Box::new(left.clone()),
@@ -263,19 +773,197 @@ impl<'a> Parser<'a> {
                     span: Span { start: left_start, end: span.end },
                 })));
             }
+            TokenKind::Compose => {
+                self.next_token();
+                return Some(self.parse_compose_expression(left.clone(), left_start));
+            }
+            TokenKind::ASSIGN => {
+                self.next_token();
+                return Some(self.parse_assign_expression(left.clone(), left_start));
+            }
+            TokenKind::Question => {
+                self.next_token();
+                return Some(self.parse_ternary_expression(left.clone(), left_start));
+            }
             TokenKind::LPAREN => {
                 self.next_token();
                 return Some(self.parse_fn_call_expression(left.clone()));
             }
             TokenKind::LBRACKET => {
                 self.next_token();
-                return Some(self.parse_index_expression(left.clone()));
+                return Some(self.parse_index_expression(left.clone(), left_start));
+            }
+            TokenKind::Dot => {
+                self.next_token();
+                return Some(self.parse_dot_expression(left.clone(), left_start));
             }
             _ => None,
         }
     }
 
-    fn parse_if_expression(&mut self) -> Result<Expression, ParseError> {
+    // `(+)` is `fn(a, b) { a + b }`, for every infix operator, to pass one as a function
+    fn parse_section(&mut self, start: usize) -> Expression {
+        let op = self.current_token.clone();
+        self.next_token();
+        let span = Span { start, end: self.current_token.span.end };
+        let name = |name: &str| IDENTIFIER {
+            name: name.to_string(),
+            span: op.span.clone(),
+            position: op.position,
+        };
+        let body = Expression::INFIX(BinaryExpression {
+            op: op.clone(),
+            left: Box::new(Expression::IDENTIFIER(name("a"))),
+            right: Box::new(Expression::IDENTIFIER(name("b"))),
+            span: span.clone(),
+        });
+        return Expression::FUNCTION(FunctionDeclaration {
+            params: vec![name("a"), name("b")],
+            body: BlockStatement { body: vec![Statement::Expr(body)], span: span.clone() },
+            span,
+            name: "".to_string(),
+            rest: false,
+        });
+    }
+
+    // `x = v`, with `=` the current token, grouping to the right; only a name can be assigned
+    fn parse_assign_expression(
+        &mut self,
+        target: Expression,
+        start: usize,
+    ) -> Result<Expression, Message> {
+        let name = match target {
+            Expression::IDENTIFIER(name) => name,
+            target => {
+                let at = self.current_token.position;
+                return Err(format!("can't assign to `{}` at {}, only to a name", target, at));
+            }
+        };
+        self.next_token();
+        let (value, span) = self.parse_expression(Precedence::LOWEST)?;
+        let span = Span { start, end: span.end };
+        return Ok(Expression::Assign(Assign { name, value: Box::new(value), span }));
+    }
+
+    // `c ? a : b` is `if (c) { a } else { b }`. Anything goes between `?` and `:`, and what's
+    // after the `:` takes in another `?`, so `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+    fn parse_ternary_expression(
+        &mut self,
+        condition: Expression,
+        start: usize,
+    ) -> Result<Expression, Message> {
+        self.next_token();
+        let (consequent, consequent_span) = self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek(&TokenKind::COLON)?;
+        self.next_token();
+        let (alternate, alternate_span) = self.parse_expression(Precedence::ASSIGN)?;
+        let block =
+            |e: Expression, span: Span| BlockStatement { body: vec![Statement::Expr(e)], span };
+        return Ok(Expression::IF(IF {
+            condition: Box::new(condition),
+            consequent: block(consequent, consequent_span),
+            alternate: Some(block(alternate, alternate_span.clone())),
+            span: Span { start, end: alternate_span.end },
+        }));
+    }
+
+    // `f >> g` is `fn(f, g) { fn(x) { g(f(x)) } }(f, g)`, `f` and `g` evaluated once
+    fn parse_compose_expression(
+        &mut self,
+        left: Expression,
+        left_start: usize,
+    ) -> Result<Expression, Message> {
+        let compose = self.current_token.clone();
+        self.next_token();
+        let (right, span) = self.parse_expression(Precedence::COMPOSE)?;
+        let span = Span { start: left_start, end: span.end };
+        let name = |name: &str| IDENTIFIER {
+            name: name.to_string(),
+            span: compose.span.clone(),
+            position: compose.position,
+        };
+        let call = |callee: &str, argument: Expression| {
+            return Expression::FunctionCall(FunctionCall {
+                callee: Box::new(Expression::IDENTIFIER(name(callee))),
+                arguments: vec![argument],
+                span: span.clone(),
+            });
+        };
+        let function = |params: Vec<IDENTIFIER>, body: Expression| {
+            return Expression::FUNCTION(FunctionDeclaration {
+                params,
+                body: BlockStatement { body: vec![Statement::Expr(body)], span: span.clone() },
+                span: span.clone(),
+                name: "".to_string(),
+                rest: false,
+            });
+        };
+        let composed = call("g", call("f", Expression::IDENTIFIER(name("x"))));
+        let composed = function(vec![name("x")], composed);
+        return Ok(Expression::FunctionCall(FunctionCall {
+            callee: Box::new(function(vec![name("f"), name("g")], composed)),
+            arguments: vec![left, right],
+            span,
+        }));
+    }
+
+    // `"a ${b} c ${d}"` is `"a " + b + " c " + d`, leaving out the empty parts but the first
+    fn parse_template_expression(&mut self) -> Result<Expression, Message> {
+        let start = self.current_token.span.start;
+        let head = match &self.current_token.kind {
+            TokenKind::TemplateHead(s) => s.to_string(),
+            _ => return Err(format!("expected a string, got {}", self.current_token)),
+        };
+        let span = self.current_token.span.clone();
+        let mut concatenation =
+            Expression::LITERAL(Literal::String(StringType { raw: head, span }));
+        loop {
+            self.next_token();
+            let position = self.current_token.position;
+            let (expr, span) = self.parse_expression(Precedence::LOWEST)?;
+            // written as `puts` writes it, see `object::template`
+            let string = IDENTIFIER { name: "string".to_string(), span: span.clone(), position };
+            let expr = Expression::FunctionCall(FunctionCall {
+                callee: Box::new(Expression::IDENTIFIER(string)),
+                arguments: vec![expr],
+                span,
+            });
+            concatenation = self.concat(concatenation, expr, start);
+
+            self.next_token();
+            let (part, tail) = match &self.current_token.kind {
+                TokenKind::TemplateMiddle(s) => (s.to_string(), false),
+                TokenKind::TemplateTail(s) => (s.to_string(), true),
+                // a part with a bad escape
+                TokenKind::Illegal { .. } => return self.parse_prefix_expression(),
+                found => {
+                    let at = self.current_token.position;
+                    return Err(format!("expected `}}` at {}, got `{}`", at, found));
+                }
+            };
+            if !part.is_empty() {
+                let span = self.current_token.span.clone();
+                let part = Expression::LITERAL(Literal::String(StringType { raw: part, span }));
+                concatenation = self.concat(concatenation, part, start);
+            }
+            if tail {
+                return Ok(concatenation);
+            }
+        }
+    }
+
+    // `left + right`, the `+` being where `right` ends
+    fn concat(&self, left: Expression, right: Expression, start: usize) -> Expression {
+        let (span, position) = (self.current_token.span.clone(), self.current_token.position);
+        return Expression::INFIX(BinaryExpression {
+            op: Token { kind: TokenKind::PLUS, span: span.clone(), position },
+            left: Box::new(left),
+            right: Box::new(right),
+            span: Span { start, end: span.end },
+        });
+    }
+
+    fn parse_if_expression(&mut self) -> Result<Expression, Message> {
         let start = self.current_token.span.start;
         self.expect_peek(&TokenKind::LPAREN)?;
         self.next_token();
@@ -304,30 +992,211 @@ impl<'a> Parser<'a> {
         }));
     }
 
-    fn parse_block_statement(&mut self) -> Result<BlockStatement, ParseError> {
+    // `match (subject) { pattern => value, pattern if guard => value }`, a comma after the last
+    // arm is fine
+    fn parse_match_expression(&mut self) -> Result<Expression, Message> {
+        let start = self.current_token.span.start;
+        self.expect_peek(&TokenKind::LPAREN)?;
+        self.next_token();
+
+        let subject = self.parse_expression(Precedence::LOWEST)?.0;
+        self.expect_peek(&TokenKind::RPAREN)?;
+        self.expect_peek(&TokenKind::LBRACE)?;
+
+        let mut arms = vec![];
+        while !self.peek_token_is(&TokenKind::RBRACE) {
+            self.next_token();
+            let arm_start = self.current_token.span.start;
+            let pattern = self.parse_pattern()?;
+            let names = pattern.names();
+            for (i, name) in names.iter().enumerate() {
+                if names[..i].iter().any(|n| n.name == name.name) {
+                    let at = name.position;
+                    return Err(format!("`{}` is bound twice in the pattern at {}", name, at));
+                }
+            }
+
+            let guard = if self.peek_token_is(&TokenKind::IF) {
+                self.next_token();
+                self.next_token();
+                Some(self.parse_expression(Precedence::LOWEST)?.0)
+            } else {
+                None
+            };
+            self.expect_peek(&TokenKind::Arrow)?;
+            self.next_token();
+
+            let body = self.parse_expression(Precedence::LOWEST)?.0;
+            let span = Span { start: arm_start, end: self.current_token.span.end };
+            arms.push(Arm { pattern, guard, body, span });
+            if !self.peek_token_is(&TokenKind::RBRACE) {
+                self.expect_peek(&TokenKind::COMMA)?;
+            }
+        }
+        self.expect_peek(&TokenKind::RBRACE)?;
+        let end = self.current_token.span.end;
+
+        return Ok(Expression::Match(Match {
+            subject: Box::new(subject),
+            arms,
+            span: Span { start, end },
+        }));
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, Message> {
+        match &self.current_token.kind {
+            TokenKind::IDENTIFIER { name } => {
+                return Ok(Pattern::Binding(self.current_identifier(name)));
+            }
+            TokenKind::LBRACKET => return self.parse_array_pattern(),
+            TokenKind::LBRACE => return self.parse_hash_pattern(),
+            _ => return Ok(Pattern::Literal(self.parse_pattern_literal()?)),
+        }
+    }
+
+    // `[first, [x, _], ...rest]`
+    fn parse_array_pattern(&mut self) -> Result<Pattern, Message> {
+        let start = self.current_token.span.start;
+        let mut elements = vec![];
+        let mut rest: Option<IDENTIFIER> = None;
+        while !self.peek_token_is(&TokenKind::RBRACKET) {
+            if let Some(rest) = &rest {
+                let at = rest.position;
+                return Err(format!("`...{}` at {} must end the array pattern", rest, at));
+            }
+            self.next_token();
+            if self.current_token_is(&TokenKind::Ellipsis) {
+                self.next_token();
+                match &self.current_token.kind {
+                    TokenKind::IDENTIFIER { name } => rest = Some(self.current_identifier(name)),
+                    found => {
+                        let at = self.current_token.position;
+                        return Err(format!(
+                            "expected a name after `...` at {}, got `{}`",
+                            at, found
+                        ));
+                    }
+                }
+            } else {
+                elements.push(self.parse_pattern()?);
+            }
+            if !self.peek_token_is(&TokenKind::RBRACKET) {
+                self.expect_peek(&TokenKind::COMMA)?;
+            }
+        }
+        self.expect_peek(&TokenKind::RBRACKET)?;
+        let end = self.current_token.span.end;
+
+        return Ok(Pattern::Array { elements, rest, span: Span { start, end } });
+    }
+
+    // `{"name": n, id: i}`, a bare name as a key is that string
+    fn parse_hash_pattern(&mut self) -> Result<Pattern, Message> {
+        let start = self.current_token.span.start;
+        let mut entries = vec![];
+        while !self.peek_token_is(&TokenKind::RBRACE) {
+            self.next_token();
+            let key = match &self.current_token.kind {
+                TokenKind::IDENTIFIER { name } => Literal::String(StringType {
+                    raw: name.to_string(),
+                    span: self.current_token.span.clone(),
+                }),
+                _ => self.parse_pattern_literal()?,
+            };
+            self.expect_peek(&TokenKind::COLON)?;
+            self.next_token();
+            entries.push((key, self.parse_pattern()?));
+            if !self.peek_token_is(&TokenKind::RBRACE) {
+                self.expect_peek(&TokenKind::COMMA)?;
+            }
+        }
+        self.expect_peek(&TokenKind::RBRACE)?;
+        let end = self.current_token.span.end;
+
+        return Ok(Pattern::Hash { entries, span: Span { start, end } });
+    }
+
+    // an integer, decimal, string or boolean, `-1` and `-0.5d` included
+    fn parse_pattern_literal(&mut self) -> Result<Literal, Message> {
+        let start = self.current_token.span.start;
+        let negative = self.current_token_is(&TokenKind::MINUS);
+        if negative {
+            self.next_token();
+        }
+        let span = Span { start, end: self.current_token.span.end };
+        match &self.current_token.kind {
+            TokenKind::INT(i) if negative => {
+                return Ok(Literal::Integer(Integer { raw: i.wrapping_neg(), span }))
+            }
+            TokenKind::INT(i) if *i == i64::MIN => return Err(self.too_large()),
+            TokenKind::INT(i) => return Ok(Literal::Integer(Integer { raw: *i, span })),
+            TokenKind::DECIMAL(d) => {
+                let raw = if negative {
+                    format!("-{}", d)
+                } else {
+                    d.to_string()
+                };
+                return Ok(Literal::Decimal(DecimalType { raw, span }));
+            }
+            TokenKind::STRING(s) if !negative => {
+                return Ok(Literal::String(StringType { raw: s.to_string(), span }));
+            }
+            TokenKind::CHAR(c) if !negative => {
+                return Ok(Literal::Char(CharType { raw: *c, span }))
+            }
+            b @ (TokenKind::TRUE | TokenKind::FALSE) if !negative => {
+                return Ok(Literal::Boolean(Boolean { raw: *b == TokenKind::TRUE, span }));
+            }
+            TokenKind::Illegal { .. } => {
+                return Err(self.current_token.diagnostic().unwrap_or_default())
+            }
+            found => {
+                let at = self.current_token.position;
+                return Err(format!("expected a pattern at {}, got `{}`", at, found));
+            }
+        }
+    }
+
+    fn parse_block_statement(&mut self) -> Result<BlockStatement, Message> {
         let start = self.current_token.span.start;
         self.next_token();
         let mut block_statement = Vec::new();
 
         while !self.current_token_is(&TokenKind::RBRACE) && !self.current_token_is(&TokenKind::EOF)
         {
-            if let Ok(statement) = self.parse_statement() {
-                block_statement.push(statement)
+            let depth = self.braces;
+            match self.parse_statements() {
+                Ok(statements) => {
+                    block_statement.extend(statements);
+                    self.end_statement();
+                }
+                Err(e) => {
+                    self.errors.push((self.current_token.span.clone(), e));
+                    self.synchronize(depth);
+                    // the `}` the mistake stopped at closes the block
+                    if self.current_token_is(&TokenKind::RBRACE) && self.braces <= depth {
+                        break;
+                    }
+                }
             }
 
             self.next_token();
         }
+        if self.current_token_is(&TokenKind::EOF) {
+            let at = self.current_token.position;
+            return Err(format!("expected `}}` at {}, got `EOF`", at));
+        }
 
         let end = self.current_token.span.end;
 
         Ok(BlockStatement { body: block_statement, span: Span { start, end } })
     }
 
-    fn parse_fn_expression(&mut self) -> Result<Expression, ParseError> {
+    fn parse_fn_expression(&mut self) -> Result<Expression, Message> {
         let start = self.current_token.span.start;
         self.expect_peek(&TokenKind::LPAREN)?;
 
-        let params = self.parse_fn_parameters()?;
+        let (params, rest) = self.parse_fn_parameters()?;
 
         self.expect_peek(&TokenKind::LBRACE)?;
 
@@ -340,56 +1209,72 @@ impl<'a> Parser<'a> {
             body: function_body,
             span: Span { start, end },
             name: "".to_string(),
+            rest,
         }))
     }
 
-    fn parse_fn_parameters(&mut self) -> Result<Vec<IDENTIFIER>, ParseError> {
+    // `(a, b)`, the last one may be `...rest`, with whether it is
+    fn parse_fn_parameters(&mut self) -> Result<(Vec<IDENTIFIER>, bool), Message> {
         let mut params = Vec::new();
+        let mut rest = false;
         if self.peek_token_is(&TokenKind::RPAREN) {
             self.next_token();
-            return Ok(params);
-        }
-
-        self.next_token();
-
-        match &self.current_token.kind {
-            TokenKind::IDENTIFIER { name } => params
-                .push(IDENTIFIER { name: name.clone(), span: self.current_token.span.clone() }),
-            token => {
-                return Err(format!("expected function params  to be an identifier, got {}", token))
-            }
+            return Ok((params, rest));
         }
 
-        while self.peek_token_is(&TokenKind::COMMA) {
-            self.next_token();
+        loop {
             self.next_token();
+            if rest {
+                let (last, at) = (&params[params.len() - 1], self.current_token.position);
+                return Err(format!(
+                    "`...{}` must be the last parameter, got `{}` at {}",
+                    last, self.current_token.kind, at
+                ));
+            }
+            if self.current_token_is(&TokenKind::Ellipsis) {
+                rest = true;
+                self.next_token();
+            }
             match &self.current_token.kind {
-                TokenKind::IDENTIFIER { name } => params
-                    .push(IDENTIFIER { name: name.clone(), span: self.current_token.span.clone() }),
-                token => {
+                TokenKind::IDENTIFIER { name } => params.push(self.current_identifier(name)),
+                _ => {
                     return Err(format!(
                         "expected function params  to be an identifier, got {}",
-                        token
+                        self.current_token
                     ))
                 }
             }
+            if !self.peek_token_is(&TokenKind::COMMA) {
+                break;
+            }
+            self.next_token();
         }
 
         self.expect_peek(&TokenKind::RPAREN)?;
 
-        return Ok(params);
+        // one of them would hide the other, so whatever's passed for the first is lost, but for
+        // `_`, which binds nothing
+        for (i, param) in params.iter().enumerate().filter(|(_, p)| !p.is_throwaway()) {
+            if let Some(first) = params[..i].iter().find(|p| p.name == param.name) {
+                let (a, b) = (first.position, param.position);
+                return Err(format!("`{}` is a parameter twice, at {} and at {}", param, a, b));
+            }
+        }
+
+        return Ok((params, rest));
     }
 
-    fn parse_fn_call_expression(&mut self, expr: Expression) -> Result<Expression, ParseError> {
+    fn parse_fn_call_expression(&mut self, expr: Expression) -> Result<Expression, Message> {
         // fake positive
         #[allow(unused_assignments)]
         let mut start = self.current_token.span.start;
+        let at = self.current_token.position;
         let (arguments, ..) = self.parse_expression_list(&TokenKind::RPAREN)?;
         let end = self.current_token.span.end;
         match &expr {
             Expression::IDENTIFIER(i) => start = i.span.start,
             Expression::FUNCTION(f) => start = f.span.start,
-            _ => return Err(format!("expected function")),
+            _ => return Err(format!("expected function before the `(` at {}", at)),
         }
         let callee = Box::new(expr);
 
@@ -399,7 +1284,7 @@ impl<'a> Parser<'a> {
     fn parse_expression_list(
         &mut self,
         end: &TokenKind,
-    ) -> Result<(Vec<Expression>, Span), ParseError> {
+    ) -> Result<(Vec<Expression>, Span), Message> {
         let start = self.current_token.span.start;
         let mut expr_list = Vec::new();
         if self.peek_token_is(end) {
@@ -424,8 +1309,16 @@ impl<'a> Parser<'a> {
         return Ok((expr_list, Span { start, end }));
     }
 
-    fn parse_index_expression(&mut self, left: Expression) -> Result<Expression, ParseError> {
-        let start = self.current_token.span.start;
+    // `a[i]`, spanning from the start of `a`
+    fn parse_index_expression(
+        &mut self,
+        left: Expression,
+        start: usize,
+    ) -> Result<Expression, Message> {
+        if self.peek_token_is(&TokenKind::RBRACKET) {
+            let at = self.peek_token.position;
+            return Err(format!("expected an index between `[` and `]` at {}", at));
+        }
         self.next_token();
         let index = self.parse_expression(Precedence::LOWEST)?.0;
 
@@ -440,31 +1333,162 @@ impl<'a> Parser<'a> {
         }));
     }
 
-    fn parse_hash_expression(&mut self) -> Result<Expression, ParseError> {
+    // `h.name` is `h["name"]`, so a hash can be a record, and `h.name(x)` calls what's at the
+    // key, as a function in a hash can't be called through `h["name"](x)`
+    fn parse_dot_expression(
+        &mut self,
+        object: Expression,
+        start: usize,
+    ) -> Result<Expression, Message> {
+        self.next_token();
+        let key = match &self.current_token.kind {
+            TokenKind::IDENTIFIER { name } => Literal::String(StringType {
+                raw: name.to_string(),
+                span: self.current_token.span.clone(),
+            }),
+            found => {
+                let at = self.current_token.position;
+                return Err(format!("expected a name after the `.` at {}, got `{}`", at, found));
+            }
+        };
+        let span = Span { start, end: self.current_token.span.end };
+        let member = Expression::Index(Index {
+            object: Box::new(object),
+            index: Box::new(Expression::LITERAL(key)),
+            span,
+        });
+        if !self.peek_token_is(&TokenKind::LPAREN) {
+            return Ok(member);
+        }
+        self.next_token();
+        let (arguments, ..) = self.parse_expression_list(&TokenKind::RPAREN)?;
+        let span = Span { start, end: self.current_token.span.end };
+        return Ok(Expression::FunctionCall(FunctionCall {
+            callee: Box::new(member),
+            arguments,
+            span,
+        }));
+    }
+
+    // `{k: v, ...}`, a trailing comma allowed as after the last arm of a `match`
+    fn parse_hash_expression(&mut self) -> Result<Expression, Message> {
         let mut map = Vec::new();
+        let mut keys: Vec<(String, Position)> = vec![];
         let start = self.current_token.span.start;
         while !self.peek_token_is(&TokenKind::RBRACE) {
+            if self.peek_token_is(&TokenKind::COMMA) || self.peek_token_is(&TokenKind::EOF) {
+                return Err(self.hash_error("a key"));
+            }
             self.next_token();
-
+            let (offset, at) = (self.current_token.span.start, self.current_token.position);
             let key = self.parse_expression(Precedence::LOWEST)?.0;
-
-            self.expect_peek(&TokenKind::COLON)?;
-
+            // any key given twice fails when the hash is made, a literal one is found here, with
+            // where both are, and the rest of the hash parsed on
+            if is_literal_key(&key) {
+                match keys.iter().find(|(k, _)| *k == key.to_string()) {
+                    Some((_, first)) => {
+                        let twice = format!(
+                            "the key {} is in the hash twice, at {} and at {}",
+                            key, first, at
+                        );
+                        let span = Span { start: offset, end: self.current_token.span.end };
+                        self.errors.push((span, twice));
+                    }
+                    None => keys.push((key.to_string(), at)),
+                }
+            }
+            if !self.peek_token_is(&TokenKind::COLON) {
+                return Err(self.hash_error(&format!("`:` after the key {}", key)));
+            }
+            self.next_token();
+            let end = [TokenKind::COMMA, TokenKind::RBRACE, TokenKind::EOF];
+            if end.contains(&self.peek_token.kind) {
+                return Err(self.hash_error(&format!("a value for the key {}", key)));
+            }
             self.next_token();
             let value = self.parse_expression(Precedence::LOWEST)?.0;
-
             map.push((key, value));
-
-            if !self.peek_token_is(&TokenKind::RBRACE) {
-                self.expect_peek(&TokenKind::COMMA)?;
+            match self.peek_token.kind {
+                TokenKind::COMMA => self.next_token(),
+                TokenKind::RBRACE => {}
+                _ => return Err(self.hash_error("`,` or `}`")),
             }
         }
-
-        self.expect_peek(&TokenKind::RBRACE)?;
+        self.next_token();
         let end = self.current_token.span.end;
-
         Ok(Expression::LITERAL(Literal::Hash(Hash { elements: map, span: Span { start, end } })))
     }
+
+    // the error for `expected` missing from a hash literal, where the peek token is
+    fn hash_error(&self, expected: &str) -> Message {
+        let (found, at) = (&self.peek_token.kind, self.peek_token.position);
+        return format!("expected {} in a hash at {}, got `{}`", expected, at, found);
+    }
+}
+
+// the operator `+=` and the others apply before assigning
+fn compound_operator(kind: &TokenKind) -> Option<TokenKind> {
+    match kind {
+        TokenKind::PlusAssign => return Some(TokenKind::PLUS),
+        TokenKind::MinusAssign => return Some(TokenKind::MINUS),
+        TokenKind::AsteriskAssign => return Some(TokenKind::ASTERISK),
+        TokenKind::SlashAssign => return Some(TokenKind::SLASH),
+        _ => return None,
+    }
+}
+
+// a key whose value is known when it's parsed, two of them the same if they're written the same
+fn is_literal_key(key: &Expression) -> bool {
+    return matches!(
+        key,
+        Expression::LITERAL(Literal::Integer(_))
+            | Expression::LITERAL(Literal::String(_))
+            | Expression::LITERAL(Literal::Boolean(_))
+            | Expression::LITERAL(Literal::Char(_))
+    );
+}
+
+// the operators `(op)` makes a function of, see `parse_section`
+fn is_section(kind: &TokenKind) -> bool {
+    return matches!(
+        kind,
+        TokenKind::PLUS
+            | TokenKind::MINUS
+            | TokenKind::ASTERISK
+            | TokenKind::SLASH
+            | TokenKind::EQ
+            | TokenKind::NotEq
+            | TokenKind::LT
+            | TokenKind::GT
+    );
+}
+
+// `2.5e-3` written without its exponent, `0.0025`, for the decimal a float literal's error
+// suggests; `None` past `e64`, that's too many digits to suggest
+fn without_exponent(x: &str) -> Option<String> {
+    let (mantissa, exponent) = match x.split_once(['e', 'E']) {
+        Some(parts) => parts,
+        None => return Some(x.to_string()),
+    };
+    let exponent = exponent.parse::<i64>().ok().filter(|e| e.abs() <= 64)?;
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", whole, fraction);
+    let point = whole.len() as i64 + exponent;
+    let (whole, fraction) = if point <= 0 {
+        ("0".to_string(), format!("{}{}", "0".repeat(-point as usize), digits))
+    } else if point as usize >= digits.len() {
+        (format!("{}{}", digits, "0".repeat(point as usize - digits.len())), String::new())
+    } else {
+        (digits[..point as usize].to_string(), digits[point as usize..].to_string())
+    };
+    let whole = match whole.trim_start_matches('0') {
+        "" => "0",
+        whole => whole,
+    };
+    if fraction.is_empty() {
+        return Some(whole.to_string());
+    }
+    return Some(format!("{}.{}", whole, fraction));
 }
 
 pub fn parse(input: &str) -> Result<Node, ParseErrors> {
@@ -475,6 +1499,20 @@ pub fn parse(input: &str) -> Result<Node, ParseErrors> {
     Ok(Node::Program(program))
 }
 
+// Whether a REPL keeps the value of `input` to itself: when its last token is a `;`, so a line
+// defining a function doesn't echo it back, while `x` shows `x` and `x;` runs it quietly.
+pub fn is_quiet(input: &str) -> bool {
+    let mut lexer = Lexer::new(input);
+    let mut last = TokenKind::EOF;
+    loop {
+        let token = lexer.next_token();
+        if token.kind == TokenKind::EOF {
+            return last == TokenKind::SEMICOLON;
+        }
+        last = token.kind;
+    }
+}
+
 pub fn parse_ast_json_string(input: &str) -> Result<String, ParseErrors> {
     let ast = match parse(input) {
         Ok(node) => serde_json::to_string_pretty(&node).unwrap(),
@@ -1,12 +1,21 @@
 #[cfg(test)]
 mod tests {
-    use crate::parse;
+    use crate::{is_quiet, parse};
+
+    // what's wrong with `input`, without where, see `error_test` for that
+    fn messages(input: &str) -> Vec<String> {
+        return parse(input)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+    }
 
     fn verify_program(test_cases: &[(&str, &str)]) {
         for (input, expected) in test_cases {
             let ast = parse(input).unwrap();
             let parsed = ast.to_string();
-            assert_eq!(&format!("{}", parsed), expected);
+            assert_eq!(&parsed, expected);
         }
     }
 
@@ -32,6 +41,16 @@ mod tests {
         verify_program(&let_tests);
     }
 
+    #[test]
+    fn parse_import_statement() {
+        let import_tests = [
+            (r#"import "utils";"#, r#"import "utils";"#),
+            (r#"import "lib/math.monkey""#, r#"import "lib/math.monkey";"#),
+        ];
+
+        verify_program(&import_tests);
+    }
+
     #[test]
     fn test_parse_prefix_expression() {
         let let_tests = [
@@ -84,7 +103,7 @@ mod tests {
             ("a * b / c", "((a * b) / c)"),
             ("a + b / c", "(a + (b / c))"),
             ("a + b * c + d / e - f", "(((a + (b * c)) + (d / e)) - f)"),
-            ("3 + 4; -5 * 5", "(3 + 4)((-5) * 5)"),
+            ("3 + 4; -5 * 5", "(3 + 4);((-5) * 5)"),
             ("5 > 4 == 3 < 4", "((5 > 4) == (3 < 4))"),
             ("5 < 4 != 3 > 4", "((5 < 4) != (3 > 4))"),
             ("3 + 4 * 5 == 3 * 1 + 4 * 5", "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)))"),
@@ -117,12 +136,260 @@ mod tests {
         verify_program(&tt);
     }
 
+    #[test]
+    fn test_loops() {
+        let tt = [
+            ("while (x < y) { x }", "while (x < y) { x }"),
+            (
+                "for (let i = 0; i < n; i = i + 1) { puts(i); }",
+                "let i = 0;while (i < n) { puts(i);let i = (i + 1); }",
+            ),
+            ("for (i = 0; i < n;) { f(i) }", "let i = 0;while (i < n) { f(i) }"),
+            ("for (;;) { f() }", "while true { f() }"),
+            ("for (; x;) {}", "while x {  }"),
+            ("fn() { for (;;) {} }", "fn () { while true {  } }"),
+            ("while (x) { break; }", "while x { break; }"),
+            (
+                "outer: while (x) { while (y) { break outer } }",
+                "outer: while x { while y { break outer; } }",
+            ),
+            ("outer: for (;;) { break\nouter }", "outer: while true { break;outer }"),
+            (
+                "for (x in xs) { f(x) }",
+                r#"let in#0 = each(xs);while true { let step#0 = next(in#0);if (step#0["done"]) { break; };let x = (step#0["value"]);f(x) }"#,
+            ),
+            (
+                "a: for (x in [1, y]) {}",
+                r#"let in#0 = each([1, y]);a: while true { let step#0 = next(in#0);if (step#0["done"]) { break; };let x = (step#0["value"]); }"#,
+            ),
+            (
+                "for (x in xs) { for (y in x) {} }",
+                r#"let in#0 = each(xs);while true { let step#0 = next(in#0);if (step#0["done"]) { break; };let x = (step#0["value"]);let in#1 = each(x);while true { let step#1 = next(in#1);if (step#1["done"]) { break; };let y = (step#1["value"]); } }"#,
+            ),
+        ];
+        verify_program(&tt);
+        let tt = [
+            ("for (let i = 0, i < n; i = i + 1) {}", "expected `;` at line 1, column 15, got `,`"),
+            ("for (let i = 0; i < n; i = i + 1 {}", "expected `)` at line 1, column 34, got `{`"),
+            ("while x {}", "expected `(` at line 1, column 7, got `x`"),
+            ("outer: if (x) {}", "expected a loop after `outer:` at line 1, column 8, got `if`"),
+            ("for (x in xs {}", "expected `)` at line 1, column 14, got `{`"),
+            ("for (1 in xs) {}", "expected `;` at line 1, column 8, got `in`"),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error);
+        }
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let tt = [
+            ("match (x) { 1 => a, \"b\" => b, _ => c }", "match x { 1 => a, \"b\" => b, _ => c }"),
+            (
+                "match (x) { -1 => a, -2.5d => b, true => c, }",
+                "match x { -1 => a, -2.5d => b, true => c }",
+            ),
+            (
+                "match (xs) { [] => 0, [x, ...rest] => x }",
+                "match xs { [] => 0, [x, ...rest] => x }",
+            ),
+            (
+                "match (p) { {name: n, \"id\": [i, _]} => n }",
+                "match p { {\"name\": n, \"id\": [i, _]} => n }",
+            ),
+            (
+                "match (n) { x if x > 0 => x, _ => 0 - n }",
+                "match n { x if (x > 0) => x, _ => (0 - n) }",
+            ),
+            ("match (n) {}", "match n {  }"),
+            ("let y = match (n) { [_, _] => 2 } + 1", "let y = (match n { [_, _] => 2 } + 1);"),
+        ];
+        verify_program(&tt);
+        let tt = [
+            ("match (x) { 1 => a b }", "expected `,` at line 1, column 20, got `b`"),
+            ("match (x) { 1 -> a }", "expected `=>` at line 1, column 15, got `-`"),
+            ("match (x) { [a, a] => a }", "`a` is bound twice in the pattern at line 1, column 17"),
+            (
+                "match (x) { [...r, a] => a }",
+                "`...r` at line 1, column 17 must end the array pattern",
+            ),
+            (
+                "match (x) { [...1] => a }",
+                "expected a name after `...` at line 1, column 17, got `1`",
+            ),
+            ("match (x) { f(y) => y }", "expected `=>` at line 1, column 14, got `(`"),
+            ("match (x) { -a => a }", "expected a pattern at line 1, column 14, got `a`"),
+            ("match x { _ => 1 }", "expected `(` at line 1, column 7, got `x`"),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_compose_expression() {
+        let tt = [
+            ("f >> g", "fn (f, g) { fn (x) { g(f(x)) } }(f, g)"),
+            ("a + b >> c == d", "fn (f, g) { fn (x) { g(f(x)) } }((a + b), (c == d))"),
+            (
+                "f >> g >> h",
+                "fn (f, g) { fn (x) { g(f(x)) } }(fn (f, g) { fn (x) { g(f(x)) } }(f, g), h)",
+            ),
+            ("[f >> g]", "[fn (f, g) { fn (x) { g(f(x)) } }(f, g)]"),
+        ];
+        verify_program(&tt);
+        let tt = [("f >> ", "no prefix function for token: `EOF` at line 1, column 6")];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_section_expression() {
+        let tt = [
+            ("(+)", "fn (a, b) { (a + b) }"),
+            ("(!=)", "fn (a, b) { (a != b) }"),
+            ("f((*), 1)", "f(fn (a, b) { (a * b) }, 1)"),
+            ("(-) == (- x)", "(fn (a, b) { (a - b) } == (-x))"),
+        ];
+        verify_program(&tt);
+        let tt = [("(*", "no prefix function for token: `*` at line 1, column 2")];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let tt = [
+            ("a[0] = 1;", "(a = set(a, 0, 1))"),
+            ("m[i][j] = m[i][j] + 1", "(m = set(m, i, set((m[i]), j, (((m[i])[j]) + 1))))"),
+            ("for (;;) { a[i] = 0 }", "while true { (a = set(a, i, 0)) }"),
+        ];
+        verify_program(&tt);
+        let tt = [
+            ("f()[0] = 1", "can't assign to an index of `f()` at line 1, column 8, only of a name"),
+            ("a[0] = ;", "no prefix function for token: `;` at line 1, column 8"),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error);
+        }
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let tt = [
+            ("x += 1;", "(x = (x + 1))"),
+            ("x -= y * 2", "(x = (x - (y * 2)))"),
+            ("a[i] *= 2", "(a = set(a, i, ((a[i]) * 2)))"),
+            ("for (;; i /= 2) {}", "while true { (i = (i / 2)) }"),
+        ];
+        verify_program(&tt);
+        let tt = [
+            ("f() += 1", "can't assign to `f()` at line 1, column 5, only to a name or an index"),
+            ("1 -= 1", "can't assign to `1` at line 1, column 3, only to a name or an index"),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error);
+        }
+    }
+
+    #[test]
+    fn test_assign_expression() {
+        let tt = [
+            ("x = 1", "(x = 1)"),
+            ("x = x + 1;", "(x = (x + 1))"),
+            ("a = b = 0", "(a = (b = 0))"),
+            ("f(x = 2)", "f((x = 2))"),
+            ("let y = x = 3;", "let y = (x = 3);"),
+            ("a[0] = x = 1", "(a = set(a, 0, (x = 1)))"),
+            (
+                "for (let i = 0; i < 2; i = i + 1) {}",
+                "let i = 0;while (i < 2) { let i = (i + 1); }",
+            ),
+        ];
+        verify_program(&tt);
+        let tt = [
+            ("1 = 2", "can't assign to `1` at line 1, column 3, only to a name"),
+            ("x + 1 = 2", "can't assign to `(x + 1)` at line 1, column 7, only to a name"),
+            ("f(a[0] = 1)", "can't assign to `(a[0])` at line 1, column 8, only to a name"),
+            ("x = ;", "no prefix function for token: `;` at line 1, column 5"),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error);
+        }
+    }
+
     #[test]
     fn test_if_else_expression() {
         let tt = [("if (x < y) { x } else { y }", "if (x < y) { x } else { y }")];
         verify_program(&tt);
     }
 
+    #[test]
+    fn test_ternary_expression() {
+        let tt = [
+            ("c ? a : b", "if c { a } else { b }"),
+            ("x < y ? x : y", "if (x < y) { x } else { y }"),
+            ("1 + c ? a + 1 : b * 2", "if (1 + c) { (a + 1) } else { (b * 2) }"),
+            // nested ternaries group to the right, whichever side they're on
+            ("a ? b : c ? d : e", "if a { b } else { if c { d } else { e } }"),
+            ("a ? b ? c : d : e", "if a { if b { c } else { d } } else { e }"),
+            ("(a ? b : c) ? d : e", "if if a { b } else { c } { d } else { e }"),
+            ("x = c ? a : b", "(x = if c { a } else { b })"),
+            ("c ? x = 1 : 2", "if c { (x = 1) } else { 2 }"),
+            ("let m = f(c ? a : b)[0];", "let m = (f(if c { a } else { b })[0]);"),
+            ("{c ? a : b: c ? 1 : 2}", "{if c { a } else { b }: if c { 1 } else { 2 }}"),
+            ("f >> g ? 1 : 2", "if fn (f, g) { fn (x) { g(f(x)) } }(f, g) { 1 } else { 2 }"),
+        ];
+        verify_program(&tt);
+        let tt = [
+            ("c ? a", "expected `:` at line 1, column 6, got `EOF`"),
+            ("c ? a ; b", "expected `:` at line 1, column 7, got `;`"),
+            ("c ? : b", "no prefix function for token: `:` at line 1, column 5"),
+            (
+                "c ? a : x = 1",
+                "can't assign to `if c { a } else { x }` at line 1, column 11, only to a name",
+            ),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_fn_statement() {
+        let tt = [
+            ("fn add(a, b) { a + b }", "let add = fn add(a, b) { (a + b) };"),
+            ("fn one() { 1 }; one()", "let one = fn one() { 1 };one()"),
+            ("fn f(x) { fn g() { x } g() }", "let f = fn f(x) { let g = fn g() { x };g() };"),
+            ("fn(x) { x }(1)", "fn (x) { x }(1)"),
+        ];
+        verify_program(&tt);
+        // its span is the statement's, from `fn` on
+        let Ok(crate::ast::Node::Program(program)) = parse("fn add(a, b) { a + b }; add") else {
+            panic!("not a program");
+        };
+        assert_eq!(program.body[0].span(), &lexer::token::Span { start: 0, end: 23 });
+        let tt = [
+            ("fn 1() {}", "expected `(` at line 1, column 4, got `1`"),
+            ("fn add { a }", "expected `(` at line 1, column 8, got `{`"),
+            (
+                "fn add(a, b, a) { a }",
+                "`a` is a parameter twice, at line 1, column 8 and at line 1, column 14",
+            ),
+            (
+                "let f = fn(x,\n  y, x) { x };",
+                "`x` is a parameter twice, at line 1, column 12 and at line 2, column 6",
+            ),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
+        // `_` binds nothing, so it can be any number of them
+        assert!(parse("fn(_, x, _) { x }").is_ok());
+    }
+
     #[test]
     fn test_fn_else_expression() {
         let tt = [
@@ -133,6 +400,37 @@ mod tests {
         verify_program(&tt);
     }
 
+    #[test]
+    fn test_rest_parameter() {
+        let tt = [
+            ("fn(first, ...rest) { rest };", "fn (first, ...rest) { rest }"),
+            ("fn(...all) { all };", "fn (...all) { all }"),
+            ("fn log(level, ...parts) { parts }", "let log = fn log(level, ...parts) { parts };"),
+        ];
+        verify_program(&tt);
+        let tt = [
+            (
+                "fn(...a, b) { a }",
+                "`...a` must be the last parameter, got `b` at line 1, column 10",
+            ),
+            (
+                "fn(...a, ...b) { a }",
+                "`...a` must be the last parameter, got `...` at line 1, column 10",
+            ),
+            (
+                "fn(a, ...a) { a }",
+                "`a` is a parameter twice, at line 1, column 4 and at line 1, column 10",
+            ),
+            (
+                "fn(...) { 1 }",
+                "expected function params  to be an identifier, got `)` at line 1, column 7",
+            ),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
+    }
+
     #[test]
     fn test_fn_call_else_expression() {
         let tt = [("add(1, 2 * 3, 4 + 5);", "add(1, (2 * 3), (4 + 5))")];
@@ -145,6 +443,384 @@ mod tests {
         verify_program(&test_case);
     }
 
+    #[test]
+    fn test_char_literal_expression() {
+        let test_case = [
+            ("'a';", "'a'"),
+            (r"['\n', '\'', '\u{1F600}']", r"['\n', '\'', '😀']"),
+            ("match (c) { 'a' => 1 }", "match c { 'a' => 1 }"),
+        ];
+        verify_program(&test_case);
+    }
+
+    #[test]
+    fn test_decimal_literal_expression() {
+        let test_case = [("1.50d", "1.50d"), ("-2d * 0.5d", "((-2d) * 0.5d)")];
+        verify_program(&test_case);
+    }
+
+    #[test]
+    fn test_line_comments() {
+        let test_case = [
+            ("// one\n// two\n1 + 2", "(1 + 2)"),
+            ("//\n1 + 2", "(1 + 2)"),
+            ("// indented\n    1 + 2", "(1 + 2)"),
+            ("1 + // the rest of the line\n2", "(1 + 2)"),
+            ("let x = 1; // one\nlet y = 2; // two", "let x = 1;let y = 2;"),
+            ("1 / 2 // half", "(1 / 2)"),
+            ("\"a // b\"", "\"a // b\""),
+            ("1 // no newline at the end", "1"),
+        ];
+        verify_program(&test_case);
+    }
+
+    #[test]
+    fn test_statement_ends() {
+        let test_case = [
+            ("let a = 1\nlet b = 2", "let a = 1;let b = 2;"),
+            ("let a = 1;let b = 2;", "let a = 1;let b = 2;"),
+            ("fn f() { 1 } f()", "let f = fn f() { 1 };f()"),
+            ("1\n2", "1;2"),
+            ("if (x) { 1 } 2", "if x { 1 };2"),
+            ("fn() { let a = 1 }", "fn () { let a = 1; }"),
+            ("while (x) { break }", "while x { break; }"),
+        ];
+        verify_program(&test_case);
+        let test_case = [
+            ("let a = 1 let b = 2", "expected `;` at line 1, column 10, got `let`"),
+            ("1 2", "expected `;` at line 1, column 2, got `2`"),
+            ("fn() { a\n b c }", "expected `;` at line 2, column 3, got `c`"),
+            ("return \"x\" puts(1)", "expected `;` at line 1, column 11, got `puts`"),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(messages(input), vec![error.to_string()], "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let test_case = [
+            ("let x 5;", "expected `=` at line 1, column 7, got `5`"),
+            ("let x = 1 +\n\n;", "no prefix function for token: `;` at line 3, column 1"),
+            ("puts(1,\n     2;", "expected `)` at line 2, column 7, got `;`"),
+            ("// a comment\n\tlet 5 = x;", "`5` at line 2, column 6 not an identifier"),
+            (
+                "let f = fn(x,\n 1) { x };",
+                "expected function params  to be an identifier, got `1` at line 2, column 2",
+            ),
+            ("let a = [f][0](1);", "expected function before the `(` at line 1, column 15"),
+            ("if (x) {\n  x;\n", "expected `}` at line 3, column 1, got `EOF`"),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_float_literal_expression() {
+        let errors = messages("let x = 3.14;");
+        let error = "float literals aren't supported yet, 3.14d is a decimal, at line 1, column 9";
+        assert_eq!(errors[0], error);
+    }
+
+    #[test]
+    fn test_exponent_literal_expression() {
+        let test_case = [
+            ("1e6", "1000000d"),
+            ("2.5e-3", "0.0025d"),
+            ("1.50E+1", "15.0d"),
+            ("0.5e1", "5d"),
+            ("1_0e-1_0", "0.0000000010d"),
+        ];
+        for (input, decimal) in test_case {
+            let error = format!(
+                "float literals aren't supported yet, {} is a decimal, at line 1, column 1",
+                decimal
+            );
+            assert_eq!(messages(input)[0], error);
+        }
+        let error = "float literals aren't supported yet, use a decimal, at line 1, column 1";
+        assert_eq!(messages("1e100")[0], error);
+        let test_case = [
+            ("1e", "missing exponent digits in number 1e at line 1, column 1"),
+            ("1e+;", "missing exponent digits in number 1e+ at line 1, column 1"),
+            ("1.5E-", "missing exponent digits in number 1.5E- at line 1, column 1"),
+            ("1_e5", "trailing `_` in number 1_e5 at line 1, column 1"),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(messages(input)[0], error);
+        }
+    }
+
+    #[test]
+    fn test_radix_literal_expression() {
+        let test_case = [
+            ("0xFF", "255"),
+            ("0o17 + 0b11", "(15 + 3)"),
+            ("-0x10", "(-16)"),
+        ];
+        verify_program(&test_case);
+        for input in ["0x", "0b102"] {
+            let errors = messages(input);
+            assert_eq!(errors[0], format!("illegal token {} at line 1, column 1", input));
+        }
+        // past `i64`, which `-` in front doesn't change, it negates the literal after it, but for
+        // `i64::MIN`, the one literal that's only written negative
+        let largest = "the largest is 9223372036854775807";
+        for input in [
+            "0xFFFFFFFFFFFFFFFFF",
+            "9223372036854775808",
+            "-99_999_999_999_999_999_999",
+            "-9223372036854775809",
+            "-(9223372036854775808)",
+        ] {
+            let number = input.trim_start_matches(['-', '(']).trim_end_matches(')');
+            let at = input.find(number).unwrap() + 1;
+            let error = format!(
+                "{} doesn't fit in an integer, {} at line 1, column {}",
+                number, largest, at
+            );
+            assert_eq!(messages(input), [error]);
+        }
+        verify_program(&[
+            ("9223372036854775807", "9223372036854775807"),
+            ("-9223372036854775808", "-9223372036854775808"),
+            ("-0x8000000000000000", "-9223372036854775808"),
+            ("1 - -9_223_372_036_854_775_808", "(1 - -9223372036854775808)"),
+            ("match (x) { -9223372036854775808 => 0 }", "match x { -9223372036854775808 => 0 }"),
+        ]);
+        assert_eq!(
+            messages("match (x) { 9223372036854775808 => 0 }"),
+            [format!(
+                "9223372036854775808 doesn't fit in an integer, {} at line 1, column 13",
+                largest
+            )]
+        );
+    }
+
+    #[test]
+    fn test_numeric_separators() {
+        let test_case = [
+            ("1_000_000", "1000000"),
+            ("0b1111_0000 + 0xFF_FF", "(240 + 65535)"),
+            ("1_000.50d", "1000.50d"),
+        ];
+        verify_program(&test_case);
+        let test_case = [
+            ("1_000_", "trailing `_` in number 1_000_ at line 1, column 1"),
+            ("1_.5", "trailing `_` in number 1_.5 at line 1, column 1"),
+            ("1._5", "leading `_` in number 1._5 at line 1, column 1"),
+            ("0x_FF", "leading `_` in number 0x_FF at line 1, column 1"),
+            ("1__0", "repeated `_` in number 1__0 at line 1, column 1"),
+            ("1_d", "trailing `_` in number 1_d at line 1, column 1"),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(messages(input)[0], error);
+        }
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        // shown escaped again
+        let test_case = [
+            (r#""a\nb""#, r#""a\nb""#),
+            (r#""say \"hi\"""#, r#""say \"hi\"""#),
+            (r#""tab\there""#, r#""tab\there""#),
+            (r#""back\\slash""#, r#""back\\slash""#),
+            (r#""\u{48}\u{1F600}""#, "\"H\u{1F600}\""),
+        ];
+        verify_program(&test_case);
+        let test_case = [
+            (r#""a\qb""#, r#"unknown escape \q in string "a\qb" at line 1, column 1"#),
+            (
+                r#""\u{110000}""#,
+                r#"invalid unicode escape \u{110000} in string "\u{110000}" at line 1, column 1"#,
+            ),
+            (
+                r#""\u{+41}""#,
+                r#"invalid unicode escape \u{+41} in string "\u{+41}" at line 1, column 1"#,
+            ),
+            (r#""\u41""#, r#"expected \u{...} in string "\u41" at line 1, column 1"#),
+            (r#""a\"#, r#"a string can't end with a lone \ in string "a\ at line 1, column 1"#),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(messages(input)[0], error);
+        }
+    }
+
+    #[test]
+    fn test_lexical_errors() {
+        // each one, the one in the block too
+        let input = "let s = 1 \u{20AC} 2;\nlet t = \"\\q\";\nlet f = fn() { let u = 0b12; };";
+        let errors = messages(input);
+        let lexical = [
+            "illegal token \u{20AC} at line 1, column 11",
+            r#"unknown escape \q in string "\q" at line 2, column 9"#,
+            "illegal token 0b12 at line 3, column 24",
+        ];
+        for e in lexical {
+            assert_eq!(errors.iter().filter(|&error| error == e).count(), 1, "{:?}", errors);
+        }
+    }
+
+    #[test]
+    fn test_misplaced_break() {
+        let test_case = [
+            ("break;", "`break` outside a loop at line 1, column 1"),
+            ("if (true) { break; }", "`break` outside a loop at line 1, column 13"),
+            ("while (true) { fn() { break; }(); }", "`break` outside a loop at line 1, column 23"),
+            (
+                "a: while (true) { let f = fn() { while (true) { break a; } }; }",
+                "no loop labeled `a` around the `break` at line 1, column 49",
+            ),
+            (
+                "for (x in xs) { [1, fn() { break }] }",
+                "`break` outside a loop at line 1, column 28",
+            ),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(messages(input), [error], "{}", input);
+        }
+        // a `return` is fine anywhere, and a `break` may leave any loop around it
+        let test_case = [
+            ("return 1;", "return 1;"),
+            (
+                "a: while (true) { b: while (true) { break a; } }",
+                "a: while true { b: while true { break a; } }",
+            ),
+        ];
+        verify_program(&test_case);
+    }
+
+    #[test]
+    fn test_error_order() {
+        // in the order of the source, the lexer's too, each once
+        let input = "let f = fn() { let u = 0b12; };\nlet x 5;\nlet y = \u{20AC};";
+        let expected = [
+            "illegal token 0b12 at line 1, column 24",
+            "expected `=` at line 2, column 7, got `5`",
+            "illegal token \u{20AC} at line 3, column 9",
+        ];
+        assert_eq!(messages(input), expected);
+
+        // and only so many of them
+        let input = "let = 1;\n".repeat(30);
+        let errors = messages(&input);
+        assert_eq!(errors.len(), 21, "{:?}", errors);
+        assert_eq!(errors[0], "`=` at line 1, column 5 not an identifier");
+        assert_eq!(errors[19], "`=` at line 20, column 5 not an identifier");
+        assert_eq!(errors[20], "too many errors, stopping");
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        // one error for each statement that's wrong, the parse picking up at the next one
+        let test_case: [(&str, &[&str]); 7] = [
+            (
+                "let x = 1 + * 2;\nlet y = 2;\nputs(x y z);\nlet z = ) ;",
+                &[
+                    "no prefix function for token: `*` at line 1, column 13",
+                    "expected `)` at line 3, column 8, got `y`",
+                    "no prefix function for token: `)` at line 4, column 9",
+                ],
+            ),
+            (
+                "let x = @ @ @;\nlet y = 1;",
+                &[
+                    "illegal token @ at line 1, column 9",
+                    "illegal token @ at line 1, column 11",
+                    "illegal token @ at line 1, column 13",
+                ],
+            ),
+            // in a block too, which still ends at its `}`
+            (
+                "if (x) { let 5 = 1; let 6 = 2; }\nlet 7 = 3;",
+                &[
+                    "`5` at line 1, column 14 not an identifier",
+                    "`6` at line 1, column 25 not an identifier",
+                    "`7` at line 2, column 5 not an identifier",
+                ],
+            ),
+            (
+                "fn f() { let = }\nlet a = 1; a +",
+                &[
+                    "`=` at line 1, column 14 not an identifier",
+                    "no prefix function for token: `EOF` at line 2, column 15",
+                ],
+            ),
+            (
+                "fn f() { let g = fn() { 1 + ; }; g() }\nlet 8 = 1;",
+                &[
+                    "no prefix function for token: `;` at line 1, column 29",
+                    "`8` at line 2, column 5 not an identifier",
+                ],
+            ),
+            // over the braces of what's skipped
+            (
+                "if (x) { let f = 1 + + fn() { 1 } let 9 = 1; }",
+                &[
+                    "no prefix function for token: `+` at line 1, column 22",
+                    "`9` at line 1, column 39 not an identifier",
+                ],
+            ),
+            (
+                "let h = {1 2: fn() { 3 }, 4: 5}; while (true) { break; } let = 1",
+                &[
+                    "expected `:` after the key 1 in a hash at line 1, column 12, got `2`",
+                    "`=` at line 1, column 62 not an identifier",
+                ],
+            ),
+        ];
+        for (input, errors) in test_case {
+            assert_eq!(messages(input), errors, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_is_quiet() {
+        let test_case = [
+            ("let add = fn(a, b) { a + b };", true),
+            ("add(1, 2);", true),
+            ("add(1, 2); // the sum\n", true),
+            ("add(1, 2)", false),
+            ("let x = 1; x", false),
+            ("\"a;\"", false),
+            ("fn f() { 1; }", false),
+            ("", false),
+        ];
+        for (input, quiet) in test_case {
+            assert_eq!(is_quiet(input), quiet, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let test_case = [
+            (r#""hello ${name}""#, r#"("hello " + string(name))"#),
+            (r#""${a}${b}""#, r#"(("" + string(a)) + string(b))"#),
+            (
+                r#""a ${1 + 2} b ${c} d""#,
+                r#"(((("a " + string((1 + 2))) + " b ") + string(c)) + " d")"#,
+            ),
+            (r#""${ {"k": "v"}["k"] }""#, r#"("" + string(({"k": "v"}["k"])))"#),
+            (r#""a ${"b ${c}"}""#, r#"("a " + string(("b " + string(c))))"#),
+            (r#""costs \${price}""#, r#""costs \${price}""#),
+            (r#""${a}" + "b""#, r#"(("" + string(a)) + "b")"#),
+        ];
+        verify_program(&test_case);
+        let test_case = [
+            (r#""a ${b"#, "expected `}` at line 1, column 7, got `EOF`"),
+            // the escape, where it is
+            (r#""a ${b} \q""#, r#"unknown escape \q in string at line 1, column 9"#),
+            (r#""a ${b}\n ${c} \q""#, r#"unknown escape \q in string at line 1, column 16"#),
+            ("\"a ${b}\n \\q\"", r#"unknown escape \q in string at line 2, column 2"#),
+            (r#""a \q ${b}""#, r#"unknown escape \q in string "a \q ${ at line 1, column 1"#),
+        ];
+        for (input, error) in test_case {
+            assert_eq!(messages(input)[0], error);
+        }
+    }
+
     #[test]
     fn test_array_literal_expression() {
         let test_case = [("[]", "[]"), ("[1, 2 * 2, 3 + 3]", "[1, (2 * 2), (3 + 3)]")];
@@ -153,8 +829,44 @@ mod tests {
 
     #[test]
     fn test_index_expression() {
-        let test_case = [("a[1]", "(a[1])"), ("a[1 + 1]", "(a[(1 + 1)])")];
+        let test_case = [
+            ("a[1]", "(a[1])"),
+            ("a[1 + 1]", "(a[(1 + 1)])"),
+            ("myArray[1 + 1]", "(myArray[(1 + 1)])"),
+            (r#"myHash["key"]"#, r#"(myHash["key"])"#),
+            ("a[0][1]", "((a[0])[1])"),
+            ("-a[0] * [1, 2][b]", "((-(a[0])) * ([1, 2][b]))"),
+            ("f(x)[0]", "(f(x)[0])"),
+        ];
+        verify_program(&test_case);
+        let tt = [
+            ("a[]", "expected an index between `[` and `]` at line 1, column 3"),
+            ("a[1", "expected `]` at line 1, column 4, got `EOF`"),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_dot_expression() {
+        let test_case = [
+            ("point.x", r#"(point["x"])"#),
+            ("config.db.port", r#"((config["db"])["port"])"#),
+            ("-p.x * q.y", r#"((-(p["x"])) * (q["y"]))"#),
+            ("shape.area(3, 4)", r#"(shape["area"])(3, 4)"#),
+            ("xs[0].name", r#"((xs[0])["name"])"#),
+            ("p.x = 5;", r#"(p = set(p, "x", 5))"#),
+        ];
         verify_program(&test_case);
+        let tt = [
+            ("p.1", "expected a name after the `.` at line 1, column 3, got `1`"),
+            ("p.", "expected a name after the `.` at line 1, column 3, got `EOF`"),
+            ("p.if", "expected a name after the `.` at line 1, column 3, got `if`"),
+        ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
     }
 
     #[test]
@@ -167,7 +879,38 @@ mod tests {
                 r#"{"one": 0 + 1, "two": 10 - 8, "three": 15 / 5}"#,
                 r#"{"one": (0 + 1), "two": (10 - 8), "three": (15 / 5)}"#,
             ),
+            (r#"{"a": 1, 2: "b", true: 3}"#, r#"{"a": 1, 2: "b", true: 3}"#),
+            ("{\n  1: 2,\n  3: 4,\n}", "{1: 2, 3: 4}"),
+        ];
+        verify_program(&test_case);
+        let tt = [
+            (
+                r#"{"a" 1}"#,
+                r#"expected `:` after the key "a" in a hash at line 1, column 6, got `1`"#,
+            ),
+            (
+                r#"{"a":}"#,
+                r#"expected a value for the key "a" in a hash at line 1, column 6, got `}`"#,
+            ),
+            (r#"{"a": 1, "b": 2"#, "expected `,` or `}` in a hash at line 1, column 16, got `EOF`"),
+            ("{1: 2 3: 4}", "expected `,` or `}` in a hash at line 1, column 7, got `3`"),
+            ("{,}", "expected a key in a hash at line 1, column 2, got `,`"),
+            ("{1: 2,,}", "expected a key in a hash at line 1, column 7, got `,`"),
+            (
+                "{1: 2,\n 3: 4, 1: 6}",
+                "the key 1 is in the hash twice, at line 1, column 2 and at line 2, column 8",
+            ),
         ];
+        for (input, error) in tt {
+            assert_eq!(messages(input)[0], error, "{}", input);
+        }
+        // each one again, and keys of other types or computed aren't the same
+        let errors = messages(r#"{"a": 1, "a": 2, "a": 3}"#);
+        let twice =
+            r#"the key "a" is in the hash twice, at line 1, column 2 and at line 1, column"#;
+        assert_eq!(errors, [format!("{} 10", twice), format!("{} 18", twice)]);
+        let test_case =
+            [(r#"{"1": 1, 1: 2, '1': 3, x: 4, x: 5}"#, r#"{"1": 1, 1: 2, '1': 3, x: 4, x: 5}"#)];
         verify_program(&test_case);
     }
 }
@@ -3,17 +3,23 @@ use lexer::token::TokenKind;
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub enum Precedence {
     LOWEST,
+    ASSIGN,      // =
+    TERNARY,     // ? :
+    COMPOSE,     // >>
     EQUALS,      // ==
     LessGreater, // > or <
     SUM,         // + or =
     PRODUCT,     // * or /
     PREFIX,      // -X or !X
     CALL,        // myFunction(x)
-    INDEX,       // array[index]
+    INDEX,       // array[index] or hash.key
 }
 
 pub fn get_token_precedence(token: &TokenKind) -> Precedence {
     match token {
+        TokenKind::ASSIGN => Precedence::ASSIGN,
+        TokenKind::Question => Precedence::TERNARY,
+        TokenKind::Compose => Precedence::COMPOSE,
         TokenKind::EQ => Precedence::EQUALS,
         TokenKind::NotEq => Precedence::EQUALS,
         TokenKind::LT => Precedence::LessGreater,
@@ -24,6 +30,7 @@ pub fn get_token_precedence(token: &TokenKind) -> Precedence {
         TokenKind::SLASH => Precedence::PRODUCT,
         TokenKind::LPAREN => Precedence::CALL,
         TokenKind::LBRACKET => Precedence::INDEX,
+        TokenKind::Dot => Precedence::INDEX,
         _ => Precedence::LOWEST,
     }
 }
@@ -0,0 +1,102 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::ast::{BlockStatement, Expression, Literal, Statement};
+use crate::Message;
+use lexer::token::Span;
+
+// where `break` may be, checked once a program is parsed so both engines reject the same programs
+// before running them; a top-level `return` ends the program
+pub(crate) fn validate(body: &[Statement]) -> Vec<(Span, Message)> {
+    let mut validator = Validator { loops: vec![], errors: vec![] };
+    validator.statements(body);
+    return validator.errors;
+}
+
+struct Validator {
+    // the loops around the code being checked in its function, innermost last, by label
+    loops: Vec<Option<String>>,
+    // each with where it is, see `Parser::sorted_errors`
+    errors: Vec<(Span, Message)>,
+}
+
+impl Validator {
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Let(l) => self.expression(&l.expr),
+                Statement::Return(r) => self.expression(&r.argument),
+                Statement::Expr(e) => self.expression(e),
+                Statement::Import(_) => {}
+                Statement::While(w) => {
+                    self.expression(&w.condition);
+                    self.loops.push(w.label.as_ref().map(|l| l.name.clone()));
+                    self.statements(&w.body.body);
+                    self.loops.pop();
+                }
+                Statement::Break(b) => {
+                    let found = match &b.label {
+                        Some(label) => self.loops.contains(&Some(label.name.clone())),
+                        None => !self.loops.is_empty(),
+                    };
+                    if !found {
+                        self.errors.push((b.span.clone(), b.misplaced()));
+                    }
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, block: &BlockStatement) {
+        self.statements(&block.body);
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::IDENTIFIER(_) => {}
+            Expression::LITERAL(Literal::Array(a)) => {
+                a.elements.iter().for_each(|e| self.expression(e));
+            }
+            Expression::LITERAL(Literal::Hash(h)) => {
+                for (k, v) in h.elements.iter() {
+                    self.expression(k);
+                    self.expression(v);
+                }
+            }
+            Expression::LITERAL(_) => {}
+            Expression::PREFIX(p) => self.expression(&p.operand),
+            Expression::INFIX(i) => {
+                self.expression(&i.left);
+                self.expression(&i.right);
+            }
+            Expression::IF(i) => {
+                self.expression(&i.condition);
+                self.block(&i.consequent);
+                i.alternate.iter().for_each(|a| self.block(a));
+            }
+            // a function's body is in no loop, whatever loop it's made in
+            Expression::FUNCTION(f) => {
+                let loops = core::mem::take(&mut self.loops);
+                self.block(&f.body);
+                self.loops = loops;
+            }
+            Expression::FunctionCall(c) => {
+                self.expression(&c.callee);
+                c.arguments.iter().for_each(|a| self.expression(a));
+            }
+            Expression::Index(i) => {
+                self.expression(&i.object);
+                self.expression(&i.index);
+            }
+            Expression::Match(m) => {
+                self.expression(&m.subject);
+                for arm in m.arms.iter() {
+                    arm.guard.iter().for_each(|g| self.expression(g));
+                    self.expression(&arm.body);
+                }
+            }
+            Expression::Assign(a) => self.expression(&a.value),
+        }
+    }
+}